@@ -1,12 +1,25 @@
 use std::time::Duration;
 
 use crate::{
-    codecs::codec_manager::CodecManager,
+    codecs::{
+        codec_manager::{CodecManager, CodecOptions},
+        dds::DDSHeaderFormat,
+        ktx2::KTX2Supercompression,
+        raw::{guess_raw_layouts, RawCodec, RawLayoutGuess, RAW_PIXEL_FORMATS},
+        ImageCodec,
+    },
     graphics::{
-        pixel_format::PixelFormat, swizzling::Platform, texture::Texture,
+        bc_encoder::{BcQuality, ChannelWeighting},
+        mipmap_generator::{MipmapFilter, MipmapOptions},
+        pixel_format::PixelFormat,
+        swizzling::{wii_u::GX2TileMode, Platform},
+        texture::Texture,
         texture_utility::TextureEffects,
     },
-    io::file_manager::FileManager,
+    io::{
+        file_manager::FileManager,
+        texture_scanner::{extract_textures, ScanOptions, ScanTask, ScannedTexture},
+    },
 };
 use egui::{Button, OpenUrl, Rect, Scene, TextureHandle, Vec2};
 use egui_notify::Toasts;
@@ -38,6 +51,8 @@ pub struct TextureViewer {
     #[serde(skip)]
     codec_manager: CodecManager,
     #[serde(skip)]
+    codec_options: CodecOptions,
+    #[serde(skip)]
     texture_effects: TextureEffects,
     #[serde(skip)]
     reset_view: bool,
@@ -45,6 +60,24 @@ pub struct TextureViewer {
     display_compressed: bool,
     #[serde(skip)]
     toasts: Toasts,
+    #[serde(skip)]
+    raw_source: Option<Vec<u8>>,
+    #[serde(skip)]
+    raw_codec: RawCodec,
+    #[serde(skip)]
+    raw_guesses: Vec<RawLayoutGuess>,
+    #[serde(skip)]
+    raw_error: Option<String>,
+    #[serde(skip)]
+    scan_options: ScanOptions,
+    #[serde(skip)]
+    scan_task: Option<ScanTask>,
+    #[serde(skip)]
+    scan_source: Option<Vec<u8>>,
+    #[serde(skip)]
+    scanned_textures: Vec<ScannedTexture>,
+    #[serde(skip)]
+    selected_scanned_texture: Option<usize>,
 }
 
 impl Default for TextureViewer {
@@ -66,6 +99,7 @@ impl Default for TextureViewer {
             max_item: 0,
             scene_rect: Rect::ZERO,
             codec_manager: CodecManager::default(),
+            codec_options: CodecOptions::default(),
             texture_source: None,
             texture_effects: TextureEffects::default(),
             reset_view: false,
@@ -75,6 +109,15 @@ impl Default for TextureViewer {
                 toasts = toasts.with_anchor(egui_notify::Anchor::BottomRight);
                 toasts
             },
+            raw_source: None,
+            raw_codec: RawCodec::default(),
+            raw_guesses: Vec::new(),
+            raw_error: None,
+            scan_options: ScanOptions::default(),
+            scan_task: None,
+            scan_source: None,
+            scanned_textures: Vec::new(),
+            selected_scanned_texture: None,
         }
     }
 }
@@ -132,6 +175,9 @@ impl eframe::App for TextureViewer {
             });
         });
 
+        self.display_raw_dialog(ctx);
+        self.display_scan_dialog(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             self.display_scene(ui, ctx);
         });
@@ -231,24 +277,22 @@ impl TextureViewer {
                 self.reset_view = true;
             }
 
-            if self.max_item > 0 {
-                if ui
+            if self.max_item > 0
+                && ui
                     .add(egui::Slider::new(&mut self.current_item, 0..=self.max_item).text("Item"))
                     .on_hover_text("Current array element being displayed")
                     .changed()
-                {
-                    self.request_texture_update = true;
-                }
+            {
+                self.request_texture_update = true;
             }
 
-            if self.max_mip > 0 {
-                if ui
+            if self.max_mip > 0
+                && ui
                     .add(egui::Slider::new(&mut self.current_mip, 0..=self.max_mip).text("Mip"))
                     .on_hover_text("Current mip being displayed")
                     .changed()
-                {
-                    self.request_texture_update = true;
-                }
+            {
+                self.request_texture_update = true;
             }
 
             if ui
@@ -311,6 +355,110 @@ impl TextureViewer {
                     ui.close_menu();
                 }
 
+                if ui.button("Open as Raw").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        match std::fs::read(&path) {
+                            Ok(source) => {
+                                self.raw_source = Some(source);
+                                self.raw_codec = RawCodec::default();
+                                self.update_raw_guesses();
+                                self.update_raw_texture();
+                            }
+                            Err(e) => {
+                                self.show_error(&e.to_string());
+                                self.log_error(&e.to_string());
+                            }
+                        }
+                    }
+
+                    ui.close_menu();
+                }
+
+                if ui.button("Scan for Textures").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        match std::fs::read(&path) {
+                            Ok(source) => {
+                                if let Some(scan_task) = self.scan_task.take() {
+                                    scan_task.cancel();
+                                }
+
+                                self.scan_source = None;
+                                self.scanned_textures.clear();
+                                self.selected_scanned_texture = None;
+                                self.scan_task = Some(ScanTask::spawn(
+                                    source,
+                                    self.codec_options,
+                                    self.scan_options,
+                                ));
+                            }
+                            Err(e) => {
+                                self.show_error(&e.to_string());
+                                self.log_error(&e.to_string());
+                            }
+                        }
+                    }
+
+                    ui.close_menu();
+                }
+
+                ui.menu_button("Load Options", |ui| {
+                    let mut codec_options = self.codec_options;
+                    let mut single_texture = codec_options.texture_index.is_some();
+
+                    ui.label("BNTX/XPR2 Textures");
+                    if ui
+                        .checkbox(&mut single_texture, "Single Texture")
+                        .on_hover_text("Load one texture entry instead of all of them as an array")
+                        .changed()
+                    {
+                        codec_options.texture_index = single_texture.then_some(0);
+                    }
+
+                    if let Some(texture_index) = &mut codec_options.texture_index {
+                        ui.add(egui::DragValue::new(texture_index).prefix("Index: "));
+                    }
+
+                    ui.separator();
+                    ui.label("Scan for Textures");
+                    ui.checkbox(&mut self.scan_options.include_tga, "Include TGA")
+                        .on_hover_text("TGA has no signature, checking every offset is slow");
+
+                    self.set_codec_options(codec_options);
+                });
+
+                ui.menu_button("Save Options", |ui| {
+                    let mut codec_options = self.codec_options;
+
+                    ui.label("DDS Header");
+                    for header_format in [
+                        DDSHeaderFormat::Auto,
+                        DDSHeaderFormat::Legacy,
+                        DDSHeaderFormat::DX10,
+                    ] {
+                        ui.selectable_value(
+                            &mut codec_options.dds_header_format,
+                            header_format,
+                            header_format.to_string(),
+                        );
+                    }
+
+                    ui.separator();
+                    ui.label("KTX2 Supercompression");
+                    for supercompression in [
+                        KTX2Supercompression::None,
+                        KTX2Supercompression::Zstandard,
+                        KTX2Supercompression::Zlib,
+                    ] {
+                        ui.selectable_value(
+                            &mut codec_options.ktx2_supercompression,
+                            supercompression,
+                            supercompression.to_string(),
+                        );
+                    }
+
+                    self.set_codec_options(codec_options);
+                });
+
                 if ui
                     .add_enabled(self.texture_source.is_some(), Button::new("Save As"))
                     .clicked()
@@ -321,11 +469,15 @@ impl TextureViewer {
                         .add_filter("BMP", &["bmp"])
                         .add_filter("TGA", &["tga"])
                         .add_filter("DDS", &["dds"])
+                        .add_filter("KTX", &["ktx"])
+                        .add_filter("KTX2", &["ktx2"])
+                        .add_filter("GXT", &["gxt"])
+                        .add_filter("GNF", &["gnf"])
                         .save_file()
                     {
                         match self.codec_manager.save_to_file(
                             &path.to_path_buf(),
-                            &self.texture_source.as_ref().unwrap(),
+                            self.texture_source.as_ref().unwrap(),
                             &self.texture_effects,
                         ) {
                             Ok(_) => {}
@@ -384,7 +536,34 @@ impl TextureViewer {
                         PixelFormat::BC4,
                         PixelFormat::BC5,
                         PixelFormat::BC6H,
-                        // PixelFormat::BC7, currently BC7 takes too long to compress
+                        PixelFormat::BC7,
+                        PixelFormat::Astc4x4,
+                        PixelFormat::Astc5x4,
+                        PixelFormat::Astc5x5,
+                        PixelFormat::Astc6x5,
+                        PixelFormat::Astc6x6,
+                        PixelFormat::Astc8x5,
+                        PixelFormat::Astc8x6,
+                        PixelFormat::Astc8x8,
+                        PixelFormat::Astc10x5,
+                        PixelFormat::Astc10x6,
+                        PixelFormat::Astc10x8,
+                        PixelFormat::Astc10x10,
+                        PixelFormat::Astc12x10,
+                        PixelFormat::Astc12x12,
+                        PixelFormat::GxI4,
+                        PixelFormat::GxI8,
+                        PixelFormat::GxIA4,
+                        PixelFormat::GxIA8,
+                        PixelFormat::GxRGB565,
+                        PixelFormat::GxRGB5A3,
+                        PixelFormat::GxRGBA8,
+                        PixelFormat::GxCMPR,
+                        PixelFormat::GxC4,
+                        PixelFormat::GxC8,
+                        PixelFormat::GxC14X2,
+                        PixelFormat::Ps2PSMT4,
+                        PixelFormat::Ps2PSMT8,
                     ];
 
                     for format in vec_pixel_format {
@@ -403,12 +582,136 @@ impl TextureViewer {
                     }
                 });
 
+                ui.menu_button("Generate Mipmaps", |ui| {
+                    let selected_filter = self.texture_effects.mipmaps.map(|m| m.filter);
+
+                    let filters = vec![
+                        None,
+                        Some(MipmapFilter::Box),
+                        Some(MipmapFilter::Triangle),
+                        Some(MipmapFilter::Kaiser),
+                        Some(MipmapFilter::Lanczos),
+                    ];
+
+                    for filter in filters {
+                        if ui
+                            .selectable_label(
+                                selected_filter == filter,
+                                match filter {
+                                    Some(f) => f.to_string(),
+                                    None => "None".to_string(),
+                                },
+                            )
+                            .clicked()
+                        {
+                            self.texture_effects.mipmaps = filter.map(|filter| MipmapOptions {
+                                filter,
+                                ..self.texture_effects.mipmaps.unwrap_or_default()
+                            });
+                            ui.close_menu();
+                        }
+                    }
+
+                    if let Some(mipmaps) = &mut self.texture_effects.mipmaps {
+                        ui.separator();
+
+                        let mut limit_levels = mipmaps.max_levels.is_some();
+                        let mut max_levels = mipmaps.max_levels.unwrap_or(1);
+
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut limit_levels, "Max Levels");
+                            ui.add_enabled(
+                                limit_levels,
+                                egui::DragValue::new(&mut max_levels).range(1..=16),
+                            );
+                        });
+
+                        let mut preserve_coverage = mipmaps.alpha_coverage_reference.is_some();
+                        let mut alpha_reference = mipmaps.alpha_coverage_reference.unwrap_or(0.5);
+
+                        ui.checkbox(&mut preserve_coverage, "Preserve Alpha Coverage")
+                            .on_hover_text(
+                                "Keep the fraction of pixels passing the alpha test in every level",
+                            );
+                        ui.add_enabled(
+                            preserve_coverage,
+                            egui::Slider::new(&mut alpha_reference, 0.0..=1.0)
+                                .text("Alpha Reference"),
+                        );
+
+                        mipmaps.max_levels = limit_levels.then_some(max_levels);
+                        mipmaps.alpha_coverage_reference =
+                            preserve_coverage.then_some(alpha_reference);
+                    }
+                });
+
+                ui.menu_button("Compression", |ui| {
+                    let compression = &mut self.texture_effects.compression;
+                    let mut selected_quality = compression.quality;
+
+                    let qualities = vec![
+                        BcQuality::UltraFast,
+                        BcQuality::VeryFast,
+                        BcQuality::Fast,
+                        BcQuality::Basic,
+                        BcQuality::Slow,
+                    ];
+
+                    ui.label("BC6H / BC7 / ASTC Quality");
+                    for quality in qualities {
+                        if ui
+                            .selectable_value(&mut selected_quality, quality, quality.to_string())
+                            .clicked()
+                        {
+                            compression.quality = selected_quality;
+                            self.request_texture_update = true;
+                        }
+                    }
+
+                    ui.separator();
+                    ui.label("Channel Weighting");
+                    for weighting in [ChannelWeighting::Perceptual, ChannelWeighting::Uniform] {
+                        if ui
+                            .selectable_value(
+                                &mut compression.weighting,
+                                weighting,
+                                weighting.to_string(),
+                            )
+                            .clicked()
+                        {
+                            self.request_texture_update = true;
+                        }
+                    }
+
+                    ui.separator();
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut compression.alpha_threshold, 0.0..=1.0)
+                                .text("BC1 Alpha Threshold"),
+                        )
+                        .changed()
+                        | ui.checkbox(&mut compression.dither, "Dithering").changed()
+                        | ui.checkbox(&mut compression.srgb, "sRGB")
+                            .on_hover_text("Measure the compression error in linear light")
+                            .changed()
+                    {
+                        self.request_texture_update = true;
+                    }
+                });
+
                 ui.separator();
                 let platforms = vec![
                     None,
                     Some(Platform::PS4),
                     Some(Platform::Xbox360),
                     Some(Platform::PSVita),
+                    Some(Platform::Switch),
+                    Some(Platform::WiiU),
+                    Some(Platform::Wii),
+                    Some(Platform::GameCube),
+                    Some(Platform::PS2),
+                    Some(Platform::PS3),
+                    Some(Platform::Xbox),
                 ];
 
                 ui.menu_button("Swizzle", |ui| {
@@ -454,6 +757,59 @@ impl TextureViewer {
                         }
                     }
                 });
+
+                ui.menu_button("Swizzle Options", |ui| {
+                    let swizzle_options = &mut self.texture_effects.swizzle_options;
+
+                    ui.label("Switch Block Height");
+                    for block_height in
+                        [None, Some(1), Some(2), Some(4), Some(8), Some(16), Some(32)]
+                    {
+                        if ui
+                            .selectable_value(
+                                &mut swizzle_options.switch_block_height,
+                                block_height,
+                                match block_height {
+                                    Some(block_height) => format!("{} GOBs", block_height),
+                                    None => "Auto".to_string(),
+                                },
+                            )
+                            .clicked()
+                        {
+                            self.request_texture_update = true;
+                        }
+                    }
+
+                    ui.separator();
+                    ui.label("Wii U Tile Mode");
+                    for tile_mode in (0..16).filter_map(|value| GX2TileMode::try_from(value).ok()) {
+                        if ui
+                            .selectable_value(
+                                &mut swizzle_options.wii_u_tile_mode,
+                                tile_mode,
+                                tile_mode.to_string(),
+                            )
+                            .clicked()
+                        {
+                            self.request_texture_update = true;
+                        }
+                    }
+
+                    let swizzle_changed = ui
+                        .horizontal(|ui| {
+                            ui.label("Wii U Swizzle");
+                            ui.add(
+                                egui::DragValue::new(&mut swizzle_options.wii_u_swizzle)
+                                    .hexadecimal(4, false, true),
+                            )
+                        })
+                        .inner
+                        .changed();
+
+                    if swizzle_changed {
+                        self.request_texture_update = true;
+                    }
+                });
             });
 
             if ui.button("About").clicked() {
@@ -467,12 +823,365 @@ impl TextureViewer {
         self.toasts.show(ctx);
     }
 
+    /// Display the raw data parameters, the texture is rebuilt whenever one changes.
+    fn display_raw_dialog(&mut self, ctx: &eframe::egui::Context) {
+        let Some(source_size) = self.raw_source.as_ref().map(|source| source.len()) else {
+            return;
+        };
+
+        let mut open = true;
+        let mut changed = false;
+        let mut offset_changed = false;
+        let raw_codec = &mut self.raw_codec;
+
+        egui::Window::new("Open as Raw")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("raw_parameters")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Offset");
+                        offset_changed = ui
+                            .add(egui::DragValue::new(&mut raw_codec.offset).range(0..=source_size))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("Width");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut raw_codec.width).range(1..=16384))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("Height");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut raw_codec.height).range(1..=16384))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("Pixel Format");
+                        egui::ComboBox::from_id_salt("raw_pixel_format")
+                            .selected_text(raw_codec.pixel_format.to_string())
+                            .show_ui(ui, |ui| {
+                                for format in RAW_PIXEL_FORMATS {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut raw_codec.pixel_format,
+                                            *format,
+                                            format.to_string(),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Mips");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut raw_codec.mip_levels).range(1..=16))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("Deswizzle");
+                        egui::ComboBox::from_id_salt("raw_platform")
+                            .selected_text(match raw_codec.platform {
+                                Some(platform) => platform.to_string(),
+                                None => "None".to_string(),
+                            })
+                            .show_ui(ui, |ui| {
+                                for platform in [
+                                    None,
+                                    Some(Platform::PS4),
+                                    Some(Platform::Xbox360),
+                                    Some(Platform::PSVita),
+                                    Some(Platform::Switch),
+                                    Some(Platform::WiiU),
+                                    Some(Platform::Wii),
+                                    Some(Platform::GameCube),
+                                    Some(Platform::PS2),
+                                    Some(Platform::PS3),
+                                    Some(Platform::Xbox),
+                                ] {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut raw_codec.platform,
+                                            platform,
+                                            match platform {
+                                                Some(p) => p.to_string(),
+                                                None => "None".to_string(),
+                                            },
+                                        )
+                                        .changed();
+                                }
+                            });
+                        ui.end_row();
+
+                        let swizzle_options = &mut raw_codec.swizzle_options;
+
+                        if raw_codec.platform == Some(Platform::Switch) {
+                            ui.label("Block Height");
+                            egui::ComboBox::from_id_salt("raw_switch_block_height")
+                                .selected_text(match swizzle_options.switch_block_height {
+                                    Some(block_height) => format!("{} GOBs", block_height),
+                                    None => "Auto".to_string(),
+                                })
+                                .show_ui(ui, |ui| {
+                                    for block_height in [
+                                        None,
+                                        Some(1),
+                                        Some(2),
+                                        Some(4),
+                                        Some(8),
+                                        Some(16),
+                                        Some(32),
+                                    ] {
+                                        changed |= ui
+                                            .selectable_value(
+                                                &mut swizzle_options.switch_block_height,
+                                                block_height,
+                                                match block_height {
+                                                    Some(block_height) => {
+                                                        format!("{} GOBs", block_height)
+                                                    }
+                                                    None => "Auto".to_string(),
+                                                },
+                                            )
+                                            .changed();
+                                    }
+                                });
+                            ui.end_row();
+                        }
+
+                        if raw_codec.platform == Some(Platform::WiiU) {
+                            ui.label("Tile Mode");
+                            egui::ComboBox::from_id_salt("raw_wii_u_tile_mode")
+                                .selected_text(swizzle_options.wii_u_tile_mode.to_string())
+                                .show_ui(ui, |ui| {
+                                    for tile_mode in (0..16)
+                                        .filter_map(|value| GX2TileMode::try_from(value).ok())
+                                    {
+                                        changed |= ui
+                                            .selectable_value(
+                                                &mut swizzle_options.wii_u_tile_mode,
+                                                tile_mode,
+                                                tile_mode.to_string(),
+                                            )
+                                            .changed();
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Swizzle");
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut swizzle_options.wii_u_swizzle)
+                                        .hexadecimal(4, false, true),
+                                )
+                                .changed();
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(error) = &self.raw_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.separator();
+                ui.label(format!(
+                    "Layouts matching the {} bytes after the offset:",
+                    source_size.saturating_sub(raw_codec.offset)
+                ));
+
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for guess in &self.raw_guesses {
+                            let label = format!(
+                                "{} x {} {} ({} mips)",
+                                guess.width, guess.height, guess.pixel_format, guess.mip_levels
+                            );
+
+                            if ui.selectable_label(false, label).clicked() {
+                                raw_codec.width = guess.width;
+                                raw_codec.height = guess.height;
+                                raw_codec.pixel_format = guess.pixel_format;
+                                raw_codec.mip_levels = guess.mip_levels;
+                                changed = true;
+                            }
+                        }
+                    });
+            });
+
+        if offset_changed {
+            self.update_raw_guesses();
+        }
+
+        if changed || offset_changed {
+            self.update_raw_texture();
+        }
+
+        if !open {
+            self.raw_source = None;
+            self.raw_guesses.clear();
+            self.raw_error = None;
+        }
+    }
+
+    fn update_raw_guesses(&mut self) {
+        let data_size = self.raw_source.as_ref().map_or(0, |source| {
+            source.len().saturating_sub(self.raw_codec.offset)
+        });
+
+        self.raw_guesses = guess_raw_layouts(data_size, RAW_PIXEL_FORMATS);
+    }
+
+    /// Rebuild the texture from the raw data and its current parameters.
+    fn update_raw_texture(&mut self) {
+        let Some(source) = &self.raw_source else {
+            return;
+        };
+
+        match self.raw_codec.load_from_memory(source) {
+            Ok(texture) => {
+                self.set_texture_source(texture);
+                self.raw_error = None;
+            }
+            Err(e) => self.raw_error = Some(e.to_string()),
+        }
+    }
+
+    /// Display the textures found by the scanner, selecting one opens it.
+    fn display_scan_dialog(&mut self, ctx: &eframe::egui::Context) {
+        if let Some(scan_task) = &self.scan_task {
+            let mut cancel = false;
+
+            egui::Window::new("Scan for Textures").show(ctx, |ui| {
+                ui.add(egui::ProgressBar::new(scan_task.get_progress()).show_percentage());
+                cancel = ui.button("Cancel").clicked();
+            });
+
+            if cancel {
+                scan_task.cancel();
+                self.scan_task = None;
+            } else if scan_task.is_finished() {
+                match self.scan_task.take().and_then(ScanTask::join) {
+                    Some((source, textures)) => {
+                        self.scanned_textures = textures;
+                        self.scan_source = Some(source);
+                    }
+                    None => {
+                        self.show_error("The texture scan failed!");
+                        self.log_error("The texture scan failed!");
+                    }
+                }
+            } else {
+                ctx.request_repaint();
+            }
+
+            return;
+        }
+
+        if self.scan_source.is_none() {
+            return;
+        }
+
+        let mut open = true;
+        let mut selected = None;
+        let mut extract = false;
+
+        egui::Window::new("Scan for Textures")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!("{} textures found", self.scanned_textures.len()));
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (index, texture) in self.scanned_textures.iter().enumerate() {
+                            let metadata = &texture.metadata;
+                            let label = format!(
+                                "0x{:08X} | {} | {} bytes | {} x {} | {} | Mips: {}",
+                                texture.offset,
+                                texture.extension.to_uppercase(),
+                                texture.size,
+                                metadata.width,
+                                metadata.height,
+                                metadata.pixel_format_info.pixel_format,
+                                metadata.mip_levels
+                            );
+
+                            if ui
+                                .selectable_label(
+                                    self.selected_scanned_texture == Some(index),
+                                    label,
+                                )
+                                .clicked()
+                            {
+                                selected = Some(index);
+                            }
+                        }
+                    });
+
+                ui.separator();
+
+                if ui
+                    .add_enabled(
+                        !self.scanned_textures.is_empty(),
+                        Button::new("Extract All"),
+                    )
+                    .clicked()
+                {
+                    extract = true;
+                }
+            });
+
+        if let (Some(index), Some(source)) = (selected, &self.scan_source) {
+            match self.scanned_textures[index].load(source, &self.codec_manager) {
+                Ok(texture) => {
+                    self.set_texture_source(texture);
+                    self.selected_scanned_texture = Some(index);
+                }
+                Err(e) => {
+                    self.show_error(&e.to_string());
+                    self.log_error(&e.to_string());
+                }
+            }
+        }
+
+        if extract {
+            if let (Some(path), Some(source)) =
+                (rfd::FileDialog::new().pick_folder(), &self.scan_source)
+            {
+                if let Err(e) = extract_textures(source, &self.scanned_textures, &path) {
+                    self.show_error(&e.to_string());
+                    self.log_error(&e.to_string());
+                }
+            }
+        }
+
+        if !open {
+            self.scan_source = None;
+            self.scanned_textures.clear();
+            self.selected_scanned_texture = None;
+        }
+    }
+
+    /// Show a texture that does not come from the selected file.
+    fn set_texture_source(&mut self, texture: Texture) {
+        self.current_mip = 0;
+        self.current_item = 0;
+        self.max_mip = texture.metadata.mip_levels - 1;
+        self.max_item = texture.metadata.array_size - 1;
+        self.texture_source = Some(texture);
+        self.request_texture_update = true;
+        self.reset_view = true;
+    }
+
     /// Update the display texture.
     fn update_display_texture(&mut self, ctx: &eframe::egui::Context) {
         if self.request_texture_source_update {
             let path = self.file_manager.get_selected_file().unwrap();
 
-            self.texture_source = match CodecManager::load_from_file(&self.codec_manager, &path) {
+            self.texture_source = match CodecManager::load_from_file(&self.codec_manager, path) {
                 Ok(texture) => Some(texture),
                 Err(e) => {
                     eprintln!("Error: {}", e);
@@ -516,7 +1225,7 @@ impl TextureViewer {
             // Get the RGBA8 pixels
             let rgba8_image = match self
                 .texture_effects
-                .get_transformed_rgba8_pixels(&image, self.display_compressed)
+                .get_transformed_rgba8_pixels(image, self.display_compressed)
             {
                 Ok(rgba8_image) => rgba8_image,
                 Err(e) => {
@@ -615,6 +1324,22 @@ impl TextureViewer {
         }
     }
 
+    /// Rebuild the codecs when their options change
+    fn set_codec_options(&mut self, codec_options: CodecOptions) {
+        if codec_options != self.codec_options {
+            // The texture index picks what is loaded, so the current file is reloaded
+            if codec_options.texture_index != self.codec_options.texture_index
+                && self.file_manager.get_selected_file().is_some()
+            {
+                self.request_texture_source_update = true;
+                self.request_texture_update = true;
+            }
+
+            self.codec_options = codec_options;
+            self.codec_manager = CodecManager::with_options(&codec_options);
+        }
+    }
+
     /// Display an error message as a notification.
     fn show_error(&mut self, message: &str) {
         self.toasts
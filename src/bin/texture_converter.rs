@@ -0,0 +1,10 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::process::ExitCode;
+
+/// Headless batch converter, see `texture_converter --help`
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    texture_viewer::cli::run(&args)
+}
@@ -0,0 +1,421 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::{
+    codecs::codec_manager::{CodecManager, CodecOptions},
+    graphics::{
+        mipmap_generator::{MipmapFilter, MipmapOptions},
+        pixel_format::PixelFormat,
+        swizzling::{wii_u::GX2TileMode, Platform},
+        texture_utility::TextureEffects,
+    },
+};
+
+const USAGE: &str = "\
+Usage: texture_converter [OPTIONS] <INPUT>... --output <DIR>
+
+Inputs can be files, directories or wildcard patterns (e.g. textures/*.dds).
+
+Options:
+  -o, --output <DIR>        Output directory (created if missing)
+  -e, --extension <EXT>     Output file extension (default: same as input)
+  -f, --format <FORMAT>     Target pixel format, e.g. R8G8B8A8, BC1, BC3 (default: R8G8B8A8)
+      --swizzle <PLATFORM>  Swizzle the output for a platform, e.g. PS4, Xbox360, PSVita
+      --deswizzle <PLATFORM>
+                            Deswizzle the input from a platform
+      --block-height <N>    Switch block height in GOBs: 1, 2, 4, 8, 16, 32 (default: from the size)
+      --tile-mode <N>       Wii U GX2TileMode value (default: 4, 2D tiled thin)
+      --wii-u-swizzle <N>   Wii U GX2Surface swizzle value (default: 0)
+      --rotate-left <N>     Rotate 90 degrees left N times
+      --rotate-right <N>    Rotate 90 degrees right N times
+      --flip-horizontal     Flip horizontally
+      --flip-vertical       Flip vertically
+      --channels <RGBA>     Channels to keep, e.g. RGB drops alpha (default: RGBA)
+      --mipmaps <FILTER>    Generate mipmaps with a filter: Box, Triangle, Kaiser, Lanczos
+      --max-mips <N>        Limit the number of generated mip levels
+      --alpha-coverage <REF>
+                            Preserve alpha test coverage of the mips for the reference (0.0 - 1.0)
+      --dds-header <HEADER> DDS header to write: Auto, Legacy, DX10 (default: Auto)
+      --ktx2-supercompression <SCHEME>
+                            KTX2 mip supercompression: None, Zstd, Zlib (default: None)
+      --texture-index <N>   Texture of BNTX and XPR2 files to load (default: all as an array)
+  -r, --recursive           Walk input directories recursively
+  -h, --help                Print this help
+";
+
+/// Options for a headless batch conversion
+pub struct BatchOptions {
+    pub inputs: Vec<String>,
+    pub output_dir: PathBuf,
+    pub output_extension: Option<String>,
+    pub recursive: bool,
+    pub effects: TextureEffects,
+    pub codec_options: CodecOptions,
+}
+
+/// Result of a batch conversion. Failures are collected per file instead of aborting the batch.
+#[derive(Default)]
+pub struct BatchReport {
+    pub converted: Vec<(PathBuf, PathBuf)>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// An input file and its path relative to the input it was found in
+struct InputFile {
+    path: PathBuf,
+    relative_path: PathBuf,
+}
+
+impl BatchOptions {
+    /// Parse the command line arguments (without the program name).
+    /// Returns `Ok(None)` if only the help was requested.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, Box<dyn Error>> {
+        let mut inputs = Vec::new();
+        let mut output_dir = None;
+        let mut output_extension = None;
+        let mut recursive = false;
+        let mut effects = TextureEffects::default();
+        let mut codec_options = CodecOptions::default();
+        let mut mipmaps: Option<MipmapOptions> = None;
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {}", name))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-o" | "--output" => output_dir = Some(PathBuf::from(value(arg)?)),
+                "-e" | "--extension" => {
+                    output_extension = Some(value(arg)?.trim_start_matches('.').to_lowercase())
+                }
+                "-f" | "--format" => effects.pixel_format = value(arg)?.parse::<PixelFormat>()?,
+                "--swizzle" => effects.swizzle = Some(value(arg)?.parse::<Platform>()?),
+                "--deswizzle" => effects.deswizzle = Some(value(arg)?.parse::<Platform>()?),
+                "--block-height" => {
+                    effects.swizzle_options.switch_block_height = Some(value(arg)?.parse()?)
+                }
+                "--tile-mode" => {
+                    effects.swizzle_options.wii_u_tile_mode = value(arg)?.parse::<GX2TileMode>()?
+                }
+                "--wii-u-swizzle" => {
+                    effects.swizzle_options.wii_u_swizzle = parse_integer(value(arg)?)?
+                }
+                "--rotate-left" => effects.rotate_90_left_count = value(arg)?.parse()?,
+                "--rotate-right" => effects.rotate_90_right_count = value(arg)?.parse()?,
+                "--flip-horizontal" => effects.flip_horizontal_count += 1,
+                "--flip-vertical" => effects.flip_vertical_count += 1,
+                "--channels" => effects.channel_filter = parse_channel_filter(value(arg)?)?,
+                "--mipmaps" => {
+                    mipmaps.get_or_insert_with(Default::default).filter =
+                        value(arg)?.parse::<MipmapFilter>()?
+                }
+                "--max-mips" => {
+                    mipmaps.get_or_insert_with(Default::default).max_levels =
+                        Some(value(arg)?.parse()?)
+                }
+                "--alpha-coverage" => {
+                    mipmaps
+                        .get_or_insert_with(Default::default)
+                        .alpha_coverage_reference = Some(value(arg)?.parse()?)
+                }
+                "--dds-header" => codec_options.dds_header_format = value(arg)?.parse()?,
+                "--ktx2-supercompression" => {
+                    codec_options.ktx2_supercompression = value(arg)?.parse()?
+                }
+                "--texture-index" => codec_options.texture_index = Some(value(arg)?.parse()?),
+                "-r" | "--recursive" => recursive = true,
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("Unknown option: {}", arg).into())
+                }
+                _ => inputs.push(arg.clone()),
+            }
+        }
+
+        if inputs.is_empty() {
+            return Err("No input files specified".into());
+        }
+
+        let output_dir = output_dir.ok_or("No output directory specified")?;
+        effects.mipmaps = mipmaps;
+
+        Ok(Some(Self {
+            inputs,
+            output_dir,
+            output_extension,
+            recursive,
+            effects,
+            codec_options,
+        }))
+    }
+}
+
+impl BatchReport {
+    pub fn has_failures(&self) -> bool {
+        !self.failed.is_empty()
+    }
+}
+
+/// Entry point of the command line converter
+pub fn run(args: &[String]) -> ExitCode {
+    let options = match BatchOptions::from_args(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let report = convert_batch(
+        &options,
+        &CodecManager::with_options(&options.codec_options),
+    );
+
+    for (source, destination) in &report.converted {
+        println!("{} -> {}", source.display(), destination.display());
+    }
+
+    for (source, error) in &report.failed {
+        eprintln!("Failed: {}: {}", source.display(), error);
+    }
+
+    println!(
+        "Converted {} file(s), {} failed",
+        report.converted.len(),
+        report.failed.len()
+    );
+
+    if report.has_failures() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Convert every input file and write the results into the output directory
+pub fn convert_batch(options: &BatchOptions, codec_manager: &CodecManager) -> BatchReport {
+    let mut report = BatchReport::default();
+    let extensions = codec_manager.get_registered_extensions();
+
+    let mut files = Vec::new();
+
+    for input in &options.inputs {
+        match collect_input_files(input, &extensions, options.recursive) {
+            Ok(found) if found.is_empty() => report
+                .failed
+                .push((PathBuf::from(input), "No matching files found".to_string())),
+            Ok(found) => files.extend(found),
+            Err(e) => report.failed.push((PathBuf::from(input), e.to_string())),
+        }
+    }
+
+    let mut destinations = HashSet::new();
+
+    for file in files {
+        let destination = get_output_path(
+            &options.output_dir,
+            &file.relative_path,
+            options.output_extension.as_deref(),
+        );
+
+        // Inputs with the same name in different directories would overwrite each other
+        if !destinations.insert(destination.clone()) {
+            report.failed.push((
+                file.path,
+                format!(
+                    "Output {} is already written by another input",
+                    destination.display()
+                ),
+            ));
+            continue;
+        }
+
+        match convert_file(codec_manager, &file.path, &destination, &options.effects) {
+            Ok(()) => report.converted.push((file.path, destination)),
+            Err(e) => report.failed.push((file.path, e.to_string())),
+        }
+    }
+
+    report
+}
+
+fn convert_file(
+    codec_manager: &CodecManager,
+    source: &Path,
+    destination: &Path,
+    effects: &TextureEffects,
+) -> Result<(), Box<dyn Error>> {
+    let texture = codec_manager.load_from_file(source)?;
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(codec_manager.save_to_file(destination, &texture, effects)?)
+}
+
+fn get_output_path(output_dir: &Path, relative_path: &Path, extension: Option<&str>) -> PathBuf {
+    let destination = output_dir.join(relative_path);
+
+    match extension {
+        Some(extension) => destination.with_extension(extension),
+        None => destination,
+    }
+}
+
+/// Expand a single input argument (file, directory or wildcard pattern) into files
+fn collect_input_files(
+    input: &str,
+    extensions: &[String],
+    recursive: bool,
+) -> Result<Vec<InputFile>, Box<dyn Error>> {
+    let path = Path::new(input);
+
+    if path.is_dir() {
+        let mut files = Vec::new();
+        walk_directory(path, path, extensions, recursive, &mut files)?;
+        return Ok(files);
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("Invalid input: {}", input))?;
+
+    if !file_name.contains(['*', '?']) {
+        if !path.is_file() {
+            return Err("File not found".into());
+        }
+
+        return Ok(vec![InputFile {
+            path: path.to_path_buf(),
+            relative_path: PathBuf::from(file_name),
+        }]);
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(parent)? {
+        let entry_path = entry?.path();
+
+        let Some(name) = entry_path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if entry_path.is_file() && matches_wildcard(file_name, name) {
+            files.push(InputFile {
+                relative_path: PathBuf::from(name),
+                path: entry_path,
+            });
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn walk_directory(
+    root: &Path,
+    directory: &Path,
+    extensions: &[String],
+    recursive: bool,
+    files: &mut Vec<InputFile>,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                walk_directory(root, &path, extensions, recursive, files)?;
+            }
+            continue;
+        }
+
+        let is_supported = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.contains(&ext.to_lowercase()));
+
+        if is_supported {
+            files.push(InputFile {
+                relative_path: path.strip_prefix(root)?.to_path_buf(),
+                path,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Match a file name against a pattern with `*` (any sequence) and `?` (any character)
+pub fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn parse_channel_filter(channels: &str) -> Result<(bool, bool, bool, bool), Box<dyn Error>> {
+    let channels = channels.to_lowercase();
+
+    if let Some(c) = channels.chars().find(|c| !"rgba".contains(*c)) {
+        return Err(format!("Unknown channel: {}", c).into());
+    }
+
+    Ok((
+        channels.contains('r'),
+        channels.contains('g'),
+        channels.contains('b'),
+        channels.contains('a'),
+    ))
+}
+
+/// Parse a decimal or 0x prefixed hexadecimal value
+fn parse_integer(value: &str) -> Result<u32, Box<dyn Error>> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => Ok(u32::from_str_radix(hex, 16)?),
+        None => Ok(value.parse()?),
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fs, path::PathBuf};
+
+use super::{check_data_size, get_checked_image_size, ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        astc_decoder::is_astc_hdr_block,
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// ARM ASTC container - https://github.com/ARM-software/astc-encoder/blob/main/Docs/FileFormat.md
+// A 16 byte header, then the blocks of every slice. There are no mipmaps or arrays, volumes
+// keep one slice per layer of blocks.
+const ASTC_MAGIC: &[u8; 4] = &[0x13, 0xAB, 0xA1, 0x5C];
+const ASTC_MAX_SIZE: u32 = 0xFFFFFF;
+const ASTC_BLOCK_SIZE: usize = 16;
+
+pub struct ASTCCodec;
+
+impl ImageCodec for ASTCCodec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        let (block_width, block_height) = pixel_format.get_astc_block_size().ok_or_else(|| {
+            TextureError::UnsupportedFormat(format!(
+                "ASTC does not support pixel format: {}",
+                pixel_format
+            ))
+        })?;
+
+        if metadata.array_size != 1 {
+            return Err(TextureError::UnsupportedFormat(
+                "ASTC does not support texture arrays or cubemaps!".to_string(),
+            ));
+        }
+
+        if metadata.width > ASTC_MAX_SIZE
+            || metadata.height > ASTC_MAX_SIZE
+            || metadata.depth > ASTC_MAX_SIZE
+        {
+            return Err(TextureError::DimensionMismatch(format!(
+                "ASTC texture too large: {}x{}x{}",
+                metadata.width, metadata.height, metadata.depth
+            )));
+        }
+
+        // Only the top mip level fits, which is every slice of a volume
+        let slices = if metadata.dimensions == TexDimension::Tex3D {
+            metadata.depth.max(1)
+        } else {
+            1
+        };
+        let slice_pitch = pixel_format.get_slice_pitch(metadata.width, metadata.height) as usize;
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(ASTC_MAGIC);
+        buffer.extend_from_slice(&[block_width as u8, block_height as u8, 1]);
+
+        for size in [metadata.width, metadata.height, slices] {
+            buffer.extend_from_slice(&size.to_le_bytes()[..3]);
+        }
+
+        for slice in 0..slices as usize {
+            let image = texture.images.get(slice).ok_or_else(|| {
+                TextureError::DimensionMismatch("ASTC texture is missing slices!".to_string())
+            })?;
+
+            if image.pixels.len() < slice_pitch {
+                return Err(TextureError::DimensionMismatch(format!(
+                    "ASTC image data too small: {} < {}",
+                    image.pixels.len(),
+                    slice_pitch
+                )));
+            }
+
+            buffer.extend_from_slice(&image.pixels[..slice_pitch]);
+        }
+
+        Ok(buffer)
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let mut reader = BinaryReader::new(source);
+
+        if reader.read_bytes(ASTC_MAGIC.len())? != ASTC_MAGIC {
+            return Err(TextureError::CorruptHeader(
+                "Invalid ASTC file identifier!".to_string(),
+            ));
+        }
+
+        let [block_width, block_height, block_depth] = reader.read_array::<3>()?;
+        let mut read_size = || -> Result<u32, TextureError> {
+            let [a, b, c] = reader.read_array::<3>()?;
+            Ok(u32::from_le_bytes([a, b, c, 0]))
+        };
+        let width = read_size()?;
+        let height = read_size()?;
+        let depth = read_size()?;
+
+        if block_depth != 1 {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported ASTC block size: {}x{}x{}",
+                block_width, block_height, block_depth
+            )));
+        }
+
+        let ldr_format = get_pixel_format(block_width as u32, block_height as u32, false)
+            .ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "Unsupported ASTC block size: {}x{}",
+                    block_width, block_height
+                ))
+            })?;
+
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(TextureError::CorruptHeader(format!(
+                "Invalid ASTC texture size: {}x{}x{}",
+                width, height, depth
+            )));
+        }
+
+        // 24 bit sizes can overflow the pitches, the data must be in the file before reading it
+        let slice_pitch = get_checked_image_size("ASTC", ldr_format, width, height, 1)?;
+        let data_size = get_checked_image_size("ASTC", ldr_format, width, height, depth.into())?;
+        check_data_size("ASTC", data_size, reader.remaining())?;
+        let data = reader.read_bytes(data_size)?;
+
+        // The header does not tell the profile, any HDR block needs it for the whole texture
+        let is_hdr = data.chunks_exact(ASTC_BLOCK_SIZE).any(is_astc_hdr_block);
+        let pixel_format_info = PixelFormatInfo {
+            pixel_format: get_pixel_format(block_width as u32, block_height as u32, is_hdr)
+                .unwrap_or(ldr_format),
+            pixel_data_type: if is_hdr {
+                PixelDataType::Float
+            } else {
+                PixelDataType::UNorm
+            },
+            color_space: ColorSpace::Linear,
+            is_premultiplied: false,
+        };
+
+        let images = data
+            .chunks_exact(slice_pitch)
+            .map(|slice| Image::new(width, height, pixel_format_info, slice.to_vec()))
+            .collect();
+
+        let dimensions = if depth > 1 {
+            TexDimension::Tex3D
+        } else {
+            TexDimension::Tex2D
+        };
+
+        let metadata = TexMetadata {
+            width,
+            height,
+            depth,
+            array_size: 1,
+            mip_levels: 1,
+            pixel_format_info,
+            alpha_mode: 0,
+            dimensions,
+            is_cubemap: false,
+            is_volumemap: dimensions == TexDimension::Tex3D,
+        };
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["astc"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(ASTC_MAGIC) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+/// ASTC pixel format of a 2D block footprint
+fn get_pixel_format(block_width: u32, block_height: u32, hdr: bool) -> Option<PixelFormat> {
+    let (ldr, hdr_format) = match (block_width, block_height) {
+        (4, 4) => (PixelFormat::Astc4x4, PixelFormat::Astc4x4Hdr),
+        (5, 4) => (PixelFormat::Astc5x4, PixelFormat::Astc5x4Hdr),
+        (5, 5) => (PixelFormat::Astc5x5, PixelFormat::Astc5x5Hdr),
+        (6, 5) => (PixelFormat::Astc6x5, PixelFormat::Astc6x5Hdr),
+        (6, 6) => (PixelFormat::Astc6x6, PixelFormat::Astc6x6Hdr),
+        (8, 5) => (PixelFormat::Astc8x5, PixelFormat::Astc8x5Hdr),
+        (8, 6) => (PixelFormat::Astc8x6, PixelFormat::Astc8x6Hdr),
+        (8, 8) => (PixelFormat::Astc8x8, PixelFormat::Astc8x8Hdr),
+        (10, 5) => (PixelFormat::Astc10x5, PixelFormat::Astc10x5Hdr),
+        (10, 6) => (PixelFormat::Astc10x6, PixelFormat::Astc10x6Hdr),
+        (10, 8) => (PixelFormat::Astc10x8, PixelFormat::Astc10x8Hdr),
+        (10, 10) => (PixelFormat::Astc10x10, PixelFormat::Astc10x10Hdr),
+        (12, 10) => (PixelFormat::Astc12x10, PixelFormat::Astc12x10Hdr),
+        (12, 12) => (PixelFormat::Astc12x12, PixelFormat::Astc12x12Hdr),
+        _ => return None,
+    };
+
+    Some(if hdr { hdr_format } else { ldr })
+}
@@ -0,0 +1,628 @@
+use std::{fs, path::PathBuf};
+
+use super::{check_mip_levels, ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        swizzling::{
+            switch::{
+                deswizzle_block_linear, get_block_height, get_mip_block_height, SwitchSwizzler,
+            },
+            Swizzable,
+        },
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// BNTX - https://github.com/gdkchan/BnTxx/blob/master/BnTxx/Formats/BinaryTexture.cs
+// A BNTX header, an NX header pointing to one BRTI block per texture, and a BRTD block holding
+// the block linear data. Every offset is absolute, and the relocation table lists the pointers,
+// so replaced textures are written in place.
+const BNTX_MAGIC: &[u8; 4] = b"BNTX";
+const NX_MAGIC: &[u8; 4] = b"NX  ";
+const BRTI_MAGIC: &[u8; 4] = b"BRTI";
+const BNTX_BOM: u16 = 0xFEFF;
+const NX_HEADER_OFFSET: usize = 0x20;
+
+// BRTI field offsets
+const BRTI_TILE_MODE: usize = 0x12;
+const BRTI_FORMAT: usize = 0x1C;
+const BRTI_TEXTURE_LAYOUT: usize = 0x34;
+const BRTI_IMAGE_SIZE: usize = 0x50;
+
+const TILE_MODE_BLOCK_LINEAR: u16 = 0;
+const BLOCK_HEIGHT_LOG2_MASK: u32 = 0x7;
+
+// SurfaceDim
+const SURFACE_DIM_1D: u32 = 0;
+const SURFACE_DIM_3D: u32 = 2;
+const SURFACE_DIM_CUBE: u32 = 3;
+const SURFACE_DIM_1D_ARRAY: u32 = 4;
+const SURFACE_DIM_CUBE_ARRAY: u32 = 8;
+
+#[derive(Default)]
+pub struct BNTXCodec {
+    /// Texture entry to load or replace. `None` loads every entry as array items, and replaces
+    /// all of them when saving.
+    pub texture_index: Option<usize>,
+    /// BNTX file the saved textures are written into. When saving to a file, the existing file
+    /// is used if this is not set.
+    pub source: Option<Vec<u8>>,
+}
+
+/// A BRTI texture entry
+struct BRTIEntry {
+    name: String,
+    offset: usize,
+    tile_mode: u16,
+    mip_levels: u32,
+    format: u32,
+    width: u32,
+    height: u32,
+    depth: u32,
+    array_length: u32,
+    texture_layout: u32,
+    image_size: usize,
+    surface_dim: u32,
+    mip_offsets_offset: usize,
+    mip_offsets: Vec<usize>,
+}
+
+impl BRTIEntry {
+    fn read(reader: &mut BinaryReader<'_>, offset: usize) -> Result<Self, TextureError> {
+        reader.seek(offset)?;
+
+        if reader.read_bytes(BRTI_MAGIC.len())? != BRTI_MAGIC {
+            return Err(TextureError::CorruptHeader(format!(
+                "Invalid BRTI block at {:#x}!",
+                offset
+            )));
+        }
+
+        reader.seek(offset + BRTI_TILE_MODE)?;
+        let tile_mode = reader.read_u16()?;
+        let _swizzle = reader.read_u16()?;
+        let mip_levels = (reader.read_u16()? as u32).max(1);
+        let _sample_count = reader.read_u32()?;
+        let format = reader.read_u32()?;
+        let _gpu_access_flags = reader.read_u32()?;
+        let width = reader.read_u32()?;
+        let height = reader.read_u32()?;
+        let depth = reader.read_u32()?.max(1);
+        let array_length = reader.read_u32()?.max(1);
+        let texture_layout = reader.read_u32()?;
+        let _texture_layout_2 = reader.read_u32()?;
+        reader.skip(20)?;
+        let image_size = reader.read_u32()? as usize;
+        let _alignment = reader.read_u32()?;
+        let _channel_types = reader.read_u32()?;
+        let surface_dim = reader.read_u32()?;
+        let name_offset = reader.read_u64()? as usize;
+        let _parent_offset = reader.read_u64()?;
+        let mip_offsets_offset = reader.read_u64()? as usize;
+
+        check_mip_levels("BNTX", mip_levels, width, height, depth)?;
+
+        // Every layer has at least a byte in the data
+        if array_length as usize > image_size.min(reader.len()) {
+            return Err(TextureError::CorruptHeader(format!(
+                "BNTX array length {} is larger than the data!",
+                array_length
+            )));
+        }
+
+        reader.seek(mip_offsets_offset)?;
+        let mip_offsets = (0..mip_levels)
+            .map(|_| reader.read_u64().map(|offset| offset as usize))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Strings are prefixed with their length
+        reader.seek(name_offset)?;
+        let name_length = reader.read_u16()? as usize;
+        let name = String::from_utf8_lossy(reader.read_bytes(name_length)?).into_owned();
+
+        Ok(Self {
+            name,
+            offset,
+            tile_mode,
+            mip_levels,
+            format,
+            width,
+            height,
+            depth,
+            array_length,
+            texture_layout,
+            image_size,
+            surface_dim,
+            mip_offsets_offset,
+            mip_offsets,
+        })
+    }
+
+    fn get_image_count(&self) -> Result<usize, TextureError> {
+        self.array_length
+            .checked_mul(self.mip_levels)
+            .map(|count| count as usize)
+            .ok_or_else(|| {
+                TextureError::CorruptHeader(format!(
+                    "BNTX texture {} has too many images!",
+                    self.name
+                ))
+            })
+    }
+
+    fn get_block_height(&self) -> u32 {
+        1 << (self.texture_layout & BLOCK_HEIGHT_LOG2_MASK)
+    }
+
+    fn get_metadata(&self) -> Result<TexMetadata, TextureError> {
+        let pixel_format_info =
+            get_pixel_format_info_from_bntx_format(self.format).ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "Unsupported BNTX format: {:#06x}",
+                    self.format
+                ))
+            })?;
+
+        let dimensions = match self.surface_dim {
+            SURFACE_DIM_1D | SURFACE_DIM_1D_ARRAY => TexDimension::Tex1D,
+            SURFACE_DIM_3D => {
+                return Err(TextureError::UnsupportedFormat(
+                    "BNTX 3D textures are not supported!".to_string(),
+                ))
+            }
+            _ => TexDimension::Tex2D,
+        };
+
+        Ok(TexMetadata {
+            width: self.width,
+            height: self.height,
+            depth: 1,
+            array_size: self.array_length,
+            mip_levels: self.mip_levels,
+            pixel_format_info,
+            alpha_mode: 0,
+            dimensions,
+            is_cubemap: self.surface_dim == SURFACE_DIM_CUBE
+                || self.surface_dim == SURFACE_DIM_CUBE_ARRAY,
+            is_volumemap: false,
+        })
+    }
+
+    /// Deswizzle every array layer and mip level, in the order of `Texture::images`
+    fn read_texture(&self, reader: &BinaryReader<'_>) -> Result<Texture, TextureError> {
+        if self.tile_mode != TILE_MODE_BLOCK_LINEAR {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported BNTX tile mode: {}",
+                self.tile_mode
+            )));
+        }
+
+        if self.depth > 1 {
+            return Err(TextureError::UnsupportedFormat(
+                "BNTX 3D textures are not supported!".to_string(),
+            ));
+        }
+
+        let metadata = self.get_metadata()?;
+        let pixel_format_info = metadata.pixel_format_info;
+        let pixel_format = pixel_format_info.pixel_format;
+        let block_height = self.get_block_height();
+        let layer_size = self.image_size / self.array_length as usize;
+        let mut images = Vec::with_capacity(self.get_image_count()?);
+
+        for layer in 0..self.array_length as usize {
+            for (mip, &mip_offset) in self.mip_offsets.iter().enumerate() {
+                let mip_width = (self.width >> mip).max(1);
+                let mip_height = (self.height >> mip).max(1);
+                let mip_block_height = get_mip_block_height(
+                    mip_height.div_ceil(pixel_format.get_block_height()),
+                    block_height,
+                );
+
+                let offset = mip_offset + layer * layer_size;
+                let data = reader.slice(offset, reader.len().saturating_sub(offset))?;
+
+                images.push(Image::new(
+                    mip_width,
+                    mip_height,
+                    pixel_format_info,
+                    deswizzle_block_linear(
+                        data,
+                        mip_width,
+                        mip_height,
+                        pixel_format,
+                        mip_block_height,
+                    )?,
+                ));
+            }
+        }
+
+        Ok(Texture { metadata, images })
+    }
+
+    /// Swizzle a texture with the same size, mips and layers over this entry's data
+    fn write_texture(&self, bntx: &mut [u8], texture: &Texture) -> Result<(), TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format_info = metadata.pixel_format_info;
+        let pixel_format = pixel_format_info.pixel_format;
+
+        if metadata.width != self.width
+            || metadata.height != self.height
+            || metadata.mip_levels != self.mip_levels
+            || metadata.array_size != self.array_length
+        {
+            return Err(TextureError::DimensionMismatch(format!(
+                "BNTX texture {} is {}x{} with {} mips and {} layers, the new texture must match",
+                self.name, self.width, self.height, self.mip_levels, self.array_length
+            )));
+        }
+
+        if self.tile_mode != TILE_MODE_BLOCK_LINEAR {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported BNTX tile mode: {}",
+                self.tile_mode
+            )));
+        }
+
+        let format =
+            get_bntx_format_from_pixel_format_info(pixel_format_info).ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "BNTX does not support pixel format: {}",
+                    pixel_format
+                ))
+            })?;
+
+        let block_height =
+            get_block_height(metadata.height.div_ceil(pixel_format.get_block_height()));
+        let swizzler = SwitchSwizzler {
+            block_height: Some(block_height),
+        };
+
+        let data = swizzler.swizzle_texture(texture)?;
+
+        if data.len() > self.image_size {
+            return Err(TextureError::CorruptHeader(format!(
+                "BNTX texture {} does not fit in its data: {} > {}",
+                self.name,
+                data.len(),
+                self.image_size
+            )));
+        }
+
+        // The new data starts where the old one did, the rest of the old space is cleared
+        let data_offset = self.mip_offsets[0];
+        let old_data = bntx
+            .get_mut(data_offset..data_offset + self.image_size)
+            .ok_or_else(|| {
+                TextureError::CorruptHeader(format!(
+                    "BNTX texture {} data is out of bounds!",
+                    self.name
+                ))
+            })?;
+
+        old_data.fill(0);
+        old_data[..data.len()].copy_from_slice(&data);
+
+        for mip in 0..self.mip_levels {
+            let mip_offset = data_offset
+                + swizzler.get_mip_chain_size(
+                    metadata.width,
+                    metadata.height,
+                    mip,
+                    pixel_format,
+                )?;
+            let offset = self.mip_offsets_offset + mip as usize * 8;
+
+            bntx[offset..offset + 8].copy_from_slice(&(mip_offset as u64).to_le_bytes());
+        }
+
+        let texture_layout =
+            (self.texture_layout & !BLOCK_HEIGHT_LOG2_MASK) | block_height.trailing_zeros();
+
+        for (field, value) in [
+            (BRTI_FORMAT, format),
+            (BRTI_TEXTURE_LAYOUT, texture_layout),
+            (BRTI_IMAGE_SIZE, data.len() as u32),
+        ] {
+            let offset = self.offset + field;
+            bntx[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the headers and every BRTI entry
+fn read_entries(source: &[u8]) -> Result<Vec<BRTIEntry>, TextureError> {
+    let mut reader = BinaryReader::new(source);
+
+    if reader.read_bytes(BNTX_MAGIC.len())? != BNTX_MAGIC {
+        return Err(TextureError::CorruptHeader(
+            "Invalid BNTX file identifier!".to_string(),
+        ));
+    }
+
+    reader.seek(0x0C)?;
+    let bom = reader.read_u16()?;
+
+    if bom != BNTX_BOM {
+        return Err(TextureError::UnsupportedFormat(
+            "Big endian BNTX files are not supported!".to_string(),
+        ));
+    }
+
+    reader.seek(NX_HEADER_OFFSET)?;
+
+    if reader.read_bytes(NX_MAGIC.len())? != NX_MAGIC {
+        return Err(TextureError::CorruptHeader(
+            "Invalid BNTX NX header!".to_string(),
+        ));
+    }
+
+    let texture_count = reader.read_u32()? as usize;
+    let info_pointers_offset = reader.read_u64()? as usize;
+
+    if texture_count > reader.len() / 8 {
+        return Err(TextureError::CorruptHeader(format!(
+            "BNTX texture count {} is larger than the file!",
+            texture_count
+        )));
+    }
+
+    let brti_offsets = (0..texture_count)
+        .map(|index| {
+            reader.seek(info_pointers_offset + index * 8)?;
+            reader.read_u64().map(|offset| offset as usize)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    brti_offsets
+        .into_iter()
+        .map(|offset| BRTIEntry::read(&mut reader, offset))
+        .collect()
+}
+
+/// Names of the textures of a BNTX file, in the order of their entries
+pub fn get_texture_names(source: &[u8]) -> Result<Vec<String>, TextureError> {
+    Ok(read_entries(source)?
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect())
+}
+
+impl BNTXCodec {
+    fn get_entries<'a>(&self, entries: &'a [BRTIEntry]) -> Result<&'a [BRTIEntry], TextureError> {
+        match self.texture_index {
+            Some(index) => entries.get(index..index + 1).ok_or_else(|| {
+                TextureError::InvalidParameter(format!(
+                    "BNTX texture index out of bounds: {}",
+                    index
+                ))
+            }),
+            None if entries.is_empty() => Err(TextureError::CorruptHeader(
+                "BNTX file has no textures!".to_string(),
+            )),
+            None => Ok(entries),
+        }
+    }
+
+    /// Replace textures of an existing BNTX file, the other entries are kept as they are
+    pub fn replace_textures(
+        &self,
+        source: &[u8],
+        texture: &Texture,
+    ) -> Result<Vec<u8>, TextureError> {
+        let all_entries = read_entries(source)?;
+        let entries = self.get_entries(&all_entries)?;
+        let mut bntx = source.to_vec();
+        let mut first_image = 0;
+        let mut first_item = 0;
+
+        for entry in entries {
+            let image_count = entry.get_image_count()?;
+            let images = texture
+                .images
+                .get(first_image..first_image + image_count)
+                .ok_or_else(|| {
+                    TextureError::DimensionMismatch(format!(
+                        "The texture has fewer array items than the BNTX entries: {}",
+                        first_item + entry.array_length
+                    ))
+                })?;
+
+            let entry_texture = Texture {
+                metadata: TexMetadata {
+                    array_size: entry.array_length,
+                    ..texture.metadata
+                },
+                images: images.to_vec(),
+            };
+
+            entry.write_texture(&mut bntx, &entry_texture)?;
+            first_image += image_count;
+            first_item += entry.array_length;
+        }
+
+        if first_item != texture.metadata.array_size {
+            return Err(TextureError::DimensionMismatch(format!(
+                "The texture has {} array items, the BNTX entries have {}",
+                texture.metadata.array_size, first_item
+            )));
+        }
+
+        Ok(bntx)
+    }
+}
+
+impl ImageCodec for BNTXCodec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let source = self.source.as_ref().ok_or_else(|| {
+            TextureError::InvalidParameter(
+                "BNTX textures can only be saved into an existing BNTX file!".to_string(),
+            )
+        })?;
+
+        self.replace_textures(source, texture)
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = match &self.source {
+            Some(source) => self.replace_textures(source, texture)?,
+            None => {
+                let source = fs::read(&filepath).map_err(|e| {
+                    TextureError::InvalidParameter(format!(
+                        "BNTX textures can only be saved into an existing BNTX file: {}",
+                        e
+                    ))
+                })?;
+
+                self.replace_textures(&source, texture)?
+            }
+        };
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let all_entries = read_entries(source)?;
+        let entries = self.get_entries(&all_entries)?;
+        let reader = BinaryReader::new(source);
+
+        // Every texture becomes an array item, so they must all look the same
+        let first = &entries[0];
+
+        if entries.iter().any(|entry| {
+            entry.width != first.width
+                || entry.height != first.height
+                || entry.mip_levels != first.mip_levels
+                || entry.format != first.format
+                || entry.surface_dim != first.surface_dim
+        }) {
+            return Err(TextureError::DimensionMismatch(format!(
+                "BNTX textures of different sizes or formats cannot be loaded as one array, pick one of: {}",
+                entries
+                    .iter()
+                    .map(|entry| entry.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let mut metadata = first.get_metadata()?;
+        let mut images = Vec::new();
+
+        for entry in entries {
+            images.extend(entry.read_texture(&reader)?.images);
+        }
+
+        metadata.array_size = entries
+            .iter()
+            .try_fold(0u32, |array_size, entry| {
+                array_size.checked_add(entry.array_length)
+            })
+            .ok_or_else(|| {
+                TextureError::CorruptHeader("BNTX textures have too many layers!".to_string())
+            })?;
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["bntx"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(BNTX_MAGIC) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+// NVN surface formats - https://github.com/KillzXGaming/Switch-Toolbox
+// The high byte is the format, the low byte the channel type.
+const CHANNEL_UNORM: u32 = 0x01;
+const CHANNEL_SNORM: u32 = 0x02;
+const CHANNEL_UINT: u32 = 0x03;
+const CHANNEL_SINT: u32 = 0x04;
+const CHANNEL_FLOAT: u32 = 0x05;
+const CHANNEL_SRGB: u32 = 0x06;
+const CHANNEL_UFLOAT: u32 = 0x0A;
+
+/// Preferred formats come first, they are used when saving
+const BNTX_FORMAT_TABLE: &[(u32, PixelFormat)] = &[
+    (0x02, PixelFormat::R8),
+    (0x07, PixelFormat::B5G6R5),
+    (0x09, PixelFormat::R8G8),
+    (0x0A, PixelFormat::R16),
+    (0x0B, PixelFormat::R8G8B8A8),
+    (0x0C, PixelFormat::B8G8R8A8),
+    (0x0E, PixelFormat::R10G10B10A2),
+    (0x0F, PixelFormat::R11G11B10),
+    (0x1A, PixelFormat::BC1),
+    (0x1B, PixelFormat::BC2),
+    (0x1C, PixelFormat::BC3),
+    (0x1D, PixelFormat::BC4),
+    (0x1E, PixelFormat::BC5),
+    (0x1F, PixelFormat::BC6H),
+    (0x20, PixelFormat::BC7),
+];
+
+fn get_pixel_format_info_from_bntx_format(format: u32) -> Option<PixelFormatInfo> {
+    let (_, pixel_format) = BNTX_FORMAT_TABLE
+        .iter()
+        .find(|(bntx_format, _)| *bntx_format == format >> 8)?;
+
+    let channel_type = format & 0xFF;
+
+    let pixel_data_type = match channel_type {
+        CHANNEL_UNORM | CHANNEL_SRGB => PixelDataType::UNorm,
+        CHANNEL_SNORM => PixelDataType::SNorm,
+        CHANNEL_UINT => PixelDataType::UInt,
+        CHANNEL_SINT => PixelDataType::SInt,
+        // BC6H is unsigned as UFLOAT and signed as FLOAT
+        CHANNEL_FLOAT if *pixel_format == PixelFormat::BC6H => PixelDataType::SFloat,
+        CHANNEL_FLOAT | CHANNEL_UFLOAT => PixelDataType::Float,
+        _ => return None,
+    };
+
+    Some(PixelFormatInfo {
+        pixel_format: *pixel_format,
+        pixel_data_type,
+        color_space: if channel_type == CHANNEL_SRGB {
+            ColorSpace::SRGB
+        } else {
+            ColorSpace::Linear
+        },
+        is_premultiplied: false,
+    })
+}
+
+fn get_bntx_format_from_pixel_format_info(pixel_format_info: PixelFormatInfo) -> Option<u32> {
+    let (bntx_format, pixel_format) = BNTX_FORMAT_TABLE
+        .iter()
+        .find(|(_, pixel_format)| *pixel_format == pixel_format_info.pixel_format)?;
+
+    let channel_type = match (
+        pixel_format_info.color_space,
+        pixel_format_info.pixel_data_type,
+    ) {
+        (ColorSpace::SRGB, _) => CHANNEL_SRGB,
+        (_, PixelDataType::Float) if *pixel_format == PixelFormat::BC6H => CHANNEL_UFLOAT,
+        (_, PixelDataType::UNorm) => CHANNEL_UNORM,
+        (_, PixelDataType::SNorm) => CHANNEL_SNORM,
+        (_, PixelDataType::UInt) => CHANNEL_UINT,
+        (_, PixelDataType::SInt) => CHANNEL_SINT,
+        (_, PixelDataType::Float | PixelDataType::SFloat) => CHANNEL_FLOAT,
+    };
+
+    Some((bntx_format << 8) | channel_type)
+}
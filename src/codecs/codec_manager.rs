@@ -1,11 +1,36 @@
-use std::{collections::HashMap, error::Error, path::Path};
+use std::{collections::HashMap, fs, path::Path};
 
 use crate::graphics::{texture::Texture, texture_utility::TextureEffects};
 
 use super::{
-    bmp::BMPCodec, dds::DDSCodec, hdr::HDRCodec, jpeg::JPEGCodec, png::PNGCodec, tga::TGACodec,
-    tiff::TIFFCodec, ImageCodec,
+    astc::ASTCCodec,
+    bmp::BMPCodec,
+    bntx::BNTXCodec,
+    dds::{DDSCodec, DDSHeaderFormat},
+    gnf::GNFCodec,
+    gxt::GXTCodec,
+    hdr::HDRCodec,
+    jpeg::JPEGCodec,
+    ktx::KTXCodec,
+    ktx2::{KTX2Codec, KTX2Supercompression},
+    png::PNGCodec,
+    tga::TGACodec,
+    tiff::TIFFCodec,
+    xpr2::XPR2Codec,
+    ImageCodec,
 };
+use crate::error::TextureError;
+
+/// Settings of the built-in codecs, see `CodecManager::with_options`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CodecOptions {
+    /// Header written when saving DDS files
+    pub dds_header_format: DDSHeaderFormat,
+    /// Supercompression of the mip levels when saving KTX2 files
+    pub ktx2_supercompression: KTX2Supercompression,
+    /// Texture entry loaded from BNTX and XPR2 files, `None` loads every entry as array items
+    pub texture_index: Option<usize>,
+}
 
 /// Codec manager is flexible. It can be used to load and save textures from different formats, and additional custom codecs can be added.
 pub struct CodecManager {
@@ -16,15 +41,7 @@ pub struct CodecManager {
 impl Default for CodecManager {
     /// Create a new codec manager with all the built-in codecs registered
     fn default() -> Self {
-        let mut codec_manager = CodecManager::new();
-        codec_manager.register_codec(PNGCodec);
-        codec_manager.register_codec(JPEGCodec);
-        codec_manager.register_codec(DDSCodec);
-        codec_manager.register_codec(BMPCodec);
-        codec_manager.register_codec(TGACodec);
-        codec_manager.register_codec(TIFFCodec);
-        codec_manager.register_codec(HDRCodec);
-        codec_manager
+        CodecManager::with_options(&CodecOptions::default())
     }
 }
 
@@ -36,6 +53,35 @@ impl CodecManager {
         }
     }
 
+    /// Create a codec manager with all the built-in codecs registered and configured
+    pub fn with_options(options: &CodecOptions) -> Self {
+        let mut codec_manager = CodecManager::new();
+        codec_manager.register_codec(PNGCodec);
+        codec_manager.register_codec(JPEGCodec);
+        codec_manager.register_codec(DDSCodec {
+            header_format: options.dds_header_format,
+        });
+        codec_manager.register_codec(BMPCodec);
+        codec_manager.register_codec(TGACodec);
+        codec_manager.register_codec(TIFFCodec);
+        codec_manager.register_codec(HDRCodec);
+        codec_manager.register_codec(KTXCodec);
+        codec_manager.register_codec(KTX2Codec {
+            supercompression: options.ktx2_supercompression,
+        });
+        codec_manager.register_codec(GXTCodec);
+        codec_manager.register_codec(GNFCodec);
+        codec_manager.register_codec(BNTXCodec {
+            texture_index: options.texture_index,
+            ..Default::default()
+        });
+        codec_manager.register_codec(XPR2Codec {
+            texture_index: options.texture_index,
+        });
+        codec_manager.register_codec(ASTCCodec);
+        codec_manager
+    }
+
     /// Register a new codec
     pub fn register_codec<T: ImageCodec + 'static>(&mut self, codec: T) {
         // Store the codec and map its supported extensions
@@ -55,16 +101,52 @@ impl CodecManager {
             .map(|codec| codec.as_ref())
     }
 
-    /// Load a texture from a file
-    pub fn load_from_file(&self, path: &Path) -> Result<Texture, Box<dyn Error>> {
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or("No file extension")?;
+    /// Get the codec whose probe scores the data highest. The codec of the extension wins ties
+    /// and is the fallback when no codec recognizes the data.
+    pub fn get_codec_for_data(&self, source: &[u8], ext: Option<&str>) -> Option<&dyn ImageCodec> {
+        let ext_codec = ext.and_then(|ext| self.get_codec_for_extension(ext));
+        let (best_score, best_codec) = self
+            .codecs
+            .iter()
+            .map(|codec| (codec.probe(source), codec.as_ref()))
+            .fold((0, None), |best, (score, codec)| {
+                if score > best.0 {
+                    (score, Some(codec))
+                } else {
+                    best
+                }
+            });
 
-        self.get_codec_for_extension(ext)
-            .ok_or_else(|| format!("Unsupported format: {}", ext))?
-            .load_from_file(path.to_path_buf())
+        match ext_codec {
+            Some(codec) if best_score == 0 || codec.probe(source) == best_score => Some(codec),
+            _ => best_codec,
+        }
+    }
+
+    /// Load a texture from memory, the format is detected from the data itself.
+    /// The extension, if any, is only used when the data is ambiguous.
+    pub fn load_from_memory(
+        &self,
+        source: &[u8],
+        ext: Option<&str>,
+    ) -> Result<Texture, TextureError> {
+        self.get_codec_for_data(source, ext)
+            .ok_or_else(|| match ext {
+                Some(ext) => {
+                    TextureError::UnsupportedFormat(format!("Unsupported format: {}", ext))
+                }
+                None => TextureError::UnsupportedFormat(
+                    "Unknown format and no file extension".to_string(),
+                ),
+            })?
+            .load_from_memory(source)
+    }
+
+    /// Load a texture from a file, the format is detected from its content then its extension
+    pub fn load_from_file(&self, path: &Path) -> Result<Texture, TextureError> {
+        let ext = path.extension().and_then(|e| e.to_str());
+
+        self.load_from_memory(&fs::read(path)?, ext)
     }
 
     /// Save a texture to a file
@@ -73,16 +155,16 @@ impl CodecManager {
         path: &Path,
         texture: &Texture,
         effects: &TextureEffects,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), TextureError> {
         let ext = path
             .extension()
             .and_then(|e| e.to_str())
-            .ok_or("No file extension")?;
+            .ok_or_else(|| TextureError::InvalidParameter("No file extension".to_string()))?;
 
-        let transformed_texture = texture.new_transformed_texture(&effects)?;
+        let transformed_texture = texture.new_transformed_texture(effects)?;
 
         self.get_codec_for_extension(ext)
-            .ok_or_else(|| format!("Unsupported format: {}", ext))?
+            .ok_or_else(|| TextureError::UnsupportedFormat(format!("Unsupported format: {}", ext)))?
             .save_to_file(path.to_path_buf(), &transformed_texture)
     }
 
@@ -1,30 +1,142 @@
-use std::{error::Error, fs, path::PathBuf};
+use std::{fmt, fs, path::PathBuf, str::FromStr};
 
-use super::ImageCodec;
+use super::{ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
 use crate::graphics::{
     crate_directxtex_utility::DirectXTexUtility,
-    texture::{Image, TexMetadata, Texture},
+    texture::{Image, TexDimension, TexMetadata, Texture},
 };
-use directxtex::{ScratchImage, CP_FLAGS_NONE, DDS_FLAGS_NONE, DXGI_FORMAT};
+use directxtex::{
+    ScratchImage, CP_FLAGS_NONE, DDS_FLAGS, DDS_FLAGS_FORCE_DX10_EXT_MISC2,
+    DDS_FLAGS_FORCE_DX9_LEGACY, DDS_FLAGS_NONE, DXGI_FORMAT, TEX_ALPHA_MODE,
+    TEX_ALPHA_MODE_PREMULTIPLIED, TEX_ALPHA_MODE_UNKNOWN, TEX_MISC_TEXTURECUBE,
+};
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+// Magic (4 bytes) + offset of ddspf.fourCC inside DDS_HEADER (80 bytes)
+const DDS_FOURCC_OFFSET: usize = 84;
+
+/// Which DDS header to write when saving
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DDSHeaderFormat {
+    /// Legacy header when the texture fits in one, DX10 extended header otherwise
+    #[default]
+    Auto,
+    /// DX9 FourCC/pixel mask header. Can't store arrays, sRGB or alpha mode.
+    Legacy,
+    /// Always write the DX10 extended header, including the alpha mode
+    DX10,
+}
+
+impl fmt::Display for DDSHeaderFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DDSHeaderFormat::Auto => "Auto",
+            DDSHeaderFormat::Legacy => "Legacy",
+            DDSHeaderFormat::DX10 => "DX10",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DDSHeaderFormat {
+    type Err = String;
+
+    /// Parse a header format from its name (case insensitive), e.g. "auto" or "DX10"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let header_format = match s.trim().to_lowercase().as_str() {
+            "auto" => DDSHeaderFormat::Auto,
+            "legacy" | "dx9" => DDSHeaderFormat::Legacy,
+            "dx10" => DDSHeaderFormat::DX10,
+            _ => return Err(format!("Unknown DDS header format: {}", s)),
+        };
+
+        Ok(header_format)
+    }
+}
+
+#[derive(Default)]
+pub struct DDSCodec {
+    pub header_format: DDSHeaderFormat,
+}
+
+impl DDSCodec {
+    fn get_dds_flags(&self) -> DDS_FLAGS {
+        match self.header_format {
+            DDSHeaderFormat::Auto => DDS_FLAGS_NONE,
+            DDSHeaderFormat::Legacy => DDS_FLAGS_FORCE_DX9_LEGACY,
+            DDSHeaderFormat::DX10 => DDS_FLAGS_FORCE_DX10_EXT_MISC2,
+        }
+    }
+
+    fn get_alpha_mode(texture: &Texture) -> TEX_ALPHA_MODE {
+        let alpha_mode = TEX_ALPHA_MODE::from(texture.metadata.alpha_mode);
+
+        if alpha_mode == TEX_ALPHA_MODE_UNKNOWN
+            && texture.metadata.pixel_format_info.is_premultiplied
+        {
+            TEX_ALPHA_MODE_PREMULTIPLIED
+        } else {
+            alpha_mode
+        }
+    }
 
-pub struct DDSCodec;
+    fn is_dx10_header(dds: &[u8]) -> bool {
+        dds.get(DDS_FOURCC_OFFSET..DDS_FOURCC_OFFSET + 4) == Some(b"DX10")
+    }
+}
 
 impl ImageCodec for DDSCodec {
-    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, Box<dyn Error>> {
-        let new_tex_metadata = directxtex::TexMetadata {
-            width: texture.metadata.width as usize,
-            height: texture.metadata.height as usize,
-            depth: texture.metadata.depth as usize,
-            array_size: texture.metadata.array_size as usize,
-            mip_levels: texture.metadata.mip_levels as usize,
-            misc_flags: 0,
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+
+        if metadata.is_cubemap && (metadata.array_size == 0 || metadata.array_size % 6 != 0) {
+            return Err(TextureError::DimensionMismatch(
+                "Cubemap array size must be a multiple of 6!".to_string(),
+            ));
+        }
+
+        let is_single_cubemap = metadata.is_cubemap && metadata.array_size == 6;
+
+        if self.header_format == DDSHeaderFormat::Legacy
+            && metadata.array_size > 1
+            && !is_single_cubemap
+        {
+            return Err(TextureError::UnsupportedFormat(
+                "Texture arrays can't be stored with a legacy DDS header!".to_string(),
+            ));
+        }
+
+        let format =
+            DirectXTexUtility::get_dxgi_format_from_pixel_format_info(metadata.pixel_format_info);
+
+        if format == DXGI_FORMAT::DXGI_FORMAT_UNKNOWN {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "DDS does not support pixel format: {}",
+                metadata.pixel_format_info.pixel_format
+            )));
+        }
+
+        let mut new_tex_metadata = directxtex::TexMetadata {
+            width: metadata.width as usize,
+            height: metadata.height as usize,
+            depth: metadata.depth as usize,
+            array_size: metadata.array_size as usize,
+            mip_levels: metadata.mip_levels as usize,
+            misc_flags: if metadata.is_cubemap && metadata.dimensions == TexDimension::Tex2D {
+                TEX_MISC_TEXTURECUBE.bits()
+            } else {
+                0
+            },
             misc_flags2: 0,
-            format: DirectXTexUtility::get_dxgi_format_from_pixel_format(
-                texture.metadata.pixel_format_info.pixel_format,
-            ),
-            dimension: DirectXTexUtility::get_texture_dimension(texture.metadata.dimensions),
+            format,
+            dimension: DirectXTexUtility::get_texture_dimension(metadata.dimensions),
         };
 
+        let alpha_mode = Self::get_alpha_mode(texture);
+        new_tex_metadata.set_alpha_mode(alpha_mode);
+
         let mut new_images = Vec::new();
 
         for img in &texture.images {
@@ -34,6 +146,14 @@ impl ImageCodec for DDSCodec {
                 CP_FLAGS_NONE,
             )?;
 
+            if img.pixels.len() < pitch.slice {
+                return Err(TextureError::DimensionMismatch(format!(
+                    "Image data too small: {} < {}",
+                    img.pixels.len(),
+                    pitch.slice
+                )));
+            }
+
             let new_image = directxtex::Image {
                 width: img.width as usize,
                 height: img.height as usize,
@@ -46,24 +166,35 @@ impl ImageCodec for DDSCodec {
             new_images.push(new_image);
         }
 
-        let blob = directxtex::save_dds(&new_images, &new_tex_metadata, DDS_FLAGS_NONE)?;
-        let buffer = blob.buffer();
+        let mut blob = directxtex::save_dds(&new_images, &new_tex_metadata, self.get_dds_flags())?;
 
-        Ok(buffer.to_vec())
+        // DirectXTex leaves the alpha mode out of DX10 headers unless asked to
+        if self.header_format == DDSHeaderFormat::Auto
+            && alpha_mode != TEX_ALPHA_MODE_UNKNOWN
+            && Self::is_dx10_header(blob.buffer())
+        {
+            blob = directxtex::save_dds(
+                &new_images,
+                &new_tex_metadata,
+                DDS_FLAGS_FORCE_DX10_EXT_MISC2,
+            )?;
+        }
+
+        Ok(blob.buffer().to_vec())
     }
 
-    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), Box<dyn Error>> {
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
         let bytes = self.save_to_memory(texture)?;
 
         Ok(fs::write(filepath, bytes)?)
     }
 
-    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, Box<dyn Error>> {
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
         let (scratch, meta) = {
             let mut meta = Default::default();
 
             let mut scratch = ScratchImage::load_dds(
-                &source,
+                source,
                 DDS_FLAGS_NONE,
                 Some(&mut meta),
                 Default::default(),
@@ -85,7 +216,7 @@ impl ImageCodec for DDSCodec {
         let mut new_images = Vec::with_capacity(images.len());
 
         let pixel_format_info =
-            DirectXTexUtility::get_pixel_format_info_from_image(&scratch.metadata());
+            DirectXTexUtility::get_pixel_format_info_from_image(scratch.metadata())?;
 
         for img in images {
             let width = img.width as u32;
@@ -116,7 +247,7 @@ impl ImageCodec for DDSCodec {
             array_size: meta.array_size as u32,
             mip_levels: meta.mip_levels as u32,
             pixel_format_info,
-            alpha_mode: 0,
+            alpha_mode: meta.get_alpha_mode().bits(),
             dimensions: DirectXTexUtility::get_texture_dimension_from_directxtex(meta.dimension),
             is_cubemap: meta.is_cubemap(),
             is_volumemap: meta.is_volumemap(),
@@ -128,11 +259,19 @@ impl ImageCodec for DDSCodec {
         })
     }
 
-    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, Box<dyn Error>> {
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
         self.load_from_memory(&fs::read(&filepath)?)
     }
 
     fn supported_extensions(&self) -> Vec<&'static str> {
         vec!["dds"]
     }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(DDS_MAGIC) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
 }
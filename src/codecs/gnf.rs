@@ -0,0 +1,673 @@
+use std::{fs, path::PathBuf};
+
+use super::{check_mip_levels, ktx2::align, reorder_mip_major, ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        swizzling::{ps4::PS4Swizzler, Swizzable},
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// GNF - https://github.com/xdanieldzd/Scarlet/blob/master/Scarlet.IO.ImageFormats/GNF.cs
+// A header followed by one sce::Gnm::Texture descriptor (8 registers) per texture, padded to
+// 256 bytes. The texture data follows, mip levels first and array slices inside each level.
+const GNF_MAGIC: &[u8; 4] = b"GNF ";
+const GNF_VERSION: u8 = 2;
+const GNF_HEADER_SIZE: usize = 8;
+const GNF_CONTENTS_SIZE: usize = 0xF8;
+const GNF_ALIGNMENT_LOG2: u8 = 8;
+
+// sce::Gnm::TextureType
+const TEXTURE_TYPE_1D: u32 = 8;
+const TEXTURE_TYPE_2D: u32 = 9;
+const TEXTURE_TYPE_3D: u32 = 10;
+const TEXTURE_TYPE_CUBEMAP: u32 = 11;
+const TEXTURE_TYPE_1D_ARRAY: u32 = 12;
+const TEXTURE_TYPE_2D_ARRAY: u32 = 13;
+
+// sce::Gnm::TileMode
+const TILE_MODE_DISPLAY_LINEAR_ALIGNED: u32 = 8;
+const TILE_MODE_THIN_1D_THIN: u32 = 13;
+
+pub struct GNFCodec;
+
+/// Fields of a sce::Gnm::Texture descriptor
+struct GnmTexture {
+    data_format: u32,
+    num_format: u32,
+    width: u32,
+    height: u32,
+    destination_select: u32,
+    base_level: u32,
+    last_level: u32,
+    tile_mode: u32,
+    texture_type: u32,
+    depth: u32,
+    pitch: u32,
+    base_array: u32,
+    last_array: u32,
+    data_size: usize,
+}
+
+impl GnmTexture {
+    fn read(reader: &mut BinaryReader<'_>) -> Result<Self, TextureError> {
+        let mut registers = [0; 8];
+
+        for register in &mut registers {
+            *register = reader.read_u32()?;
+        }
+
+        Ok(Self {
+            data_format: (registers[1] >> 20) & 0x3F,
+            num_format: (registers[1] >> 26) & 0xF,
+            width: (registers[2] & 0x3FFF) + 1,
+            height: ((registers[2] >> 14) & 0x3FFF) + 1,
+            destination_select: registers[3] & 0xFFF,
+            base_level: (registers[3] >> 12) & 0xF,
+            last_level: (registers[3] >> 16) & 0xF,
+            tile_mode: (registers[3] >> 20) & 0x1F,
+            texture_type: (registers[3] >> 28) & 0xF,
+            depth: (registers[4] & 0x1FFF) + 1,
+            pitch: ((registers[4] >> 13) & 0x3FFF) + 1,
+            base_array: registers[5] & 0x1FFF,
+            last_array: (registers[5] >> 13) & 0x1FFF,
+            // Unused by the hardware, GNF files keep the texture data size there
+            data_size: registers[7] as usize,
+        })
+    }
+
+    fn to_registers(&self) -> [u32; 8] {
+        [
+            0,
+            (self.data_format << 20) | (self.num_format << 26),
+            (self.width - 1) | ((self.height - 1) << 14),
+            self.destination_select
+                | (self.base_level << 12)
+                | (self.last_level << 16)
+                | (self.tile_mode << 20)
+                | (self.texture_type << 28),
+            (self.depth - 1) | ((self.pitch - 1) << 13),
+            self.base_array | (self.last_array << 13),
+            0,
+            self.data_size as u32,
+        ]
+    }
+
+    fn get_mip_levels(&self) -> u32 {
+        self.last_level.saturating_sub(self.base_level) + 1
+    }
+
+    fn get_array_size(&self) -> u32 {
+        let array_size = self.last_array.saturating_sub(self.base_array) + 1;
+
+        if self.texture_type == TEXTURE_TYPE_CUBEMAP && array_size % 6 != 0 {
+            array_size * 6
+        } else {
+            array_size
+        }
+    }
+}
+
+impl ImageCodec for GNFCodec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format_info = metadata.pixel_format_info;
+        let pixel_format = pixel_format_info.pixel_format;
+
+        let format = get_gnm_format_from_pixel_format_info(pixel_format_info).ok_or_else(|| {
+            TextureError::UnsupportedFormat(format!(
+                "GNF does not support pixel format: {}",
+                pixel_format
+            ))
+        })?;
+
+        if metadata.dimensions == TexDimension::Tex3D {
+            return Err(TextureError::UnsupportedFormat(
+                "GNF 3D textures are not supported!".to_string(),
+            ));
+        }
+
+        // The descriptor fields hold size - 1, last level and last array index
+        if !(1..=0x4000).contains(&metadata.width)
+            || !(1..=0x4000).contains(&metadata.height)
+            || !(1..=16).contains(&metadata.mip_levels)
+            || !(1..=0x2000).contains(&metadata.array_size)
+        {
+            return Err(TextureError::DimensionMismatch(format!(
+                "GNF texture out of range: {}x{} with {} mips and {} array items",
+                metadata.width, metadata.height, metadata.mip_levels, metadata.array_size
+            )));
+        }
+
+        check_mip_levels(
+            "GNF",
+            metadata.mip_levels,
+            metadata.width,
+            metadata.height,
+            1,
+        )?;
+
+        let data = PS4Swizzler.swizzle_texture(texture)?;
+
+        let texture_type = if metadata.is_cubemap {
+            TEXTURE_TYPE_CUBEMAP
+        } else if metadata.dimensions == TexDimension::Tex1D {
+            if metadata.array_size > 1 {
+                TEXTURE_TYPE_1D_ARRAY
+            } else {
+                TEXTURE_TYPE_1D
+            }
+        } else if metadata.array_size > 1 {
+            TEXTURE_TYPE_2D_ARRAY
+        } else {
+            TEXTURE_TYPE_2D
+        };
+
+        // Swizzled rows are padded to whole 8x8 block tiles
+        let block_width = pixel_format.get_block_width();
+        let pitch = metadata.width.div_ceil(block_width).next_multiple_of(8) * block_width;
+
+        let gnm_texture = GnmTexture {
+            data_format: format.data_format,
+            num_format: format.num_format,
+            width: metadata.width,
+            height: metadata.height,
+            destination_select: format.destination_select,
+            base_level: 0,
+            last_level: metadata.mip_levels - 1,
+            tile_mode: TILE_MODE_THIN_1D_THIN,
+            texture_type,
+            depth: 1,
+            pitch,
+            base_array: 0,
+            last_array: metadata.array_size - 1,
+            data_size: data.len(),
+        };
+
+        let data_offset = GNF_HEADER_SIZE + GNF_CONTENTS_SIZE;
+        let mut buffer = Vec::with_capacity(data_offset + data.len());
+
+        buffer.extend_from_slice(GNF_MAGIC);
+        buffer.extend_from_slice(&(GNF_CONTENTS_SIZE as u32).to_le_bytes());
+        buffer.extend_from_slice(&[GNF_VERSION, 1, GNF_ALIGNMENT_LOG2, 0]);
+        buffer.extend_from_slice(&((data_offset + data.len()) as u32).to_le_bytes());
+
+        for register in gnm_texture.to_registers() {
+            buffer.extend_from_slice(&register.to_le_bytes());
+        }
+
+        buffer.resize(data_offset, 0);
+        buffer.extend_from_slice(&data);
+
+        Ok(buffer)
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let mut reader = BinaryReader::new(source);
+
+        if reader.read_bytes(GNF_MAGIC.len())? != GNF_MAGIC {
+            return Err(TextureError::CorruptHeader(
+                "Invalid GNF file identifier!".to_string(),
+            ));
+        }
+
+        let contents_size = reader.read_u32()? as usize;
+        let _version = reader.read_u8()?;
+        let texture_count = reader.read_u8()?;
+        let alignment = 1usize << reader.read_u8()?.min(31);
+        let _unused = reader.read_u8()?;
+        let _stream_size = reader.read_u32()?;
+
+        if texture_count == 0 {
+            return Err(TextureError::CorruptHeader(
+                "GNF file has no textures!".to_string(),
+            ));
+        }
+
+        let gnm_textures = (0..texture_count)
+            .map(|_| GnmTexture::read(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Every texture becomes an array item, so they must all look the same
+        let first = &gnm_textures[0];
+
+        if gnm_textures.iter().any(|gnm_texture| {
+            gnm_texture.width != first.width
+                || gnm_texture.height != first.height
+                || gnm_texture.get_mip_levels() != first.get_mip_levels()
+                || gnm_texture.data_format != first.data_format
+                || gnm_texture.texture_type != first.texture_type
+        }) {
+            return Err(TextureError::DimensionMismatch(
+                "GNF textures of different sizes or formats cannot be loaded as one array!"
+                    .to_string(),
+            ));
+        }
+
+        let mut offset = GNF_HEADER_SIZE + contents_size;
+        let mut textures = Vec::with_capacity(gnm_textures.len());
+
+        for gnm_texture in &gnm_textures {
+            offset = align(offset, alignment);
+            textures.push(read_texture(
+                reader.slice(offset, gnm_texture.data_size)?,
+                gnm_texture,
+            )?);
+            offset += gnm_texture.data_size;
+        }
+
+        let mut metadata = textures[0].metadata;
+        metadata.array_size = textures
+            .iter()
+            .map(|texture| texture.metadata.array_size)
+            .sum();
+
+        let images = textures
+            .into_iter()
+            .flat_map(|texture| texture.images)
+            .collect();
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["gnf"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(GNF_MAGIC) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+/// Read and deswizzle the images of one texture, in the order of `Texture::images`
+fn read_texture(data: &[u8], gnm_texture: &GnmTexture) -> Result<Texture, TextureError> {
+    let pixel_format_info = get_pixel_format_info_from_gnm_format(
+        gnm_texture.data_format,
+        gnm_texture.num_format,
+        gnm_texture.destination_select,
+    )
+    .ok_or_else(|| {
+        TextureError::UnsupportedFormat(format!(
+            "Unsupported GNF format: data format {}, num format {}",
+            gnm_texture.data_format, gnm_texture.num_format
+        ))
+    })?;
+    let pixel_format = pixel_format_info.pixel_format;
+
+    let dimensions = match gnm_texture.texture_type {
+        TEXTURE_TYPE_1D | TEXTURE_TYPE_1D_ARRAY => TexDimension::Tex1D,
+        TEXTURE_TYPE_2D | TEXTURE_TYPE_2D_ARRAY | TEXTURE_TYPE_CUBEMAP => TexDimension::Tex2D,
+        TEXTURE_TYPE_3D => {
+            return Err(TextureError::UnsupportedFormat(
+                "GNF 3D textures are not supported!".to_string(),
+            ))
+        }
+        texture_type => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported GNF texture type: {}",
+                texture_type
+            )))
+        }
+    };
+
+    let width = gnm_texture.width;
+    let height = gnm_texture.height;
+    let mip_levels = gnm_texture.get_mip_levels();
+    let array_size = gnm_texture.get_array_size();
+    check_mip_levels("GNF", mip_levels, width, height, 1)?;
+
+    let metadata = TexMetadata {
+        width,
+        height,
+        depth: 1,
+        array_size,
+        mip_levels,
+        pixel_format_info,
+        alpha_mode: 0,
+        dimensions,
+        is_cubemap: gnm_texture.texture_type == TEXTURE_TYPE_CUBEMAP,
+        is_volumemap: false,
+    };
+
+    match gnm_texture.tile_mode {
+        TILE_MODE_DISPLAY_LINEAR_ALIGNED => {}
+        TILE_MODE_THIN_1D_THIN => return PS4Swizzler.deswizzle_texture(data, &metadata),
+        tile_mode => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported GNF tile mode: {}",
+                tile_mode
+            )))
+        }
+    }
+
+    let mut reader = BinaryReader::new(data);
+    let mut levels = Vec::with_capacity(mip_levels as usize);
+
+    for mip in 0..mip_levels {
+        let mip_width = (width >> mip).max(1);
+        let mip_height = (height >> mip).max(1);
+        let linear_size = pixel_format.get_slice_pitch(mip_width, mip_height) as usize;
+        let mut images = Vec::with_capacity(array_size as usize);
+
+        for _ in 0..array_size {
+            // Linear rows are padded to the pitch of their level
+            let mip_pitch = (gnm_texture.pitch >> mip).max(mip_width);
+            let row_pitch = pixel_format.get_row_pitch(mip_width) as usize;
+            let padded_row_pitch = pixel_format.get_row_pitch(mip_pitch) as usize;
+            let mut pixels = Vec::with_capacity(linear_size);
+
+            for _ in 0..linear_size / row_pitch {
+                pixels.extend_from_slice(&reader.read_bytes(padded_row_pitch)?[..row_pitch]);
+            }
+
+            images.push(Image::new(mip_width, mip_height, pixel_format_info, pixels));
+        }
+
+        levels.push(images);
+    }
+
+    let images = reorder_mip_major(levels, &metadata);
+
+    Ok(Texture { metadata, images })
+}
+
+// sce::Gnm::SurfaceFormat, components are listed from the least significant bits
+mod data_format {
+    pub const FORMAT_8: u32 = 1;
+    pub const FORMAT_16: u32 = 2;
+    pub const FORMAT_8_8: u32 = 3;
+    pub const FORMAT_32: u32 = 4;
+    pub const FORMAT_16_16: u32 = 5;
+    pub const FORMAT_10_11_11: u32 = 6;
+    pub const FORMAT_2_10_10_10: u32 = 9;
+    pub const FORMAT_8_8_8_8: u32 = 10;
+    pub const FORMAT_32_32: u32 = 11;
+    pub const FORMAT_16_16_16_16: u32 = 12;
+    pub const FORMAT_32_32_32: u32 = 13;
+    pub const FORMAT_32_32_32_32: u32 = 14;
+    pub const FORMAT_5_6_5: u32 = 16;
+    pub const FORMAT_1_5_5_5: u32 = 17;
+    pub const FORMAT_4_4_4_4: u32 = 19;
+    pub const FORMAT_5_9_9_9: u32 = 34;
+    pub const BC1: u32 = 35;
+    pub const BC2: u32 = 36;
+    pub const BC3: u32 = 37;
+    pub const BC4: u32 = 38;
+    pub const BC5: u32 = 39;
+    pub const BC6: u32 = 40;
+    pub const BC7: u32 = 41;
+}
+
+// sce::Gnm::TextureChannelType
+const NUM_FORMAT_UNORM: u32 = 0;
+const NUM_FORMAT_SNORM: u32 = 1;
+const NUM_FORMAT_UINT: u32 = 4;
+const NUM_FORMAT_SINT: u32 = 5;
+const NUM_FORMAT_FLOAT: u32 = 7;
+const NUM_FORMAT_SRGB: u32 = 9;
+
+/// Destination select of the R, G, B and A channels: 0, 1 or the X, Y, Z and W components
+const fn select(r: u32, g: u32, b: u32, a: u32) -> u32 {
+    r | (g << 3) | (b << 6) | (a << 9)
+}
+
+const SEL_1: u32 = 1;
+const SEL_X: u32 = 4;
+const SEL_Y: u32 = 5;
+const SEL_Z: u32 = 6;
+const SEL_W: u32 = 7;
+const SEL_0: u32 = 0;
+
+const XYZW: u32 = select(SEL_X, SEL_Y, SEL_Z, SEL_W);
+const ZYXW: u32 = select(SEL_Z, SEL_Y, SEL_X, SEL_W);
+
+#[derive(Clone, Copy)]
+struct GnmFormat {
+    pixel_format: PixelFormat,
+    data_format: u32,
+    num_format: u32,
+    destination_select: u32,
+}
+
+const fn gnm(
+    pixel_format: PixelFormat,
+    data_format: u32,
+    num_format: u32,
+    destination_select: u32,
+) -> GnmFormat {
+    GnmFormat {
+        pixel_format,
+        data_format,
+        num_format,
+        destination_select,
+    }
+}
+
+/// Preferred formats come first, they are used when saving
+const GNM_FORMAT_TABLE: &[GnmFormat] = &[
+    gnm(
+        PixelFormat::R8G8B8A8,
+        data_format::FORMAT_8_8_8_8,
+        NUM_FORMAT_UNORM,
+        XYZW,
+    ),
+    gnm(
+        PixelFormat::B8G8R8A8,
+        data_format::FORMAT_8_8_8_8,
+        NUM_FORMAT_UNORM,
+        ZYXW,
+    ),
+    gnm(
+        PixelFormat::B8G8R8X8,
+        data_format::FORMAT_8_8_8_8,
+        NUM_FORMAT_UNORM,
+        select(SEL_Z, SEL_Y, SEL_X, SEL_1),
+    ),
+    gnm(
+        PixelFormat::A8,
+        data_format::FORMAT_8,
+        NUM_FORMAT_UNORM,
+        select(SEL_0, SEL_0, SEL_0, SEL_X),
+    ),
+    gnm(
+        PixelFormat::L8,
+        data_format::FORMAT_8,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_X, SEL_X, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R8,
+        data_format::FORMAT_8,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_0, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::L8A8,
+        data_format::FORMAT_8_8,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_X, SEL_X, SEL_Y),
+    ),
+    gnm(
+        PixelFormat::R8G8,
+        data_format::FORMAT_8_8,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_Y, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R16,
+        data_format::FORMAT_16,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_0, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R16G16,
+        data_format::FORMAT_16_16,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_Y, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R16G16B16A16,
+        data_format::FORMAT_16_16_16_16,
+        NUM_FORMAT_UNORM,
+        XYZW,
+    ),
+    gnm(
+        PixelFormat::R32,
+        data_format::FORMAT_32,
+        NUM_FORMAT_FLOAT,
+        select(SEL_X, SEL_0, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R32G32,
+        data_format::FORMAT_32_32,
+        NUM_FORMAT_FLOAT,
+        select(SEL_X, SEL_Y, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R32G32B32,
+        data_format::FORMAT_32_32_32,
+        NUM_FORMAT_FLOAT,
+        select(SEL_X, SEL_Y, SEL_Z, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R32G32B32A32,
+        data_format::FORMAT_32_32_32_32,
+        NUM_FORMAT_FLOAT,
+        XYZW,
+    ),
+    gnm(
+        PixelFormat::R10G10B10A2,
+        data_format::FORMAT_2_10_10_10,
+        NUM_FORMAT_UNORM,
+        XYZW,
+    ),
+    gnm(
+        PixelFormat::R11G11B10,
+        data_format::FORMAT_10_11_11,
+        NUM_FORMAT_FLOAT,
+        select(SEL_X, SEL_Y, SEL_Z, SEL_1),
+    ),
+    gnm(
+        PixelFormat::R9G9B9E5,
+        data_format::FORMAT_5_9_9_9,
+        NUM_FORMAT_FLOAT,
+        select(SEL_X, SEL_Y, SEL_Z, SEL_1),
+    ),
+    gnm(
+        PixelFormat::B5G6R5,
+        data_format::FORMAT_5_6_5,
+        NUM_FORMAT_UNORM,
+        select(SEL_Z, SEL_Y, SEL_X, SEL_1),
+    ),
+    gnm(
+        PixelFormat::B5G5R5A1,
+        data_format::FORMAT_1_5_5_5,
+        NUM_FORMAT_UNORM,
+        ZYXW,
+    ),
+    gnm(
+        PixelFormat::B4G4R4A4,
+        data_format::FORMAT_4_4_4_4,
+        NUM_FORMAT_UNORM,
+        ZYXW,
+    ),
+    gnm(PixelFormat::BC1, data_format::BC1, NUM_FORMAT_UNORM, XYZW),
+    gnm(PixelFormat::BC2, data_format::BC2, NUM_FORMAT_UNORM, XYZW),
+    gnm(PixelFormat::BC3, data_format::BC3, NUM_FORMAT_UNORM, XYZW),
+    gnm(
+        PixelFormat::BC4,
+        data_format::BC4,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_0, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::BC5,
+        data_format::BC5,
+        NUM_FORMAT_UNORM,
+        select(SEL_X, SEL_Y, SEL_0, SEL_1),
+    ),
+    gnm(
+        PixelFormat::BC6H,
+        data_format::BC6,
+        NUM_FORMAT_FLOAT,
+        select(SEL_X, SEL_Y, SEL_Z, SEL_1),
+    ),
+    gnm(PixelFormat::BC7, data_format::BC7, NUM_FORMAT_UNORM, XYZW),
+];
+
+/// The data format and destination select give the pixel format, the num format its encoding
+fn get_pixel_format_info_from_gnm_format(
+    data_format: u32,
+    num_format: u32,
+    destination_select: u32,
+) -> Option<PixelFormatInfo> {
+    let candidates = || {
+        GNM_FORMAT_TABLE
+            .iter()
+            .filter(|format| format.data_format == data_format)
+    };
+
+    let gnm_format = candidates()
+        .find(|format| format.destination_select == destination_select)
+        .or_else(|| candidates().next())?;
+
+    let pixel_data_type = match num_format {
+        NUM_FORMAT_UNORM | NUM_FORMAT_SRGB => PixelDataType::UNorm,
+        NUM_FORMAT_SNORM => PixelDataType::SNorm,
+        NUM_FORMAT_UINT => PixelDataType::UInt,
+        NUM_FORMAT_SINT => PixelDataType::SInt,
+        NUM_FORMAT_FLOAT => PixelDataType::Float,
+        _ => return None,
+    };
+
+    Some(PixelFormatInfo {
+        pixel_format: gnm_format.pixel_format,
+        pixel_data_type,
+        color_space: if num_format == NUM_FORMAT_SRGB {
+            ColorSpace::SRGB
+        } else {
+            ColorSpace::Linear
+        },
+        is_premultiplied: false,
+    })
+}
+
+fn get_gnm_format_from_pixel_format_info(pixel_format_info: PixelFormatInfo) -> Option<GnmFormat> {
+    let gnm_format = GNM_FORMAT_TABLE
+        .iter()
+        .find(|format| format.pixel_format == pixel_format_info.pixel_format)?;
+
+    let num_format = match (
+        pixel_format_info.color_space,
+        pixel_format_info.pixel_data_type,
+    ) {
+        (ColorSpace::SRGB, _) => NUM_FORMAT_SRGB,
+        (_, PixelDataType::UNorm) => NUM_FORMAT_UNORM,
+        (_, PixelDataType::SNorm) => NUM_FORMAT_SNORM,
+        (_, PixelDataType::UInt) => NUM_FORMAT_UINT,
+        (_, PixelDataType::SInt) => NUM_FORMAT_SINT,
+        (_, PixelDataType::Float | PixelDataType::SFloat) => NUM_FORMAT_FLOAT,
+    };
+
+    Some(GnmFormat {
+        num_format,
+        ..*gnm_format
+    })
+}
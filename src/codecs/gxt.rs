@@ -0,0 +1,552 @@
+use std::{fs, path::PathBuf};
+
+use super::{check_mip_levels, ktx2::align, ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        swizzling::{
+            psvita::{PSVitaLayout, PSVitaSwizzler},
+            Swizzable,
+        },
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// GXT 3 - https://github.com/xdanieldzd/Scarlet/blob/master/Scarlet.IO.ImageFormats/GXT.cs
+// A header, one info per texture, then the texture data. The palettes of the P4 and P8 textures
+// sit at the end of the data, 16 entry palettes first.
+const GXT_MAGIC: &[u8; 4] = b"GXT\0";
+const GXT_VERSION: u32 = 0x10000003;
+const GXT_HEADER_SIZE: usize = 0x20;
+const GXT_TEXTURE_INFO_SIZE: usize = 0x20;
+const GXT_DATA_ALIGNMENT: usize = 16;
+
+const P4_PALETTE_SIZE: usize = 16 * 4;
+const P8_PALETTE_SIZE: usize = 256 * 4;
+
+// SceGxmTextureType
+const TEXTURE_SWIZZLED: u32 = 0x00000000;
+const TEXTURE_CUBE: u32 = 0x40000000;
+const TEXTURE_LINEAR: u32 = 0x60000000;
+const TEXTURE_TILED: u32 = 0x80000000;
+const TEXTURE_SWIZZLED_ARBITRARY: u32 = 0xA0000000;
+const TEXTURE_LINEAR_STRIDED: u32 = 0x0C000000;
+const TEXTURE_CUBE_ARBITRARY: u32 = 0xE0000000;
+
+// SceGxmTextureFormat is a base format and a component swizzle
+const BASE_FORMAT_MASK: u32 = 0x9F000000;
+const SWIZZLE_MASK: u32 = 0x0000F000;
+const BASE_FORMAT_P4: u32 = 0x94000000;
+const BASE_FORMAT_P8: u32 = 0x95000000;
+
+/// Linear rows are aligned to 8 texels
+const LINEAR_ROW_ALIGNMENT: u32 = 8;
+
+pub struct GXTCodec;
+
+struct GXTTextureInfo {
+    data_offset: usize,
+    data_size: usize,
+    palette_index: i32,
+    texture_type: u32,
+    texture_format: u32,
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+}
+
+impl ImageCodec for GXTCodec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format_info = metadata.pixel_format_info;
+
+        let texture_format =
+            get_gxm_format_from_pixel_format_info(pixel_format_info).ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "GXT does not support pixel format: {}",
+                    pixel_format_info.pixel_format
+                ))
+            })?;
+
+        if metadata.dimensions == TexDimension::Tex3D {
+            return Err(TextureError::UnsupportedFormat(
+                "GXT does not support 3D textures!".to_string(),
+            ));
+        }
+
+        if metadata.width > u16::MAX as u32 || metadata.height > u16::MAX as u32 {
+            return Err(TextureError::DimensionMismatch(format!(
+                "GXT texture too large: {}x{}",
+                metadata.width, metadata.height
+            )));
+        }
+
+        // Cubemaps are saved as cube textures, array items as separate textures
+        let (texture_type, faces) = if metadata.is_cubemap {
+            (TEXTURE_CUBE, 6)
+        } else {
+            (TEXTURE_SWIZZLED, 1)
+        };
+
+        if metadata.array_size % faces != 0 {
+            return Err(TextureError::DimensionMismatch(
+                "Cubemap array size must be a multiple of 6!".to_string(),
+            ));
+        }
+
+        let swizzler = PSVitaSwizzler::default();
+        let texture_count = metadata.array_size / faces;
+        let images_per_texture = (faces * metadata.mip_levels) as usize;
+        let mut texture_data = Vec::with_capacity(texture_count as usize);
+
+        for images in texture.images.chunks(images_per_texture) {
+            let sub_texture = Texture {
+                metadata: TexMetadata {
+                    array_size: faces,
+                    ..*metadata
+                },
+                images: images.to_vec(),
+            };
+
+            texture_data.push(swizzler.swizzle_texture(&sub_texture)?);
+        }
+
+        let data_offset = align(
+            GXT_HEADER_SIZE + texture_data.len() * GXT_TEXTURE_INFO_SIZE,
+            GXT_DATA_ALIGNMENT,
+        );
+
+        let mut buffer = Vec::new();
+        let mut data = Vec::new();
+
+        buffer.extend_from_slice(GXT_MAGIC);
+
+        let mut infos = Vec::new();
+
+        for surface in &texture_data {
+            let offset = data_offset + data.len();
+
+            for value in [
+                offset as u32,
+                surface.len() as u32,
+                u32::MAX, // No palette
+                0,
+                texture_type,
+                texture_format,
+            ] {
+                infos.extend_from_slice(&value.to_le_bytes());
+            }
+
+            infos.extend_from_slice(&(metadata.width as u16).to_le_bytes());
+            infos.extend_from_slice(&(metadata.height as u16).to_le_bytes());
+            infos.extend_from_slice(&[metadata.mip_levels as u8, 0, 0, 0]);
+
+            data.extend_from_slice(surface);
+            data.resize(align(data.len(), GXT_DATA_ALIGNMENT), 0);
+        }
+
+        for value in [
+            GXT_VERSION,
+            texture_data.len() as u32,
+            data_offset as u32,
+            data.len() as u32,
+            0, // P4 palettes
+            0, // P8 palettes
+            0,
+        ] {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+
+        buffer.extend_from_slice(&infos);
+        buffer.resize(data_offset, 0);
+        buffer.extend_from_slice(&data);
+
+        Ok(buffer)
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let mut reader = BinaryReader::new(source);
+
+        if reader.read_bytes(GXT_MAGIC.len())? != GXT_MAGIC {
+            return Err(TextureError::CorruptHeader(
+                "Invalid GXT file identifier!".to_string(),
+            ));
+        }
+
+        let version = reader.read_u32()?;
+
+        if version != GXT_VERSION {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported GXT version: {:#010x}",
+                version
+            )));
+        }
+
+        let texture_count = reader.read_u32()?;
+        let data_offset = reader.read_u32()? as usize;
+        let data_size = reader.read_u32()? as usize;
+        let p4_palette_count = reader.read_u32()? as usize;
+        let p8_palette_count = reader.read_u32()? as usize;
+        let _padding = reader.read_u32()?;
+
+        if texture_count == 0 {
+            return Err(TextureError::CorruptHeader(
+                "GXT file has no textures!".to_string(),
+            ));
+        }
+
+        if texture_count as usize > reader.remaining() / GXT_TEXTURE_INFO_SIZE {
+            return Err(TextureError::CorruptHeader(format!(
+                "GXT texture count {} is larger than the file!",
+                texture_count
+            )));
+        }
+
+        let mut infos = Vec::with_capacity(texture_count as usize);
+
+        for _ in 0..texture_count {
+            let data_offset = reader.read_u32()? as usize;
+            let data_size = reader.read_u32()? as usize;
+            let palette_index = reader.read_u32()? as i32;
+            let _flags = reader.read_u32()?;
+            let texture_type = reader.read_u32()?;
+            let texture_format = reader.read_u32()?;
+            let width = reader.read_u16()? as u32;
+            let height = reader.read_u16()? as u32;
+            let mip_levels = (reader.read_u8()? as u32).max(1);
+            reader.skip(3)?;
+
+            check_mip_levels("GXT", mip_levels, width, height, 1)?;
+
+            infos.push(GXTTextureInfo {
+                data_offset,
+                data_size,
+                palette_index,
+                texture_type,
+                texture_format,
+                width,
+                height,
+                mip_levels,
+            });
+        }
+
+        // Every texture becomes an array item, so they must all look the same
+        let first = &infos[0];
+
+        if infos.iter().any(|info| {
+            info.width != first.width
+                || info.height != first.height
+                || info.mip_levels != first.mip_levels
+                || info.texture_format != first.texture_format
+                || is_cube(info.texture_type) != is_cube(first.texture_type)
+        }) {
+            return Err(TextureError::DimensionMismatch(
+                "GXT textures of different sizes or formats cannot be loaded as one array!"
+                    .to_string(),
+            ));
+        }
+
+        let palettes_size = p4_palette_count * P4_PALETTE_SIZE + p8_palette_count * P8_PALETTE_SIZE;
+        let palettes_offset = (data_offset + data_size)
+            .checked_sub(palettes_size)
+            .ok_or_else(|| TextureError::CorruptHeader("Invalid GXT palette count!".to_string()))?;
+        let p4_palettes = reader.slice(palettes_offset, p4_palette_count * P4_PALETTE_SIZE)?;
+        let p8_palettes = reader.slice(
+            palettes_offset + p4_palettes.len(),
+            p8_palette_count * P8_PALETTE_SIZE,
+        )?;
+
+        let mut images = Vec::new();
+        let mut pixel_format_info = PixelFormatInfo::default();
+
+        for info in &infos {
+            let texture = read_texture(&reader, info, p4_palettes, p8_palettes)?;
+            pixel_format_info = texture.metadata.pixel_format_info;
+            images.extend(texture.images);
+        }
+
+        let faces = if is_cube(first.texture_type) { 6 } else { 1 };
+        let array_size = texture_count.checked_mul(faces).ok_or_else(|| {
+            TextureError::CorruptHeader(format!(
+                "GXT texture count {} is too large!",
+                texture_count
+            ))
+        })?;
+
+        let metadata = TexMetadata {
+            width: first.width,
+            height: first.height,
+            depth: 1,
+            array_size,
+            mip_levels: first.mip_levels,
+            pixel_format_info,
+            alpha_mode: 0,
+            dimensions: TexDimension::Tex2D,
+            is_cubemap: faces == 6,
+            is_volumemap: false,
+        };
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["gxt"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(GXT_MAGIC) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+fn is_cube(texture_type: u32) -> bool {
+    texture_type == TEXTURE_CUBE || texture_type == TEXTURE_CUBE_ARBITRARY
+}
+
+/// Read the linear images of one texture, paletted textures are expanded to RGBA8
+fn read_texture(
+    reader: &BinaryReader<'_>,
+    info: &GXTTextureInfo,
+    p4_palettes: &[u8],
+    p8_palettes: &[u8],
+) -> Result<Texture, TextureError> {
+    let base_format = info.texture_format & BASE_FORMAT_MASK;
+    let mut data = reader.slice(info.data_offset, info.data_size)?.to_vec();
+
+    let palette = match base_format {
+        BASE_FORMAT_P4 => {
+            // One index per byte, so the indices can be deswizzled like an 8 bit format
+            data = data
+                .iter()
+                .flat_map(|&byte| [byte & 0x0F, byte >> 4])
+                .collect();
+
+            Some(get_palette(info, p4_palettes, P4_PALETTE_SIZE)?)
+        }
+        BASE_FORMAT_P8 => Some(get_palette(info, p8_palettes, P8_PALETTE_SIZE)?),
+        _ => None,
+    };
+
+    let pixel_format_info = if palette.is_some() {
+        PixelFormatInfo {
+            pixel_format: PixelFormat::R8,
+            ..Default::default()
+        }
+    } else {
+        get_pixel_format_info_from_gxm_format(info.texture_format).ok_or_else(|| {
+            TextureError::UnsupportedFormat(format!(
+                "Unsupported GXT texture format: {:#010x}",
+                info.texture_format
+            ))
+        })?
+    };
+
+    let metadata = TexMetadata {
+        width: info.width,
+        height: info.height,
+        depth: 1,
+        array_size: if is_cube(info.texture_type) { 6 } else { 1 },
+        mip_levels: info.mip_levels,
+        pixel_format_info,
+        alpha_mode: 0,
+        dimensions: TexDimension::Tex2D,
+        is_cubemap: is_cube(info.texture_type),
+        is_volumemap: false,
+    };
+
+    let mut texture = match info.texture_type {
+        TEXTURE_SWIZZLED | TEXTURE_SWIZZLED_ARBITRARY | TEXTURE_CUBE | TEXTURE_CUBE_ARBITRARY => {
+            PSVitaSwizzler::default().deswizzle_texture(&data, &metadata)?
+        }
+        TEXTURE_TILED => PSVitaSwizzler {
+            layout: PSVitaLayout::Tiled,
+        }
+        .deswizzle_texture(&data, &metadata)?,
+        TEXTURE_LINEAR | TEXTURE_LINEAR_STRIDED => read_linear_texture(&data, &metadata)?,
+        _ => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported GXT texture type: {:#010x}",
+                info.texture_type
+            )))
+        }
+    };
+
+    if let Some(palette) = palette {
+        let rgba8_info = PixelFormatInfo::default();
+
+        for image in &mut texture.images {
+            let pixels = image
+                .pixels
+                .iter()
+                .flat_map(|&index| palette[index as usize])
+                .collect();
+
+            *image = Image::new(image.width, image.height, rgba8_info, pixels);
+        }
+
+        texture.metadata.pixel_format_info = rgba8_info;
+    }
+
+    Ok(texture)
+}
+
+/// Linear images follow each other, with rows of uncompressed formats aligned to 8 texels
+fn read_linear_texture(data: &[u8], metadata: &TexMetadata) -> Result<Texture, TextureError> {
+    let pixel_format = metadata.pixel_format_info.pixel_format;
+    let mut reader = BinaryReader::new(data);
+    let mut images = Vec::new();
+
+    for (width, height) in metadata.get_image_sizes() {
+        let row_pitch = pixel_format.get_row_pitch(width) as usize;
+        let row_count = (pixel_format.get_slice_pitch(width, height) as usize) / row_pitch;
+        let padded_row_pitch = if pixel_format.is_compressed() {
+            row_pitch
+        } else {
+            pixel_format.get_row_pitch(width.next_multiple_of(LINEAR_ROW_ALIGNMENT)) as usize
+        };
+
+        let mut pixels = Vec::with_capacity(row_pitch * row_count);
+
+        for _ in 0..row_count {
+            pixels.extend_from_slice(&reader.read_bytes(padded_row_pitch)?[..row_pitch]);
+        }
+
+        images.push(Image::new(
+            width,
+            height,
+            metadata.pixel_format_info,
+            pixels,
+        ));
+    }
+
+    Ok(Texture {
+        metadata: *metadata,
+        images,
+    })
+}
+
+/// Palette of a P4 or P8 texture as RGBA8 entries
+fn get_palette(
+    info: &GXTTextureInfo,
+    palettes: &[u8],
+    palette_size: usize,
+) -> Result<Vec<[u8; 4]>, TextureError> {
+    let offset = usize::try_from(info.palette_index).map_err(|_| {
+        TextureError::CorruptHeader("GXT paletted texture has no palette!".to_string())
+    })? * palette_size;
+
+    let palette = palettes.get(offset..offset + palette_size).ok_or_else(|| {
+        TextureError::CorruptHeader(format!("Invalid GXT palette index: {}", info.palette_index))
+    })?;
+
+    // Byte offsets of R, G, B and A, and whether the alpha is ignored
+    let (order, opaque) = match info.texture_format & SWIZZLE_MASK {
+        0x0000 => ([0, 1, 2, 3], false), // ABGR
+        0x1000 => ([2, 1, 0, 3], false), // ARGB
+        0x2000 => ([3, 2, 1, 0], false), // RGBA
+        0x3000 => ([1, 2, 3, 0], false), // BGRA
+        0x4000 => ([0, 1, 2, 3], true),  // 1BGR
+        0x5000 => ([2, 1, 0, 3], true),  // 1RGB
+        0x6000 => ([3, 2, 1, 0], true),  // RGB1
+        0x7000 => ([1, 2, 3, 0], true),  // BGR1
+        swizzle => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported GXT palette swizzle: {:#06x}",
+                swizzle
+            )))
+        }
+    };
+
+    Ok(palette
+        .chunks_exact(4)
+        .map(|entry| {
+            [
+                entry[order[0]],
+                entry[order[1]],
+                entry[order[2]],
+                if opaque { 255 } else { entry[order[3]] },
+            ]
+        })
+        .collect())
+}
+
+// SceGxmTextureFormat - https://github.com/vitasdk/vita-headers/blob/master/include/psp2/gxm.h
+// Components are named from the most to the least significant bits
+const UNORM: PixelDataType = PixelDataType::UNorm;
+const SNORM: PixelDataType = PixelDataType::SNorm;
+const FLOAT: PixelDataType = PixelDataType::Float;
+
+/// Preferred formats come first, they are used when saving
+const GXM_FORMAT_TABLE: &[(u32, PixelFormat, PixelDataType)] = &[
+    (0x0C000000, PixelFormat::R8G8B8A8, UNORM), // U8U8U8U8_ABGR
+    (0x0C001000, PixelFormat::B8G8R8A8, UNORM), // U8U8U8U8_ARGB
+    (0x0C005000, PixelFormat::B8G8R8X8, UNORM), // U8U8U8U8_1RGB
+    (0x98000000, PixelFormat::R8G8B8, UNORM),   // U8U8U8_BGR
+    (0x98001000, PixelFormat::B8G8R8, UNORM),   // U8U8U8_RGB
+    (0x05001000, PixelFormat::B5G6R5, UNORM),   // U5U6U5_RGB
+    (0x04001000, PixelFormat::B5G5R5A1, UNORM), // U1U5U5U5_ARGB
+    (0x04005000, PixelFormat::B5G5R5X1, UNORM), // U1U5U5U5_1RGB
+    (0x02001000, PixelFormat::B4G4R4A4, UNORM), // U4U4U4U4_ARGB
+    (0x00000000, PixelFormat::R8, UNORM),       // U8_R
+    (0x00001000, PixelFormat::A8, UNORM),       // U8_000R
+    (0x00005000, PixelFormat::L8, UNORM),       // U8_1RRR
+    (0x07000000, PixelFormat::R8G8, UNORM),     // U8U8_GR
+    (0x07002000, PixelFormat::L8A8, UNORM),     // U8U8_GRRR
+    (0x09000000, PixelFormat::R16, UNORM),      // U16_R
+    (0x0F000000, PixelFormat::R16G16, UNORM),   // U16U16_GR
+    (0x1C000000, PixelFormat::R16G16B16A16, UNORM), // U16U16U16U16_ABGR
+    (0x12000000, PixelFormat::R32, FLOAT),      // F32_R
+    (0x1E000000, PixelFormat::R32G32, FLOAT),   // F32F32_GR
+    (0x0E000000, PixelFormat::R10G10B10A2, UNORM), // U2U10U10U10_ABGR
+    (0x1A000000, PixelFormat::R11G11B10, FLOAT), // F11F11F10_BGR
+    (0x19000000, PixelFormat::R9G9B9E5, FLOAT), // SE5M9M9M9_BGR
+    (0x85000000, PixelFormat::BC1, UNORM),      // UBC1_ABGR
+    (0x86000000, PixelFormat::BC2, UNORM),      // UBC2_ABGR
+    (0x87000000, PixelFormat::BC3, UNORM),      // UBC3_ABGR
+    (0x88000000, PixelFormat::BC4, UNORM),      // UBC4_R
+    (0x89000000, PixelFormat::BC4, SNORM),      // SBC4_R
+    (0x8A000000, PixelFormat::BC5, UNORM),      // UBC5_GR
+    (0x8B000000, PixelFormat::BC5, SNORM),      // SBC5_GR
+];
+
+fn get_pixel_format_info_from_gxm_format(texture_format: u32) -> Option<PixelFormatInfo> {
+    let texture_format = texture_format & (BASE_FORMAT_MASK | SWIZZLE_MASK);
+
+    GXM_FORMAT_TABLE
+        .iter()
+        .find(|(gxm_format, _, _)| *gxm_format == texture_format)
+        .map(|&(_, pixel_format, pixel_data_type)| PixelFormatInfo {
+            pixel_format,
+            pixel_data_type,
+            color_space: ColorSpace::Linear,
+            is_premultiplied: false,
+        })
+}
+
+fn get_gxm_format_from_pixel_format_info(pixel_format_info: PixelFormatInfo) -> Option<u32> {
+    let candidates = || {
+        GXM_FORMAT_TABLE
+            .iter()
+            .filter(|(_, pixel_format, _)| *pixel_format == pixel_format_info.pixel_format)
+    };
+
+    candidates()
+        .find(|(_, _, pixel_data_type)| *pixel_data_type == pixel_format_info.pixel_data_type)
+        .or_else(|| candidates().next())
+        .map(|&(gxm_format, _, _)| gxm_format)
+}
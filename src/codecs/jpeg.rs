@@ -1,18 +1,19 @@
-use std::{error::Error, fs, io::Cursor, path::PathBuf};
+use std::{fs, io::Cursor, path::PathBuf};
 
 use image::ImageFormat;
 
-use super::ImageCodec;
+use super::{ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
 use crate::graphics::{crate_image_utility::ImageUtility, texture::Texture};
 
 pub struct JPEGCodec;
 
 impl ImageCodec for JPEGCodec {
-    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
         if texture.metadata.array_size != 1 || texture.metadata.depth != 1 {
-            return Err("JPEG only supports single image textures!"
-                .to_string()
-                .into());
+            return Err(TextureError::UnsupportedFormat(
+                "JPEG only supports single image textures!".to_string(),
+            ));
         }
 
         let image = &texture.images[0];
@@ -25,21 +26,28 @@ impl ImageCodec for JPEGCodec {
         Ok(buffer)
     }
 
-    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), Box<dyn Error>> {
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
         let bytes = self.save_to_memory(texture)?;
 
         Ok(fs::write(filepath, bytes)?)
     }
 
-    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, Box<dyn Error>> {
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
         ImageUtility::get_texture_from_bytes(source)
     }
 
-    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, Box<dyn Error>> {
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
         self.load_from_memory(&fs::read(&filepath)?)
     }
 
     fn supported_extensions(&self) -> Vec<&'static str> {
         vec!["jpg", "jpeg"]
     }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        match image::guess_format(source) {
+            Ok(ImageFormat::Jpeg) => PROBE_SIGNATURE,
+            _ => 0,
+        }
+    }
 }
@@ -0,0 +1,919 @@
+use std::{fs, path::PathBuf};
+
+use super::{
+    check_data_size, check_mip_levels, get_checked_image_size,
+    ktx2::{align, build_key_value_data},
+    reorder_mip_major, ImageCodec, PROBE_SIGNATURE,
+};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// KTX 1.1 - https://registry.khronos.org/KTX/specs/1.0/ktxspec.v1.html
+const KTX_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const KTX_ENDIANNESS: u32 = 0x04030201;
+
+pub struct KTXCodec;
+
+impl ImageCodec for KTXCodec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        let gl_format = get_gl_format_from_pixel_format_info(metadata.pixel_format_info)
+            .ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "KTX does not support pixel format: {}",
+                    pixel_format
+                ))
+            })?;
+
+        let is_volume = metadata.dimensions == TexDimension::Tex3D;
+        let face_count = if metadata.is_cubemap { 6 } else { 1 };
+
+        if metadata.array_size % face_count != 0 {
+            return Err(TextureError::DimensionMismatch(
+                "Cubemap array size must be a multiple of 6!".to_string(),
+            ));
+        }
+
+        let element_count = metadata.array_size / face_count;
+        let is_array = element_count > 1;
+
+        let key_value_data = build_key_value_data(&[
+            ("KTXorientation", "S=r,T=d"),
+            ("KTXwriter", "TextureViewer"),
+        ]);
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&KTX_IDENTIFIER);
+
+        for value in [
+            KTX_ENDIANNESS,
+            gl_format.gl_type,
+            gl_format.gl_type_size,
+            gl_format.gl_format,
+            gl_format.gl_internal_format,
+            gl_format.gl_base_internal_format,
+            metadata.width,
+            if metadata.dimensions == TexDimension::Tex1D {
+                0
+            } else {
+                metadata.height
+            },
+            if is_volume { metadata.depth } else { 0 },
+            if is_array { element_count } else { 0 },
+            face_count,
+            metadata.mip_levels,
+            key_value_data.len() as u32,
+        ] {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+
+        buffer.extend_from_slice(&key_value_data);
+
+        for mip in 0..metadata.mip_levels {
+            let images = if is_volume {
+                (0..std::cmp::max(1, metadata.depth >> mip))
+                    .map(|slice| texture.get_image(mip, 0, slice))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                (0..metadata.array_size)
+                    .map(|item| texture.get_image(mip, item, 0))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+
+            let padded_images: Vec<Vec<u8>> = images
+                .iter()
+                .map(|image| pad_rows(image, pixel_format))
+                .collect();
+
+            // Non-array cubemaps store the size of a single face
+            let image_size = if metadata.is_cubemap && !is_array {
+                padded_images[0].len()
+            } else {
+                padded_images.iter().map(|image| image.len()).sum()
+            };
+
+            buffer.extend_from_slice(&(image_size as u32).to_le_bytes());
+
+            for image in padded_images {
+                buffer.extend_from_slice(&image);
+
+                // Cube padding
+                if metadata.is_cubemap && !is_array {
+                    buffer.resize(align(buffer.len(), 4), 0);
+                }
+            }
+
+            // Mip padding
+            buffer.resize(align(buffer.len(), 4), 0);
+        }
+
+        Ok(buffer)
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let mut reader = BinaryReader::new(source);
+
+        if reader.read_bytes(KTX_IDENTIFIER.len())? != KTX_IDENTIFIER {
+            return Err(TextureError::CorruptHeader(
+                "Invalid KTX file identifier!".to_string(),
+            ));
+        }
+
+        let endianness = reader.read_u32()?;
+
+        if endianness == KTX_ENDIANNESS.swap_bytes() {
+            reader.set_big_endian(true);
+        } else if endianness != KTX_ENDIANNESS {
+            return Err(TextureError::CorruptHeader(format!(
+                "Invalid KTX endianness: {:#010x}",
+                endianness
+            )));
+        }
+
+        let gl_type = reader.read_u32()?;
+        let gl_type_size = reader.read_u32()?;
+        let gl_format = reader.read_u32()?;
+        let gl_internal_format = reader.read_u32()?;
+        let _gl_base_internal_format = reader.read_u32()?;
+        let pixel_width = reader.read_u32()?;
+        let pixel_height = reader.read_u32()?;
+        let pixel_depth = reader.read_u32()?;
+        let number_of_array_elements = reader.read_u32()?;
+        let number_of_faces = reader.read_u32()?;
+        let number_of_mipmap_levels = reader.read_u32()?;
+        let bytes_of_key_value_data = reader.read_u32()? as usize;
+
+        let _key_value_data = read_key_value_data(&mut reader, bytes_of_key_value_data)?;
+
+        let pixel_format_info =
+            get_pixel_format_info_from_gl_format(gl_type, gl_format, gl_internal_format)
+                .ok_or_else(|| {
+                    TextureError::UnsupportedFormat(format!(
+                "Unsupported KTX format: glType {:#x}, glFormat {:#x}, glInternalFormat {:#x}",
+                gl_type, gl_format, gl_internal_format
+            ))
+                })?;
+
+        if pixel_width == 0 {
+            return Err(TextureError::CorruptHeader(
+                "KTX texture width must not be 0!".to_string(),
+            ));
+        }
+
+        if number_of_faces != 1 && number_of_faces != 6 {
+            return Err(TextureError::CorruptHeader(format!(
+                "Invalid KTX face count: {}",
+                number_of_faces
+            )));
+        }
+
+        if pixel_depth > 0 && (number_of_array_elements > 0 || number_of_faces > 1) {
+            return Err(TextureError::UnsupportedFormat(
+                "KTX 3D texture arrays and 3D cubemaps are not supported!".to_string(),
+            ));
+        }
+
+        let pixel_format = pixel_format_info.pixel_format;
+        let width = pixel_width;
+        let height = pixel_height.max(1);
+        let depth = pixel_depth.max(1);
+        let mip_levels = number_of_mipmap_levels.max(1);
+        let array_size = number_of_array_elements
+            .max(1)
+            .checked_mul(number_of_faces)
+            .ok_or_else(|| {
+                TextureError::CorruptHeader(format!(
+                    "Invalid KTX array element count: {}",
+                    number_of_array_elements
+                ))
+            })?;
+        let is_non_array_cubemap = number_of_faces == 6 && number_of_array_elements == 0;
+        let swap_size = if reader.is_big_endian() {
+            gl_type_size as usize
+        } else {
+            1
+        };
+
+        let dimensions = if pixel_depth > 0 {
+            TexDimension::Tex3D
+        } else if pixel_height == 0 {
+            TexDimension::Tex1D
+        } else {
+            TexDimension::Tex2D
+        };
+
+        check_mip_levels("KTX", mip_levels, width, height, depth)?;
+
+        // Images of each level, indexed by array item (1D/2D) or z slice (3D)
+        let mut levels = Vec::with_capacity(mip_levels as usize);
+
+        for mip in 0..mip_levels {
+            let _image_size = reader.read_u32()?;
+
+            let mip_width = std::cmp::max(1, width >> mip);
+            let mip_height = std::cmp::max(1, height >> mip);
+            let mip_depth = std::cmp::max(1, depth >> mip);
+
+            let image_count = if dimensions == TexDimension::Tex3D {
+                mip_depth
+            } else {
+                array_size
+            };
+
+            // Rows are padded in the file, so the level takes at least this many bytes
+            let level_size = get_checked_image_size(
+                "KTX",
+                pixel_format,
+                mip_width,
+                mip_height,
+                u64::from(image_count),
+            )?;
+            check_data_size("KTX", level_size, reader.remaining())?;
+
+            let row_pitch = pixel_format.get_row_pitch(mip_width) as usize;
+            let padded_row_pitch = get_padded_row_pitch(pixel_format, mip_width);
+            let row_count =
+                pixel_format.get_slice_pitch(mip_width, mip_height) as usize / row_pitch;
+
+            let mut images = Vec::with_capacity(image_count as usize);
+
+            for _ in 0..image_count {
+                let mut pixels = Vec::with_capacity(row_pitch * row_count);
+
+                for _ in 0..row_count {
+                    let row = reader.read_bytes(padded_row_pitch)?;
+                    pixels.extend_from_slice(&row[..row_pitch]);
+                }
+
+                if swap_size > 1 {
+                    swap_endianness(&mut pixels, swap_size);
+                }
+
+                if is_non_array_cubemap {
+                    reader.align(4)?;
+                }
+
+                images.push(Image {
+                    width: mip_width,
+                    height: mip_height,
+                    pixel_format_info,
+                    row_pitch: row_pitch as u32,
+                    slice_pitch: pixels.len() as u32,
+                    pixels,
+                });
+            }
+
+            reader.align(4)?;
+            levels.push(images);
+        }
+
+        let metadata = TexMetadata {
+            width,
+            height,
+            depth,
+            array_size,
+            mip_levels,
+            pixel_format_info,
+            alpha_mode: 0,
+            dimensions,
+            is_cubemap: number_of_faces == 6,
+            is_volumemap: dimensions == TexDimension::Tex3D,
+        };
+
+        let images = reorder_mip_major(levels, &metadata);
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["ktx"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(&KTX_IDENTIFIER) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+/// Key/value metadata pairs stored after the header
+type KeyValueData = Vec<(String, Vec<u8>)>;
+
+/// Parse the key/value pairs following the header
+fn read_key_value_data(
+    reader: &mut BinaryReader<'_>,
+    length: usize,
+) -> Result<KeyValueData, TextureError> {
+    let end = reader.position() + length;
+    let mut pairs = Vec::new();
+
+    while reader.position() < end {
+        let key_and_value_size = reader.read_u32()? as usize;
+        let key_and_value = reader.read_bytes(key_and_value_size)?;
+        reader.align(4)?;
+
+        let key_length = key_and_value
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| {
+                TextureError::CorruptHeader("Invalid KTX key/value pair!".to_string())
+            })?;
+
+        let key = String::from_utf8_lossy(&key_and_value[..key_length]).into_owned();
+        let value = key_and_value[key_length + 1..].to_vec();
+
+        pairs.push((key, value));
+    }
+
+    reader.seek(end)?;
+    Ok(pairs)
+}
+
+/// Rows of uncompressed images are padded to 4 bytes
+fn get_padded_row_pitch(pixel_format: PixelFormat, width: u32) -> usize {
+    let row_pitch = pixel_format.get_row_pitch(width) as usize;
+
+    if pixel_format.is_compressed() {
+        row_pitch
+    } else {
+        align(row_pitch, 4)
+    }
+}
+
+fn pad_rows(image: &Image, pixel_format: PixelFormat) -> Vec<u8> {
+    let row_pitch = pixel_format.get_row_pitch(image.width) as usize;
+    let padded_row_pitch = get_padded_row_pitch(pixel_format, image.width);
+
+    if row_pitch == padded_row_pitch {
+        return image.pixels.clone();
+    }
+
+    image
+        .pixels
+        .chunks(row_pitch)
+        .flat_map(|row| {
+            let mut padded_row = row.to_vec();
+            padded_row.resize(padded_row_pitch, 0);
+            padded_row
+        })
+        .collect()
+}
+
+fn swap_endianness(pixels: &mut [u8], type_size: usize) {
+    for chunk in pixels.chunks_exact_mut(type_size) {
+        chunk.reverse();
+    }
+}
+
+// OpenGL enums - https://registry.khronos.org/OpenGL/api/GL/glcorearb.h
+mod gl {
+    pub const UNSIGNED_BYTE: u32 = 0x1401;
+    pub const UNSIGNED_SHORT: u32 = 0x1403;
+    pub const FLOAT: u32 = 0x1406;
+    pub const UNSIGNED_SHORT_4_4_4_4_REV: u32 = 0x8365;
+    pub const UNSIGNED_SHORT_1_5_5_5_REV: u32 = 0x8366;
+    pub const UNSIGNED_SHORT_5_6_5: u32 = 0x8363;
+    pub const UNSIGNED_INT_2_10_10_10_REV: u32 = 0x8368;
+    pub const UNSIGNED_INT_10F_11F_11F_REV: u32 = 0x8C3B;
+    pub const UNSIGNED_INT_5_9_9_9_REV: u32 = 0x8C3E;
+
+    pub const DEPTH_COMPONENT: u32 = 0x1902;
+    pub const RED: u32 = 0x1903;
+    pub const ALPHA: u32 = 0x1906;
+    pub const RGB: u32 = 0x1907;
+    pub const RGBA: u32 = 0x1908;
+    pub const LUMINANCE: u32 = 0x1909;
+    pub const LUMINANCE_ALPHA: u32 = 0x190A;
+    pub const RG: u32 = 0x8227;
+    pub const BGR: u32 = 0x80E0;
+    pub const BGRA: u32 = 0x80E1;
+
+    pub const ALPHA8: u32 = 0x803C;
+    pub const LUMINANCE8: u32 = 0x8040;
+    pub const LUMINANCE8_ALPHA8: u32 = 0x8045;
+    pub const R8: u32 = 0x8229;
+    pub const RG8: u32 = 0x822B;
+    pub const RGB8: u32 = 0x8051;
+    pub const SRGB8: u32 = 0x8C41;
+    pub const RGBA8: u32 = 0x8058;
+    pub const SRGB8_ALPHA8: u32 = 0x8C43;
+    pub const RGB565: u32 = 0x8D62;
+    pub const RGB5_A1: u32 = 0x8057;
+    pub const RGBA4: u32 = 0x8056;
+    pub const R16: u32 = 0x822A;
+    pub const RG16: u32 = 0x822C;
+    pub const RGB16: u32 = 0x8054;
+    pub const RGBA16: u32 = 0x805B;
+    pub const R32F: u32 = 0x822E;
+    pub const RG32F: u32 = 0x8230;
+    pub const RGB32F: u32 = 0x8815;
+    pub const RGBA32F: u32 = 0x8814;
+    pub const RGB10_A2: u32 = 0x8059;
+    pub const R11F_G11F_B10F: u32 = 0x8C3A;
+    pub const RGB9_E5: u32 = 0x8C3D;
+    pub const DEPTH_COMPONENT16: u32 = 0x81A5;
+
+    pub const COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83F0;
+    pub const COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+    pub const COMPRESSED_RGBA_S3TC_DXT3: u32 = 0x83F2;
+    pub const COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83F3;
+    pub const COMPRESSED_SRGB_S3TC_DXT1: u32 = 0x8C4C;
+    pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: u32 = 0x8C4D;
+    pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: u32 = 0x8C4E;
+    pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: u32 = 0x8C4F;
+    pub const COMPRESSED_RED_RGTC1: u32 = 0x8DBB;
+    pub const COMPRESSED_SIGNED_RED_RGTC1: u32 = 0x8DBC;
+    pub const COMPRESSED_RG_RGTC2: u32 = 0x8DBD;
+    pub const COMPRESSED_SIGNED_RG_RGTC2: u32 = 0x8DBE;
+    pub const COMPRESSED_RGBA_BPTC_UNORM: u32 = 0x8E8C;
+    pub const COMPRESSED_SRGB_ALPHA_BPTC_UNORM: u32 = 0x8E8D;
+    pub const COMPRESSED_RGB_BPTC_SIGNED_FLOAT: u32 = 0x8E8E;
+    pub const COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT: u32 = 0x8E8F;
+}
+
+#[derive(Clone, Copy)]
+struct GLFormat {
+    pixel_format: PixelFormat,
+    pixel_data_type: PixelDataType,
+    is_srgb: bool,
+    gl_type: u32,
+    gl_type_size: u32,
+    gl_format: u32,
+    gl_internal_format: u32,
+    gl_base_internal_format: u32,
+}
+
+const fn uncompressed(
+    pixel_format: PixelFormat,
+    pixel_data_type: PixelDataType,
+    is_srgb: bool,
+    (gl_type, gl_type_size): (u32, u32),
+    gl_format: u32,
+    gl_internal_format: u32,
+    gl_base_internal_format: u32,
+) -> GLFormat {
+    GLFormat {
+        pixel_format,
+        pixel_data_type,
+        is_srgb,
+        gl_type,
+        gl_type_size,
+        gl_format,
+        gl_internal_format,
+        gl_base_internal_format,
+    }
+}
+
+const fn compressed(
+    pixel_format: PixelFormat,
+    pixel_data_type: PixelDataType,
+    is_srgb: bool,
+    gl_internal_format: u32,
+    gl_base_internal_format: u32,
+) -> GLFormat {
+    GLFormat {
+        pixel_format,
+        pixel_data_type,
+        is_srgb,
+        gl_type: 0,
+        gl_type_size: 1,
+        gl_format: 0,
+        gl_internal_format,
+        gl_base_internal_format,
+    }
+}
+
+const UNORM: PixelDataType = PixelDataType::UNorm;
+const SNORM: PixelDataType = PixelDataType::SNorm;
+const FLOAT: PixelDataType = PixelDataType::Float;
+const SFLOAT: PixelDataType = PixelDataType::SFloat;
+
+const U8: (u32, u32) = (gl::UNSIGNED_BYTE, 1);
+const U16: (u32, u32) = (gl::UNSIGNED_SHORT, 2);
+const F32: (u32, u32) = (gl::FLOAT, 4);
+
+/// Preferred formats come first, they are used when saving
+const GL_FORMAT_TABLE: &[GLFormat] = &[
+    uncompressed(
+        PixelFormat::A8,
+        UNORM,
+        false,
+        U8,
+        gl::ALPHA,
+        gl::ALPHA8,
+        gl::ALPHA,
+    ),
+    uncompressed(
+        PixelFormat::L8,
+        UNORM,
+        false,
+        U8,
+        gl::LUMINANCE,
+        gl::LUMINANCE8,
+        gl::LUMINANCE,
+    ),
+    uncompressed(
+        PixelFormat::L8A8,
+        UNORM,
+        false,
+        U8,
+        gl::LUMINANCE_ALPHA,
+        gl::LUMINANCE8_ALPHA8,
+        gl::LUMINANCE_ALPHA,
+    ),
+    uncompressed(PixelFormat::R8, UNORM, false, U8, gl::RED, gl::R8, gl::RED),
+    uncompressed(PixelFormat::R8G8, UNORM, false, U8, gl::RG, gl::RG8, gl::RG),
+    uncompressed(
+        PixelFormat::R8G8B8,
+        UNORM,
+        false,
+        U8,
+        gl::RGB,
+        gl::RGB8,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::R8G8B8,
+        UNORM,
+        true,
+        U8,
+        gl::RGB,
+        gl::SRGB8,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::B8G8R8,
+        UNORM,
+        false,
+        U8,
+        gl::BGR,
+        gl::RGB8,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::B8G8R8,
+        UNORM,
+        true,
+        U8,
+        gl::BGR,
+        gl::SRGB8,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::R8G8B8A8,
+        UNORM,
+        false,
+        U8,
+        gl::RGBA,
+        gl::RGBA8,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::R8G8B8A8,
+        UNORM,
+        true,
+        U8,
+        gl::RGBA,
+        gl::SRGB8_ALPHA8,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::B8G8R8A8,
+        UNORM,
+        false,
+        U8,
+        gl::BGRA,
+        gl::RGBA8,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::B8G8R8A8,
+        UNORM,
+        true,
+        U8,
+        gl::BGRA,
+        gl::SRGB8_ALPHA8,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::B5G6R5,
+        UNORM,
+        false,
+        (gl::UNSIGNED_SHORT_5_6_5, 2),
+        gl::RGB,
+        gl::RGB565,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::B5G5R5A1,
+        UNORM,
+        false,
+        (gl::UNSIGNED_SHORT_1_5_5_5_REV, 2),
+        gl::BGRA,
+        gl::RGB5_A1,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::B4G4R4A4,
+        UNORM,
+        false,
+        (gl::UNSIGNED_SHORT_4_4_4_4_REV, 2),
+        gl::BGRA,
+        gl::RGBA4,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::R16,
+        UNORM,
+        false,
+        U16,
+        gl::RED,
+        gl::R16,
+        gl::RED,
+    ),
+    uncompressed(
+        PixelFormat::R16G16,
+        UNORM,
+        false,
+        U16,
+        gl::RG,
+        gl::RG16,
+        gl::RG,
+    ),
+    uncompressed(
+        PixelFormat::R16G16B16,
+        UNORM,
+        false,
+        U16,
+        gl::RGB,
+        gl::RGB16,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::R16G16B16A16,
+        UNORM,
+        false,
+        U16,
+        gl::RGBA,
+        gl::RGBA16,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::R32,
+        FLOAT,
+        false,
+        F32,
+        gl::RED,
+        gl::R32F,
+        gl::RED,
+    ),
+    uncompressed(
+        PixelFormat::R32G32,
+        FLOAT,
+        false,
+        F32,
+        gl::RG,
+        gl::RG32F,
+        gl::RG,
+    ),
+    uncompressed(
+        PixelFormat::R32G32B32,
+        FLOAT,
+        false,
+        F32,
+        gl::RGB,
+        gl::RGB32F,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::R32G32B32A32,
+        FLOAT,
+        false,
+        F32,
+        gl::RGBA,
+        gl::RGBA32F,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::R10G10B10A2,
+        UNORM,
+        false,
+        (gl::UNSIGNED_INT_2_10_10_10_REV, 4),
+        gl::RGBA,
+        gl::RGB10_A2,
+        gl::RGBA,
+    ),
+    uncompressed(
+        PixelFormat::R11G11B10,
+        FLOAT,
+        false,
+        (gl::UNSIGNED_INT_10F_11F_11F_REV, 4),
+        gl::RGB,
+        gl::R11F_G11F_B10F,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::R9G9B9E5,
+        FLOAT,
+        false,
+        (gl::UNSIGNED_INT_5_9_9_9_REV, 4),
+        gl::RGB,
+        gl::RGB9_E5,
+        gl::RGB,
+    ),
+    uncompressed(
+        PixelFormat::D16,
+        UNORM,
+        false,
+        U16,
+        gl::DEPTH_COMPONENT,
+        gl::DEPTH_COMPONENT16,
+        gl::DEPTH_COMPONENT,
+    ),
+    compressed(
+        PixelFormat::BC1,
+        UNORM,
+        false,
+        gl::COMPRESSED_RGBA_S3TC_DXT1,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC1,
+        UNORM,
+        true,
+        gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC1,
+        UNORM,
+        false,
+        gl::COMPRESSED_RGB_S3TC_DXT1,
+        gl::RGB,
+    ),
+    compressed(
+        PixelFormat::BC1,
+        UNORM,
+        true,
+        gl::COMPRESSED_SRGB_S3TC_DXT1,
+        gl::RGB,
+    ),
+    compressed(
+        PixelFormat::BC2,
+        UNORM,
+        false,
+        gl::COMPRESSED_RGBA_S3TC_DXT3,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC2,
+        UNORM,
+        true,
+        gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC3,
+        UNORM,
+        false,
+        gl::COMPRESSED_RGBA_S3TC_DXT5,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC3,
+        UNORM,
+        true,
+        gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC4,
+        UNORM,
+        false,
+        gl::COMPRESSED_RED_RGTC1,
+        gl::RED,
+    ),
+    compressed(
+        PixelFormat::BC4,
+        SNORM,
+        false,
+        gl::COMPRESSED_SIGNED_RED_RGTC1,
+        gl::RED,
+    ),
+    compressed(
+        PixelFormat::BC5,
+        UNORM,
+        false,
+        gl::COMPRESSED_RG_RGTC2,
+        gl::RG,
+    ),
+    compressed(
+        PixelFormat::BC5,
+        SNORM,
+        false,
+        gl::COMPRESSED_SIGNED_RG_RGTC2,
+        gl::RG,
+    ),
+    compressed(
+        PixelFormat::BC6H,
+        FLOAT,
+        false,
+        gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+        gl::RGB,
+    ),
+    compressed(
+        PixelFormat::BC6H,
+        SFLOAT,
+        false,
+        gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+        gl::RGB,
+    ),
+    compressed(
+        PixelFormat::BC7,
+        UNORM,
+        false,
+        gl::COMPRESSED_RGBA_BPTC_UNORM,
+        gl::RGBA,
+    ),
+    compressed(
+        PixelFormat::BC7,
+        UNORM,
+        true,
+        gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+        gl::RGBA,
+    ),
+];
+
+/// Uncompressed data is described by glFormat and glType, compressed data only by glInternalFormat
+fn get_pixel_format_info_from_gl_format(
+    gl_type: u32,
+    gl_format: u32,
+    gl_internal_format: u32,
+) -> Option<PixelFormatInfo> {
+    let gl_format_info = if gl_type == 0 {
+        GL_FORMAT_TABLE.iter().find(|format| {
+            format.pixel_format.is_compressed() && format.gl_internal_format == gl_internal_format
+        })?
+    } else {
+        let candidates = || {
+            GL_FORMAT_TABLE
+                .iter()
+                .filter(|format| format.gl_type == gl_type && format.gl_format == gl_format)
+        };
+
+        candidates()
+            .find(|format| format.gl_internal_format == gl_internal_format)
+            .or_else(|| candidates().next())?
+    };
+
+    Some(PixelFormatInfo {
+        pixel_format: gl_format_info.pixel_format,
+        pixel_data_type: gl_format_info.pixel_data_type,
+        color_space: if gl_format_info.is_srgb {
+            ColorSpace::SRGB
+        } else {
+            ColorSpace::Linear
+        },
+        is_premultiplied: false,
+    })
+}
+
+fn get_gl_format_from_pixel_format_info(pixel_format_info: PixelFormatInfo) -> Option<GLFormat> {
+    let is_srgb = pixel_format_info.color_space == ColorSpace::SRGB;
+
+    let candidates = || {
+        GL_FORMAT_TABLE
+            .iter()
+            .filter(|format| format.pixel_format == pixel_format_info.pixel_format)
+    };
+
+    candidates()
+        .find(|format| {
+            format.is_srgb == is_srgb && format.pixel_data_type == pixel_format_info.pixel_data_type
+        })
+        .or_else(|| candidates().find(|format| format.is_srgb == is_srgb))
+        .or_else(|| candidates().next())
+        .copied()
+}
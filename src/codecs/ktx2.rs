@@ -0,0 +1,1031 @@
+use std::{
+    fmt, fs,
+    io::{Read, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use super::{
+    check_mip_levels, get_checked_image_size, reorder_mip_major, ImageCodec, PROBE_SIGNATURE,
+};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// KTX 2.0 - https://registry.khronos.org/KTX/specs/2.0/ktxspec.v2.html
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASISLZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+/// Supercompression scheme applied to each mip level when saving
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum KTX2Supercompression {
+    #[default]
+    None,
+    Zstandard,
+    Zlib,
+}
+
+impl fmt::Display for KTX2Supercompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KTX2Supercompression::None => "None",
+            KTX2Supercompression::Zstandard => "Zstandard",
+            KTX2Supercompression::Zlib => "Zlib",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for KTX2Supercompression {
+    type Err = String;
+
+    /// Parse a scheme from its name (case insensitive), e.g. "none" or "zstd"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let supercompression = match s.trim().to_lowercase().as_str() {
+            "none" => KTX2Supercompression::None,
+            "zstandard" | "zstd" => KTX2Supercompression::Zstandard,
+            "zlib" => KTX2Supercompression::Zlib,
+            _ => return Err(format!("Unknown KTX2 supercompression: {}", s)),
+        };
+
+        Ok(supercompression)
+    }
+}
+
+#[derive(Default)]
+pub struct KTX2Codec {
+    pub supercompression: KTX2Supercompression,
+}
+
+impl ImageCodec for KTX2Codec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format_info = metadata.pixel_format_info;
+        let pixel_format = pixel_format_info.pixel_format;
+
+        let vk_format =
+            get_vk_format_from_pixel_format_info(pixel_format_info).ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "KTX2 does not support pixel format: {}",
+                    pixel_format
+                ))
+            })?;
+
+        let is_volume = metadata.dimensions == TexDimension::Tex3D;
+        let face_count = if metadata.is_cubemap { 6 } else { 1 };
+
+        if metadata.array_size % face_count != 0 {
+            return Err(TextureError::DimensionMismatch(
+                "Cubemap array size must be a multiple of 6!".to_string(),
+            ));
+        }
+
+        let layer_count = metadata.array_size / face_count;
+
+        let (scheme, alignment) = match self.supercompression {
+            KTX2Supercompression::None => (
+                SUPERCOMPRESSION_NONE,
+                lcm(pixel_format.get_bytes_per_block().max(1) as usize, 4),
+            ),
+            KTX2Supercompression::Zstandard => (SUPERCOMPRESSION_ZSTD, 1),
+            KTX2Supercompression::Zlib => (SUPERCOMPRESSION_ZLIB, 1),
+        };
+
+        // Each level holds every layer, face and z slice
+        let mut levels = Vec::with_capacity(metadata.mip_levels as usize);
+
+        for mip in 0..metadata.mip_levels {
+            let mut level = Vec::new();
+
+            if is_volume {
+                for slice in 0..std::cmp::max(1, metadata.depth >> mip) {
+                    level.extend_from_slice(&texture.get_image(mip, 0, slice)?.pixels);
+                }
+            } else {
+                for item in 0..metadata.array_size {
+                    level.extend_from_slice(&texture.get_image(mip, item, 0)?.pixels);
+                }
+            }
+
+            let uncompressed_length = level.len();
+
+            let level = match self.supercompression {
+                KTX2Supercompression::None => level,
+                KTX2Supercompression::Zstandard => zstd::bulk::compress(&level, 0)?,
+                KTX2Supercompression::Zlib => {
+                    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(&level)?;
+                    encoder.finish()?
+                }
+            };
+
+            levels.push((level, uncompressed_length));
+        }
+
+        let dfd = build_data_format_descriptor(pixel_format_info);
+        let kvd = build_key_value_data(&[("KTXwriter", "TextureViewer")]);
+
+        let dfd_offset = KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * levels.len();
+        let kvd_offset = dfd_offset + dfd.len();
+        let mut data_offset = kvd_offset + kvd.len();
+
+        // Levels are stored from the smallest mip to the largest one
+        let mut level_offsets = vec![0; levels.len()];
+
+        for (mip, (level, _)) in levels.iter().enumerate().rev() {
+            data_offset = align(data_offset, alignment);
+            level_offsets[mip] = data_offset;
+            data_offset += level.len();
+        }
+
+        let mut buffer = Vec::with_capacity(data_offset);
+        buffer.extend_from_slice(&KTX2_IDENTIFIER);
+
+        for value in [
+            vk_format,
+            get_type_size(pixel_format),
+            metadata.width,
+            if metadata.dimensions == TexDimension::Tex1D {
+                0
+            } else {
+                metadata.height
+            },
+            if is_volume { metadata.depth } else { 0 },
+            if layer_count > 1 { layer_count } else { 0 },
+            face_count,
+            metadata.mip_levels,
+            scheme,
+            dfd_offset as u32,
+            dfd.len() as u32,
+            kvd_offset as u32,
+            kvd.len() as u32,
+        ] {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // No supercompression global data
+        buffer.extend_from_slice(&0u64.to_le_bytes());
+        buffer.extend_from_slice(&0u64.to_le_bytes());
+
+        for ((level, uncompressed_length), offset) in levels.iter().zip(&level_offsets) {
+            buffer.extend_from_slice(&(*offset as u64).to_le_bytes());
+            buffer.extend_from_slice(&(level.len() as u64).to_le_bytes());
+            buffer.extend_from_slice(&(*uncompressed_length as u64).to_le_bytes());
+        }
+
+        buffer.extend_from_slice(&dfd);
+        buffer.extend_from_slice(&kvd);
+
+        for (mip, (level, _)) in levels.iter().enumerate().rev() {
+            buffer.resize(level_offsets[mip], 0);
+            buffer.extend_from_slice(level);
+        }
+
+        Ok(buffer)
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let mut reader = BinaryReader::new(source);
+
+        if reader.read_bytes(KTX2_IDENTIFIER.len())? != KTX2_IDENTIFIER {
+            return Err(TextureError::CorruptHeader(
+                "Invalid KTX2 file identifier!".to_string(),
+            ));
+        }
+
+        let vk_format = reader.read_u32()?;
+        let _type_size = reader.read_u32()?;
+        let pixel_width = reader.read_u32()?;
+        let pixel_height = reader.read_u32()?;
+        let pixel_depth = reader.read_u32()?;
+        let layer_count = reader.read_u32()?;
+        let face_count = reader.read_u32()?;
+        let level_count = reader.read_u32()?;
+        let supercompression_scheme = reader.read_u32()?;
+
+        let dfd_offset = reader.read_u32()? as usize;
+        let dfd_length = reader.read_u32()? as usize;
+        let _kvd_offset = reader.read_u32()?;
+        let _kvd_length = reader.read_u32()?;
+        let _sgd_offset = reader.read_u64()?;
+        let _sgd_length = reader.read_u64()?;
+
+        let mut pixel_format_info =
+            get_pixel_format_info_from_vk_format(vk_format).ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!("Unsupported KTX2 VkFormat: {}", vk_format))
+            })?;
+
+        // DFD flags (the 4th byte of the color description) hold the premultiplied alpha bit
+        if dfd_length >= 16 {
+            let flags = reader.slice(dfd_offset + 15, 1)?[0];
+            pixel_format_info.is_premultiplied = flags & 1 != 0;
+        }
+
+        if pixel_width == 0 {
+            return Err(TextureError::CorruptHeader(
+                "KTX2 texture width must not be 0!".to_string(),
+            ));
+        }
+
+        if face_count != 1 && face_count != 6 {
+            return Err(TextureError::CorruptHeader(format!(
+                "Invalid KTX2 face count: {}",
+                face_count
+            )));
+        }
+
+        if pixel_depth > 0 && (layer_count > 0 || face_count > 1) {
+            return Err(TextureError::UnsupportedFormat(
+                "KTX2 3D texture arrays and 3D cubemaps are not supported!".to_string(),
+            ));
+        }
+
+        let pixel_format = pixel_format_info.pixel_format;
+        let width = pixel_width;
+        let height = pixel_height.max(1);
+        let depth = pixel_depth.max(1);
+        let mip_levels = level_count.max(1);
+        let array_size = layer_count.max(1).checked_mul(face_count).ok_or_else(|| {
+            TextureError::CorruptHeader(format!("Invalid KTX2 layer count: {}", layer_count))
+        })?;
+
+        check_mip_levels("KTX2", mip_levels, width, height, depth)?;
+
+        let dimensions = if pixel_depth > 0 {
+            TexDimension::Tex3D
+        } else if pixel_height == 0 {
+            TexDimension::Tex1D
+        } else {
+            TexDimension::Tex2D
+        };
+
+        // Level index: offset, length and uncompressed length of each mip
+        let mut level_index = Vec::with_capacity(mip_levels as usize);
+
+        for _ in 0..mip_levels {
+            let offset = reader.read_u64()? as usize;
+            let length = reader.read_u64()? as usize;
+            let _uncompressed_length = reader.read_u64()?;
+            level_index.push((offset, length));
+        }
+
+        // Images of each level, indexed by array item (1D/2D) or z slice (3D)
+        let mut levels = Vec::with_capacity(mip_levels as usize);
+
+        for (mip, (offset, length)) in level_index.into_iter().enumerate() {
+            let mip_width = std::cmp::max(1, width >> mip);
+            let mip_height = std::cmp::max(1, height >> mip);
+            let mip_depth = std::cmp::max(1, depth >> mip);
+
+            let image_count = if dimensions == TexDimension::Tex3D {
+                mip_depth
+            } else {
+                array_size
+            };
+
+            // The uncompressed length of the level index is not trusted, decompression stops
+            // at the size the level has to be
+            let level_size = get_checked_image_size(
+                "KTX2",
+                pixel_format,
+                mip_width,
+                mip_height,
+                u64::from(image_count),
+            )?;
+
+            let compressed_data = reader.slice(offset, length)?;
+
+            let level_data = match supercompression_scheme {
+                SUPERCOMPRESSION_NONE => compressed_data.to_vec(),
+                SUPERCOMPRESSION_ZSTD => {
+                    let mut decompressed = Vec::new();
+                    zstd::stream::read::Decoder::new(compressed_data)?
+                        .take(level_size as u64)
+                        .read_to_end(&mut decompressed)?;
+                    decompressed
+                }
+                SUPERCOMPRESSION_ZLIB => {
+                    let mut decompressed = Vec::new();
+                    ZlibDecoder::new(compressed_data)
+                        .take(level_size as u64)
+                        .read_to_end(&mut decompressed)?;
+                    decompressed
+                }
+                SUPERCOMPRESSION_BASISLZ => {
+                    return Err(TextureError::UnsupportedFormat(
+                        "BasisLZ supercompression is not supported!".to_string(),
+                    ))
+                }
+                scheme => {
+                    return Err(TextureError::UnsupportedFormat(format!(
+                        "Unknown KTX2 supercompression scheme: {}",
+                        scheme
+                    )))
+                }
+            };
+
+            let row_pitch = pixel_format.get_row_pitch(mip_width);
+            let slice_pitch = pixel_format.get_slice_pitch(mip_width, mip_height);
+
+            if level_data.len() < level_size {
+                return Err(TextureError::CorruptHeader(format!(
+                    "KTX2 mip level {} is truncated!",
+                    mip
+                )));
+            }
+
+            let images: Vec<Image> = level_data
+                .chunks_exact(slice_pitch as usize)
+                .take(image_count as usize)
+                .map(|pixels| Image {
+                    width: mip_width,
+                    height: mip_height,
+                    pixel_format_info,
+                    row_pitch,
+                    slice_pitch,
+                    pixels: pixels.to_vec(),
+                })
+                .collect();
+
+            levels.push(images);
+        }
+
+        let metadata = TexMetadata {
+            width,
+            height,
+            depth,
+            array_size,
+            mip_levels,
+            pixel_format_info,
+            alpha_mode: 0,
+            dimensions,
+            is_cubemap: face_count == 6,
+            is_volumemap: dimensions == TexDimension::Tex3D,
+        };
+
+        let images = reorder_mip_major(levels, &metadata);
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["ktx2"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(&KTX2_IDENTIFIER) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+pub(super) fn align(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+
+    a / x * b
+}
+
+// VkFormat values - https://registry.khronos.org/vulkan/specs/latest/man/html/VkFormat.html
+mod vk_format {
+    pub const R5G6B5_UNORM_PACK16: u32 = 4;
+    pub const A1R5G5B5_UNORM_PACK16: u32 = 8;
+    pub const R8_UNORM: u32 = 9;
+    pub const R8_SRGB: u32 = 15;
+    pub const R8G8_UNORM: u32 = 16;
+    pub const R8G8_SRGB: u32 = 22;
+    pub const R8G8B8_UNORM: u32 = 23;
+    pub const R8G8B8_SRGB: u32 = 29;
+    pub const B8G8R8_UNORM: u32 = 30;
+    pub const B8G8R8_SRGB: u32 = 36;
+    pub const R8G8B8A8_UNORM: u32 = 37;
+    pub const R8G8B8A8_SRGB: u32 = 43;
+    pub const B8G8R8A8_UNORM: u32 = 44;
+    pub const B8G8R8A8_SRGB: u32 = 50;
+    pub const A2B10G10R10_UNORM_PACK32: u32 = 64;
+    pub const R16_UNORM: u32 = 70;
+    pub const R16G16_UNORM: u32 = 77;
+    pub const R16G16B16_UNORM: u32 = 84;
+    pub const R16G16B16A16_UNORM: u32 = 91;
+    pub const R32_SFLOAT: u32 = 100;
+    pub const R32G32_SFLOAT: u32 = 103;
+    pub const R32G32B32_SFLOAT: u32 = 106;
+    pub const R32G32B32A32_SFLOAT: u32 = 109;
+    pub const B10G11R11_UFLOAT_PACK32: u32 = 122;
+    pub const E5B9G9R9_UFLOAT_PACK32: u32 = 123;
+    pub const D16_UNORM: u32 = 124;
+    pub const BC1_RGB_UNORM_BLOCK: u32 = 131;
+    pub const BC1_RGB_SRGB_BLOCK: u32 = 132;
+    pub const BC1_RGBA_UNORM_BLOCK: u32 = 133;
+    pub const BC1_RGBA_SRGB_BLOCK: u32 = 134;
+    pub const BC2_UNORM_BLOCK: u32 = 135;
+    pub const BC2_SRGB_BLOCK: u32 = 136;
+    pub const BC3_UNORM_BLOCK: u32 = 137;
+    pub const BC3_SRGB_BLOCK: u32 = 138;
+    pub const BC4_UNORM_BLOCK: u32 = 139;
+    pub const BC4_SNORM_BLOCK: u32 = 140;
+    pub const BC5_UNORM_BLOCK: u32 = 141;
+    pub const BC5_SNORM_BLOCK: u32 = 142;
+    pub const BC6H_UFLOAT_BLOCK: u32 = 143;
+    pub const BC6H_SFLOAT_BLOCK: u32 = 144;
+    pub const BC7_UNORM_BLOCK: u32 = 145;
+    pub const BC7_SRGB_BLOCK: u32 = 146;
+    pub const A4R4G4B4_UNORM_PACK16: u32 = 1000340000;
+}
+
+/// VkFormat, PixelFormat, data type and whether the VkFormat is sRGB
+const VK_FORMAT_TABLE: &[(u32, PixelFormat, PixelDataType, bool)] = &[
+    (
+        vk_format::R5G6B5_UNORM_PACK16,
+        PixelFormat::B5G6R5,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::A1R5G5B5_UNORM_PACK16,
+        PixelFormat::B5G5R5A1,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::A4R4G4B4_UNORM_PACK16,
+        PixelFormat::B4G4R4A4,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R8_UNORM,
+        PixelFormat::R8,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R8_SRGB,
+        PixelFormat::R8,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::R8G8_UNORM,
+        PixelFormat::R8G8,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R8G8_SRGB,
+        PixelFormat::R8G8,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::R8G8B8_UNORM,
+        PixelFormat::R8G8B8,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R8G8B8_SRGB,
+        PixelFormat::R8G8B8,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::B8G8R8_UNORM,
+        PixelFormat::B8G8R8,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::B8G8R8_SRGB,
+        PixelFormat::B8G8R8,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::R8G8B8A8_UNORM,
+        PixelFormat::R8G8B8A8,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R8G8B8A8_SRGB,
+        PixelFormat::R8G8B8A8,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::B8G8R8A8_UNORM,
+        PixelFormat::B8G8R8A8,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::B8G8R8A8_SRGB,
+        PixelFormat::B8G8R8A8,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::A2B10G10R10_UNORM_PACK32,
+        PixelFormat::R10G10B10A2,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R16_UNORM,
+        PixelFormat::R16,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R16G16_UNORM,
+        PixelFormat::R16G16,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R16G16B16_UNORM,
+        PixelFormat::R16G16B16,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R16G16B16A16_UNORM,
+        PixelFormat::R16G16B16A16,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::R32_SFLOAT,
+        PixelFormat::R32,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::R32G32_SFLOAT,
+        PixelFormat::R32G32,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::R32G32B32_SFLOAT,
+        PixelFormat::R32G32B32,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::R32G32B32A32_SFLOAT,
+        PixelFormat::R32G32B32A32,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::B10G11R11_UFLOAT_PACK32,
+        PixelFormat::R11G11B10,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::E5B9G9R9_UFLOAT_PACK32,
+        PixelFormat::R9G9B9E5,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::D16_UNORM,
+        PixelFormat::D16,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC1_RGBA_UNORM_BLOCK,
+        PixelFormat::BC1,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC1_RGBA_SRGB_BLOCK,
+        PixelFormat::BC1,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::BC1_RGB_UNORM_BLOCK,
+        PixelFormat::BC1,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC1_RGB_SRGB_BLOCK,
+        PixelFormat::BC1,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::BC2_UNORM_BLOCK,
+        PixelFormat::BC2,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC2_SRGB_BLOCK,
+        PixelFormat::BC2,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::BC3_UNORM_BLOCK,
+        PixelFormat::BC3,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC3_SRGB_BLOCK,
+        PixelFormat::BC3,
+        PixelDataType::UNorm,
+        true,
+    ),
+    (
+        vk_format::BC4_UNORM_BLOCK,
+        PixelFormat::BC4,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC4_SNORM_BLOCK,
+        PixelFormat::BC4,
+        PixelDataType::SNorm,
+        false,
+    ),
+    (
+        vk_format::BC5_UNORM_BLOCK,
+        PixelFormat::BC5,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC5_SNORM_BLOCK,
+        PixelFormat::BC5,
+        PixelDataType::SNorm,
+        false,
+    ),
+    (
+        vk_format::BC6H_UFLOAT_BLOCK,
+        PixelFormat::BC6H,
+        PixelDataType::Float,
+        false,
+    ),
+    (
+        vk_format::BC6H_SFLOAT_BLOCK,
+        PixelFormat::BC6H,
+        PixelDataType::SFloat,
+        false,
+    ),
+    (
+        vk_format::BC7_UNORM_BLOCK,
+        PixelFormat::BC7,
+        PixelDataType::UNorm,
+        false,
+    ),
+    (
+        vk_format::BC7_SRGB_BLOCK,
+        PixelFormat::BC7,
+        PixelDataType::UNorm,
+        true,
+    ),
+];
+
+fn get_pixel_format_info_from_vk_format(vk_format: u32) -> Option<PixelFormatInfo> {
+    VK_FORMAT_TABLE
+        .iter()
+        .find(|(format, ..)| *format == vk_format)
+        .map(
+            |&(_, pixel_format, pixel_data_type, is_srgb)| PixelFormatInfo {
+                pixel_format,
+                pixel_data_type,
+                color_space: if is_srgb {
+                    ColorSpace::SRGB
+                } else {
+                    ColorSpace::Linear
+                },
+                is_premultiplied: false,
+            },
+        )
+}
+
+/// Pick the VkFormat matching the pixel format, data type and color space.
+/// Falls back to the UNORM variant if there is no sRGB/SNORM one.
+fn get_vk_format_from_pixel_format_info(pixel_format_info: PixelFormatInfo) -> Option<u32> {
+    let is_srgb = pixel_format_info.color_space == ColorSpace::SRGB;
+
+    let candidates = || {
+        VK_FORMAT_TABLE
+            .iter()
+            .filter(|(_, pixel_format, ..)| *pixel_format == pixel_format_info.pixel_format)
+    };
+
+    candidates()
+        .find(|(_, _, data_type, srgb)| {
+            *srgb == is_srgb && *data_type == pixel_format_info.pixel_data_type
+        })
+        .or_else(|| candidates().find(|(_, _, _, srgb)| *srgb == is_srgb))
+        .or_else(|| candidates().next())
+        .map(|(vk_format, ..)| *vk_format)
+}
+
+fn get_type_size(pixel_format: PixelFormat) -> u32 {
+    match pixel_format {
+        _ if pixel_format.is_compressed() => 1,
+        PixelFormat::R8 | PixelFormat::R8G8 | PixelFormat::R8G8B8 | PixelFormat::B8G8R8 => 1,
+        PixelFormat::R8G8B8A8 | PixelFormat::B8G8R8A8 => 1,
+        PixelFormat::B5G6R5 | PixelFormat::B5G5R5A1 | PixelFormat::B4G4R4A4 => 2,
+        PixelFormat::R16
+        | PixelFormat::R16G16
+        | PixelFormat::R16G16B16
+        | PixelFormat::R16G16B16A16
+        | PixelFormat::D16 => 2,
+        _ => 4,
+    }
+}
+
+// Khronos Data Format - https://registry.khronos.org/DataFormat/specs/1.3/dataformat.1.3.html
+const KHR_DF_MODEL_RGBSDA: u8 = 1;
+const KHR_DF_MODEL_BC1A: u8 = 128;
+const KHR_DF_MODEL_BC2: u8 = 129;
+const KHR_DF_MODEL_BC3: u8 = 130;
+const KHR_DF_MODEL_BC4: u8 = 131;
+const KHR_DF_MODEL_BC5: u8 = 132;
+const KHR_DF_MODEL_BC6H: u8 = 133;
+const KHR_DF_MODEL_BC7: u8 = 134;
+
+const KHR_DF_PRIMARIES_BT709: u8 = 1;
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+const KHR_DF_FLAG_ALPHA_PREMULTIPLIED: u8 = 1;
+
+const CHANNEL_R: u8 = 0;
+const CHANNEL_G: u8 = 1;
+const CHANNEL_B: u8 = 2;
+const CHANNEL_D: u8 = 14;
+const CHANNEL_A: u8 = 15;
+
+const QUALIFIER_LINEAR: u8 = 0x10;
+const QUALIFIER_EXPONENT: u8 = 0x20;
+const QUALIFIER_SIGNED: u8 = 0x40;
+const QUALIFIER_FLOAT: u8 = 0x80;
+
+/// Color model and samples (channel, bit offset, bit length, qualifiers) of a pixel format
+fn get_dfd_samples(pixel_format_info: PixelFormatInfo) -> (u8, Vec<(u8, u16, u8, u8)>) {
+    let signed = if matches!(
+        pixel_format_info.pixel_data_type,
+        PixelDataType::SNorm | PixelDataType::SFloat
+    ) {
+        QUALIFIER_SIGNED
+    } else {
+        0
+    };
+
+    match pixel_format_info.pixel_format {
+        PixelFormat::BC1 => (KHR_DF_MODEL_BC1A, vec![(CHANNEL_R, 0, 64, 0)]),
+        PixelFormat::BC2 => (
+            KHR_DF_MODEL_BC2,
+            vec![(CHANNEL_A, 0, 64, 0), (CHANNEL_R, 64, 64, 0)],
+        ),
+        PixelFormat::BC3 => (
+            KHR_DF_MODEL_BC3,
+            vec![(CHANNEL_A, 0, 64, 0), (CHANNEL_R, 64, 64, 0)],
+        ),
+        PixelFormat::BC4 => (KHR_DF_MODEL_BC4, vec![(CHANNEL_R, 0, 64, signed)]),
+        PixelFormat::BC5 => (
+            KHR_DF_MODEL_BC5,
+            vec![(CHANNEL_R, 0, 64, signed), (CHANNEL_G, 64, 64, signed)],
+        ),
+        PixelFormat::BC6H => (
+            KHR_DF_MODEL_BC6H,
+            vec![(CHANNEL_R, 0, 128, QUALIFIER_FLOAT | signed)],
+        ),
+        PixelFormat::BC7 => (KHR_DF_MODEL_BC7, vec![(CHANNEL_R, 0, 128, 0)]),
+        pixel_format => {
+            let float = QUALIFIER_FLOAT | QUALIFIER_SIGNED;
+
+            let samples = match pixel_format {
+                PixelFormat::R8 => vec![(CHANNEL_R, 0, 8, 0)],
+                PixelFormat::R8G8 => vec![(CHANNEL_R, 0, 8, 0), (CHANNEL_G, 8, 8, 0)],
+                PixelFormat::R8G8B8 => vec![
+                    (CHANNEL_R, 0, 8, 0),
+                    (CHANNEL_G, 8, 8, 0),
+                    (CHANNEL_B, 16, 8, 0),
+                ],
+                PixelFormat::B8G8R8 => vec![
+                    (CHANNEL_B, 0, 8, 0),
+                    (CHANNEL_G, 8, 8, 0),
+                    (CHANNEL_R, 16, 8, 0),
+                ],
+                PixelFormat::R8G8B8A8 => vec![
+                    (CHANNEL_R, 0, 8, 0),
+                    (CHANNEL_G, 8, 8, 0),
+                    (CHANNEL_B, 16, 8, 0),
+                    (CHANNEL_A, 24, 8, 0),
+                ],
+                PixelFormat::B8G8R8A8 => vec![
+                    (CHANNEL_B, 0, 8, 0),
+                    (CHANNEL_G, 8, 8, 0),
+                    (CHANNEL_R, 16, 8, 0),
+                    (CHANNEL_A, 24, 8, 0),
+                ],
+                PixelFormat::B5G6R5 => vec![
+                    (CHANNEL_B, 0, 5, 0),
+                    (CHANNEL_G, 5, 6, 0),
+                    (CHANNEL_R, 11, 5, 0),
+                ],
+                PixelFormat::B5G5R5A1 => vec![
+                    (CHANNEL_B, 0, 5, 0),
+                    (CHANNEL_G, 5, 5, 0),
+                    (CHANNEL_R, 10, 5, 0),
+                    (CHANNEL_A, 15, 1, 0),
+                ],
+                PixelFormat::B4G4R4A4 => vec![
+                    (CHANNEL_B, 0, 4, 0),
+                    (CHANNEL_G, 4, 4, 0),
+                    (CHANNEL_R, 8, 4, 0),
+                    (CHANNEL_A, 12, 4, 0),
+                ],
+                PixelFormat::R10G10B10A2 => vec![
+                    (CHANNEL_R, 0, 10, 0),
+                    (CHANNEL_G, 10, 10, 0),
+                    (CHANNEL_B, 20, 10, 0),
+                    (CHANNEL_A, 30, 2, 0),
+                ],
+                PixelFormat::R16 => vec![(CHANNEL_R, 0, 16, 0)],
+                PixelFormat::R16G16 => vec![(CHANNEL_R, 0, 16, 0), (CHANNEL_G, 16, 16, 0)],
+                PixelFormat::R16G16B16 => vec![
+                    (CHANNEL_R, 0, 16, 0),
+                    (CHANNEL_G, 16, 16, 0),
+                    (CHANNEL_B, 32, 16, 0),
+                ],
+                PixelFormat::R16G16B16A16 => vec![
+                    (CHANNEL_R, 0, 16, 0),
+                    (CHANNEL_G, 16, 16, 0),
+                    (CHANNEL_B, 32, 16, 0),
+                    (CHANNEL_A, 48, 16, 0),
+                ],
+                PixelFormat::R32 => vec![(CHANNEL_R, 0, 32, float)],
+                PixelFormat::R32G32 => vec![(CHANNEL_R, 0, 32, float), (CHANNEL_G, 32, 32, float)],
+                PixelFormat::R32G32B32 => vec![
+                    (CHANNEL_R, 0, 32, float),
+                    (CHANNEL_G, 32, 32, float),
+                    (CHANNEL_B, 64, 32, float),
+                ],
+                PixelFormat::R32G32B32A32 => vec![
+                    (CHANNEL_R, 0, 32, float),
+                    (CHANNEL_G, 32, 32, float),
+                    (CHANNEL_B, 64, 32, float),
+                    (CHANNEL_A, 96, 32, float),
+                ],
+                PixelFormat::R11G11B10 => vec![
+                    (CHANNEL_R, 0, 11, QUALIFIER_FLOAT),
+                    (CHANNEL_G, 11, 11, QUALIFIER_FLOAT),
+                    (CHANNEL_B, 22, 10, QUALIFIER_FLOAT),
+                ],
+                PixelFormat::R9G9B9E5 => vec![
+                    (CHANNEL_R, 0, 9, 0),
+                    (CHANNEL_R, 27, 5, QUALIFIER_EXPONENT),
+                    (CHANNEL_G, 9, 9, 0),
+                    (CHANNEL_G, 27, 5, QUALIFIER_EXPONENT),
+                    (CHANNEL_B, 18, 9, 0),
+                    (CHANNEL_B, 27, 5, QUALIFIER_EXPONENT),
+                ],
+                PixelFormat::D16 => vec![(CHANNEL_D, 0, 16, 0)],
+                _ => Vec::new(),
+            };
+
+            (KHR_DF_MODEL_RGBSDA, samples)
+        }
+    }
+}
+
+/// Build a data format descriptor holding a single basic descriptor block
+fn build_data_format_descriptor(pixel_format_info: PixelFormatInfo) -> Vec<u8> {
+    let pixel_format = pixel_format_info.pixel_format;
+    let (color_model, samples) = get_dfd_samples(pixel_format_info);
+    let is_srgb = pixel_format_info.color_space == ColorSpace::SRGB;
+
+    let block_size = 24 + 16 * samples.len() as u32;
+
+    let mut dfd = Vec::with_capacity(4 + block_size as usize);
+    dfd.extend_from_slice(&(4 + block_size).to_le_bytes());
+    // Vendor ID: Khronos, descriptor type: basic
+    dfd.extend_from_slice(&0u32.to_le_bytes());
+    // Version 1.3 and descriptor block size
+    dfd.extend_from_slice(&(2 | (block_size << 16)).to_le_bytes());
+
+    dfd.push(color_model);
+    dfd.push(KHR_DF_PRIMARIES_BT709);
+    dfd.push(if is_srgb {
+        KHR_DF_TRANSFER_SRGB
+    } else {
+        KHR_DF_TRANSFER_LINEAR
+    });
+    dfd.push(if pixel_format_info.is_premultiplied {
+        KHR_DF_FLAG_ALPHA_PREMULTIPLIED
+    } else {
+        0
+    });
+
+    dfd.push((pixel_format.get_block_width() - 1) as u8);
+    dfd.push((pixel_format.get_block_height() - 1) as u8);
+    dfd.extend_from_slice(&[0, 0]);
+
+    dfd.push(pixel_format.get_bytes_per_block() as u8);
+    dfd.extend_from_slice(&[0; 7]);
+
+    for (channel, bit_offset, bit_length, mut qualifiers) in samples {
+        // Alpha is always stored linearly
+        if is_srgb && channel == CHANNEL_A {
+            qualifiers |= QUALIFIER_LINEAR;
+        }
+
+        let (lower, upper) = if qualifiers & QUALIFIER_EXPONENT != 0 {
+            (15, 31)
+        } else if qualifiers & QUALIFIER_FLOAT != 0 {
+            let lower = if qualifiers & QUALIFIER_SIGNED != 0 {
+                (-1.0f32).to_bits()
+            } else {
+                0
+            };
+            (lower, 1.0f32.to_bits())
+        } else if qualifiers & QUALIFIER_SIGNED != 0 {
+            let max = if bit_length >= 32 {
+                i32::MAX as u32
+            } else {
+                (1u32 << (bit_length - 1)) - 1
+            };
+            ((-(max as i32)) as u32, max)
+        } else if bit_length >= 32 {
+            (0, u32::MAX)
+        } else {
+            (0, (1u32 << bit_length) - 1)
+        };
+
+        dfd.extend_from_slice(&bit_offset.to_le_bytes());
+        dfd.push(bit_length - 1);
+        dfd.push(channel | qualifiers);
+        dfd.extend_from_slice(&[0; 4]);
+        dfd.extend_from_slice(&lower.to_le_bytes());
+        dfd.extend_from_slice(&upper.to_le_bytes());
+    }
+
+    dfd
+}
+
+/// Build the key/value data block, each entry padded to 4 bytes
+pub(super) fn build_key_value_data(entries: &[(&str, &str)]) -> Vec<u8> {
+    let mut kvd = Vec::new();
+
+    for (key, value) in entries {
+        let length = key.len() + 1 + value.len() + 1;
+        kvd.extend_from_slice(&(length as u32).to_le_bytes());
+        kvd.extend_from_slice(key.as_bytes());
+        kvd.push(0);
+        kvd.extend_from_slice(value.as_bytes());
+        kvd.push(0);
+        kvd.resize(align(kvd.len(), 4), 0);
+    }
+
+    kvd
+}
@@ -1,22 +1,119 @@
+pub mod astc;
 pub mod bmp;
+pub mod bntx;
 pub mod codec_manager;
 pub mod dds;
+pub mod gnf;
+pub mod gxt;
 pub mod hdr;
 pub mod jpeg;
+pub mod ktx;
+pub mod ktx2;
 pub mod png;
+pub mod raw;
 pub mod tga;
 pub mod tiff;
+pub mod xpr2;
 
-use std::{error::Error, path::PathBuf};
+use std::path::PathBuf;
 
-use crate::graphics::texture::Texture;
+use crate::error::TextureError;
+use crate::graphics::mipmap_generator::MipmapGenerator;
+use crate::graphics::pixel_format::PixelFormat;
+use crate::graphics::texture::{Image, TexDimension, TexMetadata, Texture};
+
+/// Probe score of data starting with the unique signature of a format
+pub const PROBE_SIGNATURE: u32 = 100;
+/// Probe score of data that only looks plausible for a format without a signature
+pub const PROBE_HEURISTIC: u32 = 25;
 
 /// Trait for image codecs
 /// Implement this trait to add more image codecs and add them in codec_manager.rs
 pub trait ImageCodec {
-    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, Box<dyn Error>>;
-    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), Box<dyn Error>>;
-    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, Box<dyn Error>>;
-    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, Box<dyn Error>>;
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError>;
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError>;
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError>;
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError>;
     fn supported_extensions(&self) -> Vec<&'static str>;
+
+    /// How likely the data is in this format, from 0 (not at all) to `PROBE_SIGNATURE`.
+    /// Codecs that cannot tell keep the default and are only picked by extension.
+    fn probe(&self, _source: &[u8]) -> u32 {
+        0
+    }
+}
+
+/// Reject a mip count read from a header that is larger than the full chain of the size,
+/// deeper levels would shift the size by 32 bits or more
+pub(crate) fn check_mip_levels(
+    format_name: &str,
+    mip_levels: u32,
+    width: u32,
+    height: u32,
+    depth: u32,
+) -> Result<(), TextureError> {
+    let max_levels = MipmapGenerator::get_mip_count(width, height, depth, None);
+
+    if mip_levels > max_levels {
+        return Err(TextureError::CorruptHeader(format!(
+            "{} mip count {} is larger than {} for {}x{}x{}!",
+            format_name, mip_levels, max_levels, width, height, depth
+        )));
+    }
+
+    Ok(())
+}
+
+/// Size in bytes of `count` images, `CorruptHeader` when it overflows
+pub(crate) fn get_checked_image_size(
+    format_name: &str,
+    pixel_format: PixelFormat,
+    width: u32,
+    height: u32,
+    count: u64,
+) -> Result<usize, TextureError> {
+    pixel_format
+        .get_checked_size(width, height, count)
+        .and_then(|size| usize::try_from(size).ok())
+        .ok_or_else(|| {
+            TextureError::CorruptHeader(format!(
+                "{} texture of {} {}x{} {} images is too large!",
+                format_name, count, width, height, pixel_format
+            ))
+        })
+}
+
+/// Reject image data larger than what is left of the file, before allocating for it
+pub(crate) fn check_data_size(
+    format_name: &str,
+    size: usize,
+    remaining: usize,
+) -> Result<(), TextureError> {
+    if size > remaining {
+        return Err(TextureError::CorruptHeader(format!(
+            "{} image data is truncated: {} bytes needed, {} left!",
+            format_name, size, remaining
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reorder images stored level by level, `levels[mip][item]`, into the layout expected by
+/// `Texture::get_image`. Volume slices stay level by level.
+pub(crate) fn reorder_mip_major(levels: Vec<Vec<Image>>, metadata: &TexMetadata) -> Vec<Image> {
+    if metadata.dimensions == TexDimension::Tex3D {
+        return levels.into_iter().flatten().collect();
+    }
+
+    let mut images = Vec::with_capacity(levels.iter().map(Vec::len).sum());
+    let mut levels: Vec<_> = levels.into_iter().map(Vec::into_iter).collect();
+
+    for _ in 0..metadata.array_size {
+        for level in &mut levels {
+            images.extend(level.next());
+        }
+    }
+
+    images
 }
@@ -0,0 +1,300 @@
+use std::{fs, path::PathBuf};
+
+use super::ImageCodec;
+use crate::error::TextureError;
+use crate::graphics::{
+    pixel_format::{PixelFormat, PixelFormatInfo},
+    swizzling::{
+        swizzle_manager::{SwizzleOptions, Swizzler},
+        Platform,
+    },
+    texture::{Image, TexDimension, TexMetadata, Texture},
+};
+
+// Headerless pixel data. Every parameter comes from the user, so the codec is not registered
+// in the codec manager.
+
+/// Formats offered when opening raw data, and tried when guessing its layout
+pub const RAW_PIXEL_FORMATS: &[PixelFormat] = &[
+    PixelFormat::R8G8B8A8,
+    PixelFormat::B8G8R8A8,
+    PixelFormat::R8G8B8,
+    PixelFormat::B8G8R8,
+    PixelFormat::B5G6R5,
+    PixelFormat::B5G5R5A1,
+    PixelFormat::B4G4R4A4,
+    PixelFormat::A8,
+    PixelFormat::R8,
+    PixelFormat::L8,
+    PixelFormat::L8A8,
+    PixelFormat::R8G8,
+    PixelFormat::R16G16B16A16,
+    PixelFormat::R32G32B32A32,
+    PixelFormat::BC1,
+    PixelFormat::BC2,
+    PixelFormat::BC3,
+    PixelFormat::BC4,
+    PixelFormat::BC5,
+    PixelFormat::BC6H,
+    PixelFormat::BC7,
+    PixelFormat::GxI4,
+    PixelFormat::GxI8,
+    PixelFormat::GxIA4,
+    PixelFormat::GxIA8,
+    PixelFormat::GxRGB565,
+    PixelFormat::GxRGB5A3,
+    PixelFormat::GxRGBA8,
+    PixelFormat::GxCMPR,
+    PixelFormat::GxC4,
+    PixelFormat::GxC8,
+    PixelFormat::Ps2PSMT4,
+    PixelFormat::Ps2PSMT8,
+];
+
+/// Largest side tried when guessing, in texels
+const MAX_GUESS_SIZE_LOG2: u32 = 13;
+/// Longest side over shortest side of a plausible texture
+const MAX_GUESS_ASPECT_RATIO: u32 = 8;
+
+/// Reads and writes pixel data without any header
+#[derive(Debug, Clone, Copy)]
+pub struct RawCodec {
+    /// Offset of the first byte of pixel data
+    pub offset: usize,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: PixelFormat,
+    pub mip_levels: u32,
+    /// Platform whose swizzling is undone on load and applied on save
+    pub platform: Option<Platform>,
+    /// Surface settings of the platform swizzling
+    pub swizzle_options: SwizzleOptions,
+}
+
+impl Default for RawCodec {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            width: 256,
+            height: 256,
+            pixel_format: PixelFormat::R8G8B8A8,
+            mip_levels: 1,
+            platform: None,
+            swizzle_options: SwizzleOptions::default(),
+        }
+    }
+}
+
+/// A width, height, format and mip count whose data size matches the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawLayoutGuess {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: PixelFormat,
+    pub mip_levels: u32,
+}
+
+impl RawCodec {
+    fn get_metadata(&self) -> Result<TexMetadata, TextureError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(TextureError::InvalidParameter(
+                "Raw texture size must not be zero!".to_string(),
+            ));
+        }
+
+        let max_mip_levels = get_max_mip_levels(self.width, self.height);
+
+        if self.mip_levels == 0 || self.mip_levels > max_mip_levels {
+            return Err(TextureError::InvalidParameter(format!(
+                "Raw texture mip count must be between 1 and {}: {}",
+                max_mip_levels, self.mip_levels
+            )));
+        }
+
+        // Slice pitches are u32, larger images cannot be read
+        let palette_size = u64::from(self.pixel_format.get_palette_size());
+
+        if !self
+            .pixel_format
+            .get_checked_size(self.width, self.height, 1)
+            .is_some_and(|size| size + palette_size <= u64::from(u32::MAX))
+        {
+            return Err(TextureError::InvalidParameter(format!(
+                "Raw texture is too large: {}x{} {}",
+                self.width, self.height, self.pixel_format
+            )));
+        }
+
+        Ok(TexMetadata {
+            width: self.width,
+            height: self.height,
+            depth: 1,
+            array_size: 1,
+            mip_levels: self.mip_levels,
+            pixel_format_info: PixelFormatInfo {
+                pixel_format: self.pixel_format,
+                ..Default::default()
+            },
+            alpha_mode: 0,
+            dimensions: TexDimension::Tex2D,
+            is_cubemap: false,
+            is_volumemap: false,
+        })
+    }
+}
+
+impl ImageCodec for RawCodec {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        if let Some(platform) = self.platform {
+            return Swizzler::with_options(&self.swizzle_options)
+                .swizzle_texture(texture, platform);
+        }
+
+        Ok(texture
+            .images
+            .iter()
+            .flat_map(|image| image.pixels.iter().copied())
+            .collect())
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let metadata = self.get_metadata()?;
+        let data = source.get(self.offset..).ok_or_else(|| {
+            TextureError::InvalidParameter(format!(
+                "Raw data offset out of bounds: {} > {}",
+                self.offset,
+                source.len()
+            ))
+        })?;
+
+        if let Some(platform) = self.platform {
+            return Swizzler::with_options(&self.swizzle_options)
+                .deswizzle_texture(data, &metadata, platform);
+        }
+
+        let mut images = Vec::new();
+        let mut offset = 0;
+
+        for (width, height) in metadata.get_image_sizes() {
+            let size = get_image_size(width, height, self.pixel_format);
+            let pixels = data.get(offset..offset + size).ok_or_else(|| {
+                TextureError::DimensionMismatch(format!(
+                    "Raw data too small: {} < {}",
+                    source.len(),
+                    self.offset + offset + size
+                ))
+            })?;
+
+            images.push(Image::new(
+                width,
+                height,
+                metadata.pixel_format_info,
+                pixels.to_vec(),
+            ));
+            offset += size;
+        }
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["raw", "bin"]
+    }
+}
+
+/// Propose layouts whose linear data size is exactly `data_size` bytes, the squarest first.
+/// Single images may have any height, mip chains are only tried for power of two sizes.
+/// Platform padding is not taken into account.
+pub fn guess_raw_layouts(data_size: usize, pixel_formats: &[PixelFormat]) -> Vec<RawLayoutGuess> {
+    let mut guesses = Vec::new();
+
+    if data_size == 0 {
+        return guesses;
+    }
+
+    for &pixel_format in pixel_formats {
+        let palette_size = pixel_format.get_palette_size() as usize;
+
+        for width in (0..=MAX_GUESS_SIZE_LOG2).map(|log2| 1 << log2) {
+            let row_pitch = pixel_format.get_row_pitch(width) as usize;
+            let pixels_size = data_size.saturating_sub(palette_size);
+
+            if row_pitch > 0 && pixels_size % row_pitch == 0 {
+                let height = (pixels_size / row_pitch) as u32 * pixel_format.get_block_height();
+
+                if is_plausible_size(width, height)
+                    && get_image_size(width, height, pixel_format) == data_size
+                {
+                    guesses.push(RawLayoutGuess {
+                        width,
+                        height,
+                        pixel_format,
+                        mip_levels: 1,
+                    });
+                }
+            }
+
+            for height in (0..=MAX_GUESS_SIZE_LOG2).map(|log2| 1 << log2) {
+                if !is_plausible_size(width, height) {
+                    continue;
+                }
+
+                let mut size = get_image_size(width, height, pixel_format);
+
+                for mip in 1..get_max_mip_levels(width, height) {
+                    size +=
+                        get_image_size((width >> mip).max(1), (height >> mip).max(1), pixel_format);
+
+                    if size > data_size {
+                        break;
+                    }
+
+                    if size == data_size {
+                        guesses.push(RawLayoutGuess {
+                            width,
+                            height,
+                            pixel_format,
+                            mip_levels: mip + 1,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    guesses.sort_by_key(|guess| {
+        let (width, height) = (guess.width as u64, guess.height as u64);
+        // Aspect ratio in 1/1000ths, then the longest mip chains
+        (
+            width.max(height) * 1000 / width.min(height),
+            u32::MAX - guess.mip_levels,
+        )
+    });
+
+    guesses
+}
+
+fn get_image_size(width: u32, height: u32, pixel_format: PixelFormat) -> usize {
+    (pixel_format.get_slice_pitch(width, height) + pixel_format.get_palette_size()) as usize
+}
+
+fn get_max_mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).leading_zeros()
+}
+
+fn is_plausible_size(width: u32, height: u32) -> bool {
+    width > 0
+        && height > 0
+        && height <= 1 << MAX_GUESS_SIZE_LOG2
+        && width.max(height) <= width.min(height) * MAX_GUESS_ASPECT_RATIO
+}
@@ -1,23 +1,32 @@
-use std::{error::Error, fs, io::Cursor, path::PathBuf};
+use std::{fs, io::Cursor, path::PathBuf};
 
 use directxtex::{ScratchImage, DXGI_FORMAT, TEX_FILTER_DEFAULT};
 use image::ImageFormat;
 
-use super::ImageCodec;
-use crate::graphics::{
-    crate_directxtex_utility::DirectXTexUtility,
-    crate_image_utility::ImageUtility,
-    texture::{Image, TexMetadata, Texture},
+use super::{ImageCodec, PROBE_HEURISTIC, PROBE_SIGNATURE};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        crate_directxtex_utility::DirectXTexUtility,
+        crate_image_utility::ImageUtility,
+        texture::{Image, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
 };
 
+/// TGA 2.0 files end with this signature
+const TGA_FOOTER_SIGNATURE: &[u8; 18] = b"TRUEVISION-XFILE.\0";
+/// Largest side accepted by the header heuristic, TGA has no magic to rely on
+const TGA_MAX_SIZE: u32 = 8192;
+
 pub struct TGACodec;
 
 impl ImageCodec for TGACodec {
-    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn save_to_memory(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
         if texture.metadata.array_size != 1 || texture.metadata.depth != 1 {
-            return Err("TGA only supports single image textures!"
-                .to_string()
-                .into());
+            return Err(TextureError::UnsupportedFormat(
+                "TGA only supports single image textures!".to_string(),
+            ));
         }
 
         let image = &texture.images[0];
@@ -30,40 +39,35 @@ impl ImageCodec for TGACodec {
         Ok(buffer)
     }
 
-    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), Box<dyn Error>> {
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
         let bytes = self.save_to_memory(texture)?;
 
         Ok(fs::write(filepath, bytes)?)
     }
 
-    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, Box<dyn Error>> {
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
         let (scratch, meta) = {
             let mut meta = Default::default();
-            let mut scratch =
-                ScratchImage::load_tga(&source, Default::default(), Some(&mut meta)).unwrap();
+            let mut scratch = ScratchImage::load_tga(source, Default::default(), Some(&mut meta))?;
 
             if DXGI_FORMAT::is_compressed(scratch.metadata().format) {
-                let hr = scratch.decompress(DXGI_FORMAT::DXGI_FORMAT_R8G8B8A8_UNORM);
-
-                if hr.is_err() {
-                    return Err(format!("Failed to decompress texture: {:?}", hr).into());
-                }
-
-                scratch = hr.unwrap();
+                scratch = scratch
+                    .decompress(DXGI_FORMAT::DXGI_FORMAT_R8G8B8A8_UNORM)
+                    .map_err(|e| {
+                        TextureError::Codec(format!("Failed to decompress texture: {:?}", e))
+                    })?;
             }
 
             if DXGI_FORMAT::DXGI_FORMAT_R8G8B8A8_UNORM != scratch.metadata().format {
-                let hr = scratch.convert(
-                    DXGI_FORMAT::DXGI_FORMAT_R8G8B8A8_UNORM,
-                    TEX_FILTER_DEFAULT,
-                    0.5,
-                );
-
-                if hr.is_err() {
-                    return Err(format!("Failed to convert texture: {:?}", hr).into());
-                }
-
-                scratch = hr.unwrap();
+                scratch = scratch
+                    .convert(
+                        DXGI_FORMAT::DXGI_FORMAT_R8G8B8A8_UNORM,
+                        TEX_FILTER_DEFAULT,
+                        0.5,
+                    )
+                    .map_err(|e| {
+                        TextureError::Codec(format!("Failed to convert texture: {:?}", e))
+                    })?;
             }
 
             (scratch, meta)
@@ -75,7 +79,7 @@ impl ImageCodec for TGACodec {
 
         let mut new_images = Vec::with_capacity(images.len());
 
-        let pixel_format_info = DirectXTexUtility::get_pixel_format_info_from_image(&meta);
+        let pixel_format_info = DirectXTexUtility::get_pixel_format_info_from_image(&meta)?;
 
         for img in images {
             let width = img.width as u32;
@@ -118,11 +122,85 @@ impl ImageCodec for TGACodec {
         })
     }
 
-    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, Box<dyn Error>> {
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
         self.load_from_memory(&fs::read(&filepath)?)
     }
 
     fn supported_extensions(&self) -> Vec<&'static str> {
         vec!["tga"]
     }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.ends_with(TGA_FOOTER_SIGNATURE) {
+            PROBE_SIGNATURE
+        } else if get_tga_size(source).is_some_and(|size| size <= source.len()) {
+            PROBE_HEURISTIC
+        } else {
+            0
+        }
+    }
+}
+
+/// TGA has no magic, only headers with plausible types, depths and sizes are accepted.
+/// RLE packets are walked to find where the image ends.
+pub fn get_tga_size(source: &[u8]) -> Option<usize> {
+    let mut reader = BinaryReader::new(source);
+    let id_length = reader.read_u8().ok()? as usize;
+    let color_map_type = reader.read_u8().ok()?;
+    let image_type = reader.read_u8().ok()?;
+    let _color_map_first = reader.read_u16().ok()?;
+    let color_map_length = reader.read_u16().ok()? as usize;
+    let color_map_depth = reader.read_u8().ok()?;
+    let x_origin = reader.read_u16().ok()?;
+    let y_origin = reader.read_u16().ok()?;
+    let width = reader.read_u16().ok()? as u32;
+    let height = reader.read_u16().ok()? as u32;
+    let depth = reader.read_u8().ok()?;
+    let descriptor = reader.read_u8().ok()?;
+
+    let is_valid_type = match image_type & !8 {
+        1 => color_map_type == 1 && depth == 8 && matches!(color_map_depth, 15 | 16 | 24 | 32),
+        2 => color_map_type == 0 && matches!(depth, 15 | 16 | 24 | 32),
+        3 => color_map_type == 0 && matches!(depth, 8 | 16),
+        _ => false,
+    };
+
+    if !is_valid_type
+        || x_origin != 0
+        || y_origin != 0
+        || !(1..=TGA_MAX_SIZE).contains(&width)
+        || !(1..=TGA_MAX_SIZE).contains(&height)
+        || descriptor & 0xC0 != 0
+        || descriptor & 0x0F > 8
+    {
+        return None;
+    }
+
+    let bytes_per_pixel = (depth as usize).div_ceil(8);
+    let pixel_count = (width * height) as usize;
+    reader
+        .skip(id_length + color_map_length * (color_map_depth as usize).div_ceil(8))
+        .ok()?;
+
+    if image_type & 8 == 0 {
+        reader.skip(pixel_count * bytes_per_pixel).ok()?;
+        return Some(reader.position());
+    }
+
+    let mut pixels = 0;
+
+    while pixels < pixel_count {
+        let packet = reader.read_u8().ok()?;
+        let count = (packet & 0x7F) as usize + 1;
+
+        if packet & 0x80 != 0 {
+            reader.skip(bytes_per_pixel).ok()?;
+        } else {
+            reader.skip(count * bytes_per_pixel).ok()?;
+        }
+
+        pixels += count;
+    }
+
+    (pixels == pixel_count).then_some(reader.position())
 }
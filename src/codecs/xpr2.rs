@@ -0,0 +1,595 @@
+use std::{fs, path::PathBuf};
+
+use super::{check_mip_levels, ImageCodec, PROBE_SIGNATURE};
+use crate::error::TextureError;
+use crate::{
+    graphics::{
+        pixel_format::{PixelDataType, PixelFormat, PixelFormatInfo},
+        swizzling::{xbox_360::Xbox360Swizzler, Swizzable},
+        texture::{Image, TexDimension, TexMetadata, Texture},
+    },
+    io::binary_reader::BinaryReader,
+};
+
+// XPR2 - https://github.com/xenia-project/xenia/blob/master/src/xenia/gpu/xenos.h
+// A big endian header, a table of resources, their D3D headers and names, then the data.
+// Offsets in the header are relative to the end of the first 12 bytes, texture addresses
+// to the start of the data.
+const XPR2_MAGIC: &[u8; 4] = b"XPR2";
+const XPR2_BASE_OFFSET: usize = 12;
+const XPR2_RESOURCE_SIZE: usize = 16;
+const RESOURCE_TEXTURE_2D: &[u8; 4] = b"TX2D";
+const RESOURCE_TEXTURE_CUBE: &[u8; 4] = b"TXCM";
+const RESOURCE_TEXTURE_3D: &[u8; 4] = b"TX3D";
+
+/// Offset of the GPU texture fetch constant in a D3DTexture header
+const FETCH_CONSTANT_OFFSET: usize = 0x1C;
+
+// xenos::Endian, no swap is 0
+const ENDIAN_8_IN_16: u32 = 1;
+const ENDIAN_8_IN_32: u32 = 2;
+const ENDIAN_16_IN_32: u32 = 3;
+
+// xenos::DataDimension
+const DIMENSION_1D: u32 = 0;
+const DIMENSION_3D: u32 = 2;
+const DIMENSION_CUBE: u32 = 3;
+
+#[derive(Default)]
+pub struct XPR2Codec {
+    /// Texture resource to load. `None` loads every texture as array items.
+    pub texture_index: Option<usize>,
+}
+
+/// A texture resource and the fields of its fetch constant
+struct XPR2Texture {
+    name: String,
+    format: u32,
+    swizzle: u32,
+    is_tiled: bool,
+    has_packed_mips: bool,
+    endian: u32,
+    dimension: u32,
+    width: u32,
+    height: u32,
+    array_size: u32,
+    mip_levels: u32,
+    pitch: u32,
+    base_address: usize,
+    mip_address: usize,
+}
+
+impl XPR2Texture {
+    fn read(
+        reader: &mut BinaryReader<'_>,
+        offset: usize,
+        name: String,
+    ) -> Result<Self, TextureError> {
+        reader.seek(offset + FETCH_CONSTANT_OFFSET)?;
+
+        let mut dwords = [0; 6];
+
+        for dword in &mut dwords {
+            *dword = reader.read_u32()?;
+        }
+
+        let dimension = (dwords[5] >> 9) & 0x3;
+        let is_stacked = (dwords[1] >> 10) & 1 == 1;
+
+        let (width, height, array_size) = match dimension {
+            DIMENSION_1D => ((dwords[2] & 0xFFFFFF) + 1, 1, 1),
+            DIMENSION_CUBE => (
+                (dwords[2] & 0x1FFF) + 1,
+                ((dwords[2] >> 13) & 0x1FFF) + 1,
+                6,
+            ),
+            _ => (
+                (dwords[2] & 0x1FFF) + 1,
+                ((dwords[2] >> 13) & 0x1FFF) + 1,
+                if is_stacked {
+                    ((dwords[2] >> 26) & 0x3F) + 1
+                } else {
+                    1
+                },
+            ),
+        };
+
+        let mip_min_level = (dwords[4] >> 2) & 0xF;
+        let mip_max_level = (dwords[4] >> 6) & 0xF;
+        let mip_levels = mip_max_level.saturating_sub(mip_min_level) + 1;
+        check_mip_levels("XPR2", mip_levels, width, height, 1)?;
+
+        Ok(Self {
+            name,
+            format: dwords[1] & 0x3F,
+            swizzle: (dwords[3] >> 1) & 0xFFF,
+            is_tiled: dwords[0] >> 31 == 1,
+            has_packed_mips: (dwords[5] >> 11) & 1 == 1,
+            endian: (dwords[1] >> 6) & 0x3,
+            dimension,
+            width,
+            height,
+            array_size,
+            mip_levels,
+            pitch: ((dwords[0] >> 22) & 0x1FF) << 5,
+            base_address: (dwords[1] & 0xFFFFF000) as usize,
+            mip_address: (dwords[5] & 0xFFFFF000) as usize,
+        })
+    }
+
+    /// First mip level packed with the smaller ones into a single tile, levels of 16x16 or
+    /// less are packed
+    fn get_packed_mip_level(&self) -> u32 {
+        let size = self.width.max(self.height);
+        (u32::BITS - (size - 1).leading_zeros()).saturating_sub(4)
+    }
+
+    fn get_metadata(&self) -> Result<TexMetadata, TextureError> {
+        let pixel_format_info = get_pixel_format_info_from_xenos_format(self.format, self.swizzle)
+            .ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "Unsupported XPR2 texture format: {}",
+                    self.format
+                ))
+            })?;
+
+        let dimensions = match self.dimension {
+            DIMENSION_1D => TexDimension::Tex1D,
+            DIMENSION_3D => {
+                return Err(TextureError::UnsupportedFormat(
+                    "XPR2 3D textures are not supported!".to_string(),
+                ))
+            }
+            _ => TexDimension::Tex2D,
+        };
+
+        Ok(TexMetadata {
+            width: self.width,
+            height: self.height,
+            depth: 1,
+            array_size: self.array_size,
+            mip_levels: self.mip_levels,
+            pixel_format_info,
+            alpha_mode: 0,
+            dimensions,
+            is_cubemap: self.dimension == DIMENSION_CUBE,
+            is_volumemap: false,
+        })
+    }
+
+    /// Untile the texture, or read its linear rows, and fix the byte order
+    fn read_texture(&self, data: &[u8]) -> Result<Texture, TextureError> {
+        let metadata = self.get_metadata()?;
+        let level_count = if self.is_tiled { self.mip_levels } else { 1 };
+
+        if self.has_packed_mips && self.get_packed_mip_level() < level_count {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "XPR2 texture {} has packed mips, which are not supported!",
+                self.name
+            )));
+        }
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        let slice = |offset: usize, size: usize| {
+            data.get(offset..offset + size).ok_or_else(|| {
+                TextureError::CorruptHeader(format!(
+                    "XPR2 texture {} data out of bounds: {} > {}",
+                    self.name,
+                    offset + size,
+                    data.len()
+                ))
+            })
+        };
+
+        if !self.is_tiled {
+            // Linear textures are rarely mipmapped, only the top level is read
+            let metadata = TexMetadata {
+                mip_levels: 1,
+                ..metadata
+            };
+            let row_pitch = pixel_format.get_row_pitch(self.width) as usize;
+            let padded_row_pitch = pixel_format.get_row_pitch(self.pitch.max(self.width)) as usize;
+            let rows = pixel_format.get_slice_pitch(self.width, self.height) as usize / row_pitch;
+            let slice_size = padded_row_pitch * rows;
+            let mut images = Vec::with_capacity(self.array_size as usize);
+
+            for item in 0..self.array_size as usize {
+                let mut surface =
+                    slice(self.base_address + item * slice_size, slice_size)?.to_vec();
+                swap_words(&mut surface, self.endian);
+
+                let pixels = surface
+                    .chunks_exact(padded_row_pitch)
+                    .flat_map(|row| &row[..row_pitch])
+                    .copied()
+                    .collect();
+
+                images.push(Image::new(
+                    self.width,
+                    self.height,
+                    metadata.pixel_format_info,
+                    pixels,
+                ));
+            }
+
+            return Ok(Texture { metadata, images });
+        }
+
+        // The top levels and the mips are stored apart, the swizzler expects them together
+        let swizzler = Xbox360Swizzler;
+        let texture_size = swizzler.get_texture_size(&metadata)?;
+        let base_size = swizzler.get_texture_size(&TexMetadata {
+            mip_levels: 1,
+            ..metadata
+        })?;
+
+        let mut surface = slice(self.base_address, base_size)?.to_vec();
+
+        if self.mip_levels > 1 {
+            surface.extend_from_slice(slice(self.mip_address, texture_size - base_size)?);
+        }
+
+        // The swizzler swaps bytes in 16 bit words itself
+        swap_words(&mut surface, self.endian);
+        swap_words(&mut surface, ENDIAN_8_IN_16);
+
+        swizzler.deswizzle_texture(&surface, &metadata)
+    }
+}
+
+/// Convert big endian words of the given endian mode to little endian
+fn swap_words(data: &mut [u8], endian: u32) {
+    match endian {
+        ENDIAN_8_IN_16 => {
+            for chunk in data.chunks_exact_mut(2) {
+                chunk.swap(0, 1);
+            }
+        }
+        ENDIAN_8_IN_32 => {
+            for chunk in data.chunks_exact_mut(4) {
+                chunk.reverse();
+            }
+        }
+        ENDIAN_16_IN_32 => {
+            for chunk in data.chunks_exact_mut(4) {
+                chunk.rotate_left(2);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parse the resource table, every texture resource with its name
+fn read_textures(source: &[u8]) -> Result<(Vec<XPR2Texture>, usize), TextureError> {
+    let mut reader = BinaryReader::new_big_endian(source);
+
+    if reader.read_bytes(XPR2_MAGIC.len())? != XPR2_MAGIC {
+        return Err(TextureError::CorruptHeader(
+            "Invalid XPR2 file identifier!".to_string(),
+        ));
+    }
+
+    let header_size = reader.read_u32()? as usize;
+    let _data_size = reader.read_u32()?;
+    let resource_count = reader.read_u32()?;
+
+    if resource_count as usize > reader.remaining() / XPR2_RESOURCE_SIZE {
+        return Err(TextureError::CorruptHeader(format!(
+            "XPR2 resource count {} is larger than the file!",
+            resource_count
+        )));
+    }
+
+    let mut resources = Vec::with_capacity(resource_count as usize);
+
+    for _ in 0..resource_count {
+        let resource_type = reader.read_array::<4>()?;
+        let info_offset = reader.read_u32()? as usize + XPR2_BASE_OFFSET;
+        let _info_size = reader.read_u32()?;
+        let name_offset = reader.read_u32()? as usize + XPR2_BASE_OFFSET;
+
+        resources.push((resource_type, info_offset, name_offset));
+    }
+
+    let mut textures = Vec::new();
+
+    for (resource_type, info_offset, name_offset) in resources {
+        if &resource_type != RESOURCE_TEXTURE_2D
+            && &resource_type != RESOURCE_TEXTURE_CUBE
+            && &resource_type != RESOURCE_TEXTURE_3D
+        {
+            continue;
+        }
+
+        let name_bytes = reader.slice(name_offset, source.len().saturating_sub(name_offset))?;
+        let name_length = name_bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name_bytes.len());
+        let name = String::from_utf8_lossy(&name_bytes[..name_length]).into_owned();
+
+        textures.push(XPR2Texture::read(&mut reader, info_offset, name)?);
+    }
+
+    Ok((textures, header_size + XPR2_BASE_OFFSET))
+}
+
+/// Names of the texture resources of an XPR2 file, in the order they are loaded
+pub fn get_texture_names(source: &[u8]) -> Result<Vec<String>, TextureError> {
+    Ok(read_textures(source)?
+        .0
+        .into_iter()
+        .map(|texture| texture.name)
+        .collect())
+}
+
+impl ImageCodec for XPR2Codec {
+    fn save_to_memory(&self, _texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        Err(TextureError::UnsupportedFormat(
+            "Saving XPR2 files is not supported!".to_string(),
+        ))
+    }
+
+    fn save_to_file(&self, filepath: PathBuf, texture: &Texture) -> Result<(), TextureError> {
+        let bytes = self.save_to_memory(texture)?;
+
+        Ok(fs::write(filepath, bytes)?)
+    }
+
+    fn load_from_memory(&self, source: &[u8]) -> Result<Texture, TextureError> {
+        let (all_textures, data_offset) = read_textures(source)?;
+        let data = source
+            .get(data_offset..)
+            .ok_or_else(|| TextureError::CorruptHeader("Invalid XPR2 header size!".to_string()))?;
+
+        let textures = match self.texture_index {
+            Some(index) => all_textures.get(index..index + 1).ok_or_else(|| {
+                TextureError::InvalidParameter(format!(
+                    "XPR2 texture index out of bounds: {}",
+                    index
+                ))
+            })?,
+            None if all_textures.is_empty() => {
+                return Err(TextureError::CorruptHeader(
+                    "XPR2 file has no textures!".to_string(),
+                ))
+            }
+            None => &all_textures[..],
+        };
+
+        // Every texture becomes an array item, so they must all look the same
+        let first = &textures[0];
+
+        if textures.iter().any(|texture| {
+            texture.width != first.width
+                || texture.height != first.height
+                || texture.mip_levels != first.mip_levels
+                || texture.format != first.format
+                || texture.dimension != first.dimension
+        }) {
+            return Err(TextureError::DimensionMismatch(format!(
+                "XPR2 textures of different sizes or formats cannot be loaded as one array, pick one of: {}",
+                textures
+                    .iter()
+                    .map(|texture| texture.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let mut metadata = first.get_metadata()?;
+        let mut images = Vec::new();
+        let mut array_size = 0;
+
+        for texture in textures {
+            let texture = texture.read_texture(data)?;
+
+            // Linear textures only keep their top level
+            metadata.mip_levels = texture.metadata.mip_levels;
+            array_size += texture.metadata.array_size;
+            images.extend(texture.images);
+        }
+
+        metadata.array_size = array_size;
+
+        Ok(Texture { metadata, images })
+    }
+
+    fn load_from_file(&self, filepath: PathBuf) -> Result<Texture, TextureError> {
+        self.load_from_memory(&fs::read(&filepath)?)
+    }
+
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["xpr"]
+    }
+
+    fn probe(&self, source: &[u8]) -> u32 {
+        if source.starts_with(XPR2_MAGIC) {
+            PROBE_SIGNATURE
+        } else {
+            0
+        }
+    }
+}
+
+// xenos::TextureFormat
+mod xenos_format {
+    pub const K_8: u32 = 2;
+    pub const K_1_5_5_5: u32 = 3;
+    pub const K_5_6_5: u32 = 4;
+    pub const K_8_8_8_8: u32 = 6;
+    pub const K_2_10_10_10: u32 = 7;
+    pub const K_8_8: u32 = 10;
+    pub const K_4_4_4_4: u32 = 15;
+    pub const K_DXT1: u32 = 18;
+    pub const K_DXT2_3: u32 = 19;
+    pub const K_DXT4_5: u32 = 20;
+    pub const K_16_16: u32 = 25;
+    pub const K_16_16_16_16: u32 = 26;
+    pub const K_32_FLOAT: u32 = 36;
+    pub const K_32_32_FLOAT: u32 = 37;
+    pub const K_32_32_32_32_FLOAT: u32 = 38;
+    pub const K_DXN: u32 = 49;
+    pub const K_DXT5A: u32 = 59;
+}
+
+/// Source of the R, G, B and A channels: X, Y, Z, W, 0 or 1
+const fn swizzle(r: u32, g: u32, b: u32, a: u32) -> u32 {
+    r | (g << 3) | (b << 6) | (a << 9)
+}
+
+const X: u32 = 0;
+const Y: u32 = 1;
+const Z: u32 = 2;
+const W: u32 = 3;
+const ZERO: u32 = 4;
+const ONE: u32 = 5;
+
+/// Formats whose channel order depends on the swizzle come first, the others match any swizzle
+const XENOS_FORMAT_TABLE: &[(u32, Option<u32>, PixelFormat, PixelDataType)] = &[
+    (
+        xenos_format::K_8_8_8_8,
+        Some(swizzle(Z, Y, X, W)),
+        PixelFormat::B8G8R8A8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8_8_8_8,
+        Some(swizzle(Z, Y, X, ONE)),
+        PixelFormat::B8G8R8X8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8_8_8_8,
+        None,
+        PixelFormat::R8G8B8A8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8,
+        Some(swizzle(ZERO, ZERO, ZERO, X)),
+        PixelFormat::A8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8,
+        Some(swizzle(X, X, X, ONE)),
+        PixelFormat::L8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8,
+        None,
+        PixelFormat::R8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8_8,
+        Some(swizzle(X, X, X, Y)),
+        PixelFormat::L8A8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_8_8,
+        None,
+        PixelFormat::R8G8,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_1_5_5_5,
+        None,
+        PixelFormat::B5G5R5A1,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_5_6_5,
+        None,
+        PixelFormat::B5G6R5,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_4_4_4_4,
+        None,
+        PixelFormat::B4G4R4A4,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_2_10_10_10,
+        None,
+        PixelFormat::R10G10B10A2,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_16_16,
+        None,
+        PixelFormat::R16G16,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_16_16_16_16,
+        None,
+        PixelFormat::R16G16B16A16,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_32_FLOAT,
+        None,
+        PixelFormat::R32,
+        PixelDataType::Float,
+    ),
+    (
+        xenos_format::K_32_32_FLOAT,
+        None,
+        PixelFormat::R32G32,
+        PixelDataType::Float,
+    ),
+    (
+        xenos_format::K_32_32_32_32_FLOAT,
+        None,
+        PixelFormat::R32G32B32A32,
+        PixelDataType::Float,
+    ),
+    (
+        xenos_format::K_DXT1,
+        None,
+        PixelFormat::BC1,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_DXT2_3,
+        None,
+        PixelFormat::BC2,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_DXT4_5,
+        None,
+        PixelFormat::BC3,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_DXT5A,
+        None,
+        PixelFormat::BC4,
+        PixelDataType::UNorm,
+    ),
+    (
+        xenos_format::K_DXN,
+        None,
+        PixelFormat::BC5,
+        PixelDataType::UNorm,
+    ),
+];
+
+fn get_pixel_format_info_from_xenos_format(format: u32, swizzle: u32) -> Option<PixelFormatInfo> {
+    XENOS_FORMAT_TABLE
+        .iter()
+        .find(|(xenos_format, xenos_swizzle, _, _)| {
+            *xenos_format == format && xenos_swizzle.map_or(true, |value| value == swizzle)
+        })
+        .map(|&(_, _, pixel_format, pixel_data_type)| PixelFormatInfo {
+            pixel_format,
+            pixel_data_type,
+            ..Default::default()
+        })
+}
@@ -0,0 +1,73 @@
+use std::{error::Error, fmt, io};
+
+use directxtex::HResultError;
+use image::ImageError;
+
+/// Errors returned by the codecs, the pixel format conversions and the swizzlers.
+/// The variant tells the kind of failure, its message what exactly went wrong.
+#[derive(Debug)]
+pub enum TextureError {
+    /// The file format, pixel format or feature is not supported
+    UnsupportedFormat(String),
+    /// A header or the data it describes is malformed or truncated
+    CorruptHeader(String),
+    /// Sizes, formats or counts that must match do not
+    DimensionMismatch(String),
+    /// Swizzling or deswizzling an image failed
+    Swizzle(String),
+    /// A parameter given by the caller is out of range
+    InvalidParameter(String),
+    /// DirectXTex or image-rs failed to decode, encode or convert the pixels
+    Codec(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::UnsupportedFormat(message)
+            | TextureError::CorruptHeader(message)
+            | TextureError::DimensionMismatch(message)
+            | TextureError::Swizzle(message)
+            | TextureError::InvalidParameter(message)
+            | TextureError::Codec(message) => f.write_str(message),
+            TextureError::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Reads past the end of the data come from the bounds checked header readers
+impl From<io::Error> for TextureError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => TextureError::CorruptHeader(error.to_string()),
+            _ => TextureError::Io(error),
+        }
+    }
+}
+
+impl From<ImageError> for TextureError {
+    fn from(error: ImageError) -> Self {
+        match error {
+            ImageError::IoError(error) => TextureError::Io(error),
+            ImageError::Unsupported(error) => TextureError::UnsupportedFormat(error.to_string()),
+            ImageError::Decoding(error) => TextureError::CorruptHeader(error.to_string()),
+            error => TextureError::Codec(error.to_string()),
+        }
+    }
+}
+
+impl From<HResultError> for TextureError {
+    fn from(error: HResultError) -> Self {
+        TextureError::Codec(format!("DirectXTex failed: {}", error))
+    }
+}
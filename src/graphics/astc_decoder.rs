@@ -0,0 +1,1137 @@
+use super::{
+    bc_decoder::{half_to_f32, interpolate, unorm8},
+    pixel_format::PixelFormat,
+};
+use crate::error::TextureError;
+
+// Native ASTC block decompression for every 2D footprint, following the Khronos Data Format
+// Specification. LDR formats decode with the LDR profile, where HDR endpoints and HDR void
+// extents are errors. HDR formats decode with the HDR profile, which reads both.
+
+/// Most weights and color values a block can hold
+pub(super) const MAX_WEIGHTS: usize = 64;
+pub(super) const MAX_COLOR_VALUES: usize = 18;
+const MIN_WEIGHT_BITS: u32 = 24;
+const MAX_WEIGHT_BITS: u32 = 96;
+
+/// Blocks that are reserved or malformed decode to magenta, like on most GPUs
+const ERROR_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+
+/// Alpha of the HDR endpoint modes without alpha, 1.0 in the logarithmic encoding
+const HDR_ONE: i32 = 0x7800;
+
+/// Every quantization level of the integer sequence encoding, from 1 to 8 bits
+pub(super) const QUANT_LEVELS: [u32; 21] = [
+    2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256,
+];
+
+/// Weight levels of the block modes, indexed by the range field and the precision bit
+const WEIGHT_LEVELS: [u32; 12] = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32];
+
+/// How the values of an integer sequence are packed
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum IseKind {
+    Bits,
+    /// Five values share 8 bits of base 3 digits
+    Trits,
+    /// Three values share 7 bits of base 5 digits
+    Quints,
+}
+
+/// Packing of `levels` values and the number of plain bits under the trit or quint
+pub(super) fn get_ise_layout(levels: u32) -> (IseKind, u32) {
+    if levels.is_power_of_two() {
+        (IseKind::Bits, levels.trailing_zeros())
+    } else if levels % 3 == 0 {
+        (IseKind::Trits, (levels / 3).trailing_zeros())
+    } else {
+        (IseKind::Quints, (levels / 5).trailing_zeros())
+    }
+}
+
+/// Size in bits of `count` values with `levels` levels
+pub(super) fn get_ise_size(count: usize, levels: u32) -> u32 {
+    let count = count as u32;
+    let (kind, bits) = get_ise_layout(levels);
+
+    count * bits
+        + match kind {
+            IseKind::Bits => 0,
+            IseKind::Trits => (8 * count).div_ceil(5),
+            IseKind::Quints => (7 * count).div_ceil(3),
+        }
+}
+
+/// Weight grid of a block mode
+pub(super) struct BlockMode {
+    pub(super) grid_width: usize,
+    pub(super) grid_height: usize,
+    pub(super) dual_plane: bool,
+    pub(super) weight_levels: u32,
+    pub(super) weight_bits: u32,
+}
+
+impl BlockMode {
+    pub(super) fn get_weight_count(&self) -> usize {
+        self.grid_width * self.grid_height * (self.dual_plane as usize + 1)
+    }
+}
+
+/// Decode the 11 bit block mode of a 2D block, `None` for reserved modes
+pub(super) fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+    let a = ((mode >> 5) & 3) as usize;
+    let mut high_precision = (mode >> 9) & 1 == 1;
+    let mut dual_plane = (mode >> 10) & 1 == 1;
+    let mut range = (mode >> 4) & 1;
+
+    let (grid_width, grid_height) = if mode & 3 != 0 {
+        range |= (mode & 3) << 1;
+        let b = ((mode >> 7) & 3) as usize;
+
+        match (mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if mode & 0x100 != 0 => ((b & 1) + 2, a + 2),
+            _ => (a + 2, (b & 1) + 6),
+        }
+    } else {
+        range |= ((mode >> 2) & 3) << 1;
+        if range < 2 {
+            return None;
+        }
+
+        let b = ((mode >> 9) & 3) as usize;
+
+        match (mode >> 7) & 3 {
+            0 => (12, a + 2),
+            1 => (a + 2, 12),
+            2 => {
+                // The precision and dual plane bits hold the grid height here
+                high_precision = false;
+                dual_plane = false;
+                (a + 6, b + 6)
+            }
+            _ => match a {
+                0 => (6, 10),
+                1 => (10, 6),
+                _ => return None,
+            },
+        }
+    };
+
+    if range < 2 {
+        return None;
+    }
+
+    let weight_levels = WEIGHT_LEVELS[(range - 2) as usize + high_precision as usize * 6];
+    let mut block_mode = BlockMode {
+        grid_width,
+        grid_height,
+        dual_plane,
+        weight_levels,
+        weight_bits: 0,
+    };
+
+    let weight_count = block_mode.get_weight_count();
+    block_mode.weight_bits = get_ise_size(weight_count, weight_levels);
+
+    (weight_count <= MAX_WEIGHTS
+        && (MIN_WEIGHT_BITS..=MAX_WEIGHT_BITS).contains(&block_mode.weight_bits))
+    .then_some(block_mode)
+}
+
+/// Highest color quantization that fits `count` values in `bits` bits. Blocks with less
+/// than 6 levels are invalid.
+pub(super) fn get_color_levels(count: usize, bits: u32) -> Option<u32> {
+    QUANT_LEVELS
+        .iter()
+        .rev()
+        .find(|&&levels| get_ise_size(count, levels) <= bits)
+        .filter(|&&levels| levels >= 6)
+        .copied()
+}
+
+fn get_bits(bits: u128, offset: u32, count: u32) -> u32 {
+    if count == 0 || offset >= 128 {
+        return 0;
+    }
+
+    (bits >> offset) as u32 & ((1u64 << count) - 1) as u32
+}
+
+/// Base 3 digits of 8 packed bits
+pub(super) fn decode_trits(packed: u32) -> [u32; 5] {
+    let bit = |n: u32| (packed >> n) & 1;
+
+    let (c, t3, t4) = if (packed >> 2) & 7 == 7 {
+        (((packed >> 5) & 7) << 2 | (packed & 3), 2, 2)
+    } else if (packed >> 5) & 3 == 3 {
+        (packed & 0x1F, bit(7), 2)
+    } else {
+        (packed & 0x1F, (packed >> 5) & 3, bit(7))
+    };
+
+    let c_bit = |n: u32| (c >> n) & 1;
+
+    let (t0, t1, t2) = if c & 3 == 3 {
+        (c_bit(3) << 1 | (c_bit(2) & !c_bit(3) & 1), c_bit(4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        (
+            c_bit(1) << 1 | (c_bit(0) & !c_bit(1) & 1),
+            (c >> 2) & 3,
+            c_bit(4),
+        )
+    };
+
+    [t0, t1, t2, t3, t4]
+}
+
+/// Base 5 digits of 7 packed bits
+pub(super) fn decode_quints(packed: u32) -> [u32; 3] {
+    let bit = |n: u32| (packed >> n) & 1;
+
+    if (packed >> 1) & 3 == 3 && (packed >> 5) & 3 == 0 {
+        let q2 = bit(0) << 2 | (bit(4) & !bit(0) & 1) << 1 | (bit(3) & !bit(0) & 1);
+        return [4, 4, q2];
+    }
+
+    let (c, q2) = if (packed >> 1) & 3 == 3 {
+        let high = !(packed >> 5) & 3;
+        (((packed >> 3) & 3) << 3 | high << 1 | bit(0), 4)
+    } else {
+        (packed & 0x1F, (packed >> 5) & 3)
+    };
+
+    if c & 7 == 5 {
+        [c >> 3, 4, q2]
+    } else {
+        [c & 7, c >> 3, q2]
+    }
+}
+
+/// Positions of the trit and quint bits between the plain bits of each value
+const TRIT_BITS: [u32; 5] = [2, 2, 1, 2, 1];
+const QUINT_BITS: [u32; 3] = [3, 2, 2];
+
+/// Read `count` values of an integer sequence starting at bit `offset`. Every value is
+/// its trit or quint above its plain bits.
+pub(super) fn decode_ise(bits: u128, offset: u32, count: usize, levels: u32) -> Vec<u32> {
+    let (kind, plain_bits) = get_ise_layout(levels);
+
+    // Bits past the end of the sequence read as zero
+    let end = offset + get_ise_size(count, levels);
+    let bits = if end < 128 {
+        bits & ((1u128 << end) - 1)
+    } else {
+        bits
+    };
+
+    let group_bits: &[u32] = match kind {
+        IseKind::Bits => &[0],
+        IseKind::Trits => &TRIT_BITS,
+        IseKind::Quints => &QUINT_BITS,
+    };
+
+    let mut values = Vec::with_capacity(count + group_bits.len());
+    let mut position = offset;
+
+    while values.len() < count {
+        let mut packed = 0;
+        let mut packed_position = 0;
+        let mut plain = Vec::with_capacity(group_bits.len());
+
+        for &extra in group_bits {
+            plain.push(get_bits(bits, position, plain_bits));
+            packed |= get_bits(bits, position + plain_bits, extra) << packed_position;
+            position += plain_bits + extra;
+            packed_position += extra;
+        }
+
+        let digits: Vec<u32> = match kind {
+            IseKind::Bits => vec![0],
+            IseKind::Trits => decode_trits(packed).to_vec(),
+            IseKind::Quints => decode_quints(packed).to_vec(),
+        };
+
+        values.extend(
+            digits
+                .iter()
+                .zip(plain)
+                .map(|(&digit, plain)| digit << plain_bits | plain),
+        );
+    }
+
+    values.truncate(count);
+    values
+}
+
+/// Repeat the bits of a value until it has `to` bits
+fn replicate(value: u32, bits: u32, to: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+
+    while filled < to {
+        result = (result << bits) | value;
+        filled += bits;
+    }
+
+    result >> (filled - to)
+}
+
+/// Scramble pattern of the unquantization tables, `0` is a zero bit and `b` to `f` are
+/// the bits 1 to 5 of the plain value
+fn expand_pattern(pattern: &str, plain: u32) -> u32 {
+    pattern.bytes().fold(0, |result, c| {
+        let bit = if c == b'0' {
+            0
+        } else {
+            (plain >> (c - b'a')) & 1
+        };
+        (result << 1) | bit
+    })
+}
+
+/// Unquantize a trit or quint value, the lowest plain bit mirrors the result
+fn unquantize_digits(value: u32, plain_bits: u32, pattern: &str, c: u32, top_bit: u32) -> u32 {
+    let plain = value & ((1 << plain_bits) - 1);
+    let digit = value >> plain_bits;
+    let a = if plain & 1 == 1 {
+        (top_bit << 2) - 1
+    } else {
+        0
+    };
+    let t = (digit * c + expand_pattern(pattern, plain)) ^ a;
+
+    (a & top_bit) | (t >> 2)
+}
+
+/// Color endpoint value in 0 - 255
+pub(super) fn unquantize_color(value: u32, levels: u32) -> i32 {
+    let (kind, bits) = get_ise_layout(levels);
+
+    let (pattern, c) = match (kind, bits) {
+        (IseKind::Bits, _) => return replicate(value, bits, 8) as i32,
+        (IseKind::Trits, 1) => ("000000000", 204),
+        (IseKind::Quints, 1) => ("000000000", 113),
+        (IseKind::Trits, 2) => ("b000b0bb0", 93),
+        (IseKind::Quints, 2) => ("b0000bb00", 54),
+        (IseKind::Trits, 3) => ("cb000cbcb", 44),
+        (IseKind::Quints, 3) => ("cb0000cbc", 26),
+        (IseKind::Trits, 4) => ("dcb000dcb", 22),
+        (IseKind::Quints, 4) => ("dcb0000dc", 13),
+        (IseKind::Trits, 5) => ("edcb000ed", 11),
+        (IseKind::Quints, 5) => ("edcb0000e", 6),
+        _ => ("fedcb000f", 5),
+    };
+
+    unquantize_digits(value, bits, pattern, c, 0x80) as i32
+}
+
+/// Weight in 0 - 64
+pub(super) fn unquantize_weight(value: u32, levels: u32) -> i32 {
+    let (kind, bits) = get_ise_layout(levels);
+
+    let weight = match (kind, bits) {
+        (IseKind::Bits, _) => replicate(value, bits, 6),
+        (IseKind::Trits, 0) => [0, 32, 63][value as usize],
+        (IseKind::Quints, 0) => [0, 16, 32, 47, 63][value as usize],
+        (IseKind::Trits, 1) => unquantize_digits(value, bits, "0000000", 50, 0x20),
+        (IseKind::Quints, 1) => unquantize_digits(value, bits, "0000000", 28, 0x20),
+        (IseKind::Trits, 2) => unquantize_digits(value, bits, "b000b00", 23, 0x20),
+        (IseKind::Quints, 2) => unquantize_digits(value, bits, "b0000b0", 13, 0x20),
+        _ => unquantize_digits(value, bits, "cb000cb", 11, 0x20),
+    };
+
+    if weight > 32 {
+        weight as i32 + 1
+    } else {
+        weight as i32
+    }
+}
+
+/// Two endpoints in 16 bit units, UNORM16 for LDR channels and the logarithmic encoding
+/// for HDR channels
+pub(super) struct Endpoints {
+    pub(super) colors: [[i32; 4]; 2],
+    pub(super) rgb_hdr: bool,
+    pub(super) alpha_hdr: bool,
+}
+
+impl Endpoints {
+    /// LDR endpoints from 8 bit colors
+    fn ldr(e0: [i32; 4], e1: [i32; 4]) -> Self {
+        let expand = |color: [i32; 4]| color.map(|c| c.clamp(0, 255) * 257);
+
+        Self {
+            colors: [expand(e0), expand(e1)],
+            rgb_hdr: false,
+            alpha_hdr: false,
+        }
+    }
+
+    /// HDR endpoints from 12 bit colors
+    fn hdr(e0: [i32; 3], e1: [i32; 3]) -> Self {
+        let expand = |[r, g, b]: [i32; 3]| [r << 4, g << 4, b << 4, HDR_ONE];
+
+        Self {
+            colors: [expand(e0), expand(e1)],
+            rgb_hdr: true,
+            alpha_hdr: true,
+        }
+    }
+}
+
+fn blue_contract(r: i32, g: i32, b: i32, a: i32) -> [i32; 4] {
+    [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+/// Move the top bit of `a` to `b`, returning `a` as a signed 6 bit offset and the new `b`
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let b = (b >> 1) | (a & 0x80);
+    let a = (a >> 1) & 0x3F;
+
+    if a & 0x20 != 0 {
+        (a - 0x40, b)
+    } else {
+        (a, b)
+    }
+}
+
+/// Endpoints of a color endpoint mode from its unquantized values
+pub(super) fn unpack_endpoints(cem: u32, v: &[i32]) -> Endpoints {
+    match cem {
+        // Luminance, direct and base + offset
+        0 => Endpoints::ldr([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            Endpoints::ldr([l0, l0, l0, 255], [l1, l1, l1, 255])
+        }
+        2 => {
+            let (y0, y1) = if v[1] >= v[0] {
+                (v[0] << 4, v[1] << 4)
+            } else {
+                ((v[1] << 4) + 8, (v[0] << 4) - 8)
+            };
+            Endpoints::hdr([y0; 3], [y1; 3])
+        }
+        3 => {
+            let (y0, offset) = if v[0] & 0x80 != 0 {
+                (
+                    ((v[1] & 0xE0) << 4) | ((v[0] & 0x7F) << 2),
+                    (v[1] & 0x1F) << 2,
+                )
+            } else {
+                (
+                    ((v[1] & 0xF0) << 4) | ((v[0] & 0x7F) << 1),
+                    (v[1] & 0x0F) << 1,
+                )
+            };
+            let y1 = (y0 + offset).min(0xFFF);
+            Endpoints::hdr([y0; 3], [y1; 3])
+        }
+        // Luminance and alpha, direct and base + offset
+        4 => Endpoints::ldr([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        5 => {
+            let (l_offset, l0) = bit_transfer_signed(v[1], v[0]);
+            let (a_offset, a0) = bit_transfer_signed(v[3], v[2]);
+            let l1 = l0 + l_offset;
+            Endpoints::ldr([l0, l0, l0, a0], [l1, l1, l1, a0 + a_offset])
+        }
+        // RGB, base scaled for the first endpoint
+        6 => Endpoints::ldr(
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                255,
+            ],
+            [v[0], v[1], v[2], 255],
+        ),
+        7 => {
+            let [e0, e1] = unpack_hdr_rgb_scale(v);
+            Endpoints::hdr(e0, e1)
+        }
+        // RGB, direct and base + offset, blue contracted when the endpoints are swapped
+        8 => {
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                Endpoints::ldr([v[0], v[2], v[4], 255], [v[1], v[3], v[5], 255])
+            } else {
+                Endpoints::ldr(
+                    blue_contract(v[1], v[3], v[5], 255),
+                    blue_contract(v[0], v[2], v[4], 255),
+                )
+            }
+        }
+        9 => {
+            let (r, r0) = bit_transfer_signed(v[1], v[0]);
+            let (g, g0) = bit_transfer_signed(v[3], v[2]);
+            let (b, b0) = bit_transfer_signed(v[5], v[4]);
+
+            if r + g + b >= 0 {
+                Endpoints::ldr([r0, g0, b0, 255], [r0 + r, g0 + g, b0 + b, 255])
+            } else {
+                Endpoints::ldr(
+                    blue_contract(r0 + r, g0 + g, b0 + b, 255),
+                    blue_contract(r0, g0, b0, 255),
+                )
+            }
+        }
+        // RGB scaled with two alphas
+        10 => Endpoints::ldr(
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                v[4],
+            ],
+            [v[0], v[1], v[2], v[5]],
+        ),
+        11 => {
+            let [e0, e1] = unpack_hdr_rgb(v);
+            Endpoints::hdr(e0, e1)
+        }
+        // RGBA, direct and base + offset
+        12 => {
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                Endpoints::ldr([v[0], v[2], v[4], v[6]], [v[1], v[3], v[5], v[7]])
+            } else {
+                Endpoints::ldr(
+                    blue_contract(v[1], v[3], v[5], v[7]),
+                    blue_contract(v[0], v[2], v[4], v[6]),
+                )
+            }
+        }
+        13 => {
+            let (r, r0) = bit_transfer_signed(v[1], v[0]);
+            let (g, g0) = bit_transfer_signed(v[3], v[2]);
+            let (b, b0) = bit_transfer_signed(v[5], v[4]);
+            let (a, a0) = bit_transfer_signed(v[7], v[6]);
+
+            if r + g + b >= 0 {
+                Endpoints::ldr([r0, g0, b0, a0], [r0 + r, g0 + g, b0 + b, a0 + a])
+            } else {
+                Endpoints::ldr(
+                    blue_contract(r0 + r, g0 + g, b0 + b, a0 + a),
+                    blue_contract(r0, g0, b0, a0),
+                )
+            }
+        }
+        // HDR RGB with LDR or HDR alpha
+        14 => {
+            let [e0, e1] = unpack_hdr_rgb(v);
+            let mut endpoints = Endpoints::hdr(e0, e1);
+            endpoints.colors[0][3] = v[6] * 257;
+            endpoints.colors[1][3] = v[7] * 257;
+            endpoints.alpha_hdr = false;
+            endpoints
+        }
+        _ => {
+            let [e0, e1] = unpack_hdr_rgb(v);
+            let mut endpoints = Endpoints::hdr(e0, e1);
+            let [a0, a1] = unpack_hdr_alpha(v[6], v[7]);
+            endpoints.colors[0][3] = a0 << 4;
+            endpoints.colors[1][3] = a1 << 4;
+            endpoints
+        }
+    }
+}
+
+/// HDR RGB base and scale (mode 7), 12 bit endpoints
+fn unpack_hdr_rgb_scale(v: &[i32]) -> [[i32; 3]; 2] {
+    let mode_bits = ((v[0] & 0xC0) >> 6) | ((v[1] & 0x80) >> 5) | ((v[2] & 0x80) >> 4);
+
+    let (major, mode) = if mode_bits & 0xC != 0xC {
+        (mode_bits >> 2, mode_bits & 3)
+    } else if mode_bits != 0xF {
+        (mode_bits & 3, 4)
+    } else {
+        (0, 5)
+    };
+
+    let mut red = v[0] & 0x3F;
+    let mut green = v[1] & 0x1F;
+    let mut blue = v[2] & 0x1F;
+    let mut scale = v[3] & 0x1F;
+
+    let x0 = (v[1] >> 6) & 1;
+    let x1 = (v[1] >> 5) & 1;
+    let x2 = (v[2] >> 6) & 1;
+    let x3 = (v[2] >> 5) & 1;
+    let x4 = (v[3] >> 7) & 1;
+    let x5 = (v[3] >> 6) & 1;
+    let x6 = (v[3] >> 5) & 1;
+
+    // Which of the variable bits each mode stores
+    let one_hot = 1 << mode;
+    let place = |field: &mut i32, modes: i32, bit: i32, shift: i32| {
+        if one_hot & modes != 0 {
+            *field |= bit << shift;
+        }
+    };
+
+    place(&mut green, 0x30, x0, 6);
+    place(&mut green, 0x3A, x1, 5);
+    place(&mut blue, 0x30, x2, 6);
+    place(&mut blue, 0x3A, x3, 5);
+    place(&mut scale, 0x3D, x6, 5);
+    place(&mut scale, 0x2D, x5, 6);
+    place(&mut scale, 0x04, x4, 7);
+    place(&mut red, 0x3B, x4, 6);
+    place(&mut red, 0x04, x3, 6);
+    place(&mut red, 0x10, x5, 7);
+    place(&mut red, 0x0F, x2, 7);
+    place(&mut red, 0x05, x1, 8);
+    place(&mut red, 0x0A, x0, 8);
+    place(&mut red, 0x05, x0, 9);
+    place(&mut red, 0x02, x6, 9);
+    place(&mut red, 0x01, x3, 10);
+    place(&mut red, 0x02, x5, 10);
+
+    let shift = [1, 1, 2, 3, 4, 5][mode as usize];
+    red <<= shift;
+    green <<= shift;
+    blue <<= shift;
+    scale <<= shift;
+
+    // Green and blue are differences to red, except in the last mode
+    if mode != 5 {
+        green = red - green;
+        blue = red - blue;
+    }
+
+    let mut e1 = [red, green, blue];
+    if major > 0 {
+        e1.swap(0, major as usize);
+    }
+
+    let e0 = e1.map(|c| (c - scale).clamp(0, 0xFFF));
+    [e0, e1.map(|c| c.clamp(0, 0xFFF))]
+}
+
+/// HDR RGB direct (mode 11), 12 bit endpoints
+fn unpack_hdr_rgb(v: &[i32]) -> [[i32; 3]; 2] {
+    let major = ((v[4] & 0x80) >> 7) | ((v[5] & 0x80) >> 6);
+
+    if major == 3 {
+        return [
+            [v[0] << 4, v[2] << 4, (v[4] & 0x7F) << 5],
+            [v[1] << 4, v[3] << 4, (v[5] & 0x7F) << 5],
+        ];
+    }
+
+    let mode = ((v[1] & 0x80) >> 7) | ((v[2] & 0x80) >> 6) | ((v[3] & 0x80) >> 5);
+
+    let mut a = v[0] | ((v[1] & 0x40) << 2);
+    let mut b0 = v[2] & 0x3F;
+    let mut b1 = v[3] & 0x3F;
+    let mut c = v[1] & 0x3F;
+    let mut d0 = v[4] & 0x1F;
+    let mut d1 = v[5] & 0x1F;
+
+    let x0 = (v[2] >> 6) & 1;
+    let x1 = (v[3] >> 6) & 1;
+    let x2 = (v[4] >> 6) & 1;
+    let x3 = (v[5] >> 6) & 1;
+    let x4 = (v[4] >> 5) & 1;
+    let x5 = (v[5] >> 5) & 1;
+
+    let one_hot = 1 << mode;
+    let place = |field: &mut i32, modes: i32, bit: i32, shift: i32| {
+        if one_hot & modes != 0 {
+            *field |= bit << shift;
+        }
+    };
+
+    place(&mut a, 0xA4, x0, 9);
+    place(&mut a, 0x08, x2, 9);
+    place(&mut a, 0x50, x4, 9);
+    place(&mut a, 0x50, x5, 10);
+    place(&mut a, 0xA0, x1, 10);
+    place(&mut a, 0xC0, x2, 11);
+    place(&mut c, 0x04, x1, 6);
+    place(&mut c, 0xE8, x3, 6);
+    place(&mut c, 0x20, x2, 7);
+    place(&mut b0, 0x5B, x0, 6);
+    place(&mut b1, 0x5B, x1, 6);
+    place(&mut b0, 0x12, x2, 7);
+    place(&mut b1, 0x12, x3, 7);
+    place(&mut d0, 0xAF, x4, 5);
+    place(&mut d1, 0xAF, x5, 5);
+    place(&mut d0, 0x05, x2, 6);
+    place(&mut d1, 0x05, x3, 6);
+
+    let d_bits = [7, 6, 7, 6, 5, 6, 5, 6][mode as usize];
+    let sign_extend = |value: i32| (value << (32 - d_bits)) >> (32 - d_bits);
+    let (d0, d1) = (sign_extend(d0), sign_extend(d1));
+
+    let shift = (mode >> 1) ^ 3;
+    let [a, b0, b1, c, d0, d1] = [a, b0, b1, c, d0, d1].map(|value| value << shift);
+
+    let mut e0 = [a - c, a - b0 - c - d0, a - b1 - c - d1].map(|c| c.clamp(0, 0xFFF));
+    let mut e1 = [a, a - b0, a - b1].map(|c| c.clamp(0, 0xFFF));
+
+    if major > 0 {
+        e0.swap(0, major as usize);
+        e1.swap(0, major as usize);
+    }
+
+    [e0, e1]
+}
+
+/// HDR alpha of mode 15, 12 bit endpoints
+fn unpack_hdr_alpha(v6: i32, v7: i32) -> [i32; 2] {
+    let selector = ((v6 >> 7) & 1) | ((v7 >> 6) & 2);
+    let (mut a0, mut a1) = (v6 & 0x7F, v7 & 0x7F);
+
+    if selector == 3 {
+        return [a0 << 5, a1 << 5];
+    }
+
+    a0 |= (a1 << (selector + 1)) & 0x780;
+    a1 &= 0x3F >> selector;
+    a1 ^= 0x20 >> selector;
+    a1 -= 0x20 >> selector;
+    a0 <<= 4 - selector;
+    a1 <<= 4 - selector;
+
+    [a0, (a0 + a1).clamp(0, 0xFFF)]
+}
+
+/// Half float bits of an interpolated value in the logarithmic encoding
+fn lns_to_half(value: i32) -> u16 {
+    let mantissa = value & 0x7FF;
+    let exponent = value >> 11;
+
+    let mantissa = if mantissa < 512 {
+        3 * mantissa
+    } else if mantissa < 1536 {
+        4 * mantissa - 512
+    } else {
+        5 * mantissa - 2048
+    };
+
+    ((exponent << 10) | (mantissa >> 3)).min(0x7BFF) as u16
+}
+
+/// Pseudo random partition of a texel, the same hash every encoder uses
+pub(super) fn select_partition(
+    seed: u32,
+    x: usize,
+    y: usize,
+    partitions: usize,
+    small_block: bool,
+) -> usize {
+    let (x, y) = if small_block {
+        (x as u32 * 2, y as u32 * 2)
+    } else {
+        (x as u32, y as u32)
+    };
+
+    let seed = seed + (partitions as u32 - 1) * 1024;
+
+    let mut random = seed;
+    random ^= random >> 15;
+    random = random.wrapping_mul(0xEEDE0891);
+    random ^= random >> 5;
+    random = random.wrapping_add(random << 16);
+    random ^= random >> 7;
+    random ^= random >> 3;
+    random ^= random << 6;
+    random ^= random >> 17;
+
+    let mut seeds = [
+        random,
+        random >> 4,
+        random >> 8,
+        random >> 12,
+        random >> 16,
+        random >> 20,
+        random >> 24,
+        random >> 28,
+        random >> 18,
+        random >> 22,
+        random >> 26,
+        random.rotate_left(2),
+    ]
+    .map(|s| {
+        let s = s & 0xF;
+        s * s
+    });
+
+    let (shift1, shift2) = if seed & 1 != 0 {
+        (
+            if seed & 2 != 0 { 4 } else { 5 },
+            if partitions == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if partitions == 3 { 6 } else { 5 },
+            if seed & 2 != 0 { 4 } else { 5 },
+        )
+    };
+    let shift3 = if seed & 0x10 != 0 { shift1 } else { shift2 };
+
+    for (index, s) in seeds.iter_mut().enumerate() {
+        *s >>= match index {
+            8.. => shift3,
+            _ if index % 2 == 0 => shift1,
+            _ => shift2,
+        };
+    }
+
+    // 2D blocks have no z, so the z seeds drop out
+    let a = (seeds[0] * x + seeds[1] * y + (random >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (random >> 10)) & 0x3F;
+    let c = if partitions >= 3 {
+        (seeds[4] * x + seeds[5] * y + (random >> 6)) & 0x3F
+    } else {
+        0
+    };
+    let d = if partitions >= 4 {
+        (seeds[6] * x + seeds[7] * y + (random >> 2)) & 0x3F
+    } else {
+        0
+    };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// Grid points every texel blends, as (grid index, factor out of 16)
+pub(super) fn get_infill(
+    block_width: usize,
+    block_height: usize,
+    grid_width: usize,
+    grid_height: usize,
+) -> Vec<[(usize, u32); 4]> {
+    let ds = (1024 + block_width / 2) / (block_width - 1);
+    let dt = (1024 + block_height / 2) / (block_height - 1);
+
+    (0..block_width * block_height)
+        .map(|texel| {
+            let (s, t) = (texel % block_width, texel / block_width);
+            let gs = (ds * s * (grid_width - 1) + 32) >> 6;
+            let gt = (dt * t * (grid_height - 1) + 32) >> 6;
+            let (js, fs) = (gs >> 4, (gs & 0xF) as u32);
+            let (jt, ft) = (gt >> 4, (gt & 0xF) as u32);
+
+            let w11 = (fs * ft + 8) >> 4;
+            let index = js + jt * grid_width;
+
+            // Neighbours past the grid edge always have a zero factor
+            let right = (js + 1 < grid_width) as usize;
+            let down = if jt + 1 < grid_height { grid_width } else { 0 };
+
+            [
+                (index, 16 + w11 - fs - ft),
+                (index + right, fs - w11),
+                (index + down, ft - w11),
+                (index + right + down, w11),
+            ]
+        })
+        .collect()
+}
+
+/// Weight of a texel from its grid points
+pub(super) fn infill_weight(infill: &[(usize, u32); 4], weights: &[i32]) -> i32 {
+    let sum: u32 = infill
+        .iter()
+        .map(|&(index, factor)| weights[index] as u32 * factor)
+        .sum();
+
+    ((sum + 8) >> 4) as i32
+}
+
+/// Color endpoint mode of every partition. Mixed modes keep their extra bits below the
+/// weights, `below_weights` moves past them.
+fn get_endpoint_modes(bits: u128, partitions: usize, below_weights: &mut u32) -> Vec<u32> {
+    if partitions == 1 {
+        return vec![get_bits(bits, 13, 4)];
+    }
+
+    let field = get_bits(bits, 23, 6);
+    if field & 3 == 0 {
+        return vec![field >> 2; partitions];
+    }
+
+    let extra_bits = 3 * partitions as u32 - 4;
+    *below_weights -= extra_bits;
+
+    let field = field | get_bits(bits, *below_weights, extra_bits) << 6;
+    let class = (field & 3) - 1;
+
+    (0..partitions)
+        .map(|i| {
+            let high = class + ((field >> (2 + i)) & 1);
+            let low = (field >> (2 + partitions + 2 * i)) & 3;
+            high << 2 | low
+        })
+        .collect()
+}
+
+/// Whether a block needs the HDR profile, from its endpoint modes or void extent
+pub fn is_astc_hdr_block(block: &[u8]) -> bool {
+    let Some(bits) = block
+        .get(..16)
+        .and_then(|b| b.try_into().ok())
+        .map(u128::from_le_bytes)
+    else {
+        return false;
+    };
+
+    if get_bits(bits, 0, 9) == 0x1FC {
+        return get_bits(bits, 9, 1) == 1;
+    }
+
+    let Some(mode) = decode_block_mode(get_bits(bits, 0, 11)) else {
+        return false;
+    };
+
+    let partitions = get_bits(bits, 11, 2) as usize + 1;
+    let mut below_weights = 128 - mode.weight_bits;
+
+    get_endpoint_modes(bits, partitions, &mut below_weights)
+        .iter()
+        .any(|cem| matches!(cem, 2 | 3 | 7 | 11 | 14 | 15))
+}
+
+/// Decode one ASTC block to RGBA floats, row by row. Invalid blocks decode to magenta.
+pub fn decode_astc_block(
+    block: &[u8],
+    block_width: usize,
+    block_height: usize,
+    hdr: bool,
+) -> Vec<[f32; 4]> {
+    let texels = block_width * block_height;
+
+    decode_block(block, block_width, block_height, hdr).unwrap_or_else(|| vec![ERROR_COLOR; texels])
+}
+
+fn decode_block(
+    block: &[u8],
+    block_width: usize,
+    block_height: usize,
+    hdr: bool,
+) -> Option<Vec<[f32; 4]>> {
+    let bits = u128::from_le_bytes(block.get(..16)?.try_into().ok()?);
+    let texels = block_width * block_height;
+
+    if get_bits(bits, 0, 9) == 0x1FC {
+        return decode_void_extent(bits, texels, hdr);
+    }
+
+    let mode = decode_block_mode(get_bits(bits, 0, 11))?;
+    if mode.grid_width > block_width || mode.grid_height > block_height {
+        return None;
+    }
+
+    let partitions = get_bits(bits, 11, 2) as usize + 1;
+    if mode.dual_plane && partitions == 4 {
+        return None;
+    }
+
+    // Extra endpoint mode bits and the dual plane channel sit right below the weights
+    let mut below_weights = 128 - mode.weight_bits;
+    let cems = get_endpoint_modes(bits, partitions, &mut below_weights);
+    let color_offset = if partitions == 1 { 17 } else { 29 };
+
+    let value_count: usize = cems.iter().map(|&cem| (cem as usize / 4 + 1) * 2).sum();
+    if value_count > MAX_COLOR_VALUES {
+        return None;
+    }
+
+    let plane_channel = if mode.dual_plane {
+        below_weights -= 2;
+        Some(get_bits(bits, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    let color_levels = get_color_levels(value_count, below_weights.saturating_sub(color_offset))?;
+    let values: Vec<i32> = decode_ise(bits, color_offset, value_count, color_levels)
+        .into_iter()
+        .map(|value| unquantize_color(value, color_levels))
+        .collect();
+
+    let mut endpoints = Vec::with_capacity(partitions);
+    let mut offset = 0;
+
+    for &cem in &cems {
+        let count = (cem as usize / 4 + 1) * 2;
+        endpoints.push(unpack_endpoints(cem, &values[offset..offset + count]));
+        offset += count;
+    }
+
+    if !hdr && endpoints.iter().any(|e| e.rgb_hdr || e.alpha_hdr) {
+        return None;
+    }
+
+    // Weights are stored backwards from the top of the block
+    let weights: Vec<i32> = decode_ise(
+        bits.reverse_bits(),
+        0,
+        mode.get_weight_count(),
+        mode.weight_levels,
+    )
+    .into_iter()
+    .map(|value| unquantize_weight(value, mode.weight_levels))
+    .collect();
+
+    let planes = mode.dual_plane as usize + 1;
+    let plane_weights: Vec<Vec<i32>> = (0..planes)
+        .map(|plane| {
+            weights
+                .iter()
+                .skip(plane)
+                .step_by(planes)
+                .copied()
+                .collect()
+        })
+        .collect();
+
+    let infill = get_infill(block_width, block_height, mode.grid_width, mode.grid_height);
+    let seed = get_bits(bits, 13, 10);
+    let small_block = texels < 31;
+
+    let colors = (0..texels)
+        .map(|texel| {
+            let partition = if partitions > 1 {
+                select_partition(
+                    seed,
+                    texel % block_width,
+                    texel / block_width,
+                    partitions,
+                    small_block,
+                )
+            } else {
+                0
+            };
+
+            let endpoints = &endpoints[partition];
+            let texel_weights: Vec<i32> = plane_weights
+                .iter()
+                .map(|weights| infill_weight(&infill[texel], weights))
+                .collect();
+
+            std::array::from_fn(|channel| {
+                let plane = (plane_channel == Some(channel)) as usize;
+                let value = interpolate(
+                    endpoints.colors[0][channel],
+                    endpoints.colors[1][channel],
+                    texel_weights[plane],
+                );
+
+                let is_hdr = if channel < 3 {
+                    endpoints.rgb_hdr
+                } else {
+                    endpoints.alpha_hdr
+                };
+
+                if is_hdr {
+                    half_to_f32(lns_to_half(value))
+                } else {
+                    value as f32 / 65535.0
+                }
+            })
+        })
+        .collect();
+
+    Some(colors)
+}
+
+/// Blocks of a single color, LDR as UNORM16 or HDR as half floats
+fn decode_void_extent(bits: u128, texels: usize, hdr: bool) -> Option<Vec<[f32; 4]>> {
+    if get_bits(bits, 10, 2) != 3 {
+        return None;
+    }
+
+    // The extent is only a hint, but it must be valid unless it is all ones
+    let extent = [12, 25, 38, 51].map(|offset| get_bits(bits, offset, 13));
+    if extent.iter().any(|&e| e != 0x1FFF) && (extent[0] >= extent[1] || extent[2] >= extent[3]) {
+        return None;
+    }
+
+    let color = [64, 80, 96, 112].map(|offset| get_bits(bits, offset, 16));
+
+    let color = if get_bits(bits, 9, 1) == 1 {
+        if !hdr {
+            return None;
+        }
+        color.map(|c| half_to_f32(c as u16))
+    } else {
+        color.map(|c| c as f32 / 65535.0)
+    };
+
+    Some(vec![color; texels])
+}
+
+/// Decode a whole ASTC surface to RGBA8. HDR values are clamped to 0 - 1.
+pub fn decode_astc_to_rgba(
+    pixels: &[u8],
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, TextureError> {
+    let colors = decode_astc_to_rgba_f32(pixels, format, width, height)?;
+
+    Ok(colors.into_iter().map(unorm8).collect())
+}
+
+/// Decode a whole ASTC surface to RGBA32F. Partial blocks on the right and bottom edges are cropped.
+pub fn decode_astc_to_rgba_f32(
+    pixels: &[u8],
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+) -> Result<Vec<f32>, TextureError> {
+    let Some((block_width, block_height)) = format.get_astc_block_size() else {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Not an ASTC pixel format: {}",
+            format
+        )));
+    };
+
+    let (block_width, block_height) = (block_width as usize, block_height as usize);
+    let width_in_blocks = (width as usize).div_ceil(block_width);
+    let height_in_blocks = (height as usize).div_ceil(block_height);
+    let expected_size = width_in_blocks * height_in_blocks * 16;
+
+    if pixels.len() < expected_size {
+        return Err(TextureError::DimensionMismatch(format!(
+            "Image data too small for {}: {} < {}",
+            format,
+            pixels.len(),
+            expected_size
+        )));
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let hdr = format.is_astc_hdr();
+    let mut output = vec![0.0; width * height * 4];
+
+    for (index, block) in pixels[..expected_size].chunks_exact(16).enumerate() {
+        let block_x = (index % width_in_blocks) * block_width;
+        let block_y = (index / width_in_blocks) * block_height;
+        let decoded = decode_astc_block(block, block_width, block_height, hdr);
+
+        for y in 0..block_height.min(height - block_y) {
+            for x in 0..block_width.min(width - block_x) {
+                let offset = ((block_y + y) * width + block_x + x) * 4;
+                output[offset..offset + 4].copy_from_slice(&decoded[y * block_width + x]);
+            }
+        }
+    }
+
+    Ok(output)
+}
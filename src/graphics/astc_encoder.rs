@@ -0,0 +1,915 @@
+use std::{collections::HashSet, ops::Range};
+
+use super::{
+    astc_decoder::{
+        decode_block_mode, decode_quints, decode_trits, get_color_levels, get_infill,
+        get_ise_layout, infill_weight, select_partition, unpack_endpoints, unquantize_color,
+        unquantize_weight, IseKind, MAX_COLOR_VALUES,
+    },
+    bc_decoder::interpolate,
+    bc_encoder::{
+        get_line_endpoints, get_refined_endpoints, get_thread_count, BcQuality, BlockWriter, Color,
+        CompressionOptions, ErrorMetric,
+    },
+    pixel_format::PixelFormat,
+};
+use crate::error::TextureError;
+
+// Native ASTC encoder for every 2D footprint. Blocks get one or two partitions with direct
+// luminance or RGB endpoints, with or without alpha, fitted along the principal axis of
+// their texels. Two partition layouts are picked by how well they match a two color
+// clustering of the block. The block modes that fit are ranked per block by an estimate of
+// their error, and the quality preset decides how many layouts and modes get fully fitted.
+// HDR formats get LDR endpoints too, which the HDR profile decodes the same way.
+
+/// Solid blocks are stored as void extent blocks, without extent
+const VOID_EXTENT: u128 = 0x1FC | (3 << 10) | (((1 << 52) - 1) << 12);
+
+/// Direct endpoint modes, by luminance or RGB and whether they have alpha
+const CEM_LUMINANCE: u32 = 0;
+const CEM_LUMINANCE_ALPHA: u32 = 4;
+const CEM_RGB: u32 = 8;
+const CEM_RGBA: u32 = 12;
+
+/// Weight grid of some block modes, with how it maps to the texels
+struct WeightGrid {
+    infill: Vec<[(usize, u32); 4]>,
+    /// Texels that blend each grid point, with their factors
+    contributions: Vec<Vec<(usize, u32)>>,
+}
+
+/// A block mode the endpoints of a color endpoint mode fit with
+struct ModeCandidate {
+    mode: u32,
+    grid: usize,
+    weight_levels: u32,
+    color_levels: u32,
+}
+
+/// Two partition layout of a seed, by the partition of every texel
+struct PartitionLayout {
+    seed: u32,
+    partitions: Vec<usize>,
+}
+
+/// Texels of a block split in partitions, each with its own endpoints
+struct BlockPartitions {
+    seed: u32,
+    partitions: Vec<usize>,
+    endpoints: Vec<[Color; 2]>,
+}
+
+impl BlockPartitions {
+    fn new(
+        pixels: &[Color],
+        seed: u32,
+        partitions: Vec<usize>,
+        count: usize,
+        channels: Range<usize>,
+    ) -> Self {
+        let endpoints = (0..count)
+            .map(|partition| {
+                let points = get_partition_points(pixels, &partitions, partition);
+                get_line_endpoints(&points, channels.clone(), 255.0)
+            })
+            .collect();
+
+        Self {
+            seed,
+            partitions,
+            endpoints,
+        }
+    }
+}
+
+/// Nearest quantized value of every level of a range
+struct QuantTable {
+    levels: u32,
+    /// Unquantized values and their encoding, sorted by value
+    sorted: Vec<(i32, u32)>,
+}
+
+impl QuantTable {
+    fn new(levels: u32, unquantize: fn(u32, u32) -> i32) -> Self {
+        let mut sorted: Vec<(i32, u32)> = (0..levels)
+            .map(|value| (unquantize(value, levels), value))
+            .collect();
+        sorted.sort_unstable();
+
+        Self { levels, sorted }
+    }
+
+    /// Encoding and unquantized value closest to `value`
+    fn quantize(&self, value: f32) -> (u32, i32) {
+        let index = self
+            .sorted
+            .partition_point(|&(unquantized, _)| (unquantized as f32) < value);
+
+        let nearest = [index.saturating_sub(1), index.min(self.sorted.len() - 1)]
+            .into_iter()
+            .min_by(|&a, &b| {
+                let distance = |i: usize| (self.sorted[i].0 as f32 - value).abs();
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap_or(0);
+
+        let (unquantized, encoded) = self.sorted[nearest];
+        (encoded, unquantized)
+    }
+}
+
+/// Search limits of a quality preset, with everything precomputed for a footprint
+struct EncoderSettings {
+    block_width: usize,
+    block_height: usize,
+    grids: Vec<WeightGrid>,
+    /// Block modes by partition count - 1 and endpoint mode / 4
+    candidates: [[Vec<ModeCandidate>; 4]; 2],
+    /// Distinct two partition layouts, only built when they are searched
+    layouts: Vec<PartitionLayout>,
+    weight_tables: Vec<QuantTable>,
+    color_tables: Vec<QuantTable>,
+    max_candidates: usize,
+    max_layouts: usize,
+    refine_iterations: u32,
+    metric: ErrorMetric,
+}
+
+impl EncoderSettings {
+    fn new(block_width: usize, block_height: usize, options: &CompressionOptions) -> Self {
+        let (max_candidates, max_layouts, refine_iterations) = match options.quality {
+            BcQuality::UltraFast => (1, 0, 0),
+            BcQuality::VeryFast => (2, 1, 1),
+            BcQuality::Fast => (4, 2, 1),
+            BcQuality::Basic => (8, 4, 2),
+            BcQuality::Slow => (16, 8, 3),
+        };
+        let max_layouts = options.max_partitions.unwrap_or(max_layouts);
+
+        let mut grid_sizes = Vec::new();
+        let candidates = [1, 2].map(|partitions| {
+            [CEM_LUMINANCE, CEM_LUMINANCE_ALPHA, CEM_RGB, CEM_RGBA].map(|cem| {
+                get_candidates(block_width, block_height, cem, partitions, &mut grid_sizes)
+            })
+        });
+
+        let grids = grid_sizes
+            .into_iter()
+            .map(|(grid_width, grid_height)| {
+                let infill = get_infill(block_width, block_height, grid_width, grid_height);
+
+                let mut contributions = vec![Vec::new(); grid_width * grid_height];
+                for (texel, points) in infill.iter().enumerate() {
+                    for &(index, factor) in points.iter().filter(|(_, factor)| *factor > 0) {
+                        contributions[index].push((texel, factor));
+                    }
+                }
+
+                WeightGrid {
+                    infill,
+                    contributions,
+                }
+            })
+            .collect();
+
+        let layouts = if max_layouts > 0 {
+            get_partition_layouts(block_width, block_height)
+        } else {
+            Vec::new()
+        };
+
+        let weight_tables = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32]
+            .into_iter()
+            .map(|levels| QuantTable::new(levels, unquantize_weight))
+            .collect();
+
+        let mut color_levels: Vec<u32> = candidates
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|candidate| candidate.color_levels)
+            .collect();
+        color_levels.sort_unstable();
+        color_levels.dedup();
+
+        let color_tables = color_levels
+            .into_iter()
+            .map(|levels| QuantTable::new(levels, unquantize_color))
+            .collect();
+
+        Self {
+            block_width,
+            block_height,
+            grids,
+            candidates,
+            layouts,
+            weight_tables,
+            color_tables,
+            max_candidates,
+            max_layouts,
+            refine_iterations,
+            metric: ErrorMetric::new(options),
+        }
+    }
+
+    fn get_table(tables: &[QuantTable], levels: u32) -> &QuantTable {
+        tables
+            .iter()
+            .find(|table| table.levels == levels)
+            .unwrap_or(&tables[0])
+    }
+}
+
+/// Single plane block modes whose grid fits the block, the grids they use are shared
+fn get_candidates(
+    block_width: usize,
+    block_height: usize,
+    cem: u32,
+    partitions: usize,
+    grid_sizes: &mut Vec<(usize, usize)>,
+) -> Vec<ModeCandidate> {
+    let value_count = partitions * (cem as usize / 4 + 1) * 2;
+    if value_count > MAX_COLOR_VALUES {
+        return Vec::new();
+    }
+
+    // Colors start after the partition seed and the endpoint mode
+    let color_offset = if partitions == 1 { 17 } else { 29 };
+
+    (0..2048)
+        .filter_map(|mode| {
+            let block_mode = decode_block_mode(mode)?;
+
+            if block_mode.dual_plane
+                || block_mode.grid_width > block_width
+                || block_mode.grid_height > block_height
+            {
+                return None;
+            }
+
+            let color_bits = (128 - color_offset as u32).checked_sub(block_mode.weight_bits)?;
+            let color_levels = get_color_levels(value_count, color_bits)?;
+
+            let size = (block_mode.grid_width, block_mode.grid_height);
+            let grid = grid_sizes
+                .iter()
+                .position(|&grid| grid == size)
+                .unwrap_or_else(|| {
+                    grid_sizes.push(size);
+                    grid_sizes.len() - 1
+                });
+
+            Some(ModeCandidate {
+                mode,
+                grid,
+                weight_levels: block_mode.weight_levels,
+                color_levels,
+            })
+        })
+        .fold(
+            Vec::new(),
+            |mut candidates: Vec<ModeCandidate>, candidate| {
+                // Different encodings of the same grid and quantization decode alike
+                if !candidates.iter().any(|other| {
+                    other.grid == candidate.grid && other.weight_levels == candidate.weight_levels
+                }) {
+                    candidates.push(candidate);
+                }
+                candidates
+            },
+        )
+}
+
+/// Two partition layouts of every seed, without the ones that leave a partition empty or
+/// repeat another layout
+fn get_partition_layouts(block_width: usize, block_height: usize) -> Vec<PartitionLayout> {
+    let texels = block_width * block_height;
+    let mut seen = HashSet::new();
+
+    (0..1024)
+        .filter_map(|seed| {
+            let partitions: Vec<usize> = (0..texels)
+                .map(|texel| {
+                    select_partition(
+                        seed,
+                        texel % block_width,
+                        texel / block_width,
+                        2,
+                        texels < 31,
+                    )
+                })
+                .collect();
+
+            // Layouts with the partitions swapped encode the same blocks
+            let normalized: Vec<bool> = partitions.iter().map(|&p| p != partitions[0]).collect();
+            if !normalized.contains(&true) || !seen.insert(normalized) {
+                return None;
+            }
+
+            Some(PartitionLayout { seed, partitions })
+        })
+        .collect()
+}
+
+/// Compress RGBA8 pixels to any ASTC format
+pub fn encode_astc(
+    pixels: &[u8],
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+    options: &CompressionOptions,
+) -> Result<Vec<u8>, TextureError> {
+    let Some((block_width, block_height)) = format.get_astc_block_size() else {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Not an ASTC pixel format: {}",
+            format
+        )));
+    };
+
+    let settings = EncoderSettings::new(block_width as usize, block_height as usize, options);
+
+    encode_blocks(pixels, width, height, &settings, options.threads, |block| {
+        encode_block(block, &settings)
+    })
+}
+
+/// Split the surface in rows of blocks and compress them on worker threads
+fn encode_blocks(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    settings: &EncoderSettings,
+    threads: usize,
+    encode_block: impl Fn(&[Color]) -> [u8; 16] + Sync,
+) -> Result<Vec<u8>, TextureError> {
+    let (width, height) = (width as usize, height as usize);
+    let (block_width, block_height) = (settings.block_width, settings.block_height);
+
+    if pixels.len() < width * height * 4 {
+        return Err(TextureError::DimensionMismatch(format!(
+            "Image data too small for {}x{} RGBA pixels: {} < {}",
+            width,
+            height,
+            pixels.len(),
+            width * height * 4
+        )));
+    }
+
+    let width_in_blocks = width.div_ceil(block_width);
+    let height_in_blocks = height.div_ceil(block_height);
+    let row_size = width_in_blocks * 16;
+    let mut output = vec![0; row_size * height_in_blocks];
+
+    if output.is_empty() {
+        return Ok(output);
+    }
+
+    let encode_rows = |first_row: usize, rows: &mut [u8]| {
+        let mut block_pixels = Vec::with_capacity(block_width * block_height);
+
+        for (row_index, row) in rows.chunks_exact_mut(row_size).enumerate() {
+            let block_y = (first_row + row_index) * block_height;
+
+            for (block_index, block) in row.chunks_exact_mut(16).enumerate() {
+                let block_x = block_index * block_width;
+
+                // Partial blocks repeat the edge pixels so they do not skew the fit
+                block_pixels.clear();
+                block_pixels.extend((0..block_width * block_height).map(|i| {
+                    let x = (block_x + i % block_width).min(width - 1);
+                    let y = (block_y + i / block_width).min(height - 1);
+                    let offset = (y * width + x) * 4;
+                    [0, 1, 2, 3].map(|c| pixels[offset + c] as f32)
+                }));
+
+                block.copy_from_slice(&encode_block(&block_pixels));
+            }
+        }
+    };
+
+    let threads = get_thread_count(threads).min(height_in_blocks);
+
+    if threads <= 1 {
+        encode_rows(0, &mut output);
+    } else {
+        let rows_per_thread = height_in_blocks.div_ceil(threads);
+
+        std::thread::scope(|scope| {
+            for (index, rows) in output.chunks_mut(rows_per_thread * row_size).enumerate() {
+                let encode_rows = &encode_rows;
+                scope.spawn(move || encode_rows(index * rows_per_thread, rows));
+            }
+        });
+    }
+
+    Ok(output)
+}
+
+/// Quantized block, ready to be packed
+struct BlockEncoding {
+    error: f32,
+    mode: u32,
+    partitions: usize,
+    seed: u32,
+    cem: u32,
+    color_levels: u32,
+    colors: Vec<u32>,
+    weight_levels: u32,
+    weights: Vec<u32>,
+}
+
+fn encode_block(pixels: &[Color], settings: &EncoderSettings) -> [u8; 16] {
+    if pixels.iter().all(|pixel| pixel == &pixels[0]) {
+        let color = pixels[0]
+            .iter()
+            .enumerate()
+            .fold(0u128, |bits, (c, &value)| {
+                bits | (value as u128 * 257) << (64 + c * 16)
+            });
+
+        return (VOID_EXTENT | color).to_le_bytes();
+    }
+
+    let opaque = pixels.iter().all(|pixel| pixel[3] == 255.0);
+    let gray = pixels
+        .iter()
+        .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+
+    let cem = match (gray, opaque) {
+        (true, true) => CEM_LUMINANCE,
+        (true, false) => CEM_LUMINANCE_ALPHA,
+        (false, true) => CEM_RGB,
+        (false, false) => CEM_RGBA,
+    };
+    let channels = if opaque { 0..3 } else { 0..4 };
+
+    let single = BlockPartitions::new(pixels, 0, vec![0; pixels.len()], 1, channels.clone());
+    let endpoints = single.endpoints[0];
+
+    let mut layouts = vec![single];
+    layouts.extend(
+        rank_layouts(pixels, &endpoints, channels.clone(), settings)
+            .into_iter()
+            .map(|layout| {
+                BlockPartitions::new(
+                    pixels,
+                    layout.seed,
+                    layout.partitions.clone(),
+                    2,
+                    channels.clone(),
+                )
+            }),
+    );
+
+    layouts
+        .iter()
+        .flat_map(|partitions| {
+            let candidates = &settings.candidates[partitions.endpoints.len() - 1][cem as usize / 4];
+
+            get_best_candidates(pixels, partitions, channels.clone(), candidates, settings)
+                .into_iter()
+                .map(|candidate| {
+                    encode_mode(
+                        pixels,
+                        partitions,
+                        channels.clone(),
+                        cem,
+                        candidate,
+                        settings,
+                    )
+                })
+        })
+        .min_by(|a, b| a.error.total_cmp(&b.error))
+        .map_or([0; 16], |encoding| pack_block(&encoding))
+}
+
+fn get_partition_points(pixels: &[Color], partitions: &[usize], partition: usize) -> Vec<Color> {
+    pixels
+        .iter()
+        .zip(partitions)
+        .filter(|(_, &p)| p == partition)
+        .map(|(pixel, _)| *pixel)
+        .collect()
+}
+
+/// Two partition layouts closest to a two color clustering of the block, best first
+fn rank_layouts<'a>(
+    pixels: &[Color],
+    endpoints: &[Color; 2],
+    channels: Range<usize>,
+    settings: &'a EncoderSettings,
+) -> Vec<&'a PartitionLayout> {
+    if settings.max_layouts == 0 {
+        return Vec::new();
+    }
+
+    // A few k-means steps, starting from the ends of the block's principal axis
+    let mut centers = *endpoints;
+    let mut clusters = vec![0; pixels.len()];
+
+    for _ in 0..3 {
+        for (cluster, pixel) in clusters.iter_mut().zip(pixels) {
+            let distance = |center: &Color| {
+                channels
+                    .clone()
+                    .map(|c| (pixel[c] - center[c]).powi(2))
+                    .sum::<f32>()
+            };
+            *cluster = (distance(&centers[1]) < distance(&centers[0])) as usize;
+        }
+
+        for (index, center) in centers.iter_mut().enumerate() {
+            let points = get_partition_points(pixels, &clusters, index);
+
+            if !points.is_empty() {
+                for c in channels.clone() {
+                    center[c] =
+                        points.iter().map(|point| point[c]).sum::<f32>() / points.len() as f32;
+                }
+            }
+        }
+    }
+
+    let mut ranked: Vec<(usize, &PartitionLayout)> = settings
+        .layouts
+        .iter()
+        .map(|layout| {
+            let mismatches = layout
+                .partitions
+                .iter()
+                .zip(&clusters)
+                .filter(|(a, b)| a != b)
+                .count();
+            (mismatches.min(pixels.len() - mismatches), layout)
+        })
+        .collect();
+
+    ranked.sort_by_key(|&(mismatches, _)| mismatches);
+
+    ranked
+        .into_iter()
+        .take(settings.max_layouts)
+        .map(|(_, layout)| layout)
+        .collect()
+}
+
+/// Block modes worth a full fit, by an estimate of their error on this block: how well
+/// the grid follows the unquantized weights, and the quantization noise of the weights
+/// and the endpoints
+fn get_best_candidates<'a>(
+    pixels: &[Color],
+    partitions: &BlockPartitions,
+    channels: Range<usize>,
+    candidates: &'a [ModeCandidate],
+    settings: &EncoderSettings,
+) -> Vec<&'a ModeCandidate> {
+    if candidates.len() <= settings.max_candidates {
+        return candidates.iter().collect();
+    }
+
+    // Weight errors scale with the distance between the endpoints of every texel
+    let scales: Vec<f32> = partitions
+        .partitions
+        .iter()
+        .map(|&partition| {
+            let endpoints = &partitions.endpoints[partition];
+            channels
+                .clone()
+                .map(|c| (endpoints[1][c] - endpoints[0][c]).powi(2))
+                .sum::<f32>()
+                / (64.0 * 64.0)
+        })
+        .collect();
+    let total_scale: f32 = scales.iter().sum();
+
+    let ideal = get_ideal_weights(pixels, partitions, channels.clone());
+    let grid_errors: Vec<f32> = settings
+        .grids
+        .iter()
+        .map(|grid| {
+            let weights = decimate_weights(&ideal, grid);
+
+            grid.infill
+                .iter()
+                .zip(&ideal)
+                .zip(&scales)
+                .map(|((points, &ideal), &scale)| {
+                    let weight: f32 = points
+                        .iter()
+                        .map(|&(index, factor)| weights[index] * factor as f32 / 16.0)
+                        .sum();
+                    (weight - ideal).powi(2) * scale
+                })
+                .sum()
+        })
+        .collect();
+
+    let texels = pixels.len() as f32;
+
+    let mut ranked: Vec<(f32, &ModeCandidate)> = candidates
+        .iter()
+        .map(|candidate| {
+            let weight_step = 64.0 / (candidate.weight_levels - 1) as f32;
+            let color_step = 255.0 / (candidate.color_levels - 1) as f32;
+            let error = grid_errors[candidate.grid]
+                + total_scale * weight_step.powi(2) / 12.0
+                + texels * channels.len() as f32 * color_step.powi(2) / 24.0;
+
+            (error, candidate)
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    ranked
+        .into_iter()
+        .take(settings.max_candidates)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Fit a block with one partition layout and block mode
+fn encode_mode(
+    pixels: &[Color],
+    partitions: &BlockPartitions,
+    channels: Range<usize>,
+    cem: u32,
+    candidate: &ModeCandidate,
+    settings: &EncoderSettings,
+) -> BlockEncoding {
+    let weight_table = EncoderSettings::get_table(&settings.weight_tables, candidate.weight_levels);
+    let color_table = EncoderSettings::get_table(&settings.color_tables, candidate.color_levels);
+    let grid = &settings.grids[candidate.grid];
+    let count = partitions.endpoints.len();
+
+    let mut fitted = BlockPartitions {
+        seed: partitions.seed,
+        partitions: partitions.partitions.clone(),
+        endpoints: partitions.endpoints.clone(),
+    };
+
+    for _ in 0..settings.refine_iterations {
+        let (_, weights) = fit_weights(pixels, &fitted, channels.clone(), grid, weight_table);
+
+        for partition in 0..count {
+            let points = get_partition_points(pixels, &fitted.partitions, partition);
+            let point_weights: Vec<i32> = weights
+                .iter()
+                .zip(&fitted.partitions)
+                .filter(|(_, &p)| p == partition)
+                .map(|(&weight, _)| weight)
+                .collect();
+
+            if let Some(refined) =
+                get_refined_endpoints(&points, &point_weights, channels.clone(), 255.0)
+            {
+                fitted.endpoints[partition] = refined;
+            }
+        }
+    }
+
+    let colors: Vec<u32> = fitted
+        .endpoints
+        .iter()
+        .flat_map(|endpoints| quantize_endpoints(endpoints, cem, color_table))
+        .collect();
+    let values: Vec<i32> = colors
+        .iter()
+        .map(|&value| unquantize_color(value, candidate.color_levels))
+        .collect();
+
+    // Fit the weights to the endpoints the decoder sees
+    let decoded: Vec<[[i32; 4]; 2]> = values
+        .chunks_exact(values.len() / count)
+        .map(|values| unpack_endpoints(cem, values).colors)
+        .collect();
+    fitted.endpoints = decoded
+        .iter()
+        .map(|colors| colors.map(|color| color.map(|c| c as f32 / 257.0)))
+        .collect();
+
+    let (weights, texel_weights) = fit_weights(pixels, &fitted, channels, grid, weight_table);
+
+    let error = pixels
+        .iter()
+        .zip(&texel_weights)
+        .zip(&fitted.partitions)
+        .map(|((pixel, &weight), &partition)| {
+            let endpoints = &decoded[partition];
+            let color = [0, 1, 2, 3]
+                .map(|c| interpolate(endpoints[0][c], endpoints[1][c], weight) as f32 / 257.0);
+            settings.metric.get_error(pixel, &color, 0..4)
+        })
+        .sum();
+
+    BlockEncoding {
+        error,
+        mode: candidate.mode,
+        partitions: count,
+        seed: partitions.seed,
+        cem,
+        color_levels: candidate.color_levels,
+        colors,
+        weight_levels: candidate.weight_levels,
+        weights,
+    }
+}
+
+/// Position of every texel between the endpoints of its partition, in 0 - 64
+fn get_ideal_weights(
+    pixels: &[Color],
+    partitions: &BlockPartitions,
+    channels: Range<usize>,
+) -> Vec<f32> {
+    pixels
+        .iter()
+        .zip(&partitions.partitions)
+        .map(|(pixel, &partition)| {
+            let endpoints = &partitions.endpoints[partition];
+            let axis: Color = std::array::from_fn(|c| endpoints[1][c] - endpoints[0][c]);
+            let length = channels.clone().map(|c| axis[c] * axis[c]).sum::<f32>();
+
+            if length < 1e-6 {
+                return 0.0;
+            }
+
+            let t = channels
+                .clone()
+                .map(|c| (pixel[c] - endpoints[0][c]) * axis[c])
+                .sum::<f32>()
+                / length;
+            t.clamp(0.0, 1.0) * 64.0
+        })
+        .collect()
+}
+
+/// Quantized grid weights for the endpoints and the weights every texel ends up with
+fn fit_weights(
+    pixels: &[Color],
+    partitions: &BlockPartitions,
+    channels: Range<usize>,
+    grid: &WeightGrid,
+    table: &QuantTable,
+) -> (Vec<u32>, Vec<i32>) {
+    let ideal = get_ideal_weights(pixels, partitions, channels);
+    let (encoded, unquantized): (Vec<u32>, Vec<i32>) = decimate_weights(&ideal, grid)
+        .iter()
+        .map(|&weight| table.quantize(weight))
+        .unzip();
+
+    let texel_weights = grid
+        .infill
+        .iter()
+        .map(|infill| infill_weight(infill, &unquantized))
+        .collect();
+
+    (encoded, texel_weights)
+}
+
+/// Grid weights whose bilinear infill comes closest to the texel weights
+fn decimate_weights(ideal: &[f32], grid: &WeightGrid) -> Vec<f32> {
+    if grid.contributions.len() == ideal.len() {
+        return ideal.to_vec();
+    }
+
+    let mut weights: Vec<f32> = grid
+        .contributions
+        .iter()
+        .map(|texels| {
+            let total: u32 = texels.iter().map(|&(_, factor)| factor).sum();
+            let sum: f32 = texels
+                .iter()
+                .map(|&(texel, factor)| ideal[texel] * factor as f32)
+                .sum();
+            sum / total.max(1) as f32
+        })
+        .collect();
+
+    // A few gradient steps on the infill error sharpen what the average smoothed out
+    for _ in 0..2 {
+        let infilled: Vec<f32> = grid
+            .infill
+            .iter()
+            .map(|points| {
+                points
+                    .iter()
+                    .map(|&(index, factor)| weights[index] * factor as f32 / 16.0)
+                    .sum()
+            })
+            .collect();
+
+        for (weight, texels) in weights.iter_mut().zip(&grid.contributions) {
+            let (mut step, mut total) = (0.0, 0.0);
+
+            for &(texel, factor) in texels {
+                let factor = factor as f32 / 16.0;
+                step += (ideal[texel] - infilled[texel]) * factor;
+                total += factor * factor;
+            }
+
+            if total > 0.0 {
+                *weight = (*weight + step / total).clamp(0.0, 64.0);
+            }
+        }
+    }
+
+    weights
+}
+
+/// Quantized endpoint values of a direct endpoint mode. RGB endpoints are ordered so the
+/// decoder does not blue contract them.
+fn quantize_endpoints(endpoints: &[Color; 2], cem: u32, table: &QuantTable) -> Vec<u32> {
+    let channels: &[usize] = match cem {
+        CEM_LUMINANCE => &[0],
+        CEM_LUMINANCE_ALPHA => &[0, 3],
+        CEM_RGB => &[0, 1, 2],
+        _ => &[0, 1, 2, 3],
+    };
+
+    let mut quantized = endpoints.map(|endpoint| {
+        channels
+            .iter()
+            .map(|&c| table.quantize(endpoint[c]))
+            .collect::<Vec<_>>()
+    });
+
+    if cem >= CEM_RGB {
+        let sum =
+            |endpoint: &Vec<(u32, i32)>| endpoint[..3].iter().map(|&(_, value)| value).sum::<i32>();
+
+        if sum(&quantized[1]) < sum(&quantized[0]) {
+            quantized.swap(0, 1);
+        }
+    }
+
+    // Values alternate between the two endpoints, channel by channel
+    (0..channels.len())
+        .flat_map(|i| [quantized[0][i].0, quantized[1][i].0])
+        .collect()
+}
+
+/// Pack an integer sequence, every value is its trit or quint above its plain bits
+fn encode_ise(writer: &mut BlockWriter, values: &[u32], levels: u32) {
+    let (kind, plain_bits) = get_ise_layout(levels);
+    let plain_mask = (1 << plain_bits) - 1;
+
+    let (group_bits, group_size, packed_count): (&[u32], usize, u32) = match kind {
+        IseKind::Bits => {
+            for &value in values {
+                writer.write(value, plain_bits);
+            }
+            return;
+        }
+        IseKind::Trits => (&[2, 2, 1, 2, 1], 5, 256),
+        IseKind::Quints => (&[3, 2, 2], 3, 128),
+    };
+
+    for group in values.chunks(group_size) {
+        let digits: Vec<u32> = (0..group_size)
+            .map(|i| group.get(i).map_or(0, |value| value >> plain_bits))
+            .collect();
+
+        // The smallest packing keeps the bits of missing trailing values zero
+        let packed = (0..packed_count)
+            .find(|&packed| match kind {
+                IseKind::Trits => decode_trits(packed)[..] == digits[..],
+                _ => decode_quints(packed)[..] == digits[..],
+            })
+            .unwrap_or(0);
+
+        let mut shift = 0;
+        for (i, &extra) in group_bits.iter().enumerate() {
+            if i >= group.len() {
+                break;
+            }
+
+            writer.write(group[i] & plain_mask, plain_bits);
+            writer.write(packed >> shift, extra);
+            shift += extra;
+        }
+    }
+}
+
+fn pack_block(encoding: &BlockEncoding) -> [u8; 16] {
+    let mut writer = BlockWriter::new();
+    writer.write(encoding.mode, 11);
+    writer.write(encoding.partitions as u32 - 1, 2);
+
+    if encoding.partitions > 1 {
+        // Every partition shares the endpoint mode
+        writer.write(encoding.seed, 10);
+        writer.write(0, 2);
+    }
+
+    writer.write(encoding.cem, 4);
+    encode_ise(&mut writer, &encoding.colors, encoding.color_levels);
+
+    // Weights go backwards from the top of the block
+    let mut weights = BlockWriter::new();
+    encode_ise(&mut weights, &encoding.weights, encoding.weight_levels);
+
+    let bits = u128::from_le_bytes(writer.into_bytes())
+        | u128::from_le_bytes(weights.into_bytes()).reverse_bits();
+
+    bits.to_le_bytes()
+}
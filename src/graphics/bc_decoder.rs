@@ -0,0 +1,690 @@
+use super::pixel_format::PixelFormat;
+use crate::error::TextureError;
+
+// Native BC1 - BC7 block decompression. The results match DirectXTex bit for bit,
+// including its float math for BC1 - BC5, so textures look the same on every target,
+// wasm32 included, where the C++ library is not available.
+
+const BLOCK_PIXELS: usize = 16;
+
+/// Bias DirectXTex adds before truncating floats to 8 bit unorm
+const UNORM8_BIAS: f32 = 0.5 / 255.0;
+
+pub(super) const WEIGHTS_2: [i32; 4] = [0, 21, 43, 64];
+pub(super) const WEIGHTS_3: [i32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+pub(super) const WEIGHTS_4: [i32; 16] =
+    [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// BC6H / BC7 two subset partitions, bit n is set when pixel n belongs to the second subset
+#[rustfmt::skip]
+pub(super) const PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// BC7 three subset partitions, two bits per pixel
+#[rustfmt::skip]
+pub(super) const PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// Anchor pixel of the second subset in two subset partitions. Pixel 0 anchors the first one.
+#[rustfmt::skip]
+pub(super) const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor pixels of the second and third subsets in three subset partitions
+#[rustfmt::skip]
+pub(super) const ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+/// Decode a whole BC1 - BC7 surface to RGBA8. Partial blocks on the right and bottom edges are cropped.
+/// `signed` picks signed BC6H, negative values are clamped to black.
+pub fn decode_bc_to_rgba(
+    pixels: &[u8],
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+    signed: bool,
+) -> Result<Vec<u8>, TextureError> {
+    let decode_block: fn(&[u8]) -> [[u8; 4]; BLOCK_PIXELS] = match format {
+        PixelFormat::BC1 => decode_bc1_block,
+        PixelFormat::BC2 => decode_bc2_block,
+        PixelFormat::BC3 => decode_bc3_block,
+        PixelFormat::BC4 => decode_bc4_block,
+        PixelFormat::BC5 => decode_bc5_block,
+        PixelFormat::BC6H if signed => |block| unorm8_block(&decode_bc6h_block(block, true)),
+        PixelFormat::BC6H => |block| unorm8_block(&decode_bc6h_block(block, false)),
+        PixelFormat::BC7 => decode_bc7_block,
+        _ => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Not a BC pixel format: {}",
+                format
+            )))
+        }
+    };
+
+    decode_blocks(pixels, format, width, height, 4, decode_block)
+}
+
+/// Decode a whole BC6H surface to RGBA32F, keeping the HDR range
+pub fn decode_bc6h_to_rgba_f32(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    signed: bool,
+) -> Result<Vec<f32>, TextureError> {
+    decode_blocks(pixels, PixelFormat::BC6H, width, height, 0.0, |block| {
+        decode_bc6h_block(block, signed)
+    })
+}
+
+/// Walk the blocks of a surface and scatter their pixels, `T` is a single channel
+fn decode_blocks<T: Copy, const N: usize>(
+    pixels: &[u8],
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+    zero: T,
+    decode_block: impl Fn(&[u8]) -> [[T; N]; BLOCK_PIXELS],
+) -> Result<Vec<T>, TextureError> {
+    let bytes_per_block = format.get_bytes_per_block() as usize;
+    let width_in_blocks = width.div_ceil(4) as usize;
+    let height_in_blocks = height.div_ceil(4) as usize;
+    let expected_size = width_in_blocks * height_in_blocks * bytes_per_block;
+
+    if pixels.len() < expected_size {
+        return Err(TextureError::DimensionMismatch(format!(
+            "Image data too small for {}: {} < {}",
+            format,
+            pixels.len(),
+            expected_size
+        )));
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let mut output = vec![zero; width * height * N];
+
+    for (index, block) in pixels[..expected_size]
+        .chunks_exact(bytes_per_block)
+        .enumerate()
+    {
+        let block_x = (index % width_in_blocks) * 4;
+        let block_y = (index / width_in_blocks) * 4;
+        let decoded = decode_block(block);
+
+        for y in 0..4.min(height - block_y) {
+            for x in 0..4.min(width - block_x) {
+                let offset = ((block_y + y) * width + block_x + x) * N;
+                output[offset..offset + N].copy_from_slice(&decoded[y * 4 + x]);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Store floats like DirectXTex does for R8G8B8A8_UNORM: bias, saturate, scale and truncate
+pub(super) fn unorm8(value: f32) -> u8 {
+    ((value + UNORM8_BIAS).clamp(0.0, 1.0) * 255.0) as u8
+}
+
+fn unorm8_block(colors: &[[f32; 4]; BLOCK_PIXELS]) -> [[u8; 4]; BLOCK_PIXELS] {
+    colors.map(|color| color.map(unorm8))
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn decode_rgb565(value: u16) -> [f32; 4] {
+    [
+        ((value >> 11) & 0x1F) as f32 * (1.0f32 / 31.0),
+        ((value >> 5) & 0x3F) as f32 * (1.0f32 / 63.0),
+        (value & 0x1F) as f32 * (1.0f32 / 31.0),
+        1.0,
+    ]
+}
+
+/// BC1 colors, the 3 color + transparent black mode is only available to BC1 itself
+fn decode_color_block(block: &[u8], is_bc1: bool) -> [[f32; 4]; BLOCK_PIXELS] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let c0 = decode_rgb565(color0);
+    let c1 = decode_rgb565(color1);
+
+    let palette = if is_bc1 && color0 <= color1 {
+        [c0, c1, lerp(c0, c1, 0.5), [0.0; 4]]
+    } else {
+        [
+            c0,
+            c1,
+            lerp(c0, c1, 1.0f32 / 3.0),
+            lerp(c0, c1, 2.0f32 / 3.0),
+        ]
+    };
+
+    std::array::from_fn(|i| palette[(indices >> (i * 2)) as usize & 3])
+}
+
+/// BC3 alpha and BC4 / BC5 channels share the same 8 value ramp.
+/// BC3 scales the endpoints by 1/255 first, BC4 and BC5 divide every value.
+fn decode_ramp_block(block: &[u8], is_bc3: bool) -> [f32; BLOCK_PIXELS] {
+    let (e0, e1) = (block[0], block[1]);
+    let indices = u64::from_le_bytes([
+        block[2], block[3], block[4], block[5], block[6], block[7], 0, 0,
+    ]);
+
+    let mut ramp = [0.0; 8];
+
+    if is_bc3 {
+        let (a0, a1) = (e0 as f32 * (1.0f32 / 255.0), e1 as f32 * (1.0f32 / 255.0));
+        ramp[0] = a0;
+        ramp[1] = a1;
+
+        if e0 > e1 {
+            for i in 1..7 {
+                ramp[i + 1] = (a0 * (7 - i) as f32 + a1 * i as f32) * (1.0f32 / 7.0);
+            }
+        } else {
+            for i in 1..5 {
+                ramp[i + 1] = (a0 * (5 - i) as f32 + a1 * i as f32) * (1.0f32 / 5.0);
+            }
+            ramp[6] = 0.0;
+            ramp[7] = 1.0;
+        }
+    } else {
+        let (r0, r1) = (e0 as f32 / 255.0, e1 as f32 / 255.0);
+        ramp[0] = r0;
+        ramp[1] = r1;
+
+        if e0 > e1 {
+            for i in 1..7 {
+                ramp[i + 1] = (r0 * (7 - i) as f32 + r1 * i as f32) / 7.0;
+            }
+        } else {
+            for i in 1..5 {
+                ramp[i + 1] = (r0 * (5 - i) as f32 + r1 * i as f32) / 5.0;
+            }
+            ramp[6] = 0.0;
+            ramp[7] = 1.0;
+        }
+    }
+
+    std::array::from_fn(|i| ramp[(indices >> (i * 3)) as usize & 7])
+}
+
+/// BC1 / DXT1, 8 bytes per block
+pub fn decode_bc1_block(block: &[u8]) -> [[u8; 4]; BLOCK_PIXELS] {
+    unorm8_block(&decode_color_block(block, true))
+}
+
+/// BC2 / DXT3, explicit 4 bit alpha followed by a BC1 color block
+pub fn decode_bc2_block(block: &[u8]) -> [[u8; 4]; BLOCK_PIXELS] {
+    let mut colors = decode_color_block(&block[8..], false);
+    let alpha = u64::from_le_bytes([
+        block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7],
+    ]);
+
+    for (i, color) in colors.iter_mut().enumerate() {
+        color[3] = ((alpha >> (i * 4)) & 0xF) as f32 * (1.0f32 / 15.0);
+    }
+
+    unorm8_block(&colors)
+}
+
+/// BC3 / DXT5, interpolated alpha followed by a BC1 color block
+pub fn decode_bc3_block(block: &[u8]) -> [[u8; 4]; BLOCK_PIXELS] {
+    let mut colors = decode_color_block(&block[8..], false);
+    let alpha = decode_ramp_block(block, true);
+
+    for (color, alpha) in colors.iter_mut().zip(alpha) {
+        color[3] = alpha;
+    }
+
+    unorm8_block(&colors)
+}
+
+/// BC4 unorm, the red channel is copied to green and blue like DirectXTex does
+pub fn decode_bc4_block(block: &[u8]) -> [[u8; 4]; BLOCK_PIXELS] {
+    decode_ramp_block(block, false).map(|red| {
+        let red = unorm8(red);
+        [red, red, red, 0xFF]
+    })
+}
+
+/// BC5 unorm, red and green with blue left at zero
+pub fn decode_bc5_block(block: &[u8]) -> [[u8; 4]; BLOCK_PIXELS] {
+    let red = decode_ramp_block(block, false);
+    let green = decode_ramp_block(&block[8..], false);
+
+    std::array::from_fn(|i| [unorm8(red[i]), unorm8(green[i]), 0, 0xFF])
+}
+
+/// Reads BC6H / BC7 fields, least significant bit first
+struct BlockReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BlockReader {
+    fn new(block: &[u8]) -> Self {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&block[..16]);
+
+        Self {
+            bits: u128::from_le_bytes(bytes),
+            position: 0,
+        }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+
+        let value = (self.bits >> self.position) as u32 & ((1u64 << count) - 1) as u32;
+        self.position += count;
+        value
+    }
+}
+
+pub(super) fn get_subset(subsets: usize, shape: usize, pixel: usize) -> usize {
+    match subsets {
+        2 => (PARTITIONS_2[shape] >> pixel) as usize & 1,
+        3 => (PARTITIONS_3[shape] >> (pixel * 2)) as usize & 3,
+        _ => 0,
+    }
+}
+
+/// Anchor pixels store their index with one bit less, the top bit is implicitly zero
+pub(super) fn is_anchor(subsets: usize, shape: usize, pixel: usize) -> bool {
+    match subsets {
+        2 => pixel == 0 || pixel == ANCHORS_2[shape] as usize,
+        3 => pixel == 0 || ANCHORS_3[shape].contains(&(pixel as u8)),
+        _ => pixel == 0,
+    }
+}
+
+pub(super) fn get_weights(index_bits: u32) -> &'static [i32] {
+    match index_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+pub(super) fn interpolate(e0: i32, e1: i32, weight: i32) -> i32 {
+    (e0 * (64 - weight) + e1 * weight + 32) >> 6
+}
+
+pub(super) struct Bc7Mode {
+    pub(super) subsets: usize,
+    pub(super) partition_bits: u32,
+    pub(super) p_bits: usize,
+    pub(super) rotation_bits: u32,
+    pub(super) index_mode_bits: u32,
+    pub(super) index_bits: u32,
+    pub(super) index_bits_2: u32,
+    pub(super) color_bits: u32,
+    pub(super) alpha_bits: u32,
+    /// Whether the color and alpha channels get a p-bit
+    pub(super) color_p_bit: bool,
+    pub(super) alpha_p_bit: bool,
+}
+
+#[rustfmt::skip]
+pub(super) const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, p_bits: 6, rotation_bits: 0, index_mode_bits: 0, index_bits: 3, index_bits_2: 0, color_bits: 4, alpha_bits: 0, color_p_bit: true, alpha_p_bit: false },
+    Bc7Mode { subsets: 2, partition_bits: 6, p_bits: 2, rotation_bits: 0, index_mode_bits: 0, index_bits: 3, index_bits_2: 0, color_bits: 6, alpha_bits: 0, color_p_bit: true, alpha_p_bit: false },
+    Bc7Mode { subsets: 3, partition_bits: 6, p_bits: 0, rotation_bits: 0, index_mode_bits: 0, index_bits: 2, index_bits_2: 0, color_bits: 5, alpha_bits: 0, color_p_bit: false, alpha_p_bit: false },
+    Bc7Mode { subsets: 2, partition_bits: 6, p_bits: 4, rotation_bits: 0, index_mode_bits: 0, index_bits: 2, index_bits_2: 0, color_bits: 7, alpha_bits: 0, color_p_bit: true, alpha_p_bit: false },
+    Bc7Mode { subsets: 1, partition_bits: 0, p_bits: 0, rotation_bits: 2, index_mode_bits: 1, index_bits: 2, index_bits_2: 3, color_bits: 5, alpha_bits: 6, color_p_bit: false, alpha_p_bit: false },
+    Bc7Mode { subsets: 1, partition_bits: 0, p_bits: 0, rotation_bits: 2, index_mode_bits: 0, index_bits: 2, index_bits_2: 2, color_bits: 7, alpha_bits: 8, color_p_bit: false, alpha_p_bit: false },
+    Bc7Mode { subsets: 1, partition_bits: 0, p_bits: 2, rotation_bits: 0, index_mode_bits: 0, index_bits: 4, index_bits_2: 0, color_bits: 7, alpha_bits: 7, color_p_bit: true, alpha_p_bit: true },
+    Bc7Mode { subsets: 2, partition_bits: 6, p_bits: 4, rotation_bits: 0, index_mode_bits: 0, index_bits: 2, index_bits_2: 0, color_bits: 5, alpha_bits: 5, color_p_bit: true, alpha_p_bit: true },
+];
+
+/// Widen a BC7 endpoint channel by replicating its top bits
+pub(super) fn unquantize_bc7(value: u32, bits: u32) -> i32 {
+    let value = (value << (8 - bits)) & 0xFF;
+    (value | (value >> bits)) as i32
+}
+
+/// BC7, 8 modes with up to 3 subsets. Reserved modes decode to transparent black.
+pub fn decode_bc7_block(block: &[u8]) -> [[u8; 4]; BLOCK_PIXELS] {
+    let mut reader = BlockReader::new(block);
+
+    let Some(mode_index) = (0..8).find(|_| reader.read(1) == 1) else {
+        return [[0; 4]; BLOCK_PIXELS];
+    };
+    let mode = &BC7_MODES[mode_index];
+
+    let shape = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_mode = reader.read(mode.index_mode_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+
+    for channel in 0..4 {
+        let bits = if channel < 3 {
+            mode.color_bits
+        } else {
+            mode.alpha_bits
+        };
+
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = if bits > 0 { reader.read(bits) } else { 255 };
+        }
+    }
+
+    let p_bits: Vec<u32> = (0..mode.p_bits).map(|_| reader.read(1)).collect();
+    let color_bits = mode.color_bits + mode.color_p_bit as u32;
+    let alpha_bits = mode.alpha_bits + mode.alpha_p_bit as u32;
+
+    let endpoints: Vec<[i32; 4]> = endpoints[..endpoint_count]
+        .iter()
+        .enumerate()
+        .map(|(i, endpoint)| {
+            let mut endpoint = *endpoint;
+
+            if mode.p_bits > 0 {
+                let p_bit = p_bits[i * mode.p_bits / endpoint_count];
+                let channels = if mode.alpha_p_bit { 0..4 } else { 0..3 };
+
+                for channel in channels {
+                    endpoint[channel] = (endpoint[channel] << 1) | p_bit;
+                }
+            }
+
+            [
+                unquantize_bc7(endpoint[0], color_bits),
+                unquantize_bc7(endpoint[1], color_bits),
+                unquantize_bc7(endpoint[2], color_bits),
+                if alpha_bits > 0 {
+                    unquantize_bc7(endpoint[3], alpha_bits)
+                } else {
+                    255
+                },
+            ]
+        })
+        .collect();
+
+    let color_indices: [u32; BLOCK_PIXELS] = std::array::from_fn(|pixel| {
+        let anchor = is_anchor(mode.subsets, shape, pixel) as u32;
+        reader.read(mode.index_bits - anchor)
+    });
+
+    let alpha_indices: [u32; BLOCK_PIXELS] = if mode.index_bits_2 > 0 {
+        std::array::from_fn(|pixel| reader.read(mode.index_bits_2 - (pixel == 0) as u32))
+    } else {
+        color_indices
+    };
+
+    let alpha_index_bits = if mode.index_bits_2 > 0 {
+        mode.index_bits_2
+    } else {
+        mode.index_bits
+    };
+
+    // Index mode 1 swaps which index set drives the color and the alpha
+    let (color_indices, color_weights, alpha_indices, alpha_weights) = if index_mode == 0 {
+        (
+            color_indices,
+            get_weights(mode.index_bits),
+            alpha_indices,
+            get_weights(alpha_index_bits),
+        )
+    } else {
+        (
+            alpha_indices,
+            get_weights(alpha_index_bits),
+            color_indices,
+            get_weights(mode.index_bits),
+        )
+    };
+
+    std::array::from_fn(|pixel| {
+        let subset = get_subset(mode.subsets, shape, pixel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let color_weight = color_weights[color_indices[pixel] as usize];
+        let alpha_weight = alpha_weights[alpha_indices[pixel] as usize];
+
+        let mut color = [
+            interpolate(e0[0], e1[0], color_weight) as u8,
+            interpolate(e0[1], e1[1], color_weight) as u8,
+            interpolate(e0[2], e1[2], color_weight) as u8,
+            interpolate(e0[3], e1[3], alpha_weight) as u8,
+        ];
+
+        if rotation > 0 {
+            color.swap(rotation as usize - 1, 3);
+        }
+
+        color
+    })
+}
+
+// BC6H header fields: endpoint (W, X, Y, Z) * 3 + channel, or the partition shape
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+pub(super) const D: u8 = 12;
+
+/// Mode field of every BC6H mode, 2 bits for the first two and 5 bits for the others
+pub(super) const BC6H_MODE_BITS: [u32; 14] = [
+    0x00, 0x01, 0x02, 0x06, 0x0A, 0x0E, 0x12, 0x16, 0x1A, 0x1E, 0x03, 0x07, 0x0B, 0x0F,
+];
+
+pub(super) struct Bc6hMode {
+    pub(super) transformed: bool,
+    pub(super) two_regions: bool,
+    /// Precision of the first endpoint, and of the others when they are not deltas
+    pub(super) endpoint_bits: u32,
+    pub(super) delta_bits: [u32; 3],
+    /// Header bits after the mode, as (field, first bit, last bit) runs.
+    /// Runs with the first bit above the last one are stored reversed.
+    pub(super) layout: &'static [(u8, u8, u8)],
+}
+
+#[rustfmt::skip]
+pub(super) const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 10, delta_bits: [5, 5, 5], layout: &[(GY, 4, 4), (BY, 4, 4), (BZ, 4, 4), (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 7, delta_bits: [6, 6, 6], layout: &[(GY, 5, 5), (GZ, 4, 5), (RW, 0, 6), (BZ, 0, 1), (BY, 4, 4), (GW, 0, 6), (BY, 5, 5), (BZ, 2, 2), (GY, 4, 4), (BW, 0, 6), (BZ, 3, 3), (BZ, 5, 4), (RX, 0, 5), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 5), (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 11, delta_bits: [5, 4, 4], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 4), (RW, 10, 10), (GY, 0, 3), (GX, 0, 3), (GW, 10, 10), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 3), (BW, 10, 10), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 11, delta_bits: [4, 5, 4], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 10, 10), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (GW, 10, 10), (GZ, 0, 3), (BX, 0, 3), (BW, 10, 10), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 3), (BZ, 0, 0), (BZ, 2, 2), (RZ, 0, 3), (GY, 4, 4), (BZ, 3, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 11, delta_bits: [4, 4, 5], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 10, 10), (BY, 4, 4), (GY, 0, 3), (GX, 0, 3), (GW, 10, 10), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BW, 10, 10), (BY, 0, 3), (RY, 0, 3), (BZ, 1, 2), (RZ, 0, 3), (BZ, 4, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 9, delta_bits: [5, 5, 5], layout: &[(RW, 0, 8), (BY, 4, 4), (GW, 0, 8), (GY, 4, 4), (BW, 0, 8), (BZ, 4, 4), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 8, delta_bits: [6, 5, 5], layout: &[(RW, 0, 7), (GZ, 4, 4), (BY, 4, 4), (GW, 0, 7), (BZ, 2, 2), (GY, 4, 4), (BW, 0, 7), (BZ, 3, 4), (RX, 0, 5), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 8, delta_bits: [5, 6, 5], layout: &[(RW, 0, 7), (BZ, 0, 0), (BY, 4, 4), (GW, 0, 7), (GY, 5, 4), (BW, 0, 7), (GZ, 5, 5), (BZ, 4, 4), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: true, two_regions: true, endpoint_bits: 8, delta_bits: [5, 5, 6], layout: &[(RW, 0, 7), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 7), (BY, 5, 5), (GY, 4, 4), (BW, 0, 7), (BZ, 5, 4), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 5), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4)] },
+    Bc6hMode { transformed: false, two_regions: true, endpoint_bits: 6, delta_bits: [6, 6, 6], layout: &[(RW, 0, 5), (GZ, 4, 4), (BZ, 0, 1), (BY, 4, 4), (GW, 0, 5), (GY, 5, 5), (BY, 5, 5), (BZ, 2, 2), (GY, 4, 4), (BW, 0, 5), (GZ, 5, 5), (BZ, 3, 3), (BZ, 5, 4), (RX, 0, 5), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 5), (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5), (D, 0, 4)] },
+    Bc6hMode { transformed: false, two_regions: false, endpoint_bits: 10, delta_bits: [10, 10, 10], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 9), (GX, 0, 9), (BX, 0, 9)] },
+    Bc6hMode { transformed: true, two_regions: false, endpoint_bits: 11, delta_bits: [9, 9, 9], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 8), (RW, 10, 10), (GX, 0, 8), (GW, 10, 10), (BX, 0, 8), (BW, 10, 10)] },
+    Bc6hMode { transformed: true, two_regions: false, endpoint_bits: 12, delta_bits: [8, 8, 8], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 7), (RW, 11, 10), (GX, 0, 7), (GW, 11, 10), (BX, 0, 7), (BW, 11, 10)] },
+    Bc6hMode { transformed: true, two_regions: false, endpoint_bits: 16, delta_bits: [4, 4, 4], layout: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 15, 10), (GX, 0, 3), (GW, 15, 10), (BX, 0, 3), (BW, 15, 10)] },
+];
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+pub(super) fn unquantize_bc6h(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        if bits >= 15 || value == 0 {
+            value
+        } else if value == (1 << bits) - 1 {
+            0xFFFF
+        } else {
+            ((value << 16) + 0x8000) >> bits
+        }
+    } else if bits >= 16 {
+        value
+    } else {
+        let magnitude = value.abs();
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+
+        if value < 0 {
+            -unquantized
+        } else {
+            unquantized
+        }
+    }
+}
+
+/// Scale an interpolated value to the half float range and return its bits
+pub(super) fn finish_unquantize_bc6h(value: i32, signed: bool) -> u16 {
+    if !signed {
+        ((value * 31) >> 6) as u16
+    } else if value < 0 {
+        0x8000 | ((-value * 31) >> 5) as u16
+    } else {
+        ((value * 31) >> 5) as u16
+    }
+}
+
+pub(super) fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+
+    match exponent {
+        // Subnormals are exact multiples of 2^-24
+        0 => f32::from_bits(sign | (mantissa as f32 / 16_777_216.0).to_bits()),
+        31 => f32::from_bits(sign | 0x7F80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// BC6H, 14 modes of unsigned or signed half float RGB. Reserved modes decode to opaque black.
+pub fn decode_bc6h_block(block: &[u8], signed: bool) -> [[f32; 4]; BLOCK_PIXELS] {
+    let mut reader = BlockReader::new(block);
+
+    let mut mode_bits = reader.read(2);
+    if mode_bits > 1 {
+        mode_bits |= reader.read(3) << 2;
+    }
+
+    let Some(mode_index) = BC6H_MODE_BITS.iter().position(|&bits| bits == mode_bits) else {
+        return [[0.0, 0.0, 0.0, 1.0]; BLOCK_PIXELS];
+    };
+    let mode = &BC6H_MODES[mode_index];
+
+    let mut endpoints = [[0i32; 3]; 4];
+    let mut shape = 0;
+
+    for &(field, first, last) in mode.layout {
+        let bits: Vec<u8> = if first <= last {
+            (first..=last).collect()
+        } else {
+            (last..=first).rev().collect()
+        };
+
+        for bit in bits {
+            let value = (reader.read(1) << bit) as i32;
+
+            if field == D {
+                shape |= value as usize;
+            } else {
+                endpoints[field as usize / 3][field as usize % 3] |= value;
+            }
+        }
+    }
+
+    let (endpoint_count, subsets, index_bits) = if mode.two_regions {
+        (4, 2, 3)
+    } else {
+        (2, 1, 4)
+    };
+
+    if signed {
+        for channel in &mut endpoints[0] {
+            *channel = sign_extend(*channel, mode.endpoint_bits);
+        }
+    }
+
+    if signed || mode.transformed {
+        for endpoint in &mut endpoints[1..endpoint_count] {
+            for (channel, bits) in endpoint.iter_mut().zip(mode.delta_bits) {
+                *channel = sign_extend(*channel, bits);
+            }
+        }
+    }
+
+    if mode.transformed {
+        let mask = (1 << mode.endpoint_bits) - 1;
+        let base = endpoints[0];
+
+        for endpoint in &mut endpoints[1..endpoint_count] {
+            for (channel, base) in endpoint.iter_mut().zip(base) {
+                *channel = (*channel + base) & mask;
+
+                if signed {
+                    *channel = sign_extend(*channel, mode.endpoint_bits);
+                }
+            }
+        }
+    }
+
+    let endpoints =
+        endpoints.map(|endpoint| endpoint.map(|c| unquantize_bc6h(c, mode.endpoint_bits, signed)));
+    let weights = get_weights(index_bits);
+
+    std::array::from_fn(|pixel| {
+        let index = reader.read(index_bits - is_anchor(subsets, shape, pixel) as u32);
+        let subset = get_subset(subsets, shape, pixel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let weight = weights[index as usize];
+
+        let [r, g, b] = [0, 1, 2].map(|channel| {
+            let value = interpolate(e0[channel], e1[channel], weight);
+            half_to_f32(finish_unquantize_bc6h(value, signed))
+        });
+
+        [r, g, b, 1.0]
+    })
+}
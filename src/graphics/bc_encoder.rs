@@ -0,0 +1,1264 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use super::{
+    bc_decoder::{
+        finish_unquantize_bc6h, get_subset, get_weights, interpolate, is_anchor, unquantize_bc6h,
+        unquantize_bc7, Bc6hMode, Bc7Mode, ANCHORS_2, ANCHORS_3, BC6H_MODES, BC6H_MODE_BITS,
+        BC7_MODES, D,
+    },
+    mipmap_generator::srgb_to_linear,
+};
+use crate::error::TextureError;
+
+// Native BC6H and BC7 encoders. Every block is fitted along the principal axis of its
+// pixels, the best partitions are picked from a cheap estimate and refined with least
+// squares. Quality presets limit how many modes, partitions and refinement passes are tried.
+
+const BLOCK_PIXELS: usize = 16;
+
+/// Largest finite unsigned half float, BC6H UF16 cannot store more
+const MAX_HALF: f32 = 31743.0;
+
+/// Speed and quality trade-off of the BC6H and BC7 encoders
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BcQuality {
+    UltraFast,
+    VeryFast,
+    #[default]
+    Fast,
+    Basic,
+    Slow,
+}
+
+/// How much the error of each color channel counts when fitting blocks
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ChannelWeighting {
+    /// Luminance weights, green matters most and blue least
+    #[default]
+    Perceptual,
+    /// Every channel counts the same, for normal maps and other non-color data
+    Uniform,
+}
+
+/// Options for the block compressors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressionOptions {
+    pub quality: BcQuality,
+    /// BC7 modes to search, bit n enables mode n. `None` uses the modes of the quality preset.
+    pub bc7_modes: Option<u8>,
+    /// Number of best scoring partitions that get fully encoded. `None` uses the quality preset.
+    pub max_partitions: Option<usize>,
+    /// Worker threads, 0 uses every available core
+    pub threads: usize,
+    pub weighting: ChannelWeighting,
+    /// BC1 pixels with a lower alpha (0.0 - 1.0) become transparent
+    pub alpha_threshold: f32,
+    /// Dither colors and alpha before BC1 - BC3 compression
+    pub dither: bool,
+    /// The pixels are sRGB encoded, so color errors are measured in linear light
+    pub srgb: bool,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self {
+            quality: BcQuality::default(),
+            bc7_modes: None,
+            max_partitions: None,
+            threads: 0,
+            weighting: ChannelWeighting::default(),
+            alpha_threshold: 0.5,
+            dither: false,
+            srgb: false,
+        }
+    }
+}
+
+impl fmt::Display for BcQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BcQuality::UltraFast => "Ultra Fast",
+            BcQuality::VeryFast => "Very Fast",
+            BcQuality::Fast => "Fast",
+            BcQuality::Basic => "Basic",
+            BcQuality::Slow => "Slow",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ChannelWeighting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChannelWeighting::Perceptual => "Perceptual",
+            ChannelWeighting::Uniform => "Uniform",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BcQuality {
+    type Err = String;
+
+    /// Parse a preset from its name (case insensitive), e.g. "ultrafast" or "Slow"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let quality = match s
+            .trim()
+            .to_lowercase()
+            .replace([' ', '-', '_'], "")
+            .as_str()
+        {
+            "ultrafast" => BcQuality::UltraFast,
+            "veryfast" => BcQuality::VeryFast,
+            "fast" => BcQuality::Fast,
+            "basic" => BcQuality::Basic,
+            "slow" => BcQuality::Slow,
+            _ => return Err(format!("Unknown compression quality: {}", s)),
+        };
+
+        Ok(quality)
+    }
+}
+
+/// Search limits of a quality preset
+struct SearchSettings {
+    /// BC7 modes for opaque blocks and blocks with alpha, bit n enables mode n
+    bc7_opaque_modes: u8,
+    bc7_alpha_modes: u8,
+    /// BC6H modes, bit n enables the mode at index n of `BC6H_MODES`
+    bc6h_modes: u16,
+    max_partitions: usize,
+    refine_iterations: u32,
+    /// Try every rotation and index selection of BC7 modes 4 and 5
+    all_rotations: bool,
+    metric: ErrorMetric,
+}
+
+/// Channel weights and color space of the fitting error
+pub(super) struct ErrorMetric {
+    weights: Color,
+    /// sRGB to linear light for every 8 bit value, scaled back to 0 - 255
+    linear: Option<[f32; 256]>,
+}
+
+impl ErrorMetric {
+    pub(super) fn new(options: &CompressionOptions) -> Self {
+        let weights = match options.weighting {
+            // Rec. 709 luminance like DirectXTex, scaled so the color weights still add up to 3
+            ChannelWeighting::Perceptual => [0.2125 * 3.0, 0.7154 * 3.0, 0.0721 * 3.0, 1.0],
+            ChannelWeighting::Uniform => [1.0; 4],
+        };
+
+        let linear = options
+            .srgb
+            .then(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0) * 255.0));
+
+        Self { weights, linear }
+    }
+
+    /// Weighted squared distance of two colors
+    pub(super) fn get_error(&self, a: &Color, b: &Color, channels: Range<usize>) -> f32 {
+        channels
+            .map(|c| {
+                let (a, b) = match &self.linear {
+                    Some(linear) if c < 3 => (to_linear(linear, a[c]), to_linear(linear, b[c])),
+                    _ => (a[c], b[c]),
+                };
+                (a - b) * (a - b) * self.weights[c]
+            })
+            .sum()
+    }
+}
+
+/// Look up a 0 - 255 value in the linear table, interpolating between entries
+fn to_linear(linear: &[f32; 256], value: f32) -> f32 {
+    let value = value.clamp(0.0, 255.0);
+    let index = (value as usize).min(254);
+    let fraction = value - index as f32;
+
+    linear[index] + (linear[index + 1] - linear[index]) * fraction
+}
+
+impl SearchSettings {
+    fn new(options: &CompressionOptions) -> Self {
+        let (bc7_opaque_modes, bc7_alpha_modes, bc6h_modes, max_partitions, refine_iterations) =
+            match options.quality {
+                BcQuality::UltraFast => (0x40, 0x40, 0x0400, 1, 0),
+                BcQuality::VeryFast => (0x42, 0x60, 0x3C00, 1, 1),
+                BcQuality::Fast => (0x4B, 0xF0, 0x3FFF, 2, 1),
+                BcQuality::Basic => (0x4F, 0xF0, 0x3FFF, 4, 2),
+                BcQuality::Slow => (0xFF, 0xF0, 0x3FFF, 16, 3),
+            };
+
+        Self {
+            bc7_opaque_modes: options.bc7_modes.unwrap_or(bc7_opaque_modes),
+            bc7_alpha_modes: options.bc7_modes.unwrap_or(bc7_alpha_modes),
+            // The 10 bit single region mode always fits, so there is always a result
+            bc6h_modes: bc6h_modes | 0x0400,
+            max_partitions: options.max_partitions.unwrap_or(max_partitions).max(1),
+            refine_iterations,
+            all_rotations: options.quality == BcQuality::Slow,
+            metric: ErrorMetric::new(options),
+        }
+    }
+}
+
+/// Compress RGBA8 pixels to BC7
+pub fn encode_bc7(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    options: &CompressionOptions,
+) -> Result<Vec<u8>, TextureError> {
+    let settings = SearchSettings::new(options);
+
+    encode_blocks(pixels, width, height, options.threads, |block| {
+        encode_bc7_block(&block.map(|pixel| pixel.map(|c| c as f32)), &settings)
+    })
+}
+
+/// Compress RGBA32F pixels to BC6H, `signed` picks signed BC6H. Alpha is ignored, negative values
+/// are clamped to zero.
+pub fn encode_bc6h(
+    pixels: &[f32],
+    width: u32,
+    height: u32,
+    signed: bool,
+    options: &CompressionOptions,
+) -> Result<Vec<u8>, TextureError> {
+    let settings = SearchSettings::new(options);
+
+    encode_blocks(pixels, width, height, options.threads, |block| {
+        // Blocks are fitted on the half float bit patterns, like the hardware interpolates them
+        let block = block.map(|[r, g, b, _]| [r, g, b].map(f32_to_half).map(|h| h as f32));
+        encode_bc6h_block(&block, signed, &settings)
+    })
+}
+
+/// Split the surface in rows of blocks and compress them on worker threads
+fn encode_blocks<T: Copy + Default + Send + Sync>(
+    pixels: &[T],
+    width: u32,
+    height: u32,
+    threads: usize,
+    encode_block: impl Fn([[T; 4]; BLOCK_PIXELS]) -> [u8; 16] + Sync,
+) -> Result<Vec<u8>, TextureError> {
+    let (width, height) = (width as usize, height as usize);
+
+    if pixels.len() < width * height * 4 {
+        return Err(TextureError::DimensionMismatch(format!(
+            "Image data too small for {}x{} RGBA pixels: {} < {}",
+            width,
+            height,
+            pixels.len(),
+            width * height * 4
+        )));
+    }
+
+    let width_in_blocks = width.div_ceil(4);
+    let height_in_blocks = height.div_ceil(4);
+    let row_size = width_in_blocks * 16;
+    let mut output = vec![0; row_size * height_in_blocks];
+
+    if output.is_empty() {
+        return Ok(output);
+    }
+
+    let encode_rows = |first_row: usize, rows: &mut [u8]| {
+        for (row_index, row) in rows.chunks_exact_mut(row_size).enumerate() {
+            let block_y = (first_row + row_index) * 4;
+
+            for (block_index, block) in row.chunks_exact_mut(16).enumerate() {
+                let block_x = block_index * 4;
+
+                // Partial blocks repeat the edge pixels so they do not skew the fit
+                let block_pixels = std::array::from_fn(|i| {
+                    let x = (block_x + i % 4).min(width - 1);
+                    let y = (block_y + i / 4).min(height - 1);
+                    let offset = (y * width + x) * 4;
+                    [0, 1, 2, 3].map(|c| pixels[offset + c])
+                });
+
+                block.copy_from_slice(&encode_block(block_pixels));
+            }
+        }
+    };
+
+    let threads = get_thread_count(threads).min(height_in_blocks);
+
+    if threads <= 1 {
+        encode_rows(0, &mut output);
+    } else {
+        let rows_per_thread = height_in_blocks.div_ceil(threads);
+
+        std::thread::scope(|scope| {
+            for (index, rows) in output.chunks_mut(rows_per_thread * row_size).enumerate() {
+                let encode_rows = &encode_rows;
+                scope.spawn(move || encode_rows(index * rows_per_thread, rows));
+            }
+        });
+    }
+
+    Ok(output)
+}
+
+pub(super) fn get_thread_count(threads: usize) -> usize {
+    // Threads cannot be spawned on the web
+    if cfg!(target_arch = "wasm32") {
+        1
+    } else if threads == 0 {
+        std::thread::available_parallelism().map_or(1, |count| count.get())
+    } else {
+        threads
+    }
+}
+
+/// Round a float to the nearest unsigned half float, as its bit pattern
+fn f32_to_half(value: f32) -> u16 {
+    // NaN and negative values become zero
+    if value.is_nan() || value <= 0.0 {
+        return 0;
+    }
+
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127 + 15;
+
+    if exponent >= 31 {
+        return MAX_HALF as u16;
+    }
+
+    let half = if exponent <= 0 {
+        // Subnormal, scale to units of 2^-24 and round half to even
+        (value * 16_777_216.0).round_ties_even() as u32
+    } else {
+        let mantissa = bits & 0x7F_FFFF;
+        let half = ((exponent as u32) << 10) | (mantissa >> 13);
+        let rest = mantissa & 0x1FFF;
+
+        // The carry into the exponent is the correct rounding
+        if rest > 0x1000 || (rest == 0x1000 && half & 1 == 1) {
+            half + 1
+        } else {
+            half
+        }
+    };
+
+    half.min(MAX_HALF as u32) as u16
+}
+
+/// Packs BC6H / BC7 fields, least significant bit first
+pub(super) struct BlockWriter {
+    bits: u128,
+    position: u32,
+}
+
+impl BlockWriter {
+    pub(super) fn new() -> Self {
+        Self {
+            bits: 0,
+            position: 0,
+        }
+    }
+
+    pub(super) fn write(&mut self, value: u32, count: u32) {
+        if count > 0 {
+            self.bits |= ((value & ((1u64 << count) - 1) as u32) as u128) << self.position;
+            self.position += count;
+        }
+    }
+
+    pub(super) fn into_bytes(self) -> [u8; 16] {
+        self.bits.to_le_bytes()
+    }
+}
+
+pub(super) type Color = [f32; 4];
+
+/// Mean and principal axis of the points, from power iterations on their covariance
+fn fit_line(points: &[Color], channels: Range<usize>) -> (Color, Color) {
+    let count = points.len().max(1) as f32;
+    let mut mean = [0.0; 4];
+
+    for point in points {
+        for c in channels.clone() {
+            mean[c] += point[c] / count;
+        }
+    }
+
+    let mut covariance = [[0.0f32; 4]; 4];
+
+    for point in points {
+        for i in channels.clone() {
+            for j in channels.clone() {
+                covariance[i][j] += (point[i] - mean[i]) * (point[j] - mean[j]);
+            }
+        }
+    }
+
+    // Start from the row of the channel with the most variance. The diagonal itself would be
+    // orthogonal to the axis of channels that go in opposite directions.
+    let dominant = channels
+        .clone()
+        .max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b]))
+        .unwrap_or(0);
+    let mut axis = covariance[dominant];
+
+    for _ in 0..8 {
+        let mut next = [0.0; 4];
+
+        for i in channels.clone() {
+            for j in channels.clone() {
+                next[i] += covariance[i][j] * axis[j];
+            }
+        }
+
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-12 {
+            break;
+        }
+
+        axis = next.map(|v| v / length);
+    }
+
+    (mean, axis)
+}
+
+/// Squared distance of the points to their best fitting line
+fn get_line_error(points: &[Color], channels: Range<usize>) -> f32 {
+    let (mean, axis) = fit_line(points, channels.clone());
+
+    points
+        .iter()
+        .map(|point| {
+            let mut distance = 0.0;
+            let mut projection = 0.0;
+
+            for c in channels.clone() {
+                let offset = point[c] - mean[c];
+                distance += offset * offset;
+                projection += offset * axis[c];
+            }
+
+            distance - projection * projection
+        })
+        .sum()
+}
+
+/// Endpoints spanning the projection of the points on their principal axis
+pub(super) fn get_line_endpoints(points: &[Color], channels: Range<usize>, max: f32) -> [Color; 2] {
+    let (mean, axis) = fit_line(points, channels.clone());
+    let project = |point: &Color| {
+        channels
+            .clone()
+            .map(|c| (point[c] - mean[c]) * axis[c])
+            .sum::<f32>()
+    };
+
+    let (min_t, max_t) = points
+        .iter()
+        .map(project)
+        .fold((f32::MAX, f32::MIN), |(min_t, max_t), t| {
+            (min_t.min(t), max_t.max(t))
+        });
+
+    [min_t, max_t].map(|t| {
+        let mut endpoint = [0.0; 4];
+        for c in channels.clone() {
+            endpoint[c] = (mean[c] + axis[c] * t).clamp(0.0, max);
+        }
+        endpoint
+    })
+}
+
+/// Least squares endpoints for the points and their palette weights (0 - 64)
+pub(super) fn get_refined_endpoints(
+    points: &[Color],
+    weights: &[i32],
+    channels: Range<usize>,
+    max: f32,
+) -> Option<[Color; 2]> {
+    let (mut aa, mut ab, mut bb) = (0.0, 0.0, 0.0);
+    let (mut ax, mut bx) = ([0.0f32; 4], [0.0f32; 4]);
+
+    for (point, &weight) in points.iter().zip(weights) {
+        let b = weight as f32 / 64.0;
+        let a = 1.0 - b;
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+
+        for c in channels.clone() {
+            ax[c] += a * point[c];
+            bx[c] += b * point[c];
+        }
+    }
+
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+
+    let mut endpoints = [[0.0; 4]; 2];
+    for c in channels {
+        endpoints[0][c] = ((bb * ax[c] - ab * bx[c]) / determinant).clamp(0.0, max);
+        endpoints[1][c] = ((aa * bx[c] - ab * ax[c]) / determinant).clamp(0.0, max);
+    }
+
+    Some(endpoints)
+}
+
+/// Partitions of a block ordered by how well every subset fits a line, best first
+fn rank_partitions(
+    pixels: &[Color; BLOCK_PIXELS],
+    subsets: usize,
+    shapes: usize,
+    channels: Range<usize>,
+    limit: usize,
+) -> Vec<usize> {
+    let mut ranked: Vec<(f32, usize)> = (0..shapes)
+        .map(|shape| {
+            let error = (0..subsets)
+                .map(|subset| {
+                    let points = get_subset_points(pixels, subsets, shape, subset);
+                    get_line_error(&points, channels.clone())
+                })
+                .sum();
+            (error, shape)
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, shape)| shape)
+        .collect()
+}
+
+fn get_subset_points(
+    pixels: &[Color; BLOCK_PIXELS],
+    subsets: usize,
+    shape: usize,
+    subset: usize,
+) -> Vec<Color> {
+    (0..BLOCK_PIXELS)
+        .filter(|&pixel| get_subset(subsets, shape, pixel) == subset)
+        .map(|pixel| pixels[pixel])
+        .collect()
+}
+
+/// Pixel whose index is stored with one bit less
+fn get_anchor(subsets: usize, shape: usize, subset: usize) -> usize {
+    match (subsets, subset) {
+        (_, 0) => 0,
+        (2, _) => ANCHORS_2[shape] as usize,
+        _ => ANCHORS_3[shape][subset - 1] as usize,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PBits {
+    None,
+    /// Every endpoint has its own p-bit
+    Unique,
+    /// Both endpoints of a subset share a p-bit
+    Shared,
+}
+
+/// Layout of one BC7 index set and its endpoints
+struct SubsetFormat {
+    channels: Range<usize>,
+    /// Endpoint bits without the p-bit
+    bits: u32,
+    p_bits: PBits,
+    index_bits: u32,
+}
+
+#[derive(Clone)]
+struct SubsetEncoding {
+    /// Quantized endpoints without their p-bits
+    stored: [[u32; 4]; 2],
+    p_bits: [u32; 2],
+    endpoints: [[i32; 4]; 2],
+    /// Palette index of every point of the subset
+    indices: Vec<u32>,
+    error: f32,
+}
+
+impl SubsetEncoding {
+    /// Make sure the anchor index fits in one bit less by swapping the endpoints
+    fn fix_anchor(&mut self, anchor: usize, index_bits: u32) {
+        if self.indices[anchor] >> (index_bits - 1) != 0 {
+            let max = (1 << index_bits) - 1;
+            self.stored.swap(0, 1);
+            self.p_bits.swap(0, 1);
+            self.endpoints.swap(0, 1);
+            self.indices
+                .iter_mut()
+                .for_each(|index| *index = max - *index);
+        }
+    }
+}
+
+/// Closest BC7 channel value with the given p-bit, returns the stored and the unquantized values
+fn quantize_bc7_channel(value: f32, bits: u32, p_bit: Option<u32>) -> (u32, i32) {
+    let max = (1 << bits) - 1;
+    let total_bits = bits + p_bit.is_some() as u32;
+    let scale = ((1 << total_bits) - 1) as f32 / 255.0;
+    let estimate = match p_bit {
+        Some(p_bit) => ((value * scale - p_bit as f32) / 2.0).round() as i32,
+        None => (value * scale).round() as i32,
+    };
+
+    (estimate - 1..=estimate + 1)
+        .map(|stored| stored.clamp(0, max) as u32)
+        .map(|stored| {
+            let full = match p_bit {
+                Some(p_bit) => (stored << 1) | p_bit,
+                None => stored,
+            };
+            (stored, unquantize_bc7(full, total_bits))
+        })
+        .min_by(|a, b| {
+            (a.1 as f32 - value)
+                .abs()
+                .total_cmp(&(b.1 as f32 - value).abs())
+        })
+        .unwrap_or((0, 0))
+}
+
+fn quantize_bc7_endpoint(
+    endpoint: &Color,
+    format: &SubsetFormat,
+    p_bit: Option<u32>,
+    metric: &ErrorMetric,
+) -> ([u32; 4], [i32; 4], f32) {
+    let mut stored = [0; 4];
+    let mut unquantized = [255; 4];
+
+    for c in format.channels.clone() {
+        (stored[c], unquantized[c]) = quantize_bc7_channel(endpoint[c], format.bits, p_bit);
+    }
+
+    let error = metric.get_error(
+        endpoint,
+        &unquantized.map(|c| c as f32),
+        format.channels.clone(),
+    );
+
+    (stored, unquantized, error)
+}
+
+fn quantize_bc7_endpoints(
+    endpoints: &[Color; 2],
+    format: &SubsetFormat,
+    metric: &ErrorMetric,
+) -> ([[u32; 4]; 2], [u32; 2], [[i32; 4]; 2]) {
+    match format.p_bits {
+        PBits::None => {
+            let (stored0, unquantized0, _) =
+                quantize_bc7_endpoint(&endpoints[0], format, None, metric);
+            let (stored1, unquantized1, _) =
+                quantize_bc7_endpoint(&endpoints[1], format, None, metric);
+            ([stored0, stored1], [0, 0], [unquantized0, unquantized1])
+        }
+
+        PBits::Unique => {
+            let [(stored0, unquantized0, p0), (stored1, unquantized1, p1)] =
+                endpoints.each_ref().map(|endpoint| {
+                    (0..2)
+                        .map(|p_bit| {
+                            let (stored, unquantized, error) =
+                                quantize_bc7_endpoint(endpoint, format, Some(p_bit), metric);
+                            (stored, unquantized, p_bit, error)
+                        })
+                        .min_by(|a, b| a.3.total_cmp(&b.3))
+                        .map(|(stored, unquantized, p_bit, _)| (stored, unquantized, p_bit))
+                        .unwrap_or_default()
+                });
+
+            ([stored0, stored1], [p0, p1], [unquantized0, unquantized1])
+        }
+
+        PBits::Shared => (0..2)
+            .map(|p_bit| {
+                let (stored0, unquantized0, error0) =
+                    quantize_bc7_endpoint(&endpoints[0], format, Some(p_bit), metric);
+                let (stored1, unquantized1, error1) =
+                    quantize_bc7_endpoint(&endpoints[1], format, Some(p_bit), metric);
+                (
+                    [stored0, stored1],
+                    [p_bit, p_bit],
+                    [unquantized0, unquantized1],
+                    error0 + error1,
+                )
+            })
+            .min_by(|a, b| a.3.total_cmp(&b.3))
+            .map(|(stored, p_bits, unquantized, _)| (stored, p_bits, unquantized))
+            .unwrap_or_default(),
+    }
+}
+
+/// Fit, quantize and index one BC7 subset, then refine the endpoints with least squares
+fn encode_bc7_subset(
+    points: &[Color],
+    format: &SubsetFormat,
+    settings: &SearchSettings,
+) -> SubsetEncoding {
+    let weights = get_weights(format.index_bits);
+    let mut endpoints = get_line_endpoints(points, format.channels.clone(), 255.0);
+    let mut best: Option<SubsetEncoding> = None;
+
+    for iteration in 0..=settings.refine_iterations {
+        let (stored, p_bits, quantized) =
+            quantize_bc7_endpoints(&endpoints, format, &settings.metric);
+
+        let palette: Vec<Color> = weights
+            .iter()
+            .map(|&weight| {
+                let mut color = [255.0; 4];
+                for c in format.channels.clone() {
+                    color[c] = interpolate(quantized[0][c], quantized[1][c], weight) as f32;
+                }
+                color
+            })
+            .collect();
+
+        let mut error = 0.0;
+        let indices: Vec<u32> = points
+            .iter()
+            .map(|point| {
+                let (index, index_error) = palette
+                    .iter()
+                    .map(|color| {
+                        settings
+                            .metric
+                            .get_error(point, color, format.channels.clone())
+                    })
+                    .enumerate()
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap_or((0, 0.0));
+                error += index_error;
+                index as u32
+            })
+            .collect();
+
+        let is_better = best.as_ref().map_or(true, |best| error < best.error);
+        let point_weights: Vec<i32> = indices.iter().map(|&i| weights[i as usize]).collect();
+
+        if is_better {
+            best = Some(SubsetEncoding {
+                stored,
+                p_bits,
+                endpoints: quantized,
+                indices,
+                error,
+            });
+        }
+
+        if error == 0.0 || iteration == settings.refine_iterations {
+            break;
+        }
+
+        match get_refined_endpoints(points, &point_weights, format.channels.clone(), 255.0) {
+            Some(refined) => endpoints = refined,
+            None => break,
+        }
+    }
+
+    best.unwrap_or_else(|| SubsetEncoding {
+        stored: [[0; 4]; 2],
+        p_bits: [0; 2],
+        endpoints: [[0; 4]; 2],
+        indices: vec![0; points.len()],
+        error: f32::MAX,
+    })
+}
+
+fn encode_bc7_block(pixels: &[Color; BLOCK_PIXELS], settings: &SearchSettings) -> [u8; 16] {
+    let is_opaque = pixels.iter().all(|pixel| pixel[3] == 255.0);
+    let modes = if is_opaque {
+        settings.bc7_opaque_modes
+    } else {
+        settings.bc7_alpha_modes
+    };
+
+    let mut best: Option<(f32, [u8; 16])> = None;
+
+    for (index, mode) in BC7_MODES.iter().enumerate() {
+        if modes & (1 << index) == 0 {
+            continue;
+        }
+
+        let candidate = if mode.rotation_bits > 0 {
+            encode_bc7_separate_alpha(pixels, index, mode, settings)
+        } else {
+            encode_bc7_joint(pixels, index, mode, settings)
+        };
+
+        if best.map_or(true, |(error, _)| candidate.0 < error) {
+            best = Some(candidate);
+        }
+
+        if candidate.0 == 0.0 {
+            break;
+        }
+    }
+
+    match best {
+        Some((_, block)) => block,
+        None => encode_bc7_joint(pixels, 6, &BC7_MODES[6], settings).1,
+    }
+}
+
+/// Modes 0 - 3, 6 and 7, one index per pixel for the color and alpha
+fn encode_bc7_joint(
+    pixels: &[Color; BLOCK_PIXELS],
+    mode_index: usize,
+    mode: &Bc7Mode,
+    settings: &SearchSettings,
+) -> (f32, [u8; 16]) {
+    let channels = if mode.alpha_bits > 0 { 0..4 } else { 0..3 };
+    let endpoint_count = mode.subsets * 2;
+
+    let format = SubsetFormat {
+        channels: channels.clone(),
+        bits: mode.color_bits,
+        p_bits: match mode.p_bits {
+            0 => PBits::None,
+            p_bits if p_bits == endpoint_count => PBits::Unique,
+            _ => PBits::Shared,
+        },
+        index_bits: mode.index_bits,
+    };
+
+    // Modes without alpha decode it as 255
+    let alpha_error: f32 = if mode.alpha_bits == 0 {
+        pixels.iter().map(|pixel| (255.0 - pixel[3]).powi(2)).sum()
+    } else {
+        0.0
+    };
+
+    let shapes = if mode.subsets > 1 {
+        rank_partitions(
+            pixels,
+            mode.subsets,
+            1 << mode.partition_bits,
+            channels,
+            settings.max_partitions,
+        )
+    } else {
+        vec![0]
+    };
+
+    let mut best: Option<(f32, [u8; 16])> = None;
+
+    for shape in shapes {
+        let mut encodings: Vec<SubsetEncoding> = (0..mode.subsets)
+            .map(|subset| {
+                let points = get_subset_points(pixels, mode.subsets, shape, subset);
+                encode_bc7_subset(&points, &format, settings)
+            })
+            .collect();
+
+        let error = alpha_error + encodings.iter().map(|e| e.error).sum::<f32>();
+        if best.is_some_and(|(best_error, _)| best_error <= error) {
+            continue;
+        }
+
+        // Index of every pixel, and its anchor position within the subset
+        let mut positions = [0; BLOCK_PIXELS];
+        let mut counts = [0; 3];
+        for (pixel, position) in positions.iter_mut().enumerate() {
+            let subset = get_subset(mode.subsets, shape, pixel);
+            *position = counts[subset];
+            counts[subset] += 1;
+        }
+
+        for (subset, encoding) in encodings.iter_mut().enumerate() {
+            let anchor = positions[get_anchor(mode.subsets, shape, subset)];
+            encoding.fix_anchor(anchor, mode.index_bits);
+        }
+
+        let mut writer = BlockWriter::new();
+        writer.write(1 << mode_index, mode_index as u32 + 1);
+        writer.write(shape as u32, mode.partition_bits);
+
+        for c in 0..4 {
+            let bits = if c < 3 {
+                mode.color_bits
+            } else {
+                mode.alpha_bits
+            };
+
+            for encoding in &encodings {
+                writer.write(encoding.stored[0][c], bits);
+                writer.write(encoding.stored[1][c], bits);
+            }
+        }
+
+        for p_bit in 0..mode.p_bits {
+            let endpoint = p_bit * endpoint_count / mode.p_bits;
+            writer.write(encodings[endpoint / 2].p_bits[endpoint % 2], 1);
+        }
+
+        for (pixel, &position) in positions.iter().enumerate() {
+            let subset = get_subset(mode.subsets, shape, pixel);
+            let index = encodings[subset].indices[position];
+            let anchor = is_anchor(mode.subsets, shape, pixel) as u32;
+            writer.write(index, mode.index_bits - anchor);
+        }
+
+        best = Some((error, writer.into_bytes()));
+    }
+
+    best.unwrap_or((f32::MAX, [0; 16]))
+}
+
+/// Modes 4 and 5, separate color and alpha indices with a channel rotation
+fn encode_bc7_separate_alpha(
+    pixels: &[Color; BLOCK_PIXELS],
+    mode_index: usize,
+    mode: &Bc7Mode,
+    settings: &SearchSettings,
+) -> (f32, [u8; 16]) {
+    let rotations = if settings.all_rotations { 0..4 } else { 0..1 };
+    let index_modes = if settings.all_rotations && mode.index_mode_bits > 0 {
+        0..2
+    } else {
+        0..1
+    };
+
+    let mut best: Option<(f32, [u8; 16])> = None;
+
+    for rotation in rotations {
+        let rotated = pixels.map(|mut pixel| {
+            if rotation > 0 {
+                pixel.swap(rotation - 1, 3);
+            }
+            pixel
+        });
+
+        for index_mode in index_modes.clone() {
+            let (color_index_bits, alpha_index_bits) = if index_mode == 0 {
+                (mode.index_bits, mode.index_bits_2)
+            } else {
+                (mode.index_bits_2, mode.index_bits)
+            };
+
+            let color_format = SubsetFormat {
+                channels: 0..3,
+                bits: mode.color_bits,
+                p_bits: PBits::None,
+                index_bits: color_index_bits,
+            };
+            let alpha_format = SubsetFormat {
+                channels: 3..4,
+                bits: mode.alpha_bits,
+                p_bits: PBits::None,
+                index_bits: alpha_index_bits,
+            };
+
+            let mut color = encode_bc7_subset(&rotated, &color_format, settings);
+            let mut alpha = encode_bc7_subset(&rotated, &alpha_format, settings);
+            color.fix_anchor(0, color_index_bits);
+            alpha.fix_anchor(0, alpha_index_bits);
+
+            let error = color.error + alpha.error;
+            if best.is_some_and(|(best_error, _)| best_error <= error) {
+                continue;
+            }
+
+            let mut writer = BlockWriter::new();
+            writer.write(1 << mode_index, mode_index as u32 + 1);
+            writer.write(rotation as u32, mode.rotation_bits);
+            writer.write(index_mode, mode.index_mode_bits);
+
+            for c in 0..3 {
+                writer.write(color.stored[0][c], mode.color_bits);
+                writer.write(color.stored[1][c], mode.color_bits);
+            }
+            writer.write(alpha.stored[0][3], mode.alpha_bits);
+            writer.write(alpha.stored[1][3], mode.alpha_bits);
+
+            // The first index set is the one with fewer bits
+            let (first, second) = if index_mode == 0 {
+                (&color, &alpha)
+            } else {
+                (&alpha, &color)
+            };
+
+            for (pixel, &index) in first.indices.iter().enumerate() {
+                writer.write(index, mode.index_bits - (pixel == 0) as u32);
+            }
+            for (pixel, &index) in second.indices.iter().enumerate() {
+                writer.write(index, mode.index_bits_2 - (pixel == 0) as u32);
+            }
+
+            best = Some((error, writer.into_bytes()));
+        }
+    }
+
+    best.unwrap_or((f32::MAX, [0; 16]))
+}
+
+/// Largest endpoint of a mode, signed endpoints keep their top bit for the sign
+fn get_bc6h_endpoint_max(bits: u32, signed: bool) -> i32 {
+    let value_bits = if signed { bits - 1 } else { bits };
+    (1 << value_bits) - 1
+}
+
+/// Closest BC6H endpoint for a positive half float value
+fn quantize_bc6h_channel(value: f32, bits: u32, signed: bool) -> i32 {
+    let max = get_bc6h_endpoint_max(bits, signed);
+    let estimate = (value * (max + 1) as f32 / 31744.0) as i32;
+
+    (estimate - 1..=estimate + 1)
+        .map(|quantized| quantized.clamp(0, max))
+        .min_by_key(|&quantized| {
+            let unquantized = unquantize_bc6h(quantized, bits, signed);
+            (finish_unquantize_bc6h(unquantized, signed) as i32 - value as i32).abs()
+        })
+        .unwrap_or(0)
+}
+
+struct Bc6hEncoding {
+    /// Quantized endpoints in region order: A0, B0, A1, B1
+    quantized: [[i32; 3]; 4],
+    indices: [u32; BLOCK_PIXELS],
+    error: f32,
+}
+
+/// Quantize the endpoints of a mode, transformed modes store deltas from the first endpoint
+fn quantize_bc6h_endpoints(
+    endpoints: &[[Color; 2]],
+    mode: &Bc6hMode,
+    signed: bool,
+) -> [[i32; 3]; 4] {
+    let mut quantized = [[0; 3]; 4];
+
+    for (region, pair) in endpoints.iter().enumerate() {
+        for (endpoint, color) in pair.iter().enumerate() {
+            quantized[region * 2 + endpoint] = std::array::from_fn(|c| {
+                quantize_bc6h_channel(color[c], mode.endpoint_bits, signed)
+            });
+        }
+    }
+
+    if mode.transformed {
+        let base = quantized[0];
+        let max = get_bc6h_endpoint_max(mode.endpoint_bits, signed);
+
+        for endpoint in &mut quantized[1..] {
+            for c in 0..3 {
+                let range = 1 << (mode.delta_bits[c] - 1);
+                let delta = (endpoint[c] - base[c]).clamp(-range, range - 1);
+                endpoint[c] = (base[c] + delta).clamp(0, max);
+            }
+        }
+    }
+
+    quantized
+}
+
+fn get_bc6h_palette(
+    quantized: &[[i32; 3]; 4],
+    region: usize,
+    mode: &Bc6hMode,
+    signed: bool,
+) -> Vec<Color> {
+    let index_bits = if mode.two_regions { 3 } else { 4 };
+    let [e0, e1] = [quantized[region * 2], quantized[region * 2 + 1]]
+        .map(|endpoint| endpoint.map(|c| unquantize_bc6h(c, mode.endpoint_bits, signed)));
+
+    get_weights(index_bits)
+        .iter()
+        .map(|&weight| {
+            let mut color = [0.0; 4];
+            for c in 0..3 {
+                let value = interpolate(e0[c], e1[c], weight);
+                color[c] = finish_unquantize_bc6h(value, signed) as f32;
+            }
+            color
+        })
+        .collect()
+}
+
+fn encode_bc6h_block(
+    pixels: &[[f32; 3]; BLOCK_PIXELS],
+    signed: bool,
+    settings: &SearchSettings,
+) -> [u8; 16] {
+    let pixels: [Color; BLOCK_PIXELS] = pixels.map(|[r, g, b]| [r, g, b, 0.0]);
+    let mut best: Option<(f32, [u8; 16])> = None;
+
+    // Single region modes first, they are cheaper and often good enough
+    for index in (10..14).chain(0..10) {
+        if settings.bc6h_modes & (1 << index) == 0 {
+            continue;
+        }
+
+        let mode = &BC6H_MODES[index];
+        let shapes = if mode.two_regions {
+            rank_partitions(&pixels, 2, 32, 0..3, settings.max_partitions)
+        } else {
+            vec![0]
+        };
+
+        for shape in shapes {
+            let Some(candidate) = encode_bc6h_mode(&pixels, index, mode, shape, signed, settings)
+            else {
+                continue;
+            };
+
+            if best.map_or(true, |(error, _)| candidate.0 < error) {
+                best = Some(candidate);
+            }
+        }
+
+        if best.is_some_and(|(error, _)| error == 0.0) {
+            break;
+        }
+    }
+
+    best.map(|(_, block)| block).unwrap_or([0; 16])
+}
+
+fn encode_bc6h_mode(
+    pixels: &[Color; BLOCK_PIXELS],
+    mode_index: usize,
+    mode: &Bc6hMode,
+    shape: usize,
+    signed: bool,
+    settings: &SearchSettings,
+) -> Option<(f32, [u8; 16])> {
+    let (subsets, index_bits) = if mode.two_regions { (2, 3) } else { (1, 4) };
+    let weights = get_weights(index_bits);
+
+    let points: Vec<Vec<Color>> = (0..subsets)
+        .map(|subset| get_subset_points(pixels, subsets, shape, subset))
+        .collect();
+    let mut endpoints: Vec<[Color; 2]> = points
+        .iter()
+        .map(|points| get_line_endpoints(points, 0..3, MAX_HALF))
+        .collect();
+
+    let mut best: Option<Bc6hEncoding> = None;
+
+    for iteration in 0..=settings.refine_iterations {
+        let quantized = quantize_bc6h_endpoints(&endpoints, mode, signed);
+        let palettes: Vec<Vec<Color>> = (0..subsets)
+            .map(|region| get_bc6h_palette(&quantized, region, mode, signed))
+            .collect();
+
+        let mut error = 0.0;
+        let indices: [u32; BLOCK_PIXELS] = std::array::from_fn(|pixel| {
+            let palette = &palettes[get_subset(subsets, shape, pixel)];
+            let (index, index_error) = palette
+                .iter()
+                .map(|color| settings.metric.get_error(&pixels[pixel], color, 0..3))
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap_or((0, 0.0));
+            error += index_error;
+            index as u32
+        });
+
+        if best.as_ref().map_or(true, |best| error < best.error) {
+            best = Some(Bc6hEncoding {
+                quantized,
+                indices,
+                error,
+            });
+        }
+
+        if error == 0.0 || iteration == settings.refine_iterations {
+            break;
+        }
+
+        for (subset, endpoints) in endpoints.iter_mut().enumerate() {
+            let point_weights: Vec<i32> = (0..BLOCK_PIXELS)
+                .filter(|&pixel| get_subset(subsets, shape, pixel) == subset)
+                .map(|pixel| weights[indices[pixel] as usize])
+                .collect();
+
+            if let Some(refined) =
+                get_refined_endpoints(&points[subset], &point_weights, 0..3, MAX_HALF)
+            {
+                *endpoints = refined;
+            }
+        }
+    }
+
+    let mut encoding = best?;
+
+    // Anchor indices are stored with one bit less, swap the endpoints when they do not fit
+    for subset in 0..subsets {
+        let anchor = get_anchor(subsets, shape, subset);
+
+        if encoding.indices[anchor] >> (index_bits - 1) != 0 {
+            let max = (1 << index_bits) - 1;
+            encoding.quantized.swap(subset * 2, subset * 2 + 1);
+
+            for pixel in 0..BLOCK_PIXELS {
+                if get_subset(subsets, shape, pixel) == subset {
+                    encoding.indices[pixel] = max - encoding.indices[pixel];
+                }
+            }
+        }
+    }
+
+    // Stored values, the endpoints after the first one are deltas in transformed modes
+    let endpoint_count = subsets * 2;
+    let mut stored = [[0u32; 3]; 4];
+
+    for (endpoint, stored) in stored.iter_mut().enumerate().take(endpoint_count) {
+        for (c, stored) in stored.iter_mut().enumerate() {
+            let value = encoding.quantized[endpoint][c];
+
+            *stored = if endpoint > 0 && mode.transformed {
+                let delta = value - encoding.quantized[0][c];
+                let range = 1 << (mode.delta_bits[c] - 1);
+
+                // Swapping the first region can push the deltas out of range
+                if !(-range..range).contains(&delta) {
+                    return None;
+                }
+
+                (delta & ((range << 1) - 1)) as u32
+            } else {
+                value as u32
+            };
+        }
+    }
+
+    let mode_bits = BC6H_MODE_BITS[mode_index];
+    let mut writer = BlockWriter::new();
+    writer.write(mode_bits, if mode_bits < 2 { 2 } else { 5 });
+
+    for &(field, first, last) in mode.layout {
+        let value = if field == D {
+            shape as u32
+        } else {
+            stored[field as usize / 3][field as usize % 3]
+        };
+
+        let bits: Vec<u8> = if first <= last {
+            (first..=last).collect()
+        } else {
+            (last..=first).rev().collect()
+        };
+
+        for bit in bits {
+            writer.write(value >> bit, 1);
+        }
+    }
+
+    for (pixel, &index) in encoding.indices.iter().enumerate() {
+        writer.write(index, index_bits - is_anchor(subsets, shape, pixel) as u32);
+    }
+
+    Some((encoding.error, writer.into_bytes()))
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+/// Reduces RGBA8 images to a palette for the indexed formats
+pub struct ColorQuantizer;
+
+/// Unique colors of a median cut box, with their pixel counts
+struct ColorBox {
+    colors: Vec<([u8; 4], u32)>,
+}
+
+impl ColorQuantizer {
+    /// Build a palette of at most `max_colors` colors and the palette index of every pixel.
+    /// Images with few enough unique colors are kept lossless, the others use median cut.
+    pub fn quantize(rgba8_pixels: &[u8], max_colors: usize) -> (Vec<[u8; 4]>, Vec<u16>) {
+        let mut histogram = BTreeMap::new();
+
+        for pixel in rgba8_pixels.chunks_exact(4) {
+            *histogram
+                .entry([pixel[0], pixel[1], pixel[2], pixel[3]])
+                .or_insert(0u32) += 1;
+        }
+
+        let mut boxes = vec![ColorBox {
+            colors: histogram.into_iter().collect(),
+        }];
+
+        while boxes.len() < max_colors.max(1) {
+            // Split the box with the widest channel range
+            let Some((index, channel, _)) = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, color_box)| color_box.colors.len() > 1)
+                .map(|(index, color_box)| {
+                    let (channel, range) = color_box.get_widest_channel();
+                    (index, channel, range)
+                })
+                .max_by_key(|&(_, _, range)| range)
+            else {
+                break;
+            };
+
+            let color_box = boxes.swap_remove(index);
+            let (first, second) = color_box.split(channel);
+            boxes.push(first);
+            boxes.push(second);
+        }
+
+        let mut palette = Vec::with_capacity(boxes.len());
+        let mut lookup = BTreeMap::new();
+
+        for (index, color_box) in boxes.iter().enumerate() {
+            palette.push(color_box.get_average());
+
+            for (color, _) in &color_box.colors {
+                lookup.insert(*color, index as u16);
+            }
+        }
+
+        let indices = rgba8_pixels
+            .chunks_exact(4)
+            .map(|pixel| lookup[&[pixel[0], pixel[1], pixel[2], pixel[3]]])
+            .collect();
+
+        (palette, indices)
+    }
+}
+
+impl ColorBox {
+    fn get_widest_channel(&self) -> (usize, u8) {
+        (0..4)
+            .map(|channel| {
+                let values = self.colors.iter().map(|(color, _)| color[channel]);
+                let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+                (channel, range)
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap_or((0, 0))
+    }
+
+    /// Split at the weighted median of a channel, both halves keep at least one color
+    fn split(mut self, channel: usize) -> (ColorBox, ColorBox) {
+        self.colors.sort_by_key(|(color, _)| color[channel]);
+
+        let total: u64 = self.colors.iter().map(|&(_, count)| count as u64).sum();
+        let mut sum = 0;
+        let mut median = self.colors.len() - 1;
+
+        for (index, &(_, count)) in self.colors.iter().enumerate() {
+            sum += count as u64;
+
+            if sum * 2 >= total {
+                median = index + 1;
+                break;
+            }
+        }
+
+        let median = median.clamp(1, self.colors.len() - 1);
+        let second = self.colors.split_off(median);
+
+        (self, ColorBox { colors: second })
+    }
+
+    fn get_average(&self) -> [u8; 4] {
+        let mut sum = [0u64; 4];
+        let mut total = 0u64;
+
+        for &(color, count) in &self.colors {
+            for (channel, value) in sum.iter_mut().zip(color) {
+                *channel += value as u64 * count as u64;
+            }
+            total += count as u64;
+        }
+
+        sum.map(|channel| ((channel + total / 2) / total.max(1)) as u8)
+    }
+}
@@ -1,12 +1,15 @@
 use directxtex::{
-    Image, CP_FLAGS, DXGI_FORMAT, DXGI_FORMAT_R8G8B8A8_UNORM, TEX_COMPRESS_DEFAULT,
+    Image, ScratchImage, CP_FLAGS, DXGI_FORMAT, DXGI_FORMAT_R8G8B8A8_UNORM, TEX_COMPRESS_DEFAULT,
+    TEX_COMPRESS_DITHER, TEX_COMPRESS_FLAGS, TEX_COMPRESS_SRGB, TEX_COMPRESS_UNIFORM,
     TEX_FILTER_DEFAULT,
 };
 
 use super::{
+    bc_encoder::{ChannelWeighting, CompressionOptions},
     pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
     texture::TexDimension,
 };
+use crate::error::TextureError;
 
 pub struct DirectXTexUtility;
 
@@ -45,6 +48,7 @@ impl DirectXTexUtility {
             directxtex::DXGI_FORMAT_BC4_UNORM => PixelFormat::BC4,
             directxtex::DXGI_FORMAT_BC5_UNORM => PixelFormat::BC5,
             directxtex::DXGI_FORMAT_BC6H_UF16 => PixelFormat::BC6H,
+            directxtex::DXGI_FORMAT_BC6H_SF16 => PixelFormat::BC6H,
             directxtex::DXGI_FORMAT_BC7_UNORM => PixelFormat::BC7,
             directxtex::DXGI_FORMAT_BC7_UNORM_SRGB => PixelFormat::BC7,
             _ => PixelFormat::Unknown,
@@ -92,10 +96,30 @@ impl DirectXTexUtility {
         }
     }
 
+    /// Same as `get_dxgi_format_from_pixel_format`, but picks the sRGB or signed BC6H variant
+    /// when there is one
+    pub fn get_dxgi_format_from_pixel_format_info(
+        pixel_format_info: PixelFormatInfo,
+    ) -> DXGI_FORMAT {
+        if pixel_format_info.pixel_format == PixelFormat::BC6H
+            && pixel_format_info.pixel_data_type == PixelDataType::SFloat
+        {
+            return DXGI_FORMAT::DXGI_FORMAT_BC6H_SF16;
+        }
+
+        let format = Self::get_dxgi_format_from_pixel_format(pixel_format_info.pixel_format);
+
+        match pixel_format_info.color_space {
+            ColorSpace::SRGB => format.make_srgb(),
+            ColorSpace::Linear => format,
+        }
+    }
+
     pub fn get_pixel_format_datatype_from_image(
         dxgi_format: directxtex::DXGI_FORMAT,
     ) -> Option<PixelDataType> {
         match dxgi_format.format_data_type() {
+            _ if dxgi_format == directxtex::DXGI_FORMAT_BC6H_SF16 => Some(PixelDataType::SFloat),
             directxtex::FORMAT_TYPE_UNORM => Some(PixelDataType::UNorm),
             directxtex::FORMAT_TYPE_SNORM => Some(PixelDataType::SNorm),
             directxtex::FORMAT_TYPE_UINT => Some(PixelDataType::UInt),
@@ -105,10 +129,18 @@ impl DirectXTexUtility {
         }
     }
 
-    pub fn get_pixel_format_info_from_image(metadata: &directxtex::TexMetadata) -> PixelFormatInfo {
+    pub fn get_pixel_format_info_from_image(
+        metadata: &directxtex::TexMetadata,
+    ) -> Result<PixelFormatInfo, TextureError> {
         let dxgi_format = metadata.format;
         let pixel_format = Self::get_pixel_format_from_image(dxgi_format);
-        let pixel_data_type = Self::get_pixel_format_datatype_from_image(dxgi_format).unwrap();
+        let pixel_data_type =
+            Self::get_pixel_format_datatype_from_image(dxgi_format).ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!(
+                    "Unsupported DXGI format: {:?}",
+                    dxgi_format
+                ))
+            })?;
         let color_space = if dxgi_format.is_srgb() {
             ColorSpace::SRGB
         } else {
@@ -117,12 +149,12 @@ impl DirectXTexUtility {
 
         let is_premultiplied = metadata.is_pm_alpha();
 
-        PixelFormatInfo {
+        Ok(PixelFormatInfo {
             pixel_format,
             pixel_data_type,
             color_space,
             is_premultiplied,
-        }
+        })
     }
 
     pub fn get_texture_dimension_from_directxtex(
@@ -149,13 +181,14 @@ impl DirectXTexUtility {
         width: usize,
         height: usize,
         bc_pixels: Vec<u8>,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<u8>, TextureError> {
         let format = Self::get_dxgi_format_from_pixel_format(pixel_format);
 
         let pitch = format.compute_pitch(width, height, CP_FLAGS::CP_FLAGS_NONE)?;
 
         let row_pitch = pitch.row;
         let slice_pitch = pitch.slice;
+        Self::check_source_size(&bc_pixels, slice_pitch)?;
 
         let src_image = Image {
             width,
@@ -167,10 +200,7 @@ impl DirectXTexUtility {
         };
 
         let new_scratch_image = src_image.decompress(DXGI_FORMAT_R8G8B8A8_UNORM)?;
-        let slice_pitch = new_scratch_image.image(0, 0, 0).unwrap().slice_pitch;
-        let pixels = Vec::from(&new_scratch_image.pixels()[0..slice_pitch]);
-
-        Ok(pixels)
+        Self::get_first_image_pixels(&new_scratch_image)
     }
 
     pub fn compress_rgba_to_bc(
@@ -178,7 +208,8 @@ impl DirectXTexUtility {
         width: usize,
         height: usize,
         rgba_pixels: Vec<u8>,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        options: &CompressionOptions,
+    ) -> Result<Vec<u8>, TextureError> {
         let format = DXGI_FORMAT_R8G8B8A8_UNORM;
 
         let pitch =
@@ -186,6 +217,7 @@ impl DirectXTexUtility {
 
         let row_pitch = pitch.row;
         let slice_pitch = pitch.slice;
+        Self::check_source_size(&rgba_pixels, slice_pitch)?;
 
         let src_image = Image {
             width,
@@ -198,13 +230,29 @@ impl DirectXTexUtility {
 
         let new_scratch_image = src_image.compress(
             Self::get_dxgi_format_from_pixel_format(pixel_format),
-            TEX_COMPRESS_DEFAULT,
-            0.5,
+            Self::get_compress_flags(options),
+            options.alpha_threshold,
         )?;
-        let slice_pitch = new_scratch_image.image(0, 0, 0).unwrap().slice_pitch;
-        let pixels = Vec::from(&new_scratch_image.pixels()[0..slice_pitch]);
+        Self::get_first_image_pixels(&new_scratch_image)
+    }
+
+    fn get_compress_flags(options: &CompressionOptions) -> TEX_COMPRESS_FLAGS {
+        let mut flags = TEX_COMPRESS_DEFAULT;
+
+        if options.weighting == ChannelWeighting::Uniform {
+            flags |= TEX_COMPRESS_UNIFORM;
+        }
+
+        if options.dither {
+            flags |= TEX_COMPRESS_DITHER;
+        }
 
-        Ok(pixels)
+        // Both sides are sRGB, so the pixels are compressed without converting them
+        if options.srgb {
+            flags |= TEX_COMPRESS_SRGB;
+        }
+
+        flags
     }
 
     pub fn convert_rgba_to_dxgi(
@@ -212,12 +260,13 @@ impl DirectXTexUtility {
         width: usize,
         height: usize,
         rgba_pixels: Vec<u8>,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<u8>, TextureError> {
         let pitch =
             DXGI_FORMAT_R8G8B8A8_UNORM.compute_pitch(width, height, CP_FLAGS::CP_FLAGS_NONE)?;
 
         let row_pitch = pitch.row;
         let slice_pitch = pitch.slice;
+        Self::check_source_size(&rgba_pixels, slice_pitch)?;
 
         let src_image = Image {
             width,
@@ -234,10 +283,7 @@ impl DirectXTexUtility {
             0.5,
         )?;
 
-        let slice_pitch = new_scratch_image.image(0, 0, 0).unwrap().slice_pitch;
-        let pixels = Vec::from(&new_scratch_image.pixels()[0..slice_pitch]);
-
-        Ok(pixels)
+        Self::get_first_image_pixels(&new_scratch_image)
     }
 
     pub fn convert_dxgi_to_rgba(
@@ -245,7 +291,7 @@ impl DirectXTexUtility {
         width: usize,
         height: usize,
         rgba_pixels: Vec<u8>,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<u8>, TextureError> {
         let pitch = Self::get_dxgi_format_from_pixel_format(pixel_format).compute_pitch(
             width,
             height,
@@ -254,6 +300,7 @@ impl DirectXTexUtility {
 
         let row_pitch = pitch.row;
         let slice_pitch = pitch.slice;
+        Self::check_source_size(&rgba_pixels, slice_pitch)?;
 
         let src_image = Image {
             width,
@@ -267,9 +314,28 @@ impl DirectXTexUtility {
         let new_scratch_image =
             src_image.convert(DXGI_FORMAT_R8G8B8A8_UNORM, TEX_FILTER_DEFAULT, 0.5)?;
 
-        let slice_pitch = new_scratch_image.image(0, 0, 0).unwrap().slice_pitch;
-        let pixels = Vec::from(&new_scratch_image.pixels()[0..slice_pitch]);
+        Self::get_first_image_pixels(&new_scratch_image)
+    }
+
+    /// DirectXTex reads the source through a raw pointer, so it must hold the whole image
+    fn check_source_size(pixels: &[u8], slice_pitch: usize) -> Result<(), TextureError> {
+        if pixels.len() < slice_pitch {
+            return Err(TextureError::DimensionMismatch(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                slice_pitch
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn get_first_image_pixels(scratch_image: &ScratchImage) -> Result<Vec<u8>, TextureError> {
+        let slice_pitch = scratch_image
+            .image(0, 0, 0)
+            .ok_or_else(|| TextureError::Codec("DirectXTex returned no image".to_string()))?
+            .slice_pitch;
 
-        Ok(pixels)
+        Ok(Vec::from(&scratch_image.pixels()[..slice_pitch]))
     }
 }
@@ -1,12 +1,10 @@
-use std::error::Error;
-
-use bytemuck;
 use image::{DynamicImage, ImageBuffer};
 
 use super::{
     pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
     texture::{Image, TexDimension, TexMetadata, Texture},
 };
+use crate::error::TextureError;
 
 pub struct ImageUtility;
 
@@ -45,94 +43,120 @@ impl ImageUtility {
         }
     }
 
-    pub fn get_pixel_format_info_from_image(image: &image::DynamicImage) -> PixelFormatInfo {
+    pub fn get_pixel_format_info_from_image(
+        image: &image::DynamicImage,
+    ) -> Result<PixelFormatInfo, TextureError> {
         let color_type = image.color();
-        let pixel_format = Self::get_pixel_format_from_image(color_type).unwrap();
-        let pixel_data_type = Self::get_pixel_format_datatype_from_image(color_type).unwrap();
+        let unsupported =
+            || TextureError::UnsupportedFormat(format!("Unsupported color type: {:?}", color_type));
+        let pixel_format = Self::get_pixel_format_from_image(color_type).ok_or_else(unsupported)?;
+        let pixel_data_type =
+            Self::get_pixel_format_datatype_from_image(color_type).ok_or_else(unsupported)?;
         let color_space = match color_type {
             image::ColorType::Rgb8 | image::ColorType::Rgba8 => ColorSpace::SRGB,
             _ => ColorSpace::Linear,
         };
         let is_premultiplied = false;
 
-        PixelFormatInfo {
+        Ok(PixelFormatInfo {
             pixel_format,
             pixel_data_type,
             color_space,
             is_premultiplied,
-        }
+        })
     }
 
     /// Converts raw pixel data into a `DynamicImage`, preserving format.
     pub fn load_dynamic_image(
         image: &Image,
         pixel_format: PixelFormat,
-    ) -> Result<DynamicImage, Box<dyn Error>> {
+    ) -> Result<DynamicImage, TextureError> {
         let width = image.width;
         let height = image.height;
         let pixels = &image.pixels;
+        let buffer_too_small = |kind: &str| {
+            TextureError::DimensionMismatch(format!(
+                "Image data too small for a {}x{} {} image buffer: {}",
+                width,
+                height,
+                kind,
+                pixels.len()
+            ))
+        };
 
         match pixel_format {
             // RGBA8
             PixelFormat::R8G8B8A8 => {
                 let img_buffer = ImageBuffer::from_raw(width, height, pixels.clone())
-                    .ok_or("Failed to create RGBA8 image buffer")?;
+                    .ok_or_else(|| buffer_too_small("RGBA8"))?;
                 Ok(DynamicImage::ImageRgba8(img_buffer))
             }
 
             // RGB8
             PixelFormat::R8G8B8 => {
                 let img_buffer = ImageBuffer::from_raw(width, height, pixels.clone())
-                    .ok_or("Failed to create RGB8 image buffer")?;
+                    .ok_or_else(|| buffer_too_small("RGB8"))?;
                 Ok(DynamicImage::ImageRgb8(img_buffer))
             }
 
             // Grayscale (L8)
             PixelFormat::L8 => {
                 let img_buffer = ImageBuffer::from_raw(width, height, pixels.clone())
-                    .ok_or("Failed to create L8 image buffer")?;
+                    .ok_or_else(|| buffer_too_small("L8"))?;
                 Ok(DynamicImage::ImageLuma8(img_buffer))
             }
 
             // Grayscale + Alpha (LA8)
             PixelFormat::L8A8 => {
                 let img_buffer = ImageBuffer::from_raw(width, height, pixels.clone())
-                    .ok_or("Failed to create LA8 image buffer")?;
+                    .ok_or_else(|| buffer_too_small("LA8"))?;
                 Ok(DynamicImage::ImageLumaA8(img_buffer))
             }
 
             // RGBA16
             PixelFormat::R16G16B16A16 => {
-                let pixels_u16 = bytemuck::cast_slice::<u8, u16>(pixels);
-                let img_buffer = ImageBuffer::from_raw(width, height, pixels_u16.to_vec())
-                    .ok_or("Failed to create RGBA16 image buffer")?;
+                let pixels_u16: Vec<u16> = pixels
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+                    .collect();
+                let img_buffer = ImageBuffer::from_raw(width, height, pixels_u16)
+                    .ok_or_else(|| buffer_too_small("RGBA16"))?;
                 Ok(DynamicImage::ImageRgba16(img_buffer))
             }
 
             // RGB32F
             PixelFormat::R32G32B32 => {
-                let pixels_f32 = bytemuck::cast_slice::<u8, f32>(pixels);
-                let img_buffer = ImageBuffer::from_raw(width, height, pixels_f32.to_vec())
-                    .ok_or("Failed to create RGB32F image buffer")?;
+                let pixels_f32: Vec<f32> = pixels
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect();
+                let img_buffer = ImageBuffer::from_raw(width, height, pixels_f32)
+                    .ok_or_else(|| buffer_too_small("RGB32F"))?;
                 Ok(DynamicImage::ImageRgb32F(img_buffer))
             }
 
             // RGBA32F
             PixelFormat::R32G32B32A32 => {
-                let pixels_f32 = bytemuck::cast_slice::<u8, f32>(pixels);
-                let img_buffer = ImageBuffer::from_raw(width, height, pixels_f32.to_vec())
-                    .ok_or("Failed to create RGBA32F image buffer")?;
+                let pixels_f32: Vec<f32> = pixels
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect();
+                let img_buffer = ImageBuffer::from_raw(width, height, pixels_f32)
+                    .ok_or_else(|| buffer_too_small("RGBA32F"))?;
                 Ok(DynamicImage::ImageRgba32F(img_buffer))
             }
 
-            _ => Err("Unsupported pixel format!".to_string().into()),
+            _ => Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported pixel format: {}",
+                pixel_format
+            ))),
         }
     }
 
-    pub fn get_texture_from_bytes(source: &[u8]) -> Result<Texture, Box<dyn Error>> {
-        let img = image::load_from_memory(source).unwrap();
+    pub fn get_texture_from_bytes(source: &[u8]) -> Result<Texture, TextureError> {
+        let img = image::load_from_memory(source)?;
 
-        let pixel_format_info = Self::get_pixel_format_info_from_image(&img);
+        let pixel_format_info = Self::get_pixel_format_info_from_image(&img)?;
 
         let width = img.width();
         let height = img.height();
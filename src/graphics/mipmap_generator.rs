@@ -0,0 +1,427 @@
+use std::{f32::consts::PI, fmt, str::FromStr};
+
+use super::pixel_format::ColorSpace;
+use crate::error::TextureError;
+
+/// Reconstruction filter used to downsample each mip level
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MipmapFilter {
+    #[default]
+    Box,
+    Triangle,
+    Kaiser,
+    Lanczos,
+}
+
+/// Options for generating a mip chain
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MipmapOptions {
+    pub filter: MipmapFilter,
+    /// Maximum number of levels including the top level. `None` builds the full chain down to 1x1.
+    pub max_levels: Option<u32>,
+    /// Alpha test reference value (0.0 - 1.0). When set, the alpha of every level is scaled
+    /// so the fraction of pixels passing the test matches the top level.
+    pub alpha_coverage_reference: Option<f32>,
+}
+
+pub struct MipmapGenerator;
+
+/// RGBA32F working copy of a 2D image or volume
+#[derive(Clone)]
+struct FloatImage {
+    width: u32,
+    height: u32,
+    depth: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl fmt::Display for MipmapFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MipmapFilter::Box => "Box",
+            MipmapFilter::Triangle => "Triangle",
+            MipmapFilter::Kaiser => "Kaiser",
+            MipmapFilter::Lanczos => "Lanczos",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MipmapFilter {
+    type Err = String;
+
+    /// Parse a filter from its name (case insensitive), e.g. "box" or "Lanczos"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let filter = match s.trim().to_lowercase().as_str() {
+            "box" => MipmapFilter::Box,
+            "triangle" | "tent" => MipmapFilter::Triangle,
+            "kaiser" => MipmapFilter::Kaiser,
+            "lanczos" => MipmapFilter::Lanczos,
+            _ => return Err(format!("Unknown mipmap filter: {}", s)),
+        };
+
+        Ok(filter)
+    }
+}
+
+impl MipmapFilter {
+    /// Half width of the filter kernel in destination pixels
+    fn get_support(&self) -> f32 {
+        match self {
+            MipmapFilter::Box => 0.5,
+            MipmapFilter::Triangle => 1.0,
+            MipmapFilter::Kaiser | MipmapFilter::Lanczos => 3.0,
+        }
+    }
+
+    fn evaluate(&self, x: f32) -> f32 {
+        let x = x.abs();
+        let support = self.get_support();
+
+        if x > support {
+            return 0.0;
+        }
+
+        match self {
+            MipmapFilter::Box => 1.0,
+            MipmapFilter::Triangle => 1.0 - x,
+            MipmapFilter::Kaiser => {
+                const ALPHA: f32 = 4.0;
+                let t = x / support;
+                sinc(x) * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+            }
+            MipmapFilter::Lanczos => sinc(x) * sinc(x / support),
+        }
+    }
+}
+
+impl MipmapGenerator {
+    /// Number of levels in the chain for the given size, capped by `max_levels`
+    pub fn get_mip_count(width: u32, height: u32, depth: u32, max_levels: Option<u32>) -> u32 {
+        let largest = width.max(height).max(depth).max(1);
+        let full_count = u32::BITS - largest.leading_zeros();
+
+        match max_levels {
+            Some(max_levels) => full_count.min(max_levels.max(1)),
+            None => full_count,
+        }
+    }
+
+    /// Generate the mip chain of a 2D RGBA8 image. The first level is the source itself.
+    pub fn generate_2d(
+        width: u32,
+        height: u32,
+        rgba8_pixels: &[u8],
+        color_space: ColorSpace,
+        options: &MipmapOptions,
+    ) -> Result<Vec<Vec<u8>>, TextureError> {
+        let levels = Self::generate_3d(
+            width,
+            height,
+            &[rgba8_pixels.to_vec()],
+            color_space,
+            options,
+        )?;
+
+        Ok(levels
+            .into_iter()
+            .map(|mut slices| slices.remove(0))
+            .collect())
+    }
+
+    /// Generate the mip chain of a volume given as RGBA8 slices.
+    /// Each level is returned as its list of slices, the depth halves along with width and height.
+    pub fn generate_3d(
+        width: u32,
+        height: u32,
+        rgba8_slices: &[Vec<u8>],
+        color_space: ColorSpace,
+        options: &MipmapOptions,
+    ) -> Result<Vec<Vec<Vec<u8>>>, TextureError> {
+        let depth = rgba8_slices.len() as u32;
+        let slice_size = width as usize * height as usize * 4;
+
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(TextureError::InvalidParameter(
+                "Can't generate mipmaps for an empty image!".to_string(),
+            ));
+        }
+
+        if let Some(slice) = rgba8_slices.iter().find(|slice| slice.len() != slice_size) {
+            return Err(TextureError::DimensionMismatch(format!(
+                "Invalid image size for mipmap generation: {} != {}",
+                slice.len(),
+                slice_size
+            )));
+        }
+
+        let mut source = FloatImage::from_rgba8(width, height, rgba8_slices, color_space);
+        let mip_count = Self::get_mip_count(width, height, depth, options.max_levels);
+
+        let coverage = options
+            .alpha_coverage_reference
+            .map(|reference| (reference, source.get_alpha_coverage(reference, 1.0)));
+
+        let mut levels = vec![rgba8_slices.to_vec()];
+
+        // Every level is filtered from the previous one in floats, so each level only costs a
+        // fraction of the top level and rounding errors don't add up along the chain
+        for mip in 1..mip_count {
+            source = source.resample(
+                (width >> mip).max(1),
+                (height >> mip).max(1),
+                (depth >> mip).max(1),
+                options.filter,
+            );
+
+            let level = match coverage {
+                Some((reference, coverage)) => {
+                    let mut level = source.clone();
+                    level.scale_alpha_for_coverage(reference, coverage);
+                    level.to_rgba8(color_space)
+                }
+                None => source.to_rgba8(color_space),
+            };
+
+            levels.push(level);
+        }
+
+        Ok(levels)
+    }
+}
+
+impl FloatImage {
+    fn from_rgba8(width: u32, height: u32, slices: &[Vec<u8>], color_space: ColorSpace) -> Self {
+        let pixels = slices
+            .iter()
+            .flat_map(|slice| slice.chunks_exact(4))
+            .map(|pixel| {
+                let mut color =
+                    [pixel[0], pixel[1], pixel[2], pixel[3]].map(|value| value as f32 / 255.0);
+
+                // Filter in linear space, otherwise sRGB levels get darker
+                if color_space == ColorSpace::SRGB {
+                    for channel in &mut color[..3] {
+                        *channel = srgb_to_linear(*channel);
+                    }
+                }
+
+                color
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            depth: slices.len() as u32,
+            pixels,
+        }
+    }
+
+    fn to_rgba8(&self, color_space: ColorSpace) -> Vec<Vec<u8>> {
+        let slice_size = (self.width * self.height) as usize;
+
+        self.pixels
+            .chunks_exact(slice_size)
+            .map(|slice| {
+                slice
+                    .iter()
+                    .flat_map(|pixel| {
+                        let mut color = *pixel;
+
+                        if color_space == ColorSpace::SRGB {
+                            for channel in &mut color[..3] {
+                                *channel = linear_to_srgb(*channel);
+                            }
+                        }
+
+                        color.map(|value| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Separable resample, one axis at a time
+    fn resample(&self, width: u32, height: u32, depth: u32, filter: MipmapFilter) -> Self {
+        let mut image = self.resample_axis(0, width, filter);
+        image = image.resample_axis(1, height, filter);
+        image.resample_axis(2, depth, filter)
+    }
+
+    fn resample_axis(&self, axis: usize, new_size: u32, filter: MipmapFilter) -> Self {
+        let mut size = [self.width, self.height, self.depth];
+
+        if size[axis] == new_size {
+            return Self {
+                width: self.width,
+                height: self.height,
+                depth: self.depth,
+                pixels: self.pixels.clone(),
+            };
+        }
+
+        let weights = get_filter_weights(size[axis], new_size, filter);
+        size[axis] = new_size;
+
+        let [width, height, depth] = size;
+        let mut pixels = Vec::with_capacity((width * height * depth) as usize);
+
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let mut color = [0.0; 4];
+                    let position = [x, y, z];
+
+                    for &(source, weight) in &weights[position[axis] as usize] {
+                        let mut source_position = position;
+                        source_position[axis] = source;
+
+                        let pixel = self.get_pixel(source_position);
+
+                        for (channel, value) in color.iter_mut().zip(pixel) {
+                            *channel += value * weight;
+                        }
+                    }
+
+                    pixels.push(color);
+                }
+            }
+        }
+
+        Self {
+            width,
+            height,
+            depth,
+            pixels,
+        }
+    }
+
+    fn get_pixel(&self, [x, y, z]: [u32; 3]) -> [f32; 4] {
+        self.pixels[((z * self.height + y) * self.width + x) as usize]
+    }
+
+    /// Fraction of pixels whose scaled alpha passes the alpha test
+    fn get_alpha_coverage(&self, reference: f32, scale: f32) -> f32 {
+        let passing = self
+            .pixels
+            .iter()
+            .filter(|pixel| (pixel[3] * scale).min(1.0) > reference)
+            .count();
+
+        passing as f32 / self.pixels.len() as f32
+    }
+
+    /// Binary search an alpha scale that brings the coverage back to the target
+    fn scale_alpha_for_coverage(&mut self, reference: f32, target_coverage: f32) {
+        let mut min_scale = 0.0;
+        let mut max_scale = 4.0;
+        let mut best_scale = 1.0;
+        let mut best_error = f32::MAX;
+
+        for _ in 0..16 {
+            let scale = (min_scale + max_scale) / 2.0;
+            let coverage = self.get_alpha_coverage(reference, scale);
+            let error = (coverage - target_coverage).abs();
+
+            if error < best_error {
+                best_error = error;
+                best_scale = scale;
+            }
+
+            if coverage < target_coverage {
+                min_scale = scale;
+            } else if coverage > target_coverage {
+                max_scale = scale;
+            } else {
+                break;
+            }
+        }
+
+        for pixel in &mut self.pixels {
+            pixel[3] = (pixel[3] * best_scale).min(1.0);
+        }
+    }
+}
+
+/// Normalized weights of the source pixels contributing to each destination pixel.
+/// Samples outside of the image are clamped to the edge.
+fn get_filter_weights(
+    source_size: u32,
+    new_size: u32,
+    filter: MipmapFilter,
+) -> Vec<Vec<(u32, f32)>> {
+    let scale = source_size as f32 / new_size as f32;
+    let radius = filter.get_support() * scale;
+
+    (0..new_size)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let first = (center - radius).floor() as i64;
+            let last = (center + radius).ceil() as i64;
+
+            let mut weights: Vec<(u32, f32)> = (first..=last)
+                .map(|j| {
+                    let source = j.clamp(0, source_size as i64 - 1) as u32;
+                    (source, filter.evaluate((j as f32 + 0.5 - center) / scale))
+                })
+                .filter(|&(_, weight)| weight != 0.0)
+                .collect();
+
+            let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+
+            if total.abs() > f32::EPSILON {
+                for (_, weight) in &mut weights {
+                    *weight /= total;
+                }
+            }
+
+            weights
+        })
+        .collect()
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+
+    for k in 1..32 {
+        term *= half_x / k as f32;
+        sum += term * term;
+
+        if term * term < sum * 1e-8 {
+            break;
+        }
+    }
+
+    sum
+}
+
+pub(super) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -1,5 +1,11 @@
+pub mod astc_decoder;
+pub mod astc_encoder;
+pub mod bc_decoder;
+pub mod bc_encoder;
+pub mod color_quantizer;
 pub mod crate_directxtex_utility;
 pub mod crate_image_utility;
+pub mod mipmap_generator;
 pub mod pixel_decoders;
 pub mod pixel_encoders;
 pub mod pixel_format;
@@ -15,3 +15,114 @@ pub fn decode_bgr888_pixel(pixel: &[u8]) -> [u8; 4] {
 
     [r8, g8, b8, a8]
 }
+
+/// Widen a channel to 8 bits by replicating its bits, like the hardware does
+fn expand_bits(value: u16, bits: i32) -> u8 {
+    let value = value as u32;
+    let mut expanded = 0;
+    let mut shift = 8 - bits;
+
+    while shift > -bits {
+        expanded |= if shift >= 0 {
+            value << shift
+        } else {
+            value >> -shift
+        };
+        shift -= bits;
+    }
+
+    expanded as u8
+}
+
+/// GX RGB565, big endian
+pub fn decode_rgb565_be_pixel(pixel: &[u8]) -> [u8; 4] {
+    let value = u16::from_be_bytes([pixel[0], pixel[1]]);
+
+    let r8 = expand_bits(value >> 11, 5);
+    let g8 = expand_bits((value >> 5) & 0x3F, 6);
+    let b8 = expand_bits(value & 0x1F, 5);
+
+    [r8, g8, b8, 0xFF]
+}
+
+/// GX RGB5A3, big endian. Opaque RGB555 when the top bit is set, A3RGB444 otherwise.
+pub fn decode_rgb5a3_pixel(pixel: &[u8]) -> [u8; 4] {
+    let value = u16::from_be_bytes([pixel[0], pixel[1]]);
+
+    if value & 0x8000 != 0 {
+        [
+            expand_bits((value >> 10) & 0x1F, 5),
+            expand_bits((value >> 5) & 0x1F, 5),
+            expand_bits(value & 0x1F, 5),
+            0xFF,
+        ]
+    } else {
+        [
+            expand_bits((value >> 8) & 0xF, 4),
+            expand_bits((value >> 4) & 0xF, 4),
+            expand_bits(value & 0xF, 4),
+            expand_bits((value >> 12) & 0x7, 3),
+        ]
+    }
+}
+
+/// GX I4 and I8, the intensity is also used as alpha
+pub fn decode_intensity_pixel(intensity: u8) -> [u8; 4] {
+    [intensity; 4]
+}
+
+/// GX IA4, alpha in the high nibble
+pub fn decode_ia4_pixel(pixel: u8) -> [u8; 4] {
+    let i8 = expand_bits(pixel as u16 & 0xF, 4);
+    let a8 = expand_bits(pixel as u16 >> 4, 4);
+
+    [i8, i8, i8, a8]
+}
+
+/// GX IA8, alpha first
+pub fn decode_ia8_pixel(pixel: &[u8]) -> [u8; 4] {
+    [pixel[1], pixel[1], pixel[1], pixel[0]]
+}
+
+/// GX RGBA8 once detiled
+pub fn decode_argb8888_pixel(pixel: &[u8]) -> [u8; 4] {
+    [pixel[1], pixel[2], pixel[3], pixel[0]]
+}
+
+/// Convert between GX CMPR and BC1 blocks. CMPR stores the colors big endian
+/// and the first pixel of every row in the high bits, so the conversion is its own inverse.
+pub fn swap_gx_cmpr_block(block: &[u8]) -> [u8; 8] {
+    let mut swapped = [0; 8];
+
+    swapped[0] = block[1];
+    swapped[1] = block[0];
+    swapped[2] = block[3];
+    swapped[3] = block[2];
+
+    for row in 0..4 {
+        let indices = block[4 + row];
+        swapped[4 + row] = ((indices & 0x03) << 6)
+            | ((indices & 0x0C) << 2)
+            | ((indices & 0x30) >> 2)
+            | ((indices & 0xC0) >> 6);
+    }
+
+    swapped
+}
+
+/// Swap the 8 entry rows 1 and 2 of every 32 entries, the order 256 color CLUTs are stored in
+/// GS memory. The swap is its own inverse.
+pub fn shuffle_ps2_clut_index(index: usize) -> usize {
+    match index % 32 {
+        8..=15 => index + 8,
+        16..=23 => index - 8,
+        _ => index,
+    }
+}
+
+/// PS2 CLUT entry, 0x80 is fully opaque
+pub fn decode_ps2_clut_pixel(pixel: &[u8]) -> [u8; 4] {
+    let a8 = ((pixel[3] as u32 * 255 + 64) / 128).min(255) as u8;
+
+    [pixel[0], pixel[1], pixel[2], a8]
+}
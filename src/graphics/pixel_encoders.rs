@@ -13,3 +13,63 @@ pub fn encode_bgr888_pixel(pixel: &[u8; 4]) -> [u8; 3] {
 
     [r8, g8, b8]
 }
+
+/// Narrow an 8 bit channel, rounding to the nearest value
+fn reduce_bits(value: u8, bits: u32) -> u16 {
+    let max = (1 << bits) - 1;
+    ((value as u32 * max + 127) / 255) as u16
+}
+
+/// Rec. 601 luma, used for the GX intensity formats
+pub fn encode_intensity(pixel: &[u8; 4]) -> u8 {
+    ((pixel[0] as u32 * 77 + pixel[1] as u32 * 150 + pixel[2] as u32 * 29 + 128) >> 8) as u8
+}
+
+/// GX RGB565, big endian
+pub fn encode_rgb565_be_pixel(pixel: &[u8; 4]) -> [u8; 2] {
+    let value =
+        reduce_bits(pixel[0], 5) << 11 | reduce_bits(pixel[1], 6) << 5 | reduce_bits(pixel[2], 5);
+
+    value.to_be_bytes()
+}
+
+/// GX RGB5A3, big endian. Opaque pixels get the RGB555 mode, the others A3RGB444.
+pub fn encode_rgb5a3_pixel(pixel: &[u8; 4]) -> [u8; 2] {
+    let a3 = reduce_bits(pixel[3], 3);
+
+    let value = if a3 == 7 {
+        0x8000
+            | reduce_bits(pixel[0], 5) << 10
+            | reduce_bits(pixel[1], 5) << 5
+            | reduce_bits(pixel[2], 5)
+    } else {
+        a3 << 12
+            | reduce_bits(pixel[0], 4) << 8
+            | reduce_bits(pixel[1], 4) << 4
+            | reduce_bits(pixel[2], 4)
+    };
+
+    value.to_be_bytes()
+}
+
+/// GX IA4, alpha in the high nibble
+pub fn encode_ia4_pixel(pixel: &[u8; 4]) -> u8 {
+    (reduce_bits(pixel[3], 4) << 4 | reduce_bits(encode_intensity(pixel), 4)) as u8
+}
+
+/// GX IA8, alpha first
+pub fn encode_ia8_pixel(pixel: &[u8; 4]) -> [u8; 2] {
+    [pixel[3], encode_intensity(pixel)]
+}
+
+/// GX RGBA8 before tiling
+pub fn encode_argb8888_pixel(pixel: &[u8; 4]) -> [u8; 4] {
+    [pixel[3], pixel[0], pixel[1], pixel[2]]
+}
+
+/// PS2 CLUT entry, 0x80 is fully opaque
+pub fn encode_ps2_clut_pixel(pixel: &[u8; 4]) -> [u8; 4] {
+    let a = ((pixel[3] as u32 * 128 + 127) / 255) as u8;
+
+    [pixel[0], pixel[1], pixel[2], a]
+}
@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 #[derive(Debug, Clone, Copy)]
 pub struct PixelFormatInfo {
@@ -27,9 +27,9 @@ pub enum PixelFormat {
     B5G5R5A1,
     B5G5R5X1,
     R16,
-    L16, // Legacy 
+    L16, // Legacy
     R16G16,
-    L16A16, 
+    L16A16,
     R16G16B16,
     R16G16B16A16,
     R32,
@@ -47,18 +47,66 @@ pub enum PixelFormat {
     BC5, // ATI2
     BC6H,
     BC7,
+    // ASTC, 128 bit blocks of every 2D footprint. The HDR variants decode with the HDR profile.
+    Astc4x4,
+    Astc5x4,
+    Astc5x5,
+    Astc6x5,
+    Astc6x6,
+    Astc8x5,
+    Astc8x6,
+    Astc8x8,
+    Astc10x5,
+    Astc10x6,
+    Astc10x8,
+    Astc10x10,
+    Astc12x10,
+    Astc12x12,
+    Astc4x4Hdr,
+    Astc5x4Hdr,
+    Astc5x5Hdr,
+    Astc6x5Hdr,
+    Astc6x6Hdr,
+    Astc8x5Hdr,
+    Astc8x6Hdr,
+    Astc8x8Hdr,
+    Astc10x5Hdr,
+    Astc10x6Hdr,
+    Astc10x8Hdr,
+    Astc10x10Hdr,
+    Astc12x10Hdr,
+    Astc12x12Hdr,
+    // GameCube / Wii GX formats, big endian. The paletted ones are followed by their TLUT
+    // of big endian RGB5A3 entries.
+    GxI4,
+    GxI8,
+    GxIA4,
+    GxIA8,
+    GxRGB565,
+    GxRGB5A3,
+    GxRGBA8, // A, R, G, B once detiled
+    GxCMPR,  // DXT1 with big endian colors and indices
+    GxC4,
+    GxC8,
+    GxC14X2,
+    // PS2 indexed formats, followed by their CLUT of RGBA8 entries with alpha in 0-0x80.
+    // 256 color CLUTs are in the GS order. 4 bit indices start with the low nibble.
+    Ps2PSMT4,
+    Ps2PSMT8,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PixelDataType {
     SNorm,
     UNorm,
     SInt,
     UInt,
     Float,
+    /// Signed half floats of BC6H (BC6H_SF16), BC6H is unsigned with `Float`
+    SFloat,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ColorSpace {
     Linear,
     SRGB,
@@ -104,12 +152,143 @@ impl fmt::Display for PixelFormat {
             PixelFormat::BC5 => "BC5",
             PixelFormat::BC6H => "BC6H",
             PixelFormat::BC7 => "BC7",
+            PixelFormat::Astc4x4 => "ASTC 4x4",
+            PixelFormat::Astc5x4 => "ASTC 5x4",
+            PixelFormat::Astc5x5 => "ASTC 5x5",
+            PixelFormat::Astc6x5 => "ASTC 6x5",
+            PixelFormat::Astc6x6 => "ASTC 6x6",
+            PixelFormat::Astc8x5 => "ASTC 8x5",
+            PixelFormat::Astc8x6 => "ASTC 8x6",
+            PixelFormat::Astc8x8 => "ASTC 8x8",
+            PixelFormat::Astc10x5 => "ASTC 10x5",
+            PixelFormat::Astc10x6 => "ASTC 10x6",
+            PixelFormat::Astc10x8 => "ASTC 10x8",
+            PixelFormat::Astc10x10 => "ASTC 10x10",
+            PixelFormat::Astc12x10 => "ASTC 12x10",
+            PixelFormat::Astc12x12 => "ASTC 12x12",
+            PixelFormat::Astc4x4Hdr => "ASTC 4x4 HDR",
+            PixelFormat::Astc5x4Hdr => "ASTC 5x4 HDR",
+            PixelFormat::Astc5x5Hdr => "ASTC 5x5 HDR",
+            PixelFormat::Astc6x5Hdr => "ASTC 6x5 HDR",
+            PixelFormat::Astc6x6Hdr => "ASTC 6x6 HDR",
+            PixelFormat::Astc8x5Hdr => "ASTC 8x5 HDR",
+            PixelFormat::Astc8x6Hdr => "ASTC 8x6 HDR",
+            PixelFormat::Astc8x8Hdr => "ASTC 8x8 HDR",
+            PixelFormat::Astc10x5Hdr => "ASTC 10x5 HDR",
+            PixelFormat::Astc10x6Hdr => "ASTC 10x6 HDR",
+            PixelFormat::Astc10x8Hdr => "ASTC 10x8 HDR",
+            PixelFormat::Astc10x10Hdr => "ASTC 10x10 HDR",
+            PixelFormat::Astc12x10Hdr => "ASTC 12x10 HDR",
+            PixelFormat::Astc12x12Hdr => "ASTC 12x12 HDR",
+            PixelFormat::GxI4 => "GX I4",
+            PixelFormat::GxI8 => "GX I8",
+            PixelFormat::GxIA4 => "GX IA4",
+            PixelFormat::GxIA8 => "GX IA8",
+            PixelFormat::GxRGB565 => "GX RGB565",
+            PixelFormat::GxRGB5A3 => "GX RGB5A3",
+            PixelFormat::GxRGBA8 => "GX RGBA8",
+            PixelFormat::GxCMPR => "GX CMPR",
+            PixelFormat::GxC4 => "GX C4",
+            PixelFormat::GxC8 => "GX C8",
+            PixelFormat::GxC14X2 => "GX C14X2",
+            PixelFormat::Ps2PSMT4 => "PS2 PSMT4",
+            PixelFormat::Ps2PSMT8 => "PS2 PSMT8",
         };
 
         write!(f, "{}", name)
     }
 }
 
+impl FromStr for PixelFormat {
+    type Err = String;
+
+    /// Parse a pixel format from its display name (case insensitive), e.g. "bc1", "R8G8B8A8" or "gx_cmpr"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pixel_format = match s.trim().to_uppercase().replace([' ', '_'], "").as_str() {
+            "R1" => PixelFormat::R1,
+            "A8" => PixelFormat::A8,
+            "R8" => PixelFormat::R8,
+            "R8G8" => PixelFormat::R8G8,
+            "L8" => PixelFormat::L8,
+            "L8A8" => PixelFormat::L8A8,
+            "B4G4R4A4" => PixelFormat::B4G4R4A4,
+            "R8G8B8" => PixelFormat::R8G8B8,
+            "B8G8R8" => PixelFormat::B8G8R8,
+            "R8G8B8A8" => PixelFormat::R8G8B8A8,
+            "B8G8R8A8" => PixelFormat::B8G8R8A8,
+            "B8G8R8X8" => PixelFormat::B8G8R8X8,
+            "B5G6R5" => PixelFormat::B5G6R5,
+            "B5G5R5A1" => PixelFormat::B5G5R5A1,
+            "B5G5R5X1" => PixelFormat::B5G5R5X1,
+            "R16" => PixelFormat::R16,
+            "L16" => PixelFormat::L16,
+            "R16G16" => PixelFormat::R16G16,
+            "L16A16" => PixelFormat::L16A16,
+            "R16G16B16" => PixelFormat::R16G16B16,
+            "R16G16B16A16" => PixelFormat::R16G16B16A16,
+            "R32" => PixelFormat::R32,
+            "R32G32" => PixelFormat::R32G32,
+            "R32G32B32" => PixelFormat::R32G32B32,
+            "R32G32B32A32" => PixelFormat::R32G32B32A32,
+            "R10G10B10A2" => PixelFormat::R10G10B10A2,
+            "R11G11B10" => PixelFormat::R11G11B10,
+            "R9G9B9E5" => PixelFormat::R9G9B9E5,
+            "D16" => PixelFormat::D16,
+            "BC1" | "DXT1" => PixelFormat::BC1,
+            "BC2" | "DXT3" => PixelFormat::BC2,
+            "BC3" | "DXT5" => PixelFormat::BC3,
+            "BC4" | "ATI1" => PixelFormat::BC4,
+            "BC5" | "ATI2" => PixelFormat::BC5,
+            "BC6H" => PixelFormat::BC6H,
+            "BC7" => PixelFormat::BC7,
+            "ASTC4X4" => PixelFormat::Astc4x4,
+            "ASTC5X4" => PixelFormat::Astc5x4,
+            "ASTC5X5" => PixelFormat::Astc5x5,
+            "ASTC6X5" => PixelFormat::Astc6x5,
+            "ASTC6X6" => PixelFormat::Astc6x6,
+            "ASTC8X5" => PixelFormat::Astc8x5,
+            "ASTC8X6" => PixelFormat::Astc8x6,
+            "ASTC8X8" => PixelFormat::Astc8x8,
+            "ASTC10X5" => PixelFormat::Astc10x5,
+            "ASTC10X6" => PixelFormat::Astc10x6,
+            "ASTC10X8" => PixelFormat::Astc10x8,
+            "ASTC10X10" => PixelFormat::Astc10x10,
+            "ASTC12X10" => PixelFormat::Astc12x10,
+            "ASTC12X12" => PixelFormat::Astc12x12,
+            "ASTC4X4HDR" => PixelFormat::Astc4x4Hdr,
+            "ASTC5X4HDR" => PixelFormat::Astc5x4Hdr,
+            "ASTC5X5HDR" => PixelFormat::Astc5x5Hdr,
+            "ASTC6X5HDR" => PixelFormat::Astc6x5Hdr,
+            "ASTC6X6HDR" => PixelFormat::Astc6x6Hdr,
+            "ASTC8X5HDR" => PixelFormat::Astc8x5Hdr,
+            "ASTC8X6HDR" => PixelFormat::Astc8x6Hdr,
+            "ASTC8X8HDR" => PixelFormat::Astc8x8Hdr,
+            "ASTC10X5HDR" => PixelFormat::Astc10x5Hdr,
+            "ASTC10X6HDR" => PixelFormat::Astc10x6Hdr,
+            "ASTC10X8HDR" => PixelFormat::Astc10x8Hdr,
+            "ASTC10X10HDR" => PixelFormat::Astc10x10Hdr,
+            "ASTC12X10HDR" => PixelFormat::Astc12x10Hdr,
+            "ASTC12X12HDR" => PixelFormat::Astc12x12Hdr,
+            "GXI4" => PixelFormat::GxI4,
+            "GXI8" => PixelFormat::GxI8,
+            "GXIA4" => PixelFormat::GxIA4,
+            "GXIA8" => PixelFormat::GxIA8,
+            "GXRGB565" => PixelFormat::GxRGB565,
+            "GXRGB5A3" => PixelFormat::GxRGB5A3,
+            "GXRGBA8" => PixelFormat::GxRGBA8,
+            "GXCMPR" | "CMPR" => PixelFormat::GxCMPR,
+            "GXC4" => PixelFormat::GxC4,
+            "GXC8" => PixelFormat::GxC8,
+            "GXC14X2" => PixelFormat::GxC14X2,
+            "PS2PSMT4" | "PSMT4" => PixelFormat::Ps2PSMT4,
+            "PS2PSMT8" | "PSMT8" => PixelFormat::Ps2PSMT8,
+            _ => return Err(format!("Unknown pixel format: {}", s)),
+        };
+
+        Ok(pixel_format)
+    }
+}
+
 impl Default for PixelFormatInfo {
     fn default() -> Self {
         Self {
@@ -121,6 +300,16 @@ impl Default for PixelFormatInfo {
     }
 }
 
+/// Linear unsigned normalized data of the format
+impl From<PixelFormat> for PixelFormatInfo {
+    fn from(pixel_format: PixelFormat) -> Self {
+        Self {
+            pixel_format,
+            ..Default::default()
+        }
+    }
+}
+
 impl PixelFormat {
     pub fn get_bits_per_pixel(self) -> u32 {
         match self {
@@ -138,7 +327,8 @@ impl PixelFormat {
             | PixelFormat::B8G8R8X8
             | PixelFormat::R9G9B9E5
             | PixelFormat::R10G10B10A2
-            | PixelFormat::R11G11B10 => 32,
+            | PixelFormat::R11G11B10
+            | PixelFormat::GxRGBA8 => 32,
 
             PixelFormat::R8G8B8 | PixelFormat::B8G8R8 => 24,
 
@@ -147,7 +337,11 @@ impl PixelFormat {
             | PixelFormat::R8G8
             | PixelFormat::L8A8
             | PixelFormat::B5G6R5
-            | PixelFormat::B4G4R4A4 => 16,
+            | PixelFormat::B4G4R4A4
+            | PixelFormat::GxIA8
+            | PixelFormat::GxRGB565
+            | PixelFormat::GxRGB5A3
+            | PixelFormat::GxC14X2 => 16,
 
             PixelFormat::R8
             | PixelFormat::L8
@@ -156,9 +350,18 @@ impl PixelFormat {
             | PixelFormat::BC3
             | PixelFormat::BC5
             | PixelFormat::BC6H
-            | PixelFormat::BC7 => 8,
+            | PixelFormat::BC7
+            | PixelFormat::GxI8
+            | PixelFormat::GxIA4
+            | PixelFormat::GxC8
+            | PixelFormat::Ps2PSMT8 => 8,
 
-            PixelFormat::BC1 | PixelFormat::BC4 => 4,
+            PixelFormat::BC1
+            | PixelFormat::BC4
+            | PixelFormat::GxI4
+            | PixelFormat::GxCMPR
+            | PixelFormat::GxC4
+            | PixelFormat::Ps2PSMT4 => 4,
 
             PixelFormat::R1 => 1,
             _ => 0,
@@ -167,7 +370,7 @@ impl PixelFormat {
 
     pub fn get_slice_pitch(self, width: u32, height: u32) -> u32 {
         if self.is_compressed() {
-            let bh = std::cmp::max(1, (height + 3) / 4);
+            let bh = std::cmp::max(1, height.div_ceil(self.get_block_height()));
             return bh * self.get_row_pitch(width);
         }
         self.get_row_pitch(width) * height
@@ -175,19 +378,42 @@ impl PixelFormat {
 
     pub fn get_row_pitch(self, width: u32) -> u32 {
         if self.is_compressed() {
-            let bw = std::cmp::max(1, (width + 3) / 4);
+            let bw = std::cmp::max(1, width.div_ceil(self.get_block_width()));
             return bw * self.get_bytes_per_block();
         }
 
-        (self.get_bits_per_pixel() * width + 7) / 8
+        ((u64::from(self.get_bits_per_pixel()) * u64::from(width) + 7) / 8) as u32
+    }
+
+    /// Size of `count` slices in 64 bits, `None` when a single slice does not fit the u32
+    /// pitches or the total overflows. Sizes read from file headers go through this first.
+    pub fn get_checked_size(self, width: u32, height: u32, count: u64) -> Option<u64> {
+        let (width, height) = (u64::from(width), u64::from(height));
+
+        let (row_pitch, rows) = if self.is_compressed() {
+            let bw = width.div_ceil(u64::from(self.get_block_width())).max(1);
+            let bh = height.div_ceil(u64::from(self.get_block_height())).max(1);
+            (bw * u64::from(self.get_bytes_per_block()), bh)
+        } else {
+            (
+                (u64::from(self.get_bits_per_pixel()) * width + 7) / 8,
+                height,
+            )
+        };
+
+        row_pitch
+            .checked_mul(rows)
+            .filter(|&slice_pitch| slice_pitch <= u64::from(u32::MAX))?
+            .checked_mul(count)
     }
 
     pub fn get_bytes_per_block(self) -> u32 {
         match self {
-            PixelFormat::BC1 | PixelFormat::BC4 => 8,
+            PixelFormat::BC1 | PixelFormat::BC4 | PixelFormat::GxCMPR => 8,
             PixelFormat::BC2 | PixelFormat::BC3 | PixelFormat::BC5 => 16,
             PixelFormat::BC6H => 16,
             PixelFormat::BC7 => 16,
+            _ if self.is_astc() => 16,
             _ => self.get_bits_per_pixel() / 8,
         }
     }
@@ -200,8 +426,9 @@ impl PixelFormat {
             | PixelFormat::BC4
             | PixelFormat::BC5
             | PixelFormat::BC6H
-            | PixelFormat::BC7 => 4,
-            _ => 1,
+            | PixelFormat::BC7
+            | PixelFormat::GxCMPR => 4,
+            _ => self.get_astc_block_size().map_or(1, |(width, _)| width),
         }
     }
 
@@ -213,21 +440,84 @@ impl PixelFormat {
             | PixelFormat::BC4
             | PixelFormat::BC5
             | PixelFormat::BC6H
-            | PixelFormat::BC7 => 4,
-            _ => 1,
+            | PixelFormat::BC7
+            | PixelFormat::GxCMPR => 4,
+            _ => self.get_astc_block_size().map_or(1, |(_, height)| height),
         }
     }
 
+    /// Footprint of the ASTC formats in texels, width first
+    pub fn get_astc_block_size(self) -> Option<(u32, u32)> {
+        let size = match self {
+            PixelFormat::Astc4x4 | PixelFormat::Astc4x4Hdr => (4, 4),
+            PixelFormat::Astc5x4 | PixelFormat::Astc5x4Hdr => (5, 4),
+            PixelFormat::Astc5x5 | PixelFormat::Astc5x5Hdr => (5, 5),
+            PixelFormat::Astc6x5 | PixelFormat::Astc6x5Hdr => (6, 5),
+            PixelFormat::Astc6x6 | PixelFormat::Astc6x6Hdr => (6, 6),
+            PixelFormat::Astc8x5 | PixelFormat::Astc8x5Hdr => (8, 5),
+            PixelFormat::Astc8x6 | PixelFormat::Astc8x6Hdr => (8, 6),
+            PixelFormat::Astc8x8 | PixelFormat::Astc8x8Hdr => (8, 8),
+            PixelFormat::Astc10x5 | PixelFormat::Astc10x5Hdr => (10, 5),
+            PixelFormat::Astc10x6 | PixelFormat::Astc10x6Hdr => (10, 6),
+            PixelFormat::Astc10x8 | PixelFormat::Astc10x8Hdr => (10, 8),
+            PixelFormat::Astc10x10 | PixelFormat::Astc10x10Hdr => (10, 10),
+            PixelFormat::Astc12x10 | PixelFormat::Astc12x10Hdr => (12, 10),
+            PixelFormat::Astc12x12 | PixelFormat::Astc12x12Hdr => (12, 12),
+            _ => return None,
+        };
+
+        Some(size)
+    }
+
+    pub fn is_astc(self) -> bool {
+        self.get_astc_block_size().is_some()
+    }
+
+    /// ASTC formats decoded with the HDR profile
+    pub fn is_astc_hdr(self) -> bool {
+        matches!(
+            self,
+            PixelFormat::Astc4x4Hdr
+                | PixelFormat::Astc5x4Hdr
+                | PixelFormat::Astc5x5Hdr
+                | PixelFormat::Astc6x5Hdr
+                | PixelFormat::Astc6x6Hdr
+                | PixelFormat::Astc8x5Hdr
+                | PixelFormat::Astc8x6Hdr
+                | PixelFormat::Astc8x8Hdr
+                | PixelFormat::Astc10x5Hdr
+                | PixelFormat::Astc10x6Hdr
+                | PixelFormat::Astc10x8Hdr
+                | PixelFormat::Astc10x10Hdr
+                | PixelFormat::Astc12x10Hdr
+                | PixelFormat::Astc12x12Hdr
+        )
+    }
+
     pub fn is_compressed(self) -> bool {
+        self.is_astc()
+            || matches!(
+                self,
+                PixelFormat::BC1
+                    | PixelFormat::BC2
+                    | PixelFormat::BC3
+                    | PixelFormat::BC4
+                    | PixelFormat::BC5
+                    | PixelFormat::BC6H
+                    | PixelFormat::BC7
+                    | PixelFormat::GxCMPR
+            )
+    }
+
+    /// Size in bytes of the palette stored after the indices of paletted formats
+    pub fn get_palette_size(self) -> u32 {
         match self {
-            PixelFormat::BC1
-            | PixelFormat::BC2
-            | PixelFormat::BC3
-            | PixelFormat::BC4
-            | PixelFormat::BC5
-            | PixelFormat::BC6H
-            | PixelFormat::BC7 => true,
-            _ => false,
+            PixelFormat::GxC4 => 16 * 2,
+            PixelFormat::GxC8 => 256 * 2,
+            PixelFormat::GxC14X2 => 16384 * 2,
+            PixelFormat::Ps2PSMT4 => 16 * 4,
+            PixelFormat::Ps2PSMT8 => 256 * 4,
+            _ => 0,
         }
     }
 }
@@ -1,18 +1,28 @@
 use super::{
+    astc_decoder::decode_astc_to_rgba,
+    bc_decoder::decode_bc_to_rgba,
     crate_directxtex_utility::DirectXTexUtility,
-    pixel_decoders::{decode_bgr888_pixel, decode_rgb888_pixel},
-    pixel_format::PixelFormat,
+    pixel_decoders::{
+        decode_argb8888_pixel, decode_bgr888_pixel, decode_ia4_pixel, decode_ia8_pixel,
+        decode_intensity_pixel, decode_ps2_clut_pixel, decode_rgb565_be_pixel, decode_rgb5a3_pixel,
+        decode_rgb888_pixel, shuffle_ps2_clut_index, swap_gx_cmpr_block,
+    },
+    pixel_format::{PixelDataType, PixelFormat, PixelFormatInfo},
 };
+use crate::error::TextureError;
 
 pub struct PixelDecoder;
 
 impl PixelDecoder {
+    /// Decode pixels to RGBA8, the data type of the format info tells signed BC6H apart
     pub fn decode(
         pixels: &[u8],
-        format: PixelFormat,
+        pixel_format_info: PixelFormatInfo,
         width: u32,
         height: u32,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<u8>, TextureError> {
+        let format = pixel_format_info.pixel_format;
+
         match format {
             PixelFormat::R8G8B8A8 => {
                 // No conversion needed
@@ -78,17 +88,185 @@ impl PixelDecoder {
             | PixelFormat::BC5
             | PixelFormat::BC6H
             | PixelFormat::BC7 => {
-                // Convert from BC formats using DirectXTex
-                let output = DirectXTexUtility::decompress_bc_to_rgba(
-                    format,
-                    width as usize,
-                    height as usize,
-                    pixels.to_vec(),
-                )?;
-                Ok(output)
+                // Native block decoder, DirectXTex is not needed for BC formats
+                let signed = pixel_format_info.pixel_data_type == PixelDataType::SFloat;
+                decode_bc_to_rgba(pixels, format, width, height, signed)
+            }
+
+            PixelFormat::GxI4
+            | PixelFormat::GxI8
+            | PixelFormat::GxIA4
+            | PixelFormat::GxIA8
+            | PixelFormat::GxRGB565
+            | PixelFormat::GxRGB5A3
+            | PixelFormat::GxRGBA8
+            | PixelFormat::GxCMPR
+            | PixelFormat::GxC4
+            | PixelFormat::GxC8
+            | PixelFormat::GxC14X2 => Self::decode_gx(pixels, format, width, height),
+
+            PixelFormat::Ps2PSMT4 | PixelFormat::Ps2PSMT8 => {
+                Self::decode_ps2(pixels, format, width, height)
+            }
+
+            _ if format.is_astc() => decode_astc_to_rgba(pixels, format, width, height),
+
+            _ => Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported pixel format: {}",
+                format
+            ))),
+        }
+    }
+
+    /// Decode detiled GameCube / Wii pixels
+    fn decode_gx(
+        pixels: &[u8],
+        format: PixelFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, TextureError> {
+        let image_size = format.get_slice_pitch(width, height) as usize;
+        let palette_size = format.get_palette_size() as usize;
+
+        if pixels.len() < image_size + palette_size {
+            return Err(TextureError::DimensionMismatch(format!(
+                "Image data too small for {}: {} < {}",
+                format,
+                pixels.len(),
+                image_size + palette_size
+            )));
+        }
+
+        let (pixels, palette) = pixels.split_at(image_size);
+        let palette: Vec<[u8; 4]> = palette[..palette_size]
+            .chunks_exact(2)
+            .map(decode_rgb5a3_pixel)
+            .collect();
+
+        let output = match format {
+            PixelFormat::GxCMPR => {
+                let bc1_pixels: Vec<u8> = pixels
+                    .chunks_exact(8)
+                    .flat_map(swap_gx_cmpr_block)
+                    .collect();
+
+                return decode_bc_to_rgba(&bc1_pixels, PixelFormat::BC1, width, height, false);
             }
 
-            _ => Err("Unsupported pixel format".into()),
+            PixelFormat::GxI4 | PixelFormat::GxC4 => {
+                // Two pixels per byte, the first one in the high nibble
+                let row_pitch = format.get_row_pitch(width) as usize;
+
+                (0..height as usize)
+                    .flat_map(|y| (0..width as usize).map(move |x| (y, x)))
+                    .flat_map(|(y, x)| {
+                        let byte = pixels[y * row_pitch + x / 2];
+                        let nibble = if x % 2 == 0 { byte >> 4 } else { byte & 0xF };
+
+                        if format == PixelFormat::GxI4 {
+                            decode_intensity_pixel(nibble * 0x11)
+                        } else {
+                            palette[nibble as usize]
+                        }
+                    })
+                    .collect()
+            }
+
+            PixelFormat::GxI8 => pixels
+                .iter()
+                .flat_map(|&pixel| decode_intensity_pixel(pixel))
+                .collect(),
+
+            PixelFormat::GxIA4 => pixels
+                .iter()
+                .flat_map(|&pixel| decode_ia4_pixel(pixel))
+                .collect(),
+
+            PixelFormat::GxC8 => pixels
+                .iter()
+                .flat_map(|&index| palette[index as usize])
+                .collect(),
+
+            PixelFormat::GxIA8 => pixels.chunks_exact(2).flat_map(decode_ia8_pixel).collect(),
+
+            PixelFormat::GxRGB565 => pixels
+                .chunks_exact(2)
+                .flat_map(decode_rgb565_be_pixel)
+                .collect(),
+
+            PixelFormat::GxRGB5A3 => pixels
+                .chunks_exact(2)
+                .flat_map(decode_rgb5a3_pixel)
+                .collect(),
+
+            PixelFormat::GxC14X2 => pixels
+                .chunks_exact(2)
+                .flat_map(|pixel| {
+                    let index = u16::from_be_bytes([pixel[0], pixel[1]]) & 0x3FFF;
+                    palette[index as usize]
+                })
+                .collect(),
+
+            PixelFormat::GxRGBA8 => pixels
+                .chunks_exact(4)
+                .flat_map(decode_argb8888_pixel)
+                .collect(),
+
+            _ => {
+                return Err(TextureError::UnsupportedFormat(format!(
+                    "Not a GX pixel format: {}",
+                    format
+                )))
+            }
+        };
+
+        Ok(output)
+    }
+
+    /// Decode PS2 indexed pixels with their CLUT
+    fn decode_ps2(
+        pixels: &[u8],
+        format: PixelFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, TextureError> {
+        let image_size = format.get_slice_pitch(width, height) as usize;
+        let clut_size = format.get_palette_size() as usize;
+
+        if pixels.len() < image_size + clut_size {
+            return Err(TextureError::DimensionMismatch(format!(
+                "Image data too small for {}: {} < {}",
+                format,
+                pixels.len(),
+                image_size + clut_size
+            )));
         }
+
+        let (pixels, clut) = pixels.split_at(image_size);
+        let clut: Vec<[u8; 4]> = clut[..clut_size]
+            .chunks_exact(4)
+            .map(decode_ps2_clut_pixel)
+            .collect();
+
+        let output = if format == PixelFormat::Ps2PSMT8 {
+            pixels
+                .iter()
+                .flat_map(|&index| clut[shuffle_ps2_clut_index(index as usize)])
+                .collect()
+        } else {
+            let row_pitch = format.get_row_pitch(width) as usize;
+
+            (0..height as usize)
+                .flat_map(|y| (0..width as usize).map(move |x| (y, x)))
+                .flat_map(|(y, x)| {
+                    let byte = pixels[y * row_pitch + x / 2];
+                    let index = if x % 2 == 0 { byte & 0xF } else { byte >> 4 };
+
+                    clut[index as usize]
+                })
+                .collect()
+        };
+
+        Ok(output)
     }
 }
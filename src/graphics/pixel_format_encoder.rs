@@ -1,18 +1,29 @@
-use std::error::Error;
-
 use super::{
-    crate_directxtex_utility::DirectXTexUtility, pixel_encoders, pixel_format::PixelFormat,
+    astc_encoder::encode_astc,
+    bc_encoder::{encode_bc6h, encode_bc7, CompressionOptions},
+    color_quantizer::ColorQuantizer,
+    crate_directxtex_utility::DirectXTexUtility,
+    pixel_decoders::{
+        decode_ps2_clut_pixel, decode_rgb5a3_pixel, shuffle_ps2_clut_index, swap_gx_cmpr_block,
+    },
+    pixel_encoders,
+    pixel_format::{PixelDataType, PixelFormat, PixelFormatInfo},
 };
+use crate::error::TextureError;
 
 pub struct PixelEncoder;
 
 impl PixelEncoder {
+    /// Encode RGBA8 pixels, the data type of the format info picks signed BC6H
     pub fn encode(
         pixels: &[u8],
-        pixel_format: PixelFormat,
+        pixel_format_info: PixelFormatInfo,
         width: u32,
         height: u32,
-    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        options: &CompressionOptions,
+    ) -> Result<Vec<u8>, TextureError> {
+        let pixel_format = pixel_format_info.pixel_format;
+
         match pixel_format {
             PixelFormat::R8G8B8A8 => {
                 // No conversion needed
@@ -81,13 +92,17 @@ impl PixelEncoder {
                 )?;
                 Ok(output)
             }
+            PixelFormat::BC6H => {
+                let pixels: Vec<f32> = pixels.iter().map(|&c| c as f32 / 255.0).collect();
+                let signed = pixel_format_info.pixel_data_type == PixelDataType::SFloat;
+                encode_bc6h(&pixels, width, height, signed, options)
+            }
+            PixelFormat::BC7 => encode_bc7(pixels, width, height, options),
             PixelFormat::BC1
             | PixelFormat::BC2
             | PixelFormat::BC3
             | PixelFormat::BC4
-            | PixelFormat::BC5
-            | PixelFormat::BC6H
-            | PixelFormat::BC7 => {
+            | PixelFormat::BC5 => {
                 // Convert from BC formats using DirectXTex
                 // BC formats are in linear space
                 DirectXTexUtility::compress_rgba_to_bc(
@@ -95,9 +110,205 @@ impl PixelEncoder {
                     width as usize,
                     height as usize,
                     pixels.to_vec(),
+                    options,
                 )
             }
-            _ => Err("Unsupported pixel format".into()),
+            PixelFormat::GxI4
+            | PixelFormat::GxI8
+            | PixelFormat::GxIA4
+            | PixelFormat::GxIA8
+            | PixelFormat::GxRGB565
+            | PixelFormat::GxRGB5A3
+            | PixelFormat::GxRGBA8
+            | PixelFormat::GxCMPR
+            | PixelFormat::GxC4
+            | PixelFormat::GxC8
+            | PixelFormat::GxC14X2 => Self::encode_gx(pixels, pixel_format, width, height, options),
+            PixelFormat::Ps2PSMT4 | PixelFormat::Ps2PSMT8 => {
+                Self::encode_ps2(pixels, pixel_format, width)
+            }
+            _ if pixel_format.is_astc() => {
+                encode_astc(pixels, pixel_format, width, height, options)
+            }
+            _ => Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported pixel format: {}",
+                pixel_format
+            ))),
+        }
+    }
+
+    /// Encode GameCube / Wii pixels, before tiling
+    fn encode_gx(
+        pixels: &[u8],
+        pixel_format: PixelFormat,
+        width: u32,
+        height: u32,
+        options: &CompressionOptions,
+    ) -> Result<Vec<u8>, TextureError> {
+        let rgba8_pixels = pixels
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]]);
+
+        let output = match pixel_format {
+            PixelFormat::GxCMPR => {
+                let bc1_pixels = DirectXTexUtility::compress_rgba_to_bc(
+                    PixelFormat::BC1,
+                    width as usize,
+                    height as usize,
+                    pixels.to_vec(),
+                    options,
+                )?;
+
+                bc1_pixels
+                    .chunks_exact(8)
+                    .flat_map(swap_gx_cmpr_block)
+                    .collect()
+            }
+
+            PixelFormat::GxI4 => {
+                let intensities: Vec<u8> = rgba8_pixels
+                    .map(|pixel| pixel_encoders::encode_intensity(&pixel))
+                    .collect();
+
+                Self::pack_nibbles(&intensities, width, false, |intensity| {
+                    ((intensity as u32 * 15 + 127) / 255) as u8
+                })
+            }
+
+            PixelFormat::GxI8 => rgba8_pixels
+                .map(|pixel| pixel_encoders::encode_intensity(&pixel))
+                .collect(),
+
+            PixelFormat::GxIA4 => rgba8_pixels
+                .map(|pixel| pixel_encoders::encode_ia4_pixel(&pixel))
+                .collect(),
+
+            PixelFormat::GxIA8 => rgba8_pixels
+                .flat_map(|pixel| pixel_encoders::encode_ia8_pixel(&pixel))
+                .collect(),
+
+            PixelFormat::GxRGB565 => rgba8_pixels
+                .flat_map(|pixel| pixel_encoders::encode_rgb565_be_pixel(&pixel))
+                .collect(),
+
+            PixelFormat::GxRGB5A3 => rgba8_pixels
+                .flat_map(|pixel| pixel_encoders::encode_rgb5a3_pixel(&pixel))
+                .collect(),
+
+            PixelFormat::GxRGBA8 => rgba8_pixels
+                .flat_map(|pixel| pixel_encoders::encode_argb8888_pixel(&pixel))
+                .collect(),
+
+            PixelFormat::GxC4 | PixelFormat::GxC8 | PixelFormat::GxC14X2 => {
+                // Quantize the colors the TLUT can actually store
+                let tlut_pixels: Vec<u8> = rgba8_pixels
+                    .flat_map(|pixel| {
+                        decode_rgb5a3_pixel(&pixel_encoders::encode_rgb5a3_pixel(&pixel))
+                    })
+                    .collect();
+
+                let palette_entries = pixel_format.get_palette_size() as usize / 2;
+                let (palette, indices) = ColorQuantizer::quantize(&tlut_pixels, palette_entries);
+
+                let mut output = match pixel_format {
+                    PixelFormat::GxC4 => {
+                        let indices: Vec<u8> = indices.iter().map(|&index| index as u8).collect();
+                        Self::pack_nibbles(&indices, width, false, |index| index)
+                    }
+                    PixelFormat::GxC8 => indices.iter().map(|&index| index as u8).collect(),
+                    _ => indices
+                        .iter()
+                        .flat_map(|index| index.to_be_bytes())
+                        .collect(),
+                };
+
+                let mut tlut: Vec<u8> = palette
+                    .iter()
+                    .flat_map(pixel_encoders::encode_rgb5a3_pixel)
+                    .collect();
+                tlut.resize(palette_entries * 2, 0);
+
+                output.extend(tlut);
+                output
+            }
+
+            _ => {
+                return Err(TextureError::UnsupportedFormat(format!(
+                    "Not a GX pixel format: {}",
+                    pixel_format
+                )))
+            }
+        };
+
+        Ok(output)
+    }
+
+    /// Encode PS2 indexed pixels, followed by their CLUT
+    fn encode_ps2(
+        pixels: &[u8],
+        pixel_format: PixelFormat,
+        width: u32,
+    ) -> Result<Vec<u8>, TextureError> {
+        // Quantize the colors the CLUT can actually store
+        let clut_pixels: Vec<u8> = pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                decode_ps2_clut_pixel(&pixel_encoders::encode_ps2_clut_pixel(&[
+                    pixel[0], pixel[1], pixel[2], pixel[3],
+                ]))
+            })
+            .collect();
+
+        let clut_entries = pixel_format.get_palette_size() as usize / 4;
+        let (palette, indices) = ColorQuantizer::quantize(&clut_pixels, clut_entries);
+
+        let mut output = if pixel_format == PixelFormat::Ps2PSMT8 {
+            indices.iter().map(|&index| index as u8).collect()
+        } else {
+            let indices: Vec<u8> = indices.iter().map(|&index| index as u8).collect();
+            Self::pack_nibbles(&indices, width, true, |index| index)
+        };
+
+        let mut clut = vec![0; clut_entries * 4];
+
+        for (index, color) in palette.iter().enumerate() {
+            let index = if clut_entries == 256 {
+                shuffle_ps2_clut_index(index)
+            } else {
+                index
+            };
+
+            clut[index * 4..index * 4 + 4]
+                .copy_from_slice(&pixel_encoders::encode_ps2_clut_pixel(color));
+        }
+
+        output.extend(clut);
+        Ok(output)
+    }
+
+    /// Pack 4 bit values in rows of whole bytes
+    fn pack_nibbles(
+        values: &[u8],
+        width: u32,
+        low_nibble_first: bool,
+        to_nibble: impl Fn(u8) -> u8,
+    ) -> Vec<u8> {
+        let width = width as usize;
+        let row_pitch = width.div_ceil(2);
+        let height = values.len() / width.max(1);
+        let mut output = vec![0; row_pitch * height];
+
+        for (index, &value) in values.iter().enumerate() {
+            let (x, y) = (index % width, index / width);
+            let shift = if (x % 2 == 0) != low_nibble_first {
+                4
+            } else {
+                0
+            };
+
+            output[y * row_pitch + x / 2] |= (to_nibble(value) & 0xF) << shift;
         }
+
+        output
     }
 }
@@ -0,0 +1,280 @@
+use crate::graphics::pixel_format::PixelFormat;
+
+use super::{Platform, Swizzable};
+use crate::error::TextureError;
+
+// GameCube / Wii textures are stored in 32 byte tiles, laid out left to right, top to bottom.
+// Tiles are 8x8 pixels for 4 bit formats, 8x4 for 8 bit and 4x4 for 16 and 32 bit ones.
+// 32 bit tiles take two passes: the first 32 bytes hold the AR pairs, the next 32 the GB pairs.
+// CMPR tiles are 2x2 DXT1 blocks.
+const TILE_SIZE_IN_BYTES: usize = 32;
+
+/// GameCube and Wii GX texture tiling
+pub struct GXSwizzler {
+    pub platform: Platform,
+}
+
+impl Default for GXSwizzler {
+    fn default() -> Self {
+        Self {
+            platform: Platform::Wii,
+        }
+    }
+}
+
+/// Tile layout in elements. An element is a pixel, a byte of two 4 bit pixels or a CMPR block.
+struct TileLayout {
+    /// Element size in pixels
+    element_width: u32,
+    element_height: u32,
+    element_size: usize,
+    /// Tile size in elements
+    tile_width: usize,
+    tile_height: usize,
+    two_pass: bool,
+}
+
+impl Swizzable for GXSwizzler {
+    fn swizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+        let palette_size = pixel_format.get_palette_size() as usize;
+
+        if pixels.len() < linear_size + palette_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                linear_size + palette_size
+            )));
+        }
+
+        let swizzled = swizzle_tiles(&pixels[..linear_size], width, height, pixel_format)?;
+
+        if swizzled.len() + palette_size > pixels.len() {
+            return Err(TextureError::Swizzle(format!(
+                "Buffer too small for the tiled surface: {} < {}",
+                pixels.len(),
+                swizzled.len() + palette_size
+            )));
+        }
+
+        // Paletted formats keep their TLUT right after the pixels
+        pixels.copy_within(linear_size..linear_size + palette_size, swizzled.len());
+        pixels[..swizzled.len()].copy_from_slice(&swizzled);
+        Ok(())
+    }
+
+    fn deswizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        let surface_size = get_surface_size(width, height, pixel_format)?;
+        let palette_size = pixel_format.get_palette_size() as usize;
+
+        if pixels.len() < surface_size + palette_size {
+            return Err(TextureError::Swizzle(format!(
+                "Tiled data too small: {} < {}",
+                pixels.len(),
+                surface_size + palette_size
+            )));
+        }
+
+        let linear = deswizzle_tiles(pixels, width, height, pixel_format)?;
+
+        pixels.copy_within(surface_size..surface_size + palette_size, linear.len());
+        pixels[..linear.len()].copy_from_slice(&linear);
+        Ok(())
+    }
+
+    fn get_platform(&self) -> Platform {
+        self.platform
+    }
+
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        Ok(get_surface_size(width, height, pixel_format)?
+            + pixel_format.get_palette_size() as usize)
+    }
+}
+
+impl TileLayout {
+    fn new(pixel_format: PixelFormat) -> Result<Self, TextureError> {
+        let unsupported = || {
+            TextureError::UnsupportedFormat(format!(
+                "Unsupported GX tiling format: {}",
+                pixel_format
+            ))
+        };
+
+        if pixel_format.is_compressed() {
+            // CMPR is the only block format, other BC formats reuse its layout
+            if pixel_format.get_bytes_per_block() != 8 {
+                return Err(unsupported());
+            }
+
+            return Ok(Self {
+                element_width: 4,
+                element_height: 4,
+                element_size: 8,
+                tile_width: 2,
+                tile_height: 2,
+                two_pass: false,
+            });
+        }
+
+        let (element_width, element_size, tile_width, tile_height) =
+            match pixel_format.get_bits_per_pixel() {
+                4 => (2, 1, 4, 8),
+                8 => (1, 1, 8, 4),
+                16 => (1, 2, 4, 4),
+                32 => (1, 4, 4, 4),
+                _ => return Err(unsupported()),
+            };
+
+        Ok(Self {
+            element_width,
+            element_height: 1,
+            element_size,
+            tile_width,
+            tile_height,
+            two_pass: element_size == 4,
+        })
+    }
+
+    /// Image size in elements
+    fn get_size_in_elements(&self, width: u32, height: u32) -> (usize, usize) {
+        (
+            width.div_ceil(self.element_width) as usize,
+            height.div_ceil(self.element_height) as usize,
+        )
+    }
+
+    /// Image size in tiles
+    fn get_size_in_tiles(&self, width: u32, height: u32) -> (usize, usize) {
+        let (width, height) = self.get_size_in_elements(width, height);
+        (
+            width.div_ceil(self.tile_width),
+            height.div_ceil(self.tile_height),
+        )
+    }
+
+    fn get_tile_size(&self) -> usize {
+        self.tile_width * self.tile_height * self.element_size
+    }
+
+    /// Copy every element between the linear and tiled layouts
+    fn convert(
+        &self,
+        source: &[u8],
+        destination: &mut [u8],
+        width: u32,
+        height: u32,
+        deswizzle: bool,
+    ) {
+        let (width_in_elements, height_in_elements) = self.get_size_in_elements(width, height);
+        let (width_in_tiles, _) = self.get_size_in_tiles(width, height);
+        let tile_size = self.get_tile_size();
+
+        // Two pass tiles split every element in halves
+        let (part_count, part_size) = if self.two_pass {
+            (2, self.element_size / 2)
+        } else {
+            (1, self.element_size)
+        };
+
+        for y in 0..height_in_elements {
+            for x in 0..width_in_elements {
+                let tile_index = (y / self.tile_height) * width_in_tiles + x / self.tile_width;
+                let index_in_tile = (y % self.tile_height) * self.tile_width + x % self.tile_width;
+                let linear = (y * width_in_elements + x) * self.element_size;
+
+                for part in 0..part_count {
+                    let tiled = tile_index * tile_size
+                        + part * TILE_SIZE_IN_BYTES
+                        + index_in_tile * part_size;
+                    let linear = linear + part * part_size;
+
+                    if deswizzle {
+                        destination[linear..linear + part_size]
+                            .copy_from_slice(&source[tiled..tiled + part_size]);
+                    } else {
+                        destination[tiled..tiled + part_size]
+                            .copy_from_slice(&source[linear..linear + part_size]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Size in bytes of a tiled surface, padded to whole tiles. Palettes are not included.
+pub fn get_surface_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<usize, TextureError> {
+    let layout = TileLayout::new(pixel_format)?;
+    let (width_in_tiles, height_in_tiles) = layout.get_size_in_tiles(width, height);
+
+    Ok(width_in_tiles * height_in_tiles * layout.get_tile_size())
+}
+
+/// Tile a linear image into a new, padded surface
+pub fn swizzle_tiles(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let layout = TileLayout::new(pixel_format)?;
+    let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if linear.len() < linear_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            linear.len(),
+            linear_size
+        )));
+    }
+
+    let mut tiled = vec![0; get_surface_size(width, height, pixel_format)?];
+    layout.convert(linear, &mut tiled, width, height, false);
+
+    Ok(tiled)
+}
+
+/// Untile a surface into a tightly packed linear image
+pub fn deswizzle_tiles(
+    tiled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let layout = TileLayout::new(pixel_format)?;
+    let surface_size = get_surface_size(width, height, pixel_format)?;
+
+    if tiled.len() < surface_size {
+        return Err(TextureError::Swizzle(format!(
+            "Tiled data too small: {} < {}",
+            tiled.len(),
+            surface_size
+        )));
+    }
+
+    let mut linear = vec![0; pixel_format.get_slice_pitch(width, height) as usize];
+    layout.convert(tiled, &mut linear, width, height, true);
+
+    Ok(linear)
+}
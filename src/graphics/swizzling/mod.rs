@@ -1,10 +1,19 @@
-use std::{error::Error, fmt};
+use std::{fmt, str::FromStr};
 
-use super::pixel_format::PixelFormat;
+use super::{
+    pixel_format::PixelFormat,
+    texture::{Image, TexMetadata, Texture},
+};
+use crate::error::TextureError;
 
+pub mod gx;
+pub mod morton;
+pub mod ps2;
 pub mod ps4;
 pub mod psvita;
+pub mod switch;
 pub mod swizzle_manager;
+pub mod wii_u;
 pub mod xbox_360;
 
 /// Trait for swizzling and deswizzling images
@@ -16,15 +25,116 @@ pub trait Swizzable {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<(), TextureError>;
     fn deswizzle(
         &self,
         pixels: &mut [u8],
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<(), TextureError>;
     fn get_platform(&self) -> Platform;
+
+    /// Size in bytes of one swizzled image with its platform padding. Palettes are included.
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        Ok(
+            (pixel_format.get_slice_pitch(width, height) + pixel_format.get_palette_size())
+                as usize,
+        )
+    }
+
+    /// Size in bytes of a whole swizzled texture: every mip, array item and face
+    fn get_texture_size(&self, metadata: &TexMetadata) -> Result<usize, TextureError> {
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        metadata
+            .get_image_sizes()
+            .into_iter()
+            .map(|(width, height)| self.get_surface_size(width, height, pixel_format))
+            .sum()
+    }
+
+    /// Swizzle every image of a texture into one contiguous surface.
+    /// By default the images follow each other in the order of `Texture::images`.
+    fn swizzle_texture(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let mut swizzled = Vec::new();
+
+        for image in &texture.images {
+            let pixel_format = image.pixel_format_info.pixel_format;
+            let linear_size = (pixel_format.get_slice_pitch(image.width, image.height)
+                + pixel_format.get_palette_size()) as usize;
+            let surface_size = self.get_surface_size(image.width, image.height, pixel_format)?;
+
+            let mut pixels = image
+                .pixels
+                .get(..linear_size)
+                .ok_or_else(|| {
+                    TextureError::Swizzle(format!(
+                        "Image data too small: {} < {}",
+                        image.pixels.len(),
+                        linear_size
+                    ))
+                })?
+                .to_vec();
+            pixels.resize(surface_size.max(linear_size), 0);
+
+            self.swizzle(&mut pixels, image.width, image.height, pixel_format)?;
+            pixels.truncate(surface_size);
+            swizzled.extend(pixels);
+        }
+
+        Ok(swizzled)
+    }
+
+    /// Split a contiguous swizzled surface into the linear images of a texture
+    fn deswizzle_texture(
+        &self,
+        data: &[u8],
+        metadata: &TexMetadata,
+    ) -> Result<Texture, TextureError> {
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+        let mut images = Vec::new();
+        let mut offset = 0;
+
+        for (width, height) in metadata.get_image_sizes() {
+            let linear_size = (pixel_format.get_slice_pitch(width, height)
+                + pixel_format.get_palette_size()) as usize;
+            let surface_size = self.get_surface_size(width, height, pixel_format)?;
+
+            let mut pixels = data
+                .get(offset..offset + surface_size)
+                .ok_or_else(|| {
+                    TextureError::Swizzle(format!(
+                        "Swizzled data too small: {} < {}",
+                        data.len(),
+                        offset + surface_size
+                    ))
+                })?
+                .to_vec();
+            pixels.resize(surface_size.max(linear_size), 0);
+
+            self.deswizzle(&mut pixels, width, height, pixel_format)?;
+            pixels.truncate(linear_size);
+            images.push(Image::new(
+                width,
+                height,
+                metadata.pixel_format_info,
+                pixels,
+            ));
+
+            offset += surface_size;
+        }
+
+        Ok(Texture {
+            metadata: *metadata,
+            images,
+        })
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -59,3 +169,26 @@ impl fmt::Display for Platform {
         write!(f, "{}", name)
     }
 }
+
+impl FromStr for Platform {
+    type Err = String;
+
+    /// Parse a platform from its name (case insensitive, spaces ignored), e.g. "ps4" or "Xbox 360"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let platform = match s.to_lowercase().replace([' ', '_', '-'], "").as_str() {
+            "ps4" => Platform::PS4,
+            "psvita" | "vita" => Platform::PSVita,
+            "xbox360" | "x360" => Platform::Xbox360,
+            "ps3" => Platform::PS3,
+            "switch" | "nintendoswitch" => Platform::Switch,
+            "wiiu" => Platform::WiiU,
+            "wii" => Platform::Wii,
+            "xbox" | "xbox1" => Platform::Xbox,
+            "gamecube" | "gc" => Platform::GameCube,
+            "ps2" => Platform::PS2,
+            _ => return Err(format!("Unknown platform: {}", s)),
+        };
+
+        Ok(platform)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::graphics::pixel_format::PixelFormat;
+
+use super::{Platform, Swizzable};
+use crate::error::TextureError;
+
+// PS3 (RSX) and original Xbox (NV2A) swizzled textures use the same Z-order layout.
+// The bits of x and y are interleaved, x first, until the smaller dimension runs out,
+// then the remaining bits of the larger dimension follow as is.
+// Block compressed textures are never swizzled by these GPUs and are left untouched.
+
+/// PS3 and original Xbox Morton (Z-order) swizzling
+pub struct MortonSwizzler {
+    pub platform: Platform,
+}
+
+impl Default for MortonSwizzler {
+    fn default() -> Self {
+        Self {
+            platform: Platform::PS3,
+        }
+    }
+}
+
+impl Swizzable for MortonSwizzler {
+    fn swizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        convert_morton(pixels, width, height, pixel_format, false)
+    }
+
+    fn deswizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        convert_morton(pixels, width, height, pixel_format, true)
+    }
+
+    fn get_platform(&self) -> Platform {
+        self.platform
+    }
+}
+
+/// Index of a pixel in a swizzled power of two surface
+pub fn get_morton_index(x: u32, y: u32, width: u32, height: u32) -> usize {
+    let (mut x, mut y) = (x as usize, y as usize);
+    let (mut width, mut height) = (width, height);
+    let mut index = 0;
+    let mut shift = 0;
+
+    while width > 1 || height > 1 {
+        if width > 1 {
+            index |= (x & 1) << shift;
+            x >>= 1;
+            width >>= 1;
+            shift += 1;
+        }
+        if height > 1 {
+            index |= (y & 1) << shift;
+            y >>= 1;
+            height >>= 1;
+            shift += 1;
+        }
+    }
+
+    index
+}
+
+/// Swizzle a linear image into a new buffer
+pub fn swizzle_morton(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let mut pixels = linear.to_vec();
+    convert_morton(&mut pixels, width, height, pixel_format, false)?;
+    Ok(pixels)
+}
+
+/// Deswizzle an image into a new buffer
+pub fn deswizzle_morton(
+    swizzled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let mut pixels = swizzled.to_vec();
+    convert_morton(&mut pixels, width, height, pixel_format, true)?;
+    Ok(pixels)
+}
+
+fn convert_morton(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    deswizzle: bool,
+) -> Result<(), TextureError> {
+    let image_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if pixels.len() < image_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            pixels.len(),
+            image_size
+        )));
+    }
+
+    if pixel_format.is_compressed() {
+        return Ok(());
+    }
+
+    let bits_per_pixel = pixel_format.get_bits_per_pixel() as usize;
+
+    if bits_per_pixel % 8 != 0 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Unsupported Morton swizzling format: {}",
+            pixel_format
+        )));
+    }
+
+    if !width.is_power_of_two() || !height.is_power_of_two() {
+        return Err(TextureError::Swizzle(format!(
+            "Morton swizzling needs power of two sizes: {}x{}",
+            width, height
+        )));
+    }
+
+    // Paletted formats keep their palette after the pixels
+    let bytes_per_pixel = bits_per_pixel / 8;
+    let source = pixels[..image_size].to_vec();
+
+    for y in 0..height {
+        for x in 0..width {
+            let linear = (y * width + x) as usize * bytes_per_pixel;
+            let swizzled = get_morton_index(x, y, width, height) * bytes_per_pixel;
+
+            let (from, to) = if deswizzle {
+                (swizzled, linear)
+            } else {
+                (linear, swizzled)
+            };
+
+            pixels[to..to + bytes_per_pixel].copy_from_slice(&source[from..from + bytes_per_pixel]);
+        }
+    }
+
+    Ok(())
+}
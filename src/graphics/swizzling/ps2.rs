@@ -0,0 +1,259 @@
+use crate::graphics::pixel_format::PixelFormat;
+
+use super::{Platform, Swizzable};
+use crate::error::TextureError;
+
+// GS local memory - GS User's Manual, 8.3 and https://github.com/PCSX2/pcsx2/blob/master/pcsx2/GS/GSTables.cpp
+// Memory is made of 8 KB pages, 32 blocks of 256 bytes each, split in 4 columns of 64 bytes.
+// A PSMCT32 page is 64x32 pixels, a PSMT8 page 128x64 and a PSMT4 page 128x128.
+// Swizzled textures are the PSMCT32 view of the memory the texture was written to, which is
+// how games upload 4 and 8 bit textures with 32 bit transfers. PSMCT32 textures are unchanged.
+const PAGE_SIZE: usize = 8192;
+const BLOCK_SIZE: usize = 256;
+const COLUMN_SIZE: usize = 64;
+
+const BLOCK_TABLE_32: [[usize; 8]; 4] = [
+    [0, 1, 4, 5, 16, 17, 20, 21],
+    [2, 3, 6, 7, 18, 19, 22, 23],
+    [8, 9, 12, 13, 24, 25, 28, 29],
+    [10, 11, 14, 15, 26, 27, 30, 31],
+];
+
+const BLOCK_TABLE_4: [[usize; 4]; 8] = [
+    [0, 2, 8, 10],
+    [1, 3, 9, 11],
+    [4, 6, 12, 14],
+    [5, 7, 13, 15],
+    [16, 18, 24, 26],
+    [17, 19, 25, 27],
+    [20, 22, 28, 30],
+    [21, 23, 29, 31],
+];
+
+/// Word of every pixel in a 8x2 PSMCT32 column
+const COLUMN_TABLE_32: [[usize; 8]; 2] = [[0, 1, 4, 5, 8, 9, 12, 13], [2, 3, 6, 7, 10, 11, 14, 15]];
+
+/// PS2 GS memory swizzler
+pub struct PS2Swizzler;
+
+/// GS storage format of a texture
+#[derive(Clone, Copy, PartialEq)]
+enum StorageFormat {
+    PSMCT32,
+    PSMT8,
+    PSMT4,
+}
+
+impl Swizzable for PS2Swizzler {
+    fn swizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        convert_ps2(pixels, width, height, pixel_format, false)
+    }
+
+    fn deswizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        convert_ps2(pixels, width, height, pixel_format, true)
+    }
+
+    fn get_platform(&self) -> Platform {
+        Platform::PS2
+    }
+}
+
+impl StorageFormat {
+    fn new(pixel_format: PixelFormat) -> Result<Self, TextureError> {
+        if pixel_format.is_compressed() {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported PS2 swizzling format: {}",
+                pixel_format
+            )));
+        }
+
+        match pixel_format.get_bits_per_pixel() {
+            32 => Ok(StorageFormat::PSMCT32),
+            8 => Ok(StorageFormat::PSMT8),
+            4 => Ok(StorageFormat::PSMT4),
+            _ => Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported PS2 swizzling format: {}",
+                pixel_format
+            ))),
+        }
+    }
+
+    fn get_page_width(self) -> u32 {
+        match self {
+            StorageFormat::PSMCT32 => 64,
+            StorageFormat::PSMT8 | StorageFormat::PSMT4 => 128,
+        }
+    }
+
+    /// Size of the PSMCT32 view of a texture
+    fn get_psmct32_size(self, width: u32, height: u32) -> Result<(u32, u32), TextureError> {
+        let (width_divisor, height_divisor) = match self {
+            StorageFormat::PSMCT32 => (1, 1),
+            StorageFormat::PSMT8 => (2, 2),
+            StorageFormat::PSMT4 => (2, 4),
+        };
+
+        if width % width_divisor != 0 || height % height_divisor != 0 {
+            return Err(TextureError::Swizzle(format!(
+                "PS2 texture size must be a multiple of {}x{}: {}x{}",
+                width_divisor, height_divisor, width, height
+            )));
+        }
+
+        Ok((width / width_divisor, height / height_divisor))
+    }
+
+    /// Address of a pixel in GS memory, in nibbles
+    fn get_address(self, x: u32, y: u32, width_in_pages: u32) -> usize {
+        let (x, y, width_in_pages) = (x as usize, y as usize, width_in_pages as usize);
+
+        match self {
+            StorageFormat::PSMCT32 => 2 * get_psmct32_address(x, y, width_in_pages),
+            StorageFormat::PSMT8 => {
+                let page = (y / 64) * width_in_pages + x / 128;
+                let block = BLOCK_TABLE_32[(y % 64) / 16][(x % 128) / 16];
+                let byte = (y / 2 % 2) + (x / 8 % 2) * 2;
+
+                2 * (page * PAGE_SIZE + block * BLOCK_SIZE + get_column_word_offset(x, y) + byte)
+            }
+            StorageFormat::PSMT4 => {
+                let page = (y / 128) * width_in_pages + x / 128;
+                let block = BLOCK_TABLE_4[(y % 128) / 16][(x % 128) / 32];
+                let nibble = (y / 2 % 2) + (x / 8 % 4) * 2;
+
+                2 * (page * PAGE_SIZE + block * BLOCK_SIZE + get_column_word_offset(x, y)) + nibble
+            }
+        }
+    }
+}
+
+/// Byte address of a PSMCT32 pixel in GS memory
+fn get_psmct32_address(x: usize, y: usize, width_in_pages: usize) -> usize {
+    let page = (y / 32) * width_in_pages + x / 64;
+    let block = BLOCK_TABLE_32[(y % 32) / 8][(x % 64) / 8];
+    let column = (y % 8) / 2;
+
+    page * PAGE_SIZE + block * BLOCK_SIZE + column * COLUMN_SIZE + COLUMN_TABLE_32[y % 2][x % 8] * 4
+}
+
+/// Byte offset in a block of the word holding a 4 or 8 bit pixel. These columns are 4 rows tall,
+/// every other pair of rows is shifted by 4 words.
+fn get_column_word_offset(x: usize, y: usize) -> usize {
+    let column = (y % 16) / 4;
+    let shift = ((y + 2) / 4 % 2) * 4;
+
+    column * COLUMN_SIZE + COLUMN_TABLE_32[y % 2][(x + shift) % 8] * 4
+}
+
+/// Nibble offset of every pixel of a texture in its PSMCT32 view
+fn get_swizzled_offsets(
+    width: u32,
+    height: u32,
+    storage_format: StorageFormat,
+) -> Result<Vec<usize>, TextureError> {
+    let (width_32, height_32) = storage_format.get_psmct32_size(width, height)?;
+    let width_in_pages = width.div_ceil(storage_format.get_page_width());
+    let width_in_pages_32 = width_32.div_ceil(64) as usize;
+
+    // Word address in GS memory -> word index in the PSMCT32 view
+    let mut words = Vec::new();
+
+    for y in 0..height_32 as usize {
+        for x in 0..width_32 as usize {
+            let word = get_psmct32_address(x, y, width_in_pages_32) / 4;
+
+            if word >= words.len() {
+                words.resize(word + 1, None);
+            }
+            words[word] = Some(y * width_32 as usize + x);
+        }
+    }
+
+    let mut offsets = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let address = storage_format.get_address(x, y, width_in_pages);
+            let word = words.get(address / 8).copied().flatten().ok_or_else(|| {
+                TextureError::Swizzle(format!(
+                    "PS2 texture of {}x{} doesn't fit in a {}x{} PSMCT32 transfer",
+                    width, height, width_32, height_32
+                ))
+            })?;
+
+            offsets.push(word * 8 + address % 8);
+        }
+    }
+
+    Ok(offsets)
+}
+
+fn convert_ps2(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    deswizzle: bool,
+) -> Result<(), TextureError> {
+    let storage_format = StorageFormat::new(pixel_format)?;
+    let image_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if pixels.len() < image_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            pixels.len(),
+            image_size
+        )));
+    }
+
+    if storage_format == StorageFormat::PSMCT32 {
+        return Ok(());
+    }
+
+    // The CLUT of paletted formats stays after the pixels, the PSMCT32 view has the same size
+    let offsets = get_swizzled_offsets(width, height, storage_format)?;
+    let source = pixels[..image_size].to_vec();
+    let get_nibble = |offset: usize| (source[offset / 2] >> ((offset % 2) * 4)) & 0xF;
+
+    match storage_format {
+        StorageFormat::PSMT8 => {
+            for (linear, &swizzled) in offsets.iter().enumerate() {
+                let swizzled = swizzled / 2;
+
+                if deswizzle {
+                    pixels[linear] = source[swizzled];
+                } else {
+                    pixels[swizzled] = source[linear];
+                }
+            }
+        }
+        _ => {
+            // 4 bit pixels, the first one in the low nibble
+            pixels[..image_size].fill(0);
+
+            for (linear, &swizzled) in offsets.iter().enumerate() {
+                let (from, to) = if deswizzle {
+                    (swizzled, linear)
+                } else {
+                    (linear, swizzled)
+                };
+
+                pixels[to / 2] |= get_nibble(from) << ((to % 2) * 4);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,10 +1,19 @@
-use std::error::Error;
-
-use crate::graphics::pixel_format::PixelFormat;
+use crate::graphics::{
+    pixel_format::PixelFormat,
+    texture::{Image, TexDimension, TexMetadata, Texture},
+};
 
 use super::Swizzable;
+use crate::codecs::reorder_mip_major;
+use crate::error::TextureError;
 
 // ReverseBox - https://github.com/bartlomiejduda/ReverseBox/tree/main/reversebox/image/swizzling
+// Surfaces are made of 8x8 tiles of pixels or compressed blocks, in Morton order inside a tile.
+// Sizes that are not a multiple of 8 blocks are padded to whole tiles.
+// Whole textures are stored like GNF: mip levels one after the other, each with the surfaces
+// of every array item and face.
+const TILE_SIZE_IN_BLOCKS: u32 = 8;
+
 pub struct PS4Swizzler;
 
 impl Swizzable for PS4Swizzler {
@@ -14,16 +23,28 @@ impl Swizzable for PS4Swizzler {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        swizzle_ps4(
-            pixels,
-            width,
-            height,
-            pixel_format.get_block_width(),
-            pixel_format.get_block_height(),
-            pixel_format.get_bytes_per_block(),
-        );
+    ) -> Result<(), TextureError> {
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+        if pixels.len() < linear_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                linear_size
+            )));
+        }
 
+        let swizzled = swizzle_surface(&pixels[..linear_size], width, height, pixel_format)?;
+
+        if swizzled.len() > pixels.len() {
+            return Err(TextureError::Swizzle(format!(
+                "Buffer too small for the swizzled surface: {} < {}",
+                pixels.len(),
+                swizzled.len()
+            )));
+        }
+
+        pixels[..swizzled.len()].copy_from_slice(&swizzled);
         Ok(())
     }
 
@@ -33,22 +54,120 @@ impl Swizzable for PS4Swizzler {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        unswizzle_ps4(
-            pixels,
-            width,
-            height,
-            pixel_format.get_block_width(),
-            pixel_format.get_block_height(),
-            pixel_format.get_bytes_per_block(),
-        );
+    ) -> Result<(), TextureError> {
+        let linear = deswizzle_surface(pixels, width, height, pixel_format)?;
 
+        pixels[..linear.len()].copy_from_slice(&linear);
         Ok(())
     }
 
     fn get_platform(&self) -> super::Platform {
         super::Platform::PS4
     }
+
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        get_surface_size(width, height, pixel_format)
+    }
+
+    fn get_texture_size(&self, metadata: &TexMetadata) -> Result<usize, TextureError> {
+        validate_dimensions(metadata)?;
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        (0..metadata.mip_levels)
+            .map(|mip| {
+                let (width, height) = get_mip_size(metadata, mip);
+                Ok(get_surface_size(width, height, pixel_format)? * metadata.array_size as usize)
+            })
+            .sum()
+    }
+
+    fn swizzle_texture(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        validate_dimensions(metadata)?;
+
+        let mut swizzled = Vec::with_capacity(self.get_texture_size(metadata)?);
+
+        for mip in 0..metadata.mip_levels {
+            for item in 0..metadata.array_size {
+                let image = texture.get_image(mip, item, 0)?;
+
+                swizzled.extend(swizzle_surface(
+                    &image.pixels,
+                    image.width,
+                    image.height,
+                    metadata.pixel_format_info.pixel_format,
+                )?);
+            }
+        }
+
+        Ok(swizzled)
+    }
+
+    fn deswizzle_texture(
+        &self,
+        data: &[u8],
+        metadata: &TexMetadata,
+    ) -> Result<Texture, TextureError> {
+        validate_dimensions(metadata)?;
+
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+        let mut levels = Vec::with_capacity(metadata.mip_levels as usize);
+        let mut offset = 0;
+
+        for mip in 0..metadata.mip_levels {
+            let (width, height) = get_mip_size(metadata, mip);
+            let surface_size = get_surface_size(width, height, pixel_format)?;
+            let mut images = Vec::with_capacity(metadata.array_size as usize);
+
+            for _ in 0..metadata.array_size {
+                let surface = data.get(offset..offset + surface_size).ok_or_else(|| {
+                    TextureError::Swizzle(format!(
+                        "Swizzled data too small for mip {}: {} < {}",
+                        mip,
+                        data.len(),
+                        offset + surface_size
+                    ))
+                })?;
+
+                images.push(Image::new(
+                    width,
+                    height,
+                    metadata.pixel_format_info,
+                    deswizzle_surface(surface, width, height, pixel_format)?,
+                ));
+                offset += surface_size;
+            }
+
+            levels.push(images);
+        }
+
+        Ok(Texture {
+            metadata: *metadata,
+            images: reorder_mip_major(levels, metadata),
+        })
+    }
+}
+
+fn validate_dimensions(metadata: &TexMetadata) -> Result<(), TextureError> {
+    if metadata.dimensions == TexDimension::Tex3D {
+        return Err(TextureError::UnsupportedFormat(
+            "Swizzling 3D PS4 textures is not supported".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn get_mip_size(metadata: &TexMetadata, mip: u32) -> (u32, u32) {
+    (
+        (metadata.width >> mip).max(1),
+        (metadata.height >> mip).max(1),
+    )
 }
 
 fn calculate_morton_index_ps4(t: u32, input_img_width: u32, input_img_height: u32) -> u32 {
@@ -73,74 +192,126 @@ fn calculate_morton_index_ps4(t: u32, input_img_width: u32, input_img_height: u3
     num4 * input_img_width + num3
 }
 
-fn unswizzle_ps4(
-    image_data: &mut [u8],
-    img_width: u32,
-    img_height: u32,
-    block_width: u32,
-    block_height: u32,
-    block_data_size: u32,
-) {
-    let mut temp_data = image_data.to_vec();
-    let mut source_index = 0;
-    let img_height = img_height / block_height;
-    let img_width = img_width / block_width;
-
-    for y in 0..((img_height + 7) / 8) {
-        for x in 0..((img_width + 7) / 8) {
-            for t in 0..64 {
-                let morton_index = calculate_morton_index_ps4(t, 8, 8);
-                let data_y = morton_index / 8;
-                let data_x = morton_index % 8;
-                if x * 8 + data_x < img_width && y * 8 + data_y < img_height {
-                    let destination_index = (block_data_size
-                        * ((y * 8 + data_y) * img_width + x * 8 + data_x))
-                        as usize;
-                    temp_data[destination_index..destination_index + block_data_size as usize]
-                        .copy_from_slice(
-                            &image_data[source_index..source_index + block_data_size as usize],
-                        );
-                    source_index += block_data_size as usize;
-                }
-            }
-        }
+/// Image size in blocks, padded to whole tiles
+fn get_padded_size_in_blocks(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<(u32, u32), TextureError> {
+    if pixel_format.get_bytes_per_block() == 0 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Unsupported swizzling format: {}",
+            pixel_format
+        )));
     }
 
-    image_data.copy_from_slice(&temp_data);
+    Ok((
+        width
+            .div_ceil(pixel_format.get_block_width())
+            .next_multiple_of(TILE_SIZE_IN_BLOCKS),
+        height
+            .div_ceil(pixel_format.get_block_height())
+            .next_multiple_of(TILE_SIZE_IN_BLOCKS),
+    ))
 }
 
-fn swizzle_ps4(
-    image_data: &mut [u8],
-    img_width: u32,
-    img_height: u32,
-    block_width: u32,
-    block_height: u32,
-    block_data_size: u32,
-) {
-    let mut temp_data = image_data.to_vec();
-    let mut source_index = 0;
-    let img_height = img_height / block_height;
-    let img_width = img_width / block_width;
-
-    for y in 0..((img_height + 7) / 8) {
-        for x in 0..((img_width + 7) / 8) {
-            for t in 0..64 {
-                let morton_index = calculate_morton_index_ps4(t, 8, 8);
-                let data_y = morton_index / 8;
-                let data_x = morton_index % 8;
-                if x * 8 + data_x < img_width && y * 8 + data_y < img_height {
-                    let destination_index = (block_data_size
-                        * ((y * 8 + data_y) * img_width + x * 8 + data_x))
-                        as usize;
-                    temp_data[source_index..source_index + block_data_size as usize]
-                        .copy_from_slice(
-                            &image_data
-                                [destination_index..destination_index + block_data_size as usize],
-                        );
-                    source_index += block_data_size as usize;
+/// Size in bytes of a swizzled surface, padded to whole tiles
+pub fn get_surface_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<usize, TextureError> {
+    let (width_in_blocks, height_in_blocks) =
+        get_padded_size_in_blocks(width, height, pixel_format)?;
+
+    Ok((width_in_blocks * height_in_blocks * pixel_format.get_bytes_per_block()) as usize)
+}
+
+/// Copy every block between the linear and swizzled layouts, padding blocks are skipped
+fn convert_ps4(
+    source: &[u8],
+    destination: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    deswizzle: bool,
+) -> Result<(), TextureError> {
+    let (padded_width, padded_height) = get_padded_size_in_blocks(width, height, pixel_format)?;
+    let width_in_blocks = width.div_ceil(pixel_format.get_block_width());
+    let height_in_blocks = height.div_ceil(pixel_format.get_block_height());
+    let block_size = pixel_format.get_bytes_per_block() as usize;
+    let mut swizzled = 0;
+
+    for y in (0..padded_height).step_by(TILE_SIZE_IN_BLOCKS as usize) {
+        for x in (0..padded_width).step_by(TILE_SIZE_IN_BLOCKS as usize) {
+            for t in 0..TILE_SIZE_IN_BLOCKS * TILE_SIZE_IN_BLOCKS {
+                let morton_index =
+                    calculate_morton_index_ps4(t, TILE_SIZE_IN_BLOCKS, TILE_SIZE_IN_BLOCKS);
+                let block_x = x + morton_index % TILE_SIZE_IN_BLOCKS;
+                let block_y = y + morton_index / TILE_SIZE_IN_BLOCKS;
+
+                if block_x < width_in_blocks && block_y < height_in_blocks {
+                    let linear = (block_y * width_in_blocks + block_x) as usize * block_size;
+
+                    if deswizzle {
+                        destination[linear..linear + block_size]
+                            .copy_from_slice(&source[swizzled..swizzled + block_size]);
+                    } else {
+                        destination[swizzled..swizzled + block_size]
+                            .copy_from_slice(&source[linear..linear + block_size]);
+                    }
                 }
+
+                swizzled += block_size;
             }
         }
     }
-    image_data.copy_from_slice(&temp_data);
+
+    Ok(())
+}
+
+/// Swizzle a linear image into a new surface, padded to whole tiles
+pub fn swizzle_surface(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if linear.len() < linear_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            linear.len(),
+            linear_size
+        )));
+    }
+
+    let mut swizzled = vec![0; get_surface_size(width, height, pixel_format)?];
+    convert_ps4(linear, &mut swizzled, width, height, pixel_format, false)?;
+
+    Ok(swizzled)
+}
+
+/// Deswizzle a padded surface into a tightly packed linear image
+pub fn deswizzle_surface(
+    swizzled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let surface_size = get_surface_size(width, height, pixel_format)?;
+
+    if swizzled.len() < surface_size {
+        return Err(TextureError::Swizzle(format!(
+            "Swizzled data too small: {} < {}",
+            swizzled.len(),
+            surface_size
+        )));
+    }
+
+    let mut linear = vec![0; pixel_format.get_slice_pitch(width, height) as usize];
+    convert_ps4(swizzled, &mut linear, width, height, pixel_format, true)?;
+
+    Ok(linear)
 }
@@ -1,11 +1,29 @@
-use std::error::Error;
-
 use crate::graphics::pixel_format::PixelFormat;
 
 use super::Swizzable;
+use crate::error::TextureError;
 
 // ReverseBox - https://github.com/bartlomiejduda/ReverseBox/tree/main/reversebox/image/swizzling
-pub struct PSVitaSwizzler;
+// Swizzled surfaces are in Morton order, y first. Sizes that are not a power of two are padded
+// to the next one. Tiled surfaces are made of 32x32 texel tiles, linear inside a tile.
+// Block compressed formats are swizzled and tiled per 4x4 block, 4 bit formats per texel.
+const TILE_SIZE_IN_TEXELS: u32 = 32;
+
+/// Memory layout of a PS Vita texture
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PSVitaLayout {
+    /// Morton order, padded to powers of two
+    #[default]
+    Swizzled,
+    /// 32x32 texel tiles
+    Tiled,
+}
+
+/// PS Vita GXM texture swizzling
+#[derive(Default)]
+pub struct PSVitaSwizzler {
+    pub layout: PSVitaLayout,
+}
 
 impl Swizzable for PSVitaSwizzler {
     fn swizzle(
@@ -14,18 +32,38 @@ impl Swizzable for PSVitaSwizzler {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        if !is_supported_format(pixel_format) {
-            return Err("Unsupported swizzling format!".into());
+    ) -> Result<(), TextureError> {
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+        let palette_size = pixel_format.get_palette_size() as usize;
+
+        if pixels.len() < linear_size + palette_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                linear_size + palette_size
+            )));
+        }
+
+        let swizzled = match self.layout {
+            PSVitaLayout::Swizzled => {
+                swizzle_surface(&pixels[..linear_size], width, height, pixel_format)?
+            }
+            PSVitaLayout::Tiled => {
+                tile_surface(&pixels[..linear_size], width, height, pixel_format)?
+            }
+        };
+
+        if swizzled.len() + palette_size > pixels.len() {
+            return Err(TextureError::Swizzle(format!(
+                "Buffer too small for the swizzled surface: {} < {}",
+                pixels.len(),
+                swizzled.len() + palette_size
+            )));
         }
-        vita_swizzle(
-            pixels,
-            width as usize,
-            height as usize,
-            pixel_format.get_bytes_per_block() as usize,
-            pixel_format.get_bits_per_pixel() as usize,
-            0,
-        );
+
+        // Paletted formats keep their palette right after the pixels
+        pixels.copy_within(linear_size..linear_size + palette_size, swizzled.len());
+        pixels[..swizzled.len()].copy_from_slice(&swizzled);
         Ok(())
     }
 
@@ -35,120 +73,357 @@ impl Swizzable for PSVitaSwizzler {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        if !is_supported_format(pixel_format) {
-            println!("{}", pixel_format.to_string());
-            return Err("Unsupported swizzling format!".into());
+    ) -> Result<(), TextureError> {
+        let surface_size = self.get_layout_size(width, height, pixel_format)?;
+        let palette_size = pixel_format.get_palette_size() as usize;
+
+        if pixels.len() < surface_size + palette_size {
+            return Err(TextureError::Swizzle(format!(
+                "Swizzled data too small: {} < {}",
+                pixels.len(),
+                surface_size + palette_size
+            )));
         }
 
-        vita_deswizzle(
-            pixels,
-            width as usize,
-            height as usize,
-            pixel_format.get_bytes_per_block() as usize,
-            pixel_format.get_bits_per_pixel() as usize,
-        );
+        let linear = match self.layout {
+            PSVitaLayout::Swizzled => deswizzle_surface(pixels, width, height, pixel_format)?,
+            PSVitaLayout::Tiled => untile_surface(pixels, width, height, pixel_format)?,
+        };
+
+        pixels.copy_within(surface_size..surface_size + palette_size, linear.len());
+        pixels[..linear.len()].copy_from_slice(&linear);
         Ok(())
     }
 
     fn get_platform(&self) -> super::Platform {
         super::Platform::PSVita
     }
+
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        Ok(self.get_layout_size(width, height, pixel_format)?
+            + pixel_format.get_palette_size() as usize)
+    }
 }
 
-fn is_supported_format(pixel_format: PixelFormat) -> bool {
-    match pixel_format {
-        PixelFormat::R8G8B8A8
-        | PixelFormat::B8G8R8A8
-        | PixelFormat::R16G16B16A16
-        | PixelFormat::R32G32B32A32 => true,
-        _ => false,
+impl PSVitaSwizzler {
+    /// Size of the swizzled or tiled surface, without the palette
+    fn get_layout_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        match self.layout {
+            PSVitaLayout::Swizzled => get_surface_size(width, height, pixel_format),
+            PSVitaLayout::Tiled => get_tiled_surface_size(width, height, pixel_format),
+        }
     }
 }
 
-fn vita_deswizzle(
-    image_data: &mut [u8],
-    width: usize,
-    height: usize,
-    bytes_per_pixel: usize,
-    format_bpp: usize,
-) {
-    if bytes_per_pixel >= image_data.len() {
-        return;
+/// Element size in bits. Elements are texels, or 4x4 blocks for compressed formats.
+fn get_element_bits(pixel_format: PixelFormat) -> Result<usize, TextureError> {
+    let bits = if pixel_format.is_compressed() {
+        pixel_format.get_bytes_per_block() * 8
+    } else {
+        pixel_format.get_bits_per_pixel()
+    };
+
+    match bits {
+        4 => Ok(4),
+        bits if bits >= 8 && bits % 8 == 0 => Ok(bits as usize),
+        _ => Err(TextureError::UnsupportedFormat(format!(
+            "Unsupported swizzling format: {}",
+            pixel_format
+        ))),
     }
-    let buffer_size = (format_bpp * width * height) / 8;
-    let mut temp_data = vec![0; buffer_size.max(bytes_per_pixel)];
+}
 
-    let max_u = (width as f64).log2() as usize;
-    let max_v = (height as f64).log2() as usize;
+/// Image size in elements
+fn get_size_in_elements(width: u32, height: u32, pixel_format: PixelFormat) -> (usize, usize) {
+    (
+        width.div_ceil(pixel_format.get_block_width()) as usize,
+        height.div_ceil(pixel_format.get_block_height()) as usize,
+    )
+}
 
-    for j in 0..(width * height) {
-        if j * bytes_per_pixel >= image_data.len() {
-            break;
-        }
-        let mut u = 0;
-        let mut v = 0;
-        let mut orig_coord = j;
-        for k in 0..max_u.max(max_v) {
-            if k < max_v {
-                v |= (orig_coord & 1) << k;
-                orig_coord >>= 1;
-            }
-            if k < max_u {
-                u |= (orig_coord & 1) << k;
-                orig_coord >>= 1;
-            }
+/// Tile size in elements
+fn get_tile_size_in_elements(pixel_format: PixelFormat) -> (usize, usize) {
+    (
+        (TILE_SIZE_IN_TEXELS / pixel_format.get_block_width()) as usize,
+        (TILE_SIZE_IN_TEXELS / pixel_format.get_block_height()) as usize,
+    )
+}
+
+/// Image size in elements, padded to powers of two
+fn get_padded_size_in_elements(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> (usize, usize) {
+    let (width, height) = get_size_in_elements(width, height, pixel_format);
+
+    (width.next_power_of_two(), height.next_power_of_two())
+}
+
+/// Image size in elements, padded to whole tiles
+fn get_tiled_size_in_elements(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> (usize, usize) {
+    let (width, height) = get_size_in_elements(width, height, pixel_format);
+    let (tile_width, tile_height) = get_tile_size_in_elements(pixel_format);
+
+    (
+        width.next_multiple_of(tile_width),
+        height.next_multiple_of(tile_height),
+    )
+}
+
+/// Size in bytes of a swizzled surface, padded to powers of two
+pub fn get_surface_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<usize, TextureError> {
+    let element_bits = get_element_bits(pixel_format)?;
+    let (width, height) = get_padded_size_in_elements(width, height, pixel_format);
+
+    Ok((width * height * element_bits).div_ceil(8))
+}
+
+/// Size in bytes of a tiled surface, padded to whole tiles
+pub fn get_tiled_surface_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<usize, TextureError> {
+    let element_bits = get_element_bits(pixel_format)?;
+    let (width, height) = get_tiled_size_in_elements(width, height, pixel_format);
+
+    Ok((width * height * element_bits).div_ceil(8))
+}
+
+/// Coordinates of the element at a Morton index, y first
+fn get_morton_coordinates(index: usize, max_u: u32, max_v: u32) -> (usize, usize) {
+    let mut u = 0;
+    let mut v = 0;
+    let mut orig_coord = index;
+
+    for k in 0..max_u.max(max_v) {
+        if k < max_v {
+            v |= (orig_coord & 1) << k;
+            orig_coord >>= 1;
         }
-        if u < width && v < height {
-            let src_idx = j * bytes_per_pixel;
-            let dst_idx = (v * width + u) * bytes_per_pixel;
-            temp_data[dst_idx..dst_idx + bytes_per_pixel]
-                .copy_from_slice(&image_data[src_idx..src_idx + bytes_per_pixel]);
+        if k < max_u {
+            u |= (orig_coord & 1) << k;
+            orig_coord >>= 1;
         }
     }
-    image_data.copy_from_slice(&temp_data);
+
+    (u, v)
 }
 
-fn vita_swizzle(
-    image_data: &mut [u8],
-    width: usize,
-    height: usize,
-    bytes_per_pixel: usize,
-    format_bpp: usize,
-    min_buffer_size: usize,
+/// Copy an element, 4 bit elements have the first one in the low nibble
+fn copy_element(
+    source: &[u8],
+    source_index: usize,
+    destination: &mut [u8],
+    destination_index: usize,
+    element_bits: usize,
 ) {
-    if bytes_per_pixel >= image_data.len() {
-        return;
+    if element_bits == 4 {
+        let nibble = (source[source_index / 2] >> ((source_index % 2) * 4)) & 0xF;
+        let shift = (destination_index % 2) * 4;
+
+        destination[destination_index / 2] &= !(0xF << shift);
+        destination[destination_index / 2] |= nibble << shift;
+    } else {
+        let size = element_bits / 8;
+        let (from, to) = (source_index * size, destination_index * size);
+
+        destination[to..to + size].copy_from_slice(&source[from..from + size]);
     }
-    let buffer_size = (format_bpp * width * height) / 8;
-    let mut temp_data = vec![0; buffer_size.max(min_buffer_size)];
+}
 
-    let max_u = (width as f64).log2() as usize;
-    let max_v = (height as f64).log2() as usize;
+/// Copy every element between the linear and swizzled or tiled layouts, padding is skipped.
+/// `positions` are the linear coordinates of every element of the surface, in order.
+fn convert_elements(
+    source: &[u8],
+    destination: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    positions: impl Iterator<Item = (usize, usize)>,
+    deswizzle: bool,
+) -> Result<(), TextureError> {
+    let element_bits = get_element_bits(pixel_format)?;
+    let (width_in_elements, height_in_elements) = get_size_in_elements(width, height, pixel_format);
+    // Linear rows of 4 bit formats start on a byte
+    let row_pitch = pixel_format.get_row_pitch(width) as usize * 8 / element_bits;
 
-    for j in 0..(width * height) {
-        if j * bytes_per_pixel >= image_data.len() {
-            break;
-        }
-        let mut u = 0;
-        let mut v = 0;
-        let mut orig_coord = j;
-        for k in 0..max_u.max(max_v) {
-            if k < max_v {
-                v |= (orig_coord & 1) << k;
-                orig_coord >>= 1;
-            }
-            if k < max_u {
-                u |= (orig_coord & 1) << k;
-                orig_coord >>= 1;
+    for (index, (u, v)) in positions.enumerate() {
+        if u < width_in_elements && v < height_in_elements {
+            let linear = v * row_pitch + u;
+
+            if deswizzle {
+                copy_element(source, index, destination, linear, element_bits);
+            } else {
+                copy_element(source, linear, destination, index, element_bits);
             }
         }
-        if u < width && v < height {
-            let src_idx = (v * width + u) * bytes_per_pixel;
-            let dst_idx = j * bytes_per_pixel;
-            temp_data[dst_idx..dst_idx + bytes_per_pixel]
-                .copy_from_slice(&image_data[src_idx..src_idx + bytes_per_pixel]);
-        }
     }
-    image_data.copy_from_slice(&temp_data);
+
+    Ok(())
+}
+
+fn convert_swizzled(
+    source: &[u8],
+    destination: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    deswizzle: bool,
+) -> Result<(), TextureError> {
+    let (padded_width, padded_height) = get_padded_size_in_elements(width, height, pixel_format);
+    let max_u = padded_width.ilog2();
+    let max_v = padded_height.ilog2();
+
+    convert_elements(
+        source,
+        destination,
+        width,
+        height,
+        pixel_format,
+        (0..padded_width * padded_height).map(|index| get_morton_coordinates(index, max_u, max_v)),
+        deswizzle,
+    )
+}
+
+fn convert_tiled(
+    source: &[u8],
+    destination: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    deswizzle: bool,
+) -> Result<(), TextureError> {
+    let (padded_width, padded_height) = get_tiled_size_in_elements(width, height, pixel_format);
+    let (tile_width, tile_height) = get_tile_size_in_elements(pixel_format);
+    let width_in_tiles = padded_width / tile_width;
+    let tile_size = tile_width * tile_height;
+
+    convert_elements(
+        source,
+        destination,
+        width,
+        height,
+        pixel_format,
+        (0..padded_width * padded_height).map(|index| {
+            let tile = index / tile_size;
+            let index_in_tile = index % tile_size;
+
+            (
+                (tile % width_in_tiles) * tile_width + index_in_tile % tile_width,
+                (tile / width_in_tiles) * tile_height + index_in_tile / tile_width,
+            )
+        }),
+        deswizzle,
+    )
+}
+
+fn check_linear_size(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<(), TextureError> {
+    let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if linear.len() < linear_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            linear.len(),
+            linear_size
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_surface_size(surface: &[u8], surface_size: usize) -> Result<(), TextureError> {
+    if surface.len() < surface_size {
+        return Err(TextureError::Swizzle(format!(
+            "Swizzled data too small: {} < {}",
+            surface.len(),
+            surface_size
+        )));
+    }
+
+    Ok(())
+}
+
+/// Swizzle a linear image into a new surface, padded to powers of two
+pub fn swizzle_surface(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    check_linear_size(linear, width, height, pixel_format)?;
+
+    let mut swizzled = vec![0; get_surface_size(width, height, pixel_format)?];
+    convert_swizzled(linear, &mut swizzled, width, height, pixel_format, false)?;
+
+    Ok(swizzled)
+}
+
+/// Deswizzle a padded surface into a tightly packed linear image
+pub fn deswizzle_surface(
+    swizzled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    check_surface_size(swizzled, get_surface_size(width, height, pixel_format)?)?;
+
+    let mut linear = vec![0; pixel_format.get_slice_pitch(width, height) as usize];
+    convert_swizzled(swizzled, &mut linear, width, height, pixel_format, true)?;
+
+    Ok(linear)
+}
+
+/// Tile a linear image into a new surface, padded to whole tiles
+pub fn tile_surface(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    check_linear_size(linear, width, height, pixel_format)?;
+
+    let mut tiled = vec![0; get_tiled_surface_size(width, height, pixel_format)?];
+    convert_tiled(linear, &mut tiled, width, height, pixel_format, false)?;
+
+    Ok(tiled)
+}
+
+/// Untile a padded surface into a tightly packed linear image
+pub fn untile_surface(
+    tiled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    check_surface_size(tiled, get_tiled_surface_size(width, height, pixel_format)?)?;
+
+    let mut linear = vec![0; pixel_format.get_slice_pitch(width, height) as usize];
+    convert_tiled(tiled, &mut linear, width, height, pixel_format, true)?;
+
+    Ok(linear)
 }
@@ -0,0 +1,512 @@
+use crate::graphics::{
+    pixel_format::PixelFormat,
+    texture::{Image, TexDimension, TexMetadata, Texture},
+};
+
+use super::Swizzable;
+use crate::error::TextureError;
+
+// Tegra X1 block linear layout - https://github.com/ScanMountGoat/tegra_swizzle
+// A GOB (group of bytes) is 64 bytes wide and 8 rows tall. Blocks are columns of GOBs,
+// their height in GOBs is the block height.
+const GOB_WIDTH_IN_BYTES: usize = 64;
+const GOB_HEIGHT_IN_ROWS: usize = 8;
+const GOB_SIZE_IN_BYTES: usize = GOB_WIDTH_IN_BYTES * GOB_HEIGHT_IN_ROWS;
+
+const MAX_BLOCK_HEIGHT: u32 = 32;
+
+/// Nintendo Switch block linear swizzler
+#[derive(Default)]
+pub struct SwitchSwizzler {
+    /// Block height in GOBs (1, 2, 4, 8, 16 or 32) of a single image or the top level of a mip chain.
+    /// `None` picks the height the same way NVN does for the surface size.
+    pub block_height: Option<u32>,
+}
+
+impl Swizzable for SwitchSwizzler {
+    fn swizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        let block_height = self.get_image_block_height(height, pixel_format)?;
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+        if pixels.len() < linear_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                linear_size
+            )));
+        }
+
+        let swizzled = swizzle_block_linear(
+            &pixels[..linear_size],
+            width,
+            height,
+            pixel_format,
+            block_height,
+        )?;
+
+        if swizzled.len() > pixels.len() {
+            return Err(TextureError::Swizzle(format!(
+                "Buffer too small for the swizzled surface: {} < {}",
+                pixels.len(),
+                swizzled.len()
+            )));
+        }
+
+        pixels[..swizzled.len()].copy_from_slice(&swizzled);
+        Ok(())
+    }
+
+    fn deswizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        let block_height = self.get_image_block_height(height, pixel_format)?;
+        let linear = deswizzle_block_linear(pixels, width, height, pixel_format, block_height)?;
+
+        pixels[..linear.len()].copy_from_slice(&linear);
+        Ok(())
+    }
+
+    fn get_platform(&self) -> super::Platform {
+        super::Platform::Switch
+    }
+
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        validate_pixel_format(pixel_format)?;
+        let block_height = self.get_image_block_height(height, pixel_format)?;
+
+        Ok(get_surface_size(width, height, pixel_format, block_height))
+    }
+
+    /// Array items are whole mip chains, aligned to the block size of their top level
+    fn get_texture_size(&self, metadata: &TexMetadata) -> Result<usize, TextureError> {
+        let layer_size = self.get_layer_size(metadata)?;
+
+        Ok(layer_size * metadata.array_size as usize)
+    }
+
+    fn swizzle_texture(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let layer_size = self.get_layer_size(metadata)?;
+        let mut swizzled = Vec::with_capacity(layer_size * metadata.array_size as usize);
+
+        for item in 0..metadata.array_size {
+            let levels = (0..metadata.mip_levels)
+                .map(|mip| Ok(texture.get_image(mip, item, 0)?.pixels.clone()))
+                .collect::<Result<Vec<_>, TextureError>>()?;
+
+            let mut layer = self.swizzle_mip_chain(
+                &levels,
+                metadata.width,
+                metadata.height,
+                metadata.pixel_format_info.pixel_format,
+            )?;
+            layer.resize(layer_size, 0);
+            swizzled.extend(layer);
+        }
+
+        Ok(swizzled)
+    }
+
+    fn deswizzle_texture(
+        &self,
+        data: &[u8],
+        metadata: &TexMetadata,
+    ) -> Result<Texture, TextureError> {
+        let layer_size = self.get_layer_size(metadata)?;
+        let mut images = Vec::new();
+
+        for item in 0..metadata.array_size as usize {
+            let offset = item * layer_size;
+            let layer = data.get(offset..offset + layer_size).ok_or_else(|| {
+                TextureError::Swizzle(format!(
+                    "Swizzled data too small for item {}: {} < {}",
+                    item,
+                    data.len(),
+                    offset + layer_size
+                ))
+            })?;
+
+            let levels = self.deswizzle_mip_chain(
+                layer,
+                metadata.width,
+                metadata.height,
+                metadata.mip_levels,
+                metadata.pixel_format_info.pixel_format,
+            )?;
+
+            for (mip, pixels) in levels.into_iter().enumerate() {
+                let (width, height) = get_mip_size(metadata.width, metadata.height, mip as u32);
+                images.push(Image::new(
+                    width,
+                    height,
+                    metadata.pixel_format_info,
+                    pixels,
+                ));
+            }
+        }
+
+        Ok(Texture {
+            metadata: *metadata,
+            images,
+        })
+    }
+}
+
+impl SwitchSwizzler {
+    /// Block height of a single image, or of the top level of a mip chain
+    fn get_image_block_height(
+        &self,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<u32, TextureError> {
+        match self.block_height {
+            Some(block_height) => {
+                validate_block_height(block_height)?;
+                Ok(block_height)
+            }
+            None => Ok(get_block_height(
+                height.div_ceil(pixel_format.get_block_height()),
+            )),
+        }
+    }
+
+    /// Size of one array item. With more than one item, it is padded to a whole top level block.
+    fn get_layer_size(&self, metadata: &TexMetadata) -> Result<usize, TextureError> {
+        if metadata.dimensions == TexDimension::Tex3D {
+            return Err(TextureError::UnsupportedFormat(
+                "Swizzling 3D Switch textures is not supported".to_string(),
+            ));
+        }
+
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+        validate_pixel_format(pixel_format)?;
+
+        let mip_chain_size = self.get_mip_chain_size(
+            metadata.width,
+            metadata.height,
+            metadata.mip_levels,
+            pixel_format,
+        )?;
+
+        if metadata.array_size > 1 {
+            let block_height = self.get_image_block_height(metadata.height, pixel_format)?;
+            let block_size = GOB_SIZE_IN_BYTES * block_height as usize;
+
+            Ok(mip_chain_size.next_multiple_of(block_size))
+        } else {
+            Ok(mip_chain_size)
+        }
+    }
+
+    /// Size of a whole swizzled mip chain, each level padded to its own block height
+    pub fn get_mip_chain_size(
+        &self,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        let block_height = self.get_image_block_height(height, pixel_format)?;
+
+        Ok((0..mip_levels)
+            .map(|mip| {
+                let (mip_width, mip_height) = get_mip_size(width, height, mip);
+                let height_in_blocks = mip_height.div_ceil(pixel_format.get_block_height());
+
+                get_surface_size(
+                    mip_width,
+                    mip_height,
+                    pixel_format,
+                    get_mip_block_height(height_in_blocks, block_height),
+                )
+            })
+            .sum())
+    }
+
+    /// Swizzle linear mip levels (largest first) into one contiguous surface
+    pub fn swizzle_mip_chain(
+        &self,
+        levels: &[Vec<u8>],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<Vec<u8>, TextureError> {
+        let block_height = self.get_image_block_height(height, pixel_format)?;
+        let mut swizzled = Vec::new();
+
+        for (mip, level) in levels.iter().enumerate() {
+            let (mip_width, mip_height) = get_mip_size(width, height, mip as u32);
+            let height_in_blocks = mip_height.div_ceil(pixel_format.get_block_height());
+
+            swizzled.extend(swizzle_block_linear(
+                level,
+                mip_width,
+                mip_height,
+                pixel_format,
+                get_mip_block_height(height_in_blocks, block_height),
+            )?);
+        }
+
+        Ok(swizzled)
+    }
+
+    /// Split a contiguous swizzled surface into linear mip levels (largest first)
+    pub fn deswizzle_mip_chain(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<Vec<Vec<u8>>, TextureError> {
+        let block_height = self.get_image_block_height(height, pixel_format)?;
+        let mut levels = Vec::with_capacity(mip_levels as usize);
+        let mut offset = 0;
+
+        for mip in 0..mip_levels {
+            let (mip_width, mip_height) = get_mip_size(width, height, mip);
+            let height_in_blocks = mip_height.div_ceil(pixel_format.get_block_height());
+            let mip_block_height = get_mip_block_height(height_in_blocks, block_height);
+            let size = get_surface_size(mip_width, mip_height, pixel_format, mip_block_height);
+
+            let level = data.get(offset..offset + size).ok_or_else(|| {
+                TextureError::Swizzle(format!(
+                    "Swizzled data too small for mip {}: {} < {}",
+                    mip,
+                    data.len(),
+                    offset + size
+                ))
+            })?;
+
+            levels.push(deswizzle_block_linear(
+                level,
+                mip_width,
+                mip_height,
+                pixel_format,
+                mip_block_height,
+            )?);
+
+            offset += size;
+        }
+
+        Ok(levels)
+    }
+}
+
+/// Block height in GOBs NVN picks for the top level, from its height in blocks
+pub fn get_block_height(height_in_blocks: u32) -> u32 {
+    let height_and_half = height_in_blocks + height_in_blocks / 2;
+
+    match height_and_half {
+        128.. => 16,
+        64.. => 8,
+        32.. => 4,
+        16.. => 2,
+        _ => 1,
+    }
+}
+
+/// Smaller mips use smaller blocks, so they aren't mostly padding
+pub fn get_mip_block_height(mip_height_in_blocks: u32, block_height_mip0: u32) -> u32 {
+    let mut block_height = block_height_mip0;
+
+    while block_height > 1 && mip_height_in_blocks <= (block_height / 2) * GOB_HEIGHT_IN_ROWS as u32
+    {
+        block_height /= 2;
+    }
+
+    block_height
+}
+
+/// Size in bytes of a swizzled surface, padded to whole GOBs and blocks
+pub fn get_surface_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    block_height: u32,
+) -> usize {
+    let (width_in_gobs, height_in_block_rows) =
+        get_surface_size_in_gobs(width, height, pixel_format, block_height);
+
+    width_in_gobs * height_in_block_rows * block_height as usize * GOB_SIZE_IN_BYTES
+}
+
+fn get_surface_size_in_gobs(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    block_height: u32,
+) -> (usize, usize) {
+    let width_in_bytes =
+        width.div_ceil(pixel_format.get_block_width()) * pixel_format.get_bytes_per_block();
+    let height_in_blocks = height.div_ceil(pixel_format.get_block_height());
+
+    (
+        (width_in_bytes as usize).div_ceil(GOB_WIDTH_IN_BYTES),
+        (height_in_blocks as usize).div_ceil(GOB_HEIGHT_IN_ROWS * block_height as usize),
+    )
+}
+
+fn validate_pixel_format(pixel_format: PixelFormat) -> Result<(), TextureError> {
+    if pixel_format.get_bytes_per_block() == 0 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Unsupported swizzling format: {}",
+            pixel_format
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_block_height(block_height: u32) -> Result<(), TextureError> {
+    if !block_height.is_power_of_two() || block_height > MAX_BLOCK_HEIGHT {
+        return Err(TextureError::Swizzle(format!(
+            "Invalid Switch block height: {}",
+            block_height
+        )));
+    }
+
+    Ok(())
+}
+
+fn get_mip_size(width: u32, height: u32, mip: u32) -> (u32, u32) {
+    ((width >> mip).max(1), (height >> mip).max(1))
+}
+
+/// Byte offset of a byte at (x, y) in the swizzled surface. x is in bytes, y in block rows.
+fn get_swizzled_offset(x: usize, y: usize, width_in_gobs: usize, block_height: usize) -> usize {
+    let block_size = GOB_SIZE_IN_BYTES * block_height;
+    let block_row_height = GOB_HEIGHT_IN_ROWS * block_height;
+
+    let gob_offset = (y / block_row_height) * block_size * width_in_gobs
+        + (x / GOB_WIDTH_IN_BYTES) * block_size
+        + ((y % block_row_height) / GOB_HEIGHT_IN_ROWS) * GOB_SIZE_IN_BYTES;
+
+    let x = x % GOB_WIDTH_IN_BYTES;
+    let y = y % GOB_HEIGHT_IN_ROWS;
+
+    gob_offset + (x / 32) * 256 + (y / 2) * 64 + ((x % 32) / 16) * 32 + (y % 2) * 16 + (x % 16)
+}
+
+/// Copy every 16 byte GOB sector between the linear and swizzled layouts
+fn convert_block_linear(
+    source: &[u8],
+    destination: &mut [u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    block_height: u32,
+    deswizzle: bool,
+) {
+    let width_in_bytes = (width.div_ceil(pixel_format.get_block_width())
+        * pixel_format.get_bytes_per_block()) as usize;
+    let height_in_blocks = height.div_ceil(pixel_format.get_block_height()) as usize;
+    let (width_in_gobs, _) = get_surface_size_in_gobs(width, height, pixel_format, block_height);
+
+    for y in 0..height_in_blocks {
+        let mut x = 0;
+
+        while x < width_in_bytes {
+            // Sectors are 16 bytes wide and never cross a GOB
+            let count = (16 - x % 16).min(width_in_bytes - x);
+            let linear = y * width_in_bytes + x;
+            let swizzled = get_swizzled_offset(x, y, width_in_gobs, block_height as usize);
+
+            if deswizzle {
+                destination[linear..linear + count]
+                    .copy_from_slice(&source[swizzled..swizzled + count]);
+            } else {
+                destination[swizzled..swizzled + count]
+                    .copy_from_slice(&source[linear..linear + count]);
+            }
+
+            x += count;
+        }
+    }
+}
+
+/// Swizzle a linear image into a new, padded block linear surface
+pub fn swizzle_block_linear(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    block_height: u32,
+) -> Result<Vec<u8>, TextureError> {
+    validate_pixel_format(pixel_format)?;
+    validate_block_height(block_height)?;
+
+    let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if linear.len() < linear_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            linear.len(),
+            linear_size
+        )));
+    }
+
+    let mut swizzled = vec![0; get_surface_size(width, height, pixel_format, block_height)];
+    convert_block_linear(
+        linear,
+        &mut swizzled,
+        width,
+        height,
+        pixel_format,
+        block_height,
+        false,
+    );
+
+    Ok(swizzled)
+}
+
+/// Deswizzle a block linear surface into a tightly packed linear image
+pub fn deswizzle_block_linear(
+    swizzled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    block_height: u32,
+) -> Result<Vec<u8>, TextureError> {
+    validate_pixel_format(pixel_format)?;
+    validate_block_height(block_height)?;
+
+    let surface_size = get_surface_size(width, height, pixel_format, block_height);
+
+    if swizzled.len() < surface_size {
+        return Err(TextureError::Swizzle(format!(
+            "Swizzled data too small: {} < {} (block height {})",
+            swizzled.len(),
+            surface_size,
+            block_height
+        )));
+    }
+
+    let mut linear = vec![0; pixel_format.get_slice_pitch(width, height) as usize];
+    convert_block_linear(
+        swizzled,
+        &mut linear,
+        width,
+        height,
+        pixel_format,
+        block_height,
+        true,
+    );
+
+    Ok(linear)
+}
@@ -1,10 +1,31 @@
-use std::error::Error;
-
-use crate::graphics::pixel_format::PixelFormat;
+use crate::graphics::{
+    pixel_format::PixelFormat,
+    texture::{TexMetadata, Texture},
+};
 
 use super::{
-    ps4::PS4Swizzler, psvita::PSVitaSwizzler, xbox_360::Xbox360Swizzler, Platform, Swizzable,
+    gx::GXSwizzler,
+    morton::MortonSwizzler,
+    ps2::PS2Swizzler,
+    ps4::PS4Swizzler,
+    psvita::PSVitaSwizzler,
+    switch::SwitchSwizzler,
+    wii_u::{GX2TileMode, WiiUSwizzler},
+    xbox_360::Xbox360Swizzler,
+    Platform, Swizzable,
 };
+use crate::error::TextureError;
+
+/// Settings of the built-in swizzlers, see `Swizzler::with_options`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SwizzleOptions {
+    /// Switch block height in GOBs, `None` picks it from the surface size
+    pub switch_block_height: Option<u32>,
+    /// Wii U tile mode of the top level
+    pub wii_u_tile_mode: GX2TileMode,
+    /// Wii U GX2Surface swizzle value
+    pub wii_u_swizzle: u32,
+}
 
 /// Swizzler is a manager for swizzling and deswizzling images. It is also flexible and can be extended with custom swizzles.
 pub struct Swizzler {
@@ -14,12 +35,7 @@ pub struct Swizzler {
 impl Default for Swizzler {
     /// Create a new codec manager with all the built-in codecs registered
     fn default() -> Self {
-        let mut swizzlers = Swizzler::new();
-        swizzlers.register_swizzler(PS4Swizzler);
-        swizzlers.register_swizzler(PSVitaSwizzler);
-        swizzlers.register_swizzler(Xbox360Swizzler);
-
-        swizzlers
+        Swizzler::with_options(&SwizzleOptions::default())
     }
 }
 
@@ -30,6 +46,32 @@ impl Swizzler {
         }
     }
 
+    /// Create a swizzler with all the built-in swizzlers registered and configured
+    pub fn with_options(options: &SwizzleOptions) -> Self {
+        let mut swizzlers = Swizzler::new();
+        swizzlers.register_swizzler(PS4Swizzler);
+        swizzlers.register_swizzler(PSVitaSwizzler::default());
+        swizzlers.register_swizzler(Xbox360Swizzler);
+        swizzlers.register_swizzler(SwitchSwizzler {
+            block_height: options.switch_block_height,
+        });
+        swizzlers.register_swizzler(WiiUSwizzler {
+            tile_mode: options.wii_u_tile_mode,
+            swizzle: options.wii_u_swizzle,
+        });
+        swizzlers.register_swizzler(GXSwizzler::default());
+        swizzlers.register_swizzler(GXSwizzler {
+            platform: Platform::GameCube,
+        });
+        swizzlers.register_swizzler(PS2Swizzler);
+        swizzlers.register_swizzler(MortonSwizzler::default());
+        swizzlers.register_swizzler(MortonSwizzler {
+            platform: Platform::Xbox,
+        });
+
+        swizzlers
+    }
+
     /// Register a new swizzler
     pub fn register_swizzler<T: Swizzable + 'static>(&mut self, swizzler: T) {
         self.swizzlers.push(Box::new(swizzler));
@@ -43,14 +85,16 @@ impl Swizzler {
         height: u32,
         pixel_format: PixelFormat,
         platform: Platform,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), TextureError> {
         for swizzle in &self.swizzlers {
             if swizzle.get_platform() == platform {
                 return swizzle.swizzle(data, width, height, pixel_format);
             }
         }
 
-        return Err("No swizzling found for platform!".into());
+        Err(TextureError::Swizzle(
+            "No swizzling found for platform!".to_string(),
+        ))
     }
 
     /// Deswizzle the image data
@@ -61,13 +105,66 @@ impl Swizzler {
         height: u32,
         pixel_format: PixelFormat,
         platform: Platform,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), TextureError> {
         for swizzle in &self.swizzlers {
             if swizzle.get_platform() == platform {
                 return swizzle.deswizzle(data, width, height, pixel_format);
             }
         }
 
-        return Err("No deswizzling found for platform!".into());
+        Err(TextureError::Swizzle(
+            "No deswizzling found for platform!".to_string(),
+        ))
+    }
+
+    /// Size of one swizzled image, with its platform padding
+    pub fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+        platform: Platform,
+    ) -> Result<usize, TextureError> {
+        self.get_swizzler(platform)?
+            .get_surface_size(width, height, pixel_format)
+    }
+
+    /// Size of a whole swizzled texture, with every mip, array item and face
+    pub fn get_texture_size(
+        &self,
+        metadata: &TexMetadata,
+        platform: Platform,
+    ) -> Result<usize, TextureError> {
+        self.get_swizzler(platform)?.get_texture_size(metadata)
+    }
+
+    /// Swizzle a whole texture into one contiguous surface
+    pub fn swizzle_texture(
+        &self,
+        texture: &Texture,
+        platform: Platform,
+    ) -> Result<Vec<u8>, TextureError> {
+        self.get_swizzler(platform)?.swizzle_texture(texture)
+    }
+
+    /// Split a contiguous swizzled surface into the images of a texture
+    pub fn deswizzle_texture(
+        &self,
+        data: &[u8],
+        metadata: &TexMetadata,
+        platform: Platform,
+    ) -> Result<Texture, TextureError> {
+        self.get_swizzler(platform)?
+            .deswizzle_texture(data, metadata)
+    }
+
+    fn get_swizzler(&self, platform: Platform) -> Result<&dyn Swizzable, TextureError> {
+        self.swizzlers
+            .iter()
+            .find(|swizzler| swizzler.get_platform() == platform)
+            .map(|swizzler| swizzler.as_ref())
+            .ok_or_else(|| {
+                TextureError::Swizzle(format!("No swizzling found for platform {}!", platform))
+            })
     }
 }
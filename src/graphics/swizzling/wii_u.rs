@@ -0,0 +1,636 @@
+use std::{fmt, str::FromStr};
+
+use crate::graphics::{
+    pixel_format::PixelFormat,
+    texture::{Image, TexDimension, TexMetadata, Texture},
+};
+
+use super::Swizzable;
+use crate::error::TextureError;
+
+// AddrLib (R600) as used by GX2 - https://github.com/aboood40091/BFRES-Tool/blob/master/addrlib.py
+// The Wii U GPU has 4 banks and 2 pipes, interleaved every 256 bytes.
+const NUM_BANKS: u64 = 4;
+const NUM_BANK_BITS: u64 = 2;
+const NUM_PIPES: u64 = 2;
+const NUM_PIPE_BITS: u64 = 1;
+const PIPE_INTERLEAVE_BYTES: u64 = 256;
+const NUM_GROUP_BITS: u64 = 8;
+const ROW_SIZE: u64 = 2048;
+const SWAP_SIZE: u64 = 256;
+
+const MICRO_TILE_PIXELS: u64 = 64;
+const BANK_SWAP_ORDER: [u64; 10] = [0, 1, 3, 2, 6, 7, 5, 4, 0, 0];
+
+/// GX2TileMode
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum GX2TileMode {
+    LinearGeneral = 0,
+    LinearAligned = 1,
+    Tiled1DThin1 = 2,
+    Tiled1DThick = 3,
+    #[default]
+    Tiled2DThin1 = 4,
+    Tiled2DThin2 = 5,
+    Tiled2DThin4 = 6,
+    Tiled2DThick = 7,
+    Tiled2BThin1 = 8,
+    Tiled2BThin2 = 9,
+    Tiled2BThin4 = 10,
+    Tiled2BThick = 11,
+    Tiled3DThin1 = 12,
+    Tiled3DThick = 13,
+    Tiled3BThin1 = 14,
+    Tiled3BThick = 15,
+}
+
+/// Wii U GX2 surface tiling
+#[derive(Default)]
+pub struct WiiUSwizzler {
+    pub tile_mode: GX2TileMode,
+    /// GX2Surface swizzle value. Bit 8 is the pipe swizzle, bits 9-10 the bank swizzle.
+    pub swizzle: u32,
+}
+
+/// Layout of a tiled surface, in elements (pixels or compressed blocks)
+struct SurfaceInfo {
+    tile_mode: GX2TileMode,
+    bits_per_element: u64,
+    width: u64,
+    height: u64,
+    pitch: u64,
+    aligned_height: u64,
+    pipe_swizzle: u64,
+    bank_swizzle: u64,
+}
+
+impl Swizzable for WiiUSwizzler {
+    fn swizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+        if pixels.len() < linear_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                linear_size
+            )));
+        }
+
+        let swizzled = self.swizzle_surface(&pixels[..linear_size], width, height, pixel_format)?;
+
+        if swizzled.len() > pixels.len() {
+            return Err(TextureError::Swizzle(format!(
+                "Buffer too small for the tiled surface: {} < {}",
+                pixels.len(),
+                swizzled.len()
+            )));
+        }
+
+        pixels[..swizzled.len()].copy_from_slice(&swizzled);
+        Ok(())
+    }
+
+    fn deswizzle(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<(), TextureError> {
+        let linear = self.deswizzle_surface(pixels, width, height, pixel_format)?;
+
+        pixels[..linear.len()].copy_from_slice(&linear);
+        Ok(())
+    }
+
+    fn get_platform(&self) -> super::Platform {
+        super::Platform::WiiU
+    }
+
+    /// Size in bytes of the tiled surface, including the pitch and height padding
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        let surface = self.get_surface_info(width, height, pixel_format)?;
+
+        Ok(surface.get_size())
+    }
+
+    /// Mip levels smaller than a macro tile fall back to 1D tiling, see `get_mip_tile_mode`
+    fn get_texture_size(&self, metadata: &TexMetadata) -> Result<usize, TextureError> {
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        get_image_mips(metadata)
+            .into_iter()
+            .map(|(width, height, mip)| {
+                self.get_mip_swizzler(width, height, mip, pixel_format)
+                    .get_surface_size(width, height, pixel_format)
+            })
+            .sum()
+    }
+
+    fn swizzle_texture(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let pixel_format = texture.metadata.pixel_format_info.pixel_format;
+        let mut swizzled = Vec::new();
+
+        for (image, (width, height, mip)) in
+            texture.images.iter().zip(get_image_mips(&texture.metadata))
+        {
+            swizzled.extend(
+                self.get_mip_swizzler(width, height, mip, pixel_format)
+                    .swizzle_surface(&image.pixels, width, height, pixel_format)?,
+            );
+        }
+
+        Ok(swizzled)
+    }
+
+    fn deswizzle_texture(
+        &self,
+        data: &[u8],
+        metadata: &TexMetadata,
+    ) -> Result<Texture, TextureError> {
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+        let mut images = Vec::new();
+        let mut offset = 0;
+
+        for (width, height, mip) in get_image_mips(metadata) {
+            let swizzler = self.get_mip_swizzler(width, height, mip, pixel_format);
+            let surface_size = swizzler.get_surface_size(width, height, pixel_format)?;
+
+            let tiled = data.get(offset..offset + surface_size).ok_or_else(|| {
+                TextureError::Swizzle(format!(
+                    "Swizzled data too small for mip {}: {} < {}",
+                    mip,
+                    data.len(),
+                    offset + surface_size
+                ))
+            })?;
+
+            images.push(Image::new(
+                width,
+                height,
+                metadata.pixel_format_info,
+                swizzler.deswizzle_surface(tiled, width, height, pixel_format)?,
+            ));
+
+            offset += surface_size;
+        }
+
+        Ok(Texture {
+            metadata: *metadata,
+            images,
+        })
+    }
+}
+
+impl WiiUSwizzler {
+    /// Tile a linear image into a new, padded surface
+    pub fn swizzle_surface(
+        &self,
+        linear: &[u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<Vec<u8>, TextureError> {
+        let surface = self.get_surface_info(width, height, pixel_format)?;
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+        if linear.len() < linear_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                linear.len(),
+                linear_size
+            )));
+        }
+
+        let mut tiled = vec![0; surface.get_size()];
+        surface.convert(linear, &mut tiled, false);
+
+        Ok(tiled)
+    }
+
+    /// Untile a surface into a tightly packed linear image
+    pub fn deswizzle_surface(
+        &self,
+        tiled: &[u8],
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<Vec<u8>, TextureError> {
+        let surface = self.get_surface_info(width, height, pixel_format)?;
+        let surface_size = surface.get_size();
+
+        if tiled.len() < surface_size {
+            return Err(TextureError::Swizzle(format!(
+                "Tiled data too small: {} < {} ({:?})",
+                tiled.len(),
+                surface_size,
+                self.tile_mode
+            )));
+        }
+
+        let mut linear = vec![0; pixel_format.get_slice_pitch(width, height) as usize];
+        surface.convert(tiled, &mut linear, true);
+
+        Ok(linear)
+    }
+
+    /// Tile mode of a mip level (computeSurfaceMipLevelTileMode). Levels below the top one
+    /// drop the bank swapping, and use 1D tiling once they are smaller than a macro tile.
+    pub fn get_mip_tile_mode(
+        &self,
+        width: u32,
+        height: u32,
+        mip: u32,
+        pixel_format: PixelFormat,
+    ) -> GX2TileMode {
+        if mip == 0 {
+            return self.tile_mode;
+        }
+
+        let tile_mode = self.tile_mode.get_non_bank_swapped();
+        let bpp = match pixel_format.get_bytes_per_block() as u64 * 8 {
+            bits @ (24 | 48 | 96) => bits / 3,
+            bits => bits,
+        };
+
+        let width = (width.div_ceil(pixel_format.get_block_width()) as u64).next_power_of_two();
+        let height = (height.div_ceil(pixel_format.get_block_height()) as u64).next_power_of_two();
+
+        // Thin micro tiles of small elements are grouped to fill a pipe interleave
+        let micro_tile_bytes =
+            (MICRO_TILE_PIXELS * tile_mode.get_thickness() * bpp.next_power_of_two()).div_ceil(8);
+        let width_align_factor = (PIPE_INTERLEAVE_BYTES / micro_tile_bytes.max(1)).max(1);
+
+        let (macro_tile_width, macro_tile_height) = tile_mode.get_macro_tile_size();
+        let is_smaller_than_macro_tile =
+            width < width_align_factor * macro_tile_width || height < macro_tile_height;
+
+        match tile_mode {
+            GX2TileMode::Tiled2DThin1
+            | GX2TileMode::Tiled2DThin2
+            | GX2TileMode::Tiled2DThin4
+            | GX2TileMode::Tiled3DThin1
+                if is_smaller_than_macro_tile =>
+            {
+                GX2TileMode::Tiled1DThin1
+            }
+            GX2TileMode::Tiled2DThick | GX2TileMode::Tiled3DThick if is_smaller_than_macro_tile => {
+                GX2TileMode::Tiled1DThick
+            }
+            _ => tile_mode,
+        }
+    }
+
+    /// Swizzler of a single mip level, with the tile mode of that level
+    fn get_mip_swizzler(
+        &self,
+        width: u32,
+        height: u32,
+        mip: u32,
+        pixel_format: PixelFormat,
+    ) -> WiiUSwizzler {
+        WiiUSwizzler {
+            tile_mode: self.get_mip_tile_mode(width, height, mip, pixel_format),
+            swizzle: self.swizzle,
+        }
+    }
+
+    fn get_surface_info(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<SurfaceInfo, TextureError> {
+        let bits_per_element = pixel_format.get_bytes_per_block() as u64 * 8;
+
+        if bits_per_element == 0 {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Unsupported swizzling format: {}",
+                pixel_format
+            )));
+        }
+
+        if self.tile_mode.get_thickness() > 1 {
+            return Err(TextureError::Swizzle(format!(
+                "Thick tile modes need the whole volume: {:?}",
+                self.tile_mode
+            )));
+        }
+
+        let width = width.div_ceil(pixel_format.get_block_width()) as u64;
+        let height = height.div_ceil(pixel_format.get_block_height()) as u64;
+        let (pitch_align, height_align) = self.tile_mode.get_alignment(bits_per_element);
+
+        Ok(SurfaceInfo {
+            tile_mode: self.tile_mode,
+            bits_per_element,
+            width,
+            height,
+            pitch: width.div_ceil(pitch_align) * pitch_align,
+            aligned_height: height.div_ceil(height_align) * height_align,
+            pipe_swizzle: (self.swizzle as u64 >> 8) & 1,
+            bank_swizzle: (self.swizzle as u64 >> 9) & 3,
+        })
+    }
+}
+
+impl GX2TileMode {
+    fn get_thickness(self) -> u64 {
+        match self {
+            GX2TileMode::Tiled1DThick
+            | GX2TileMode::Tiled2DThick
+            | GX2TileMode::Tiled2BThick
+            | GX2TileMode::Tiled3DThick
+            | GX2TileMode::Tiled3BThick => 4,
+            _ => 1,
+        }
+    }
+
+    /// Same tiling without the bank swapping (convertToNonBankSwappedMode)
+    fn get_non_bank_swapped(self) -> GX2TileMode {
+        match self {
+            GX2TileMode::Tiled2BThin1 => GX2TileMode::Tiled2DThin1,
+            GX2TileMode::Tiled2BThin2 => GX2TileMode::Tiled2DThin2,
+            GX2TileMode::Tiled2BThin4 => GX2TileMode::Tiled2DThin4,
+            GX2TileMode::Tiled2BThick => GX2TileMode::Tiled2DThick,
+            GX2TileMode::Tiled3BThin1 => GX2TileMode::Tiled3DThin1,
+            GX2TileMode::Tiled3BThick => GX2TileMode::Tiled3DThick,
+            tile_mode => tile_mode,
+        }
+    }
+
+    fn is_bank_swapped(self) -> bool {
+        matches!(
+            self,
+            GX2TileMode::Tiled2BThin1
+                | GX2TileMode::Tiled2BThin2
+                | GX2TileMode::Tiled2BThin4
+                | GX2TileMode::Tiled2BThick
+                | GX2TileMode::Tiled3BThin1
+                | GX2TileMode::Tiled3BThick
+        )
+    }
+
+    fn get_macro_tile_aspect_ratio(self) -> u64 {
+        match self {
+            GX2TileMode::Tiled2DThin2 | GX2TileMode::Tiled2BThin2 => 2,
+            GX2TileMode::Tiled2DThin4 | GX2TileMode::Tiled2BThin4 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Macro tile size in elements (width, height)
+    fn get_macro_tile_size(self) -> (u64, u64) {
+        let aspect_ratio = self.get_macro_tile_aspect_ratio();
+        (8 * NUM_BANKS / aspect_ratio, 8 * NUM_PIPES * aspect_ratio)
+    }
+
+    /// Pitch and height alignment in elements
+    fn get_alignment(self, bits_per_element: u64) -> (u64, u64) {
+        let bpp = match bits_per_element {
+            24 | 48 | 96 => bits_per_element / 3,
+            _ => bits_per_element,
+        };
+        let thickness = self.get_thickness();
+
+        match self {
+            GX2TileMode::LinearGeneral => (1, 1),
+            GX2TileMode::LinearAligned => ((PIPE_INTERLEAVE_BYTES * 8 / bpp).max(64), 1),
+            GX2TileMode::Tiled1DThin1 | GX2TileMode::Tiled1DThick => {
+                ((PIPE_INTERLEAVE_BYTES / bpp / thickness).max(8), 8)
+            }
+            _ => {
+                let (macro_tile_width, macro_tile_height) = self.get_macro_tile_size();
+                let pitch_align = macro_tile_width
+                    .max(macro_tile_width * (PIPE_INTERLEAVE_BYTES / bpp / (8 * thickness)));
+
+                (pitch_align, macro_tile_height)
+            }
+        }
+    }
+}
+
+impl SurfaceInfo {
+    fn get_size(&self) -> usize {
+        (self.pitch * self.aligned_height * self.bits_per_element / 8) as usize
+    }
+
+    /// Copy every element between the linear and tiled layouts
+    fn convert(&self, source: &[u8], destination: &mut [u8], deswizzle: bool) {
+        let bytes_per_element = (self.bits_per_element / 8) as usize;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let tiled = self.get_element_address(x, y) as usize;
+                let linear = (y * self.width + x) as usize * bytes_per_element;
+
+                if deswizzle {
+                    destination[linear..linear + bytes_per_element]
+                        .copy_from_slice(&source[tiled..tiled + bytes_per_element]);
+                } else {
+                    destination[tiled..tiled + bytes_per_element]
+                        .copy_from_slice(&source[linear..linear + bytes_per_element]);
+                }
+            }
+        }
+    }
+
+    fn get_element_address(&self, x: u64, y: u64) -> u64 {
+        match self.tile_mode {
+            GX2TileMode::LinearGeneral | GX2TileMode::LinearAligned => {
+                (y * self.pitch + x) * self.bits_per_element / 8
+            }
+            GX2TileMode::Tiled1DThin1 | GX2TileMode::Tiled1DThick => {
+                self.get_micro_tiled_address(x, y)
+            }
+            _ => self.get_macro_tiled_address(x, y),
+        }
+    }
+
+    /// computeSurfaceAddrFromCoordMicroTiled (slice 0)
+    fn get_micro_tiled_address(&self, x: u64, y: u64) -> u64 {
+        let bpp = self.bits_per_element;
+        let micro_tile_bytes = (MICRO_TILE_PIXELS * bpp).div_ceil(8);
+        let micro_tiles_per_row = self.pitch >> 3;
+        let micro_tile_offset = micro_tile_bytes * ((x >> 3) + (y >> 3) * micro_tiles_per_row);
+
+        let pixel_offset = (bpp * get_pixel_index_within_micro_tile(x, y, bpp)) >> 3;
+
+        pixel_offset + micro_tile_offset
+    }
+
+    /// computeSurfaceAddrFromCoordMacroTiled (slice 0, single sample)
+    fn get_macro_tiled_address(&self, x: u64, y: u64) -> u64 {
+        let bpp = self.bits_per_element;
+        let pixel_index = get_pixel_index_within_micro_tile(x, y, bpp);
+        let element_offset = (bpp * pixel_index).div_ceil(8);
+
+        let mut bank_pipe = get_pipe(x, y) + NUM_PIPES * get_bank(x, y);
+        bank_pipe ^= self.pipe_swizzle + NUM_PIPES * self.bank_swizzle;
+        bank_pipe %= NUM_PIPES * NUM_BANKS;
+
+        let pipe = bank_pipe % NUM_PIPES;
+        let mut bank = bank_pipe / NUM_PIPES;
+
+        let (macro_tile_pitch, macro_tile_height) = self.tile_mode.get_macro_tile_size();
+        let macro_tiles_per_row = self.pitch / macro_tile_pitch;
+        let macro_tile_bytes = (bpp * macro_tile_height * macro_tile_pitch).div_ceil(8);
+        let macro_tile_index_x = x / macro_tile_pitch;
+        let macro_tile_index_y = y / macro_tile_height;
+        let macro_tile_offset =
+            (macro_tile_index_x + macro_tiles_per_row * macro_tile_index_y) * macro_tile_bytes;
+
+        if self.tile_mode.is_bank_swapped() {
+            let bank_swap_width = self.get_bank_swapped_width();
+            let swap_index = macro_tile_pitch * macro_tile_index_x / bank_swap_width;
+            bank ^= BANK_SWAP_ORDER[(swap_index & (NUM_BANKS - 1)) as usize];
+        }
+
+        let group_mask = (1 << NUM_GROUP_BITS) - 1;
+        let num_swizzle_bits = NUM_BANK_BITS + NUM_PIPE_BITS;
+
+        let total_offset = element_offset + (macro_tile_offset >> num_swizzle_bits);
+        let offset_high = (total_offset & !group_mask) << num_swizzle_bits;
+        let offset_low = total_offset & group_mask;
+
+        let pipe_bits = pipe << NUM_GROUP_BITS;
+        let bank_bits = bank << (NUM_PIPE_BITS + NUM_GROUP_BITS);
+
+        bank_bits | pipe_bits | offset_low | offset_high
+    }
+
+    /// computeSurfaceBankSwappedWidth (single sample)
+    fn get_bank_swapped_width(&self) -> u64 {
+        let bpp = self.bits_per_element;
+        // AddrLib really counts bits as bytes here, keep it to match the hardware layout
+        let bytes_per_sample = 8 * bpp;
+        // A single sample always fits in one tile slice
+        let bytes_per_tile_slice = bytes_per_sample;
+
+        let factor = self.tile_mode.get_macro_tile_aspect_ratio();
+        let swap_tiles = ((SWAP_SIZE >> 1) / bpp).max(1);
+
+        let swap_width = swap_tiles * 8 * NUM_BANKS;
+        let height_bytes = factor * NUM_PIPES * bpp;
+        let swap_max = NUM_PIPES * NUM_BANKS * ROW_SIZE / height_bytes;
+        let swap_min = PIPE_INTERLEAVE_BYTES * 8 * NUM_BANKS / bytes_per_tile_slice;
+
+        let mut bank_swap_width = swap_max.min(swap_min.max(swap_width));
+
+        while bank_swap_width >= 2 * self.pitch && bank_swap_width > 1 {
+            bank_swap_width >>= 1;
+        }
+
+        bank_swap_width
+    }
+}
+
+/// computePixelIndexWithinMicroTile for thin, non-depth surfaces
+fn get_pixel_index_within_micro_tile(x: u64, y: u64, bpp: u64) -> u64 {
+    let x0 = x & 1;
+    let x1 = (x & 2) >> 1;
+    let x2 = (x & 4) >> 2;
+    let y0 = y & 1;
+    let y1 = (y & 2) >> 1;
+    let y2 = (y & 4) >> 2;
+
+    let bits = match bpp {
+        8 => [x0, x1, x2, y1, y0, y2],
+        16 => [x0, x1, x2, y0, y1, y2],
+        64 => [x0, y0, x1, x2, y1, y2],
+        128 => [y0, x0, x1, x2, y1, y2],
+        _ => [x0, x1, y0, x2, y1, y2],
+    };
+
+    bits.iter()
+        .enumerate()
+        .fold(0, |index, (bit, value)| index | (value << bit))
+}
+
+fn get_pipe(x: u64, y: u64) -> u64 {
+    ((y >> 3) ^ (x >> 3)) & 1
+}
+
+fn get_bank(x: u64, y: u64) -> u64 {
+    let bank_bit0 = ((y / (16 * NUM_PIPES)) ^ (x >> 3)) & 1;
+    bank_bit0 | (2 * (((y / (8 * NUM_PIPES)) ^ (x >> 4)) & 1))
+}
+
+/// Size and mip level of every image, in the order of `Texture::images`
+fn get_image_mips(metadata: &TexMetadata) -> Vec<(u32, u32, u32)> {
+    let mips: Vec<u32> = if metadata.dimensions == TexDimension::Tex3D {
+        (0..metadata.mip_levels)
+            .flat_map(|mip| std::iter::repeat_n(mip, (metadata.depth >> mip).max(1) as usize))
+            .collect()
+    } else {
+        (0..metadata.array_size)
+            .flat_map(|_| 0..metadata.mip_levels)
+            .collect()
+    };
+
+    metadata
+        .get_image_sizes()
+        .into_iter()
+        .zip(mips)
+        .map(|((width, height), mip)| (width, height, mip))
+        .collect()
+}
+
+impl fmt::Display for GX2TileMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for GX2TileMode {
+    type Err = String;
+
+    /// Parse a tile mode from its GX2TileMode value, e.g. "4"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: u32 = s
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid tile mode: {}", s))?;
+
+        GX2TileMode::try_from(value)
+    }
+}
+
+impl TryFrom<u32> for GX2TileMode {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        let tile_mode = match value {
+            0 => GX2TileMode::LinearGeneral,
+            1 => GX2TileMode::LinearAligned,
+            2 => GX2TileMode::Tiled1DThin1,
+            3 => GX2TileMode::Tiled1DThick,
+            4 => GX2TileMode::Tiled2DThin1,
+            5 => GX2TileMode::Tiled2DThin2,
+            6 => GX2TileMode::Tiled2DThin4,
+            7 => GX2TileMode::Tiled2DThick,
+            8 => GX2TileMode::Tiled2BThin1,
+            9 => GX2TileMode::Tiled2BThin2,
+            10 => GX2TileMode::Tiled2BThin4,
+            11 => GX2TileMode::Tiled2BThick,
+            12 => GX2TileMode::Tiled3DThin1,
+            13 => GX2TileMode::Tiled3DThick,
+            14 => GX2TileMode::Tiled3BThin1,
+            15 => GX2TileMode::Tiled3BThick,
+            _ => return Err(format!("Invalid tile mode: {}", value)),
+        };
+
+        Ok(tile_mode)
+    }
+}
@@ -1,12 +1,30 @@
-use std::error::Error;
-
-use crate::graphics::pixel_format::PixelFormat;
+use crate::graphics::{
+    pixel_format::PixelFormat,
+    texture::{Image, TexDimension, TexMetadata, Texture},
+};
 
 use super::Swizzable;
+use crate::error::TextureError;
+
+// Whole textures - https://github.com/xenia-project/xenia/blob/master/src/xenia/gpu/texture_util.cc
+// Tiled surfaces are padded to 32x32 blocks and start on a 4 KB boundary. The top level of every
+// array item comes first, then each mip level of every item. Mips below 16 texels on their smaller
+// side are packed together into a single surface, the mip tail.
+const TILE_SIZE_IN_BLOCKS: u32 = 32;
+const SURFACE_ALIGNMENT: usize = 4096;
 
 // ReverseBox - https://github.com/bartlomiejduda/ReverseBox/tree/main/reversebox/image/swizzling
 pub struct Xbox360Swizzler;
 
+/// A tiled surface of a texture, with the mip levels stored in it
+struct SurfaceLevel {
+    /// Padded size in blocks
+    width_in_blocks: u32,
+    height_in_blocks: u32,
+    /// Offset in blocks of every mip level in the surface
+    mips: Vec<(u32, u32, u32)>,
+}
+
 impl Swizzable for Xbox360Swizzler {
     fn swizzle(
         &self,
@@ -14,14 +32,28 @@ impl Swizzable for Xbox360Swizzler {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        swizzle_x360(
-            pixels,
-            width as usize,
-            height as usize,
-            pixel_format.get_block_width() as usize,
-            pixel_format.get_bytes_per_block() as usize,
-        );
+    ) -> Result<(), TextureError> {
+        let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+        if pixels.len() < linear_size {
+            return Err(TextureError::Swizzle(format!(
+                "Image data too small: {} < {}",
+                pixels.len(),
+                linear_size
+            )));
+        }
+
+        let tiled = swizzle_surface(&pixels[..linear_size], width, height, pixel_format)?;
+
+        if tiled.len() > pixels.len() {
+            return Err(TextureError::Swizzle(format!(
+                "Buffer too small for the tiled surface: {} < {}",
+                pixels.len(),
+                tiled.len()
+            )));
+        }
+
+        pixels[..tiled.len()].copy_from_slice(&tiled);
         Ok(())
     }
 
@@ -31,30 +63,292 @@ impl Swizzable for Xbox360Swizzler {
         width: u32,
         height: u32,
         pixel_format: PixelFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        unswizzle_x360(
-            pixels,
-            width as usize,
-            height as usize,
-            pixel_format.get_block_width() as usize,
-            pixel_format.get_bytes_per_block() as usize,
-        );
+    ) -> Result<(), TextureError> {
+        let linear = deswizzle_surface(pixels, width, height, pixel_format)?;
+
+        pixels[..linear.len()].copy_from_slice(&linear);
         Ok(())
     }
 
     fn get_platform(&self) -> super::Platform {
         super::Platform::Xbox360
     }
+
+    fn get_surface_size(
+        &self,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Result<usize, TextureError> {
+        get_surface_size(width, height, pixel_format)
+    }
+
+    fn get_texture_size(&self, metadata: &TexMetadata) -> Result<usize, TextureError> {
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+
+        Ok(get_surface_levels(metadata)?
+            .iter()
+            .map(|surface| surface.get_size(pixel_format) * metadata.array_size as usize)
+            .sum())
+    }
+
+    fn swizzle_texture(&self, texture: &Texture) -> Result<Vec<u8>, TextureError> {
+        let metadata = &texture.metadata;
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+        let block_height = pixel_format.get_block_height();
+        let bytes_per_block = pixel_format.get_bytes_per_block() as usize;
+        let mut swizzled = Vec::new();
+
+        for surface in get_surface_levels(metadata)? {
+            let row_pitch = surface.width_in_blocks as usize * bytes_per_block;
+
+            for item in 0..metadata.array_size {
+                let mut pixels = vec![0; surface.get_tiled_size(pixel_format)];
+
+                for &(mip, x, y) in &surface.mips {
+                    let image = texture.get_image(mip, item, 0)?;
+                    let image_row_pitch = pixel_format.get_row_pitch(image.width) as usize;
+                    let rows = image.height.div_ceil(block_height) as usize;
+
+                    if image.pixels.len() < image_row_pitch * rows {
+                        return Err(TextureError::Swizzle(format!(
+                            "Image data too small for mip {}: {} < {}",
+                            mip,
+                            image.pixels.len(),
+                            image_row_pitch * rows
+                        )));
+                    }
+
+                    for (row, source) in image
+                        .pixels
+                        .chunks_exact(image_row_pitch)
+                        .take(rows)
+                        .enumerate()
+                    {
+                        let offset = (y as usize + row) * row_pitch + x as usize * bytes_per_block;
+                        pixels[offset..offset + image_row_pitch].copy_from_slice(source);
+                    }
+                }
+
+                swizzle_x360(
+                    &mut pixels,
+                    surface.width_in_blocks as usize,
+                    surface.height_in_blocks as usize,
+                    bytes_per_block,
+                )?;
+                pixels.resize(surface.get_size(pixel_format), 0);
+                swizzled.extend(pixels);
+            }
+        }
+
+        Ok(swizzled)
+    }
+
+    fn deswizzle_texture(
+        &self,
+        data: &[u8],
+        metadata: &TexMetadata,
+    ) -> Result<Texture, TextureError> {
+        let pixel_format = metadata.pixel_format_info.pixel_format;
+        let block_height = pixel_format.get_block_height();
+        let bytes_per_block = pixel_format.get_bytes_per_block() as usize;
+        let mut images =
+            vec![Image::default(); (metadata.array_size * metadata.mip_levels) as usize];
+        let mut offset = 0;
+
+        for surface in get_surface_levels(metadata)? {
+            let row_pitch = surface.width_in_blocks as usize * bytes_per_block;
+            let tiled_size = surface.get_tiled_size(pixel_format);
+
+            for item in 0..metadata.array_size {
+                let mut pixels = data
+                    .get(offset..offset + tiled_size)
+                    .ok_or_else(|| {
+                        TextureError::Swizzle(format!(
+                            "Swizzled data too small: {} < {}",
+                            data.len(),
+                            offset + tiled_size
+                        ))
+                    })?
+                    .to_vec();
+
+                unswizzle_x360(
+                    &mut pixels,
+                    surface.width_in_blocks as usize,
+                    surface.height_in_blocks as usize,
+                    bytes_per_block,
+                )?;
+
+                for &(mip, x, y) in &surface.mips {
+                    let width = (metadata.width >> mip).max(1);
+                    let height = (metadata.height >> mip).max(1);
+                    let image_row_pitch = pixel_format.get_row_pitch(width) as usize;
+                    let rows = height.div_ceil(block_height) as usize;
+                    let mut image_pixels = Vec::with_capacity(image_row_pitch * rows);
+
+                    for row in 0..rows {
+                        let start = (y as usize + row) * row_pitch + x as usize * bytes_per_block;
+                        image_pixels.extend_from_slice(&pixels[start..start + image_row_pitch]);
+                    }
+
+                    images[(item * metadata.mip_levels + mip) as usize] =
+                        Image::new(width, height, metadata.pixel_format_info, image_pixels);
+                }
+
+                offset += surface.get_size(pixel_format);
+            }
+        }
+
+        Ok(Texture {
+            metadata: *metadata,
+            images,
+        })
+    }
+}
+
+impl SurfaceLevel {
+    fn get_tiled_size(&self, pixel_format: PixelFormat) -> usize {
+        (self.width_in_blocks * self.height_in_blocks * pixel_format.get_bytes_per_block()) as usize
+    }
+
+    fn get_size(&self, pixel_format: PixelFormat) -> usize {
+        self.get_tiled_size(pixel_format)
+            .next_multiple_of(SURFACE_ALIGNMENT)
+    }
+}
+
+/// First mip level stored in the mip tail
+pub fn get_packed_mip_base(width: u32, height: u32) -> u32 {
+    let log2_size = width
+        .next_power_of_two()
+        .ilog2()
+        .min(height.next_power_of_two().ilog2());
+
+    log2_size.saturating_sub(4)
+}
+
+/// Offset in texels of a mip level in the mip tail, `None` if the level is not packed
+pub fn get_packed_mip_offset(width: u32, height: u32, mip: u32) -> Option<(u32, u32)> {
+    let log2_width = width.next_power_of_two().ilog2();
+    let log2_height = height.next_power_of_two().ilog2();
+    let packed_mip_base = get_packed_mip_base(width, height);
+
+    if mip < packed_mip_base {
+        return None;
+    }
+
+    let packed_mip = mip - packed_mip_base;
+    let is_wide = log2_width > log2_height;
+
+    let offset = if packed_mip < 3 {
+        16 >> packed_mip
+    } else if is_wide {
+        (1 << (log2_width - packed_mip_base)) >> (packed_mip - 2)
+    } else {
+        (1 << (log2_height - packed_mip_base)) >> (packed_mip - 2)
+    };
+
+    // The first three packed mips are stacked along the smaller side, the others along the larger one
+    match (packed_mip < 3, is_wide) {
+        (true, true) | (false, false) => Some((0, offset)),
+        (true, false) | (false, true) => Some((offset, 0)),
+    }
+}
+
+/// Tiled surfaces of a texture, the top level first. Textures with a single level are never packed.
+fn get_surface_levels(metadata: &TexMetadata) -> Result<Vec<SurfaceLevel>, TextureError> {
+    if metadata.dimensions == TexDimension::Tex3D {
+        return Err(TextureError::UnsupportedFormat(
+            "Swizzling 3D Xbox 360 textures is not supported".to_string(),
+        ));
+    }
+
+    let pixel_format = metadata.pixel_format_info.pixel_format;
+
+    if pixel_format.get_bytes_per_block() == 0 || pixel_format.get_palette_size() > 0 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Unsupported swizzling format: {}",
+            pixel_format
+        )));
+    }
+
+    let (width, height) = (metadata.width, metadata.height);
+    let packed_mip_base = if metadata.mip_levels > 1 {
+        get_packed_mip_base(width, height)
+    } else {
+        u32::MAX
+    };
+
+    let mut surfaces = Vec::new();
+
+    for mip in 0..metadata.mip_levels.min(packed_mip_base.saturating_add(1)) {
+        // Mips are stored with power of two sizes
+        let (surface_width, surface_height) = if mip == 0 {
+            (width, height)
+        } else {
+            (
+                (width.next_power_of_two() >> mip).max(1),
+                (height.next_power_of_two() >> mip).max(1),
+            )
+        };
+
+        let mips = if mip == packed_mip_base {
+            (mip..metadata.mip_levels)
+                .map(|packed_mip| {
+                    let (x, y) = get_packed_mip_offset(width, height, packed_mip).unwrap_or((0, 0));
+                    (
+                        packed_mip,
+                        x / pixel_format.get_block_width(),
+                        y / pixel_format.get_block_height(),
+                    )
+                })
+                .collect()
+        } else {
+            vec![(mip, 0, 0)]
+        };
+
+        // The surface covers every mip stored in it
+        let (mut width_in_blocks, mut height_in_blocks) = (
+            surface_width.div_ceil(pixel_format.get_block_width()),
+            surface_height.div_ceil(pixel_format.get_block_height()),
+        );
+
+        for &(mip, x, y) in &mips {
+            width_in_blocks = width_in_blocks.max(
+                x + (width >> mip)
+                    .max(1)
+                    .div_ceil(pixel_format.get_block_width()),
+            );
+            height_in_blocks = height_in_blocks.max(
+                y + (height >> mip)
+                    .max(1)
+                    .div_ceil(pixel_format.get_block_height()),
+            );
+        }
+
+        surfaces.push(SurfaceLevel {
+            width_in_blocks: width_in_blocks.next_multiple_of(TILE_SIZE_IN_BLOCKS),
+            height_in_blocks: height_in_blocks.next_multiple_of(TILE_SIZE_IN_BLOCKS),
+            mips,
+        });
+    }
+
+    Ok(surfaces)
 }
 
-fn swap_byte_order(image_data: &mut [u8]) {
+fn swap_byte_order(image_data: &mut [u8]) -> Result<(), TextureError> {
     if image_data.len() % 2 != 0 {
-        panic!("Data size must be a multiple of 2 bytes!");
+        return Err(TextureError::Swizzle(format!(
+            "Data size must be a multiple of 2 bytes: {}",
+            image_data.len()
+        )));
     }
 
     for chunk in image_data.chunks_exact_mut(2) {
         chunk.swap(0, 1);
     }
+
+    Ok(())
 }
 
 fn xg_address_2d_tiled_x(
@@ -94,7 +388,7 @@ fn xg_address_2d_tiled_y(
     let macro_y = (offset_macro / (aligned_width >> 5)) << 2;
     let tile = ((offset_tile >> (6 + log_bpp)) & 1) + ((offset_byte & 0x800) >> 10);
     let macro_val = (macro_y + tile) << 3;
-    let micro = (((offset_tile & ((texel_byte_pitch << 6) - 1 & !0x1F))
+    let micro = (((offset_tile & (((texel_byte_pitch << 6) - 1) & !0x1F))
         + ((offset_tile & 0xF) << 1))
         >> (3 + log_bpp))
         & !1;
@@ -102,17 +396,25 @@ fn xg_address_2d_tiled_y(
     macro_val + micro + ((offset_tile & 0x10) >> 4)
 }
 
+/// Move every block between the linear and tiled layouts of a surface padded to whole tiles
 fn convert_x360_image_data(
     image_data: &mut [u8],
-    image_width: usize,
-    image_height: usize,
-    block_pixel_size: usize,
+    width_in_blocks: usize,
+    height_in_blocks: usize,
     texel_byte_pitch: usize,
     swizzle_flag: bool,
-) {
-    let width_in_blocks = image_width / block_pixel_size;
-    let height_in_blocks = image_height / block_pixel_size;
-    let temp_data = image_data.to_vec();
+) -> Result<(), TextureError> {
+    let surface_size = width_in_blocks * height_in_blocks * texel_byte_pitch;
+
+    if image_data.len() < surface_size {
+        return Err(TextureError::Swizzle(format!(
+            "Tiled surface too small: {} < {}",
+            image_data.len(),
+            surface_size
+        )));
+    }
+
+    let temp_data = image_data[..surface_size].to_vec();
 
     for j in 0..height_in_blocks {
         for i in 0..width_in_blocks {
@@ -122,8 +424,11 @@ fn convert_x360_image_data(
             let src_byte_offset = j * width_in_blocks * texel_byte_pitch + i * texel_byte_pitch;
             let dest_byte_offset = y * width_in_blocks * texel_byte_pitch + x * texel_byte_pitch;
 
-            if dest_byte_offset + texel_byte_pitch > image_data.len() {
-                continue;
+            if dest_byte_offset + texel_byte_pitch > surface_size {
+                return Err(TextureError::Swizzle(format!(
+                    "Tiled block ({}, {}) is outside of the {}x{} surface",
+                    x, y, width_in_blocks, height_in_blocks
+                )));
             }
             if !swizzle_flag {
                 image_data[dest_byte_offset..dest_byte_offset + texel_byte_pitch].copy_from_slice(
@@ -136,40 +441,151 @@ fn convert_x360_image_data(
             }
         }
     }
+
+    Ok(())
 }
 
 fn unswizzle_x360(
     image_data: &mut [u8],
-    img_width: usize,
-    img_height: usize,
-    block_pixel_size: usize,
+    width_in_blocks: usize,
+    height_in_blocks: usize,
     texel_byte_pitch: usize,
-) {
-    swap_byte_order(image_data);
+) -> Result<(), TextureError> {
+    swap_byte_order(image_data)?;
     convert_x360_image_data(
         image_data,
-        img_width,
-        img_height,
-        block_pixel_size,
+        width_in_blocks,
+        height_in_blocks,
         texel_byte_pitch,
         false,
-    );
+    )
 }
 
 fn swizzle_x360(
     image_data: &mut [u8],
-    img_width: usize,
-    img_height: usize,
-    block_pixel_size: usize,
+    width_in_blocks: usize,
+    height_in_blocks: usize,
     texel_byte_pitch: usize,
-) {
-    swap_byte_order(image_data);
+) -> Result<(), TextureError> {
+    swap_byte_order(image_data)?;
     convert_x360_image_data(
         image_data,
-        img_width,
-        img_height,
-        block_pixel_size,
+        width_in_blocks,
+        height_in_blocks,
         texel_byte_pitch,
         true,
-    );
+    )
+}
+
+/// Image size in blocks, padded to whole tiles
+fn get_padded_size_in_blocks(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<(usize, usize), TextureError> {
+    if pixel_format.get_bytes_per_block() == 0 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Unsupported swizzling format: {}",
+            pixel_format
+        )));
+    }
+
+    Ok((
+        width
+            .div_ceil(pixel_format.get_block_width())
+            .next_multiple_of(TILE_SIZE_IN_BLOCKS) as usize,
+        height
+            .div_ceil(pixel_format.get_block_height())
+            .next_multiple_of(TILE_SIZE_IN_BLOCKS) as usize,
+    ))
+}
+
+/// Size in bytes of a tiled surface, padded to 32x32 blocks
+pub fn get_surface_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<usize, TextureError> {
+    let (width_in_blocks, height_in_blocks) =
+        get_padded_size_in_blocks(width, height, pixel_format)?;
+
+    Ok(width_in_blocks * height_in_blocks * pixel_format.get_bytes_per_block() as usize)
+}
+
+/// Tile a linear image into a new surface padded to 32x32 blocks
+pub fn swizzle_surface(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let (width_in_blocks, height_in_blocks) =
+        get_padded_size_in_blocks(width, height, pixel_format)?;
+    let linear_size = pixel_format.get_slice_pitch(width, height) as usize;
+
+    if linear.len() < linear_size {
+        return Err(TextureError::Swizzle(format!(
+            "Image data too small: {} < {}",
+            linear.len(),
+            linear_size
+        )));
+    }
+
+    let bytes_per_block = pixel_format.get_bytes_per_block() as usize;
+    let row_pitch = pixel_format.get_row_pitch(width) as usize;
+    let padded_row_pitch = width_in_blocks * bytes_per_block;
+    let mut tiled = vec![0; padded_row_pitch * height_in_blocks];
+
+    for (row, source) in linear[..linear_size].chunks_exact(row_pitch).enumerate() {
+        tiled[row * padded_row_pitch..row * padded_row_pitch + row_pitch].copy_from_slice(source);
+    }
+
+    swizzle_x360(
+        &mut tiled,
+        width_in_blocks,
+        height_in_blocks,
+        bytes_per_block,
+    )?;
+
+    Ok(tiled)
+}
+
+/// Untile a padded surface into a tightly packed linear image
+pub fn deswizzle_surface(
+    tiled: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, TextureError> {
+    let (width_in_blocks, height_in_blocks) =
+        get_padded_size_in_blocks(width, height, pixel_format)?;
+    let surface_size = get_surface_size(width, height, pixel_format)?;
+
+    if tiled.len() < surface_size {
+        return Err(TextureError::Swizzle(format!(
+            "Tiled data too small: {} < {}",
+            tiled.len(),
+            surface_size
+        )));
+    }
+
+    let bytes_per_block = pixel_format.get_bytes_per_block() as usize;
+    let row_pitch = pixel_format.get_row_pitch(width) as usize;
+    let rows = height.div_ceil(pixel_format.get_block_height()) as usize;
+    let padded_row_pitch = width_in_blocks * bytes_per_block;
+
+    let mut padded = tiled[..surface_size].to_vec();
+    unswizzle_x360(
+        &mut padded,
+        width_in_blocks,
+        height_in_blocks,
+        bytes_per_block,
+    )?;
+
+    Ok(padded
+        .chunks_exact(padded_row_pitch)
+        .take(rows)
+        .flat_map(|row| &row[..row_pitch])
+        .copied()
+        .collect())
 }
@@ -1,8 +1,11 @@
-use std::error::Error;
-
 use crate::graphics::pixel_format::PixelFormatInfo;
 
-use super::texture_utility::TextureEffects;
+use super::{
+    mipmap_generator::{MipmapGenerator, MipmapOptions},
+    swizzling::{swizzle_manager::Swizzler, Platform},
+    texture_utility::TextureEffects,
+};
+use crate::error::TextureError;
 
 #[derive(Default)]
 pub struct Texture {
@@ -34,7 +37,7 @@ pub struct TexMetadata {
     pub is_volumemap: bool,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
 pub enum TexDimension {
     Tex1D,
     #[default]
@@ -42,6 +45,47 @@ pub enum TexDimension {
     Tex3D,
 }
 
+impl Image {
+    /// Create an image from tightly packed pixels
+    pub fn new(
+        width: u32,
+        height: u32,
+        pixel_format_info: PixelFormatInfo,
+        pixels: Vec<u8>,
+    ) -> Self {
+        let pixel_format = pixel_format_info.pixel_format;
+
+        Self {
+            width,
+            height,
+            pixel_format_info,
+            row_pitch: pixel_format.get_row_pitch(width),
+            slice_pitch: pixel_format.get_slice_pitch(width, height),
+            pixels,
+        }
+    }
+}
+
+impl TexMetadata {
+    /// Size of every image, in the order of `Texture::images`
+    pub fn get_image_sizes(&self) -> Vec<(u32, u32)> {
+        let get_mip_size = |mip: u32| ((self.width >> mip).max(1), (self.height >> mip).max(1));
+
+        if self.dimensions == TexDimension::Tex3D {
+            (0..self.mip_levels)
+                .flat_map(|mip| {
+                    let depth = (self.depth >> mip).max(1);
+                    std::iter::repeat_n(get_mip_size(mip), depth as usize)
+                })
+                .collect()
+        } else {
+            (0..self.array_size)
+                .flat_map(|_| (0..self.mip_levels).map(get_mip_size))
+                .collect()
+        }
+    }
+}
+
 impl Texture {
     /// Creates a new, empty texture.
     pub fn new() -> Self {
@@ -64,43 +108,46 @@ impl Texture {
 
     // Level (mip index), layer (array index), slice (z index)
     // DirectXTex - GetImage
-    pub fn get_image(&self, mip: u32, item: u32, slice: u32) -> Result<&Image, Box<dyn Error>> {
+    pub fn get_image(&self, mip: u32, item: u32, slice: u32) -> Result<&Image, TextureError> {
         if mip >= self.metadata.mip_levels {
-            return Err(format!(
+            return Err(TextureError::InvalidParameter(format!(
                 "Mip index out of bounds: {} >= {}",
                 mip, self.metadata.mip_levels
-            )
-            .into());
+            )));
         }
 
         match self.metadata.dimensions {
             TexDimension::Tex1D | TexDimension::Tex2D => {
                 if slice > 0 {
-                    return Err(format!("Slice index out of bounds: {} >= 0", slice).into());
+                    return Err(TextureError::InvalidParameter(format!(
+                        "Slice index out of bounds: {} >= 0",
+                        slice
+                    )));
                 }
 
                 if item >= self.metadata.array_size {
-                    return Err(format!(
+                    return Err(TextureError::InvalidParameter(format!(
                         "Item index out of bounds: {} >= {}",
                         item, self.metadata.array_size
-                    )
-                    .into());
+                    )));
                 }
 
                 self.images
                     .get((item * self.metadata.mip_levels + mip) as usize)
                     .ok_or_else(|| {
-                        format!(
+                        TextureError::DimensionMismatch(format!(
                             "Image not found for mip: {}, item: {}, slice: {}",
                             mip, item, slice
-                        )
-                        .into()
+                        ))
                     })
             }
 
             TexDimension::Tex3D => {
                 if item > 0 {
-                    return Err(format!("Item index out of bounds: {} >= 0", item).into());
+                    return Err(TextureError::InvalidParameter(format!(
+                        "Item index out of bounds: {} >= 0",
+                        item
+                    )));
                 }
 
                 let mut index = 0;
@@ -114,17 +161,19 @@ impl Texture {
                 }
 
                 if slice >= depth {
-                    return Err(format!("Slice index out of bounds: {} >= {}", slice, depth).into());
+                    return Err(TextureError::InvalidParameter(format!(
+                        "Slice index out of bounds: {} >= {}",
+                        slice, depth
+                    )));
                 }
 
-                index += depth;
+                index += slice;
 
                 self.images.get((index) as usize).ok_or_else(|| {
-                    format!(
+                    TextureError::DimensionMismatch(format!(
                         "Image not found for mip: {}, item: {}, slice: {}",
                         mip, item, slice
-                    )
-                    .into()
+                    ))
                 })
             }
         }
@@ -153,16 +202,82 @@ impl Texture {
     pub fn new_transformed_texture(
         &self,
         effects: &TextureEffects,
-    ) -> Result<Texture, Box<dyn Error>> {
-        let mut metadata = self.metadata.clone();
-
-        metadata.pixel_format_info = PixelFormatInfo {
-            pixel_format: effects.pixel_format,
-            pixel_data_type: super::pixel_format::PixelDataType::UNorm,
-            color_space: super::pixel_format::ColorSpace::Linear,
-            is_premultiplied: false,
+    ) -> Result<Texture, TextureError> {
+        let swizzler = Swizzler::with_options(&effects.swizzle_options);
+
+        // The whole texture is one surface on the platforms, its images can't be swizzled one at a time
+        let deswizzled;
+        let source = match effects.deswizzle {
+            Some(platform) => {
+                deswizzled = self.new_deswizzled_texture(&swizzler, platform)?;
+                &deswizzled
+            }
+            None => self,
         };
 
+        let mut texture = source.new_processed_texture(effects)?;
+
+        if let Some(platform) = effects.swizzle {
+            let data = swizzler.swizzle_texture(&texture, platform)?;
+            texture.split_surface(&data, &swizzler, platform)?;
+        }
+
+        Ok(texture)
+    }
+
+    /// Deswizzle the images of the texture, read one after another, as one surface
+    fn new_deswizzled_texture(
+        &self,
+        swizzler: &Swizzler,
+        platform: Platform,
+    ) -> Result<Texture, TextureError> {
+        let mut data: Vec<u8> = self
+            .images
+            .iter()
+            .flat_map(|image| image.pixels.iter().copied())
+            .collect();
+
+        // The images may not include the padding at the end of the surface
+        let texture_size = swizzler.get_texture_size(&self.metadata, platform)?;
+        data.resize(data.len().max(texture_size), 0);
+
+        swizzler.deswizzle_texture(&data, &self.metadata, platform)
+    }
+
+    /// Store a swizzled surface in the images, so writing them one after another gives back the surface
+    fn split_surface(
+        &mut self,
+        data: &[u8],
+        swizzler: &Swizzler,
+        platform: Platform,
+    ) -> Result<(), TextureError> {
+        let mut offset = 0;
+        let last = self.images.len().saturating_sub(1);
+
+        for (index, image) in self.images.iter_mut().enumerate() {
+            let end = if index == last {
+                data.len()
+            } else {
+                let pixel_format = image.pixel_format_info.pixel_format;
+                let size =
+                    swizzler.get_surface_size(image.width, image.height, pixel_format, platform)?;
+                (offset + size).min(data.len())
+            };
+
+            image.pixels = data[offset..end].to_vec();
+            offset = end;
+        }
+
+        Ok(())
+    }
+
+    /// Pipeline: Decompress -> Decode -> Process -> Compress
+    fn new_processed_texture(&self, effects: &TextureEffects) -> Result<Texture, TextureError> {
+        let mut metadata = self.metadata;
+
+        metadata.pixel_format_info =
+            effects.get_encoded_pixel_format_info(self.metadata.pixel_format_info);
+
         let total_rotation =
             (effects.rotate_90_left_count as i32 % 4) - (effects.rotate_90_right_count as i32 % 4);
 
@@ -170,6 +285,10 @@ impl Texture {
             std::mem::swap(&mut metadata.width, &mut metadata.height);
         }
 
+        if let Some(mipmap_options) = &effects.mipmaps {
+            return self.new_mipmapped_texture(metadata, effects, mipmap_options);
+        }
+
         let mut new_images = Vec::new();
 
         for image in &self.images {
@@ -181,4 +300,75 @@ impl Texture {
             images: new_images,
         })
     }
+
+    /// Process the top level images and rebuild the mip chain from them.
+    /// Pipeline: Decompress -> Decode -> Process -> Generate mips -> Compress
+    fn new_mipmapped_texture(
+        &self,
+        mut metadata: TexMetadata,
+        effects: &TextureEffects,
+        mipmap_options: &MipmapOptions,
+    ) -> Result<Texture, TextureError> {
+        let source_info = self.metadata.pixel_format_info;
+        let color_space = source_info.color_space;
+        let mut new_images = Vec::new();
+
+        if self.metadata.dimensions == TexDimension::Tex3D {
+            let mut slices = Vec::with_capacity(self.metadata.depth as usize);
+            let (mut width, mut height) = (metadata.width, metadata.height);
+
+            for slice in 0..self.metadata.depth {
+                let (slice_width, slice_height, pixels) =
+                    effects.process_rgba8_pixels(self.get_image(0, 0, slice)?)?;
+                (width, height) = (slice_width, slice_height);
+                slices.push(pixels);
+            }
+
+            let levels =
+                MipmapGenerator::generate_3d(width, height, &slices, color_space, mipmap_options)?;
+            metadata.mip_levels = levels.len() as u32;
+
+            for (mip, level) in levels.iter().enumerate() {
+                let mip_width = (width >> mip).max(1);
+                let mip_height = (height >> mip).max(1);
+
+                for slice in level {
+                    new_images.push(effects.get_encoded_image(
+                        mip_width,
+                        mip_height,
+                        slice,
+                        source_info,
+                    )?);
+                }
+            }
+        } else {
+            for item in 0..self.metadata.array_size {
+                let (width, height, pixels) =
+                    effects.process_rgba8_pixels(self.get_image(0, item, 0)?)?;
+
+                let levels = MipmapGenerator::generate_2d(
+                    width,
+                    height,
+                    &pixels,
+                    color_space,
+                    mipmap_options,
+                )?;
+                metadata.mip_levels = levels.len() as u32;
+
+                for (mip, level) in levels.iter().enumerate() {
+                    new_images.push(effects.get_encoded_image(
+                        (width >> mip).max(1),
+                        (height >> mip).max(1),
+                        level,
+                        source_info,
+                    )?);
+                }
+            }
+        }
+
+        Ok(Texture {
+            metadata,
+            images: new_images,
+        })
+    }
 }
@@ -1,14 +1,20 @@
-use std::error::Error;
+use std::cmp::Ordering;
 
 use image::{DynamicImage, ImageBuffer, Rgba};
 
 use super::{
-    pixel_format::{ColorSpace, PixelFormat, PixelFormatInfo},
+    bc_encoder::CompressionOptions,
+    mipmap_generator::MipmapOptions,
+    pixel_format::{PixelDataType, PixelFormat, PixelFormatInfo},
     pixel_format_decoder::PixelDecoder,
     pixel_format_encoder::PixelEncoder,
-    swizzling::{swizzle_manager::Swizzler, Platform},
+    swizzling::{
+        swizzle_manager::{SwizzleOptions, Swizzler},
+        Platform,
+    },
     texture::Image,
 };
+use crate::error::TextureError;
 
 pub struct TextureEffects {
     pub rotate_90_left_count: u32,
@@ -19,6 +25,12 @@ pub struct TextureEffects {
     pub swizzle: Option<Platform>,
     pub deswizzle: Option<Platform>,
     pub pixel_format: PixelFormat,
+    /// Regenerate the mip chain from the top level when saving
+    pub mipmaps: Option<MipmapOptions>,
+    /// Block compressor settings for BC6H and BC7
+    pub compression: CompressionOptions,
+    /// Platform specific surface settings of the swizzle and deswizzle
+    pub swizzle_options: SwizzleOptions,
 }
 
 impl Default for TextureEffects {
@@ -32,6 +44,9 @@ impl Default for TextureEffects {
             swizzle: None,
             deswizzle: None,
             pixel_format: PixelFormat::R8G8B8A8,
+            mipmaps: None,
+            compression: CompressionOptions::default(),
+            swizzle_options: SwizzleOptions::default(),
         }
     }
 }
@@ -42,84 +57,77 @@ impl TextureEffects {
         &self,
         image: &Image,
         display_compressed: bool,
-    ) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
-        let mut width = image.width;
-        let mut height = image.height;
-        let mut pixels = image.pixels.clone();
-
-        if let Some(platform) = self.deswizzle {
-            let swizzler = Swizzler::default();
-            swizzler.deswizzle(
-                &mut pixels,
-                width,
-                height,
-                image.pixel_format_info.pixel_format,
-                platform,
-            )?;
-        }
-
-        let mut pixels =
-            PixelDecoder::decode(&pixels, image.pixel_format_info.pixel_format, width, height)?;
+    ) -> Result<(u32, u32, Vec<u8>), TextureError> {
+        let (width, height, mut pixels) = self.get_processed_rgba8_pixels(image)?;
+        let encoded_info = self.get_encoded_pixel_format_info(image.pixel_format_info);
 
-        if self.flip_vertical_count % 2 == 1 {
-            TextureProcessingUtility::flip_vertical_raw(&mut pixels, width, height);
+        if display_compressed {
+            pixels = PixelEncoder::encode(&pixels, encoded_info, width, height, &self.compression)?;
         }
 
-        if self.flip_horizontal_count % 2 == 1 {
-            TextureProcessingUtility::flip_horizontal_raw(&mut pixels, width, height);
-        }
+        if let Some(platform) = self.swizzle {
+            let swizzler = Swizzler::with_options(&self.swizzle_options);
+            let pixel_format = if display_compressed {
+                self.pixel_format
+            } else {
+                PixelFormat::R8G8B8A8
+            };
 
-        let total_rotation =
-            (self.rotate_90_left_count as i32 % 4) - (self.rotate_90_right_count as i32 % 4);
+            // Padded surfaces are bigger than the image
+            let surface_size = swizzler.get_surface_size(width, height, pixel_format, platform)?;
+            pixels.resize(pixels.len().max(surface_size), 0);
 
-        if total_rotation > 0 {
-            for _ in 0..total_rotation {
-                TextureProcessingUtility::rotate_90_left_raw(&mut pixels, width, height);
-                std::mem::swap(&mut width, &mut height);
-            }
-        } else if total_rotation < 0 {
-            for _ in 0..-total_rotation {
-                TextureProcessingUtility::rotate_90_right_raw(&mut pixels, width, height);
-                std::mem::swap(&mut width, &mut height);
-            }
-        }
+            swizzler.swizzle(&mut pixels, width, height, pixel_format, platform)?;
 
-        TextureProcessingUtility::filter_colors(&mut pixels, width, height, self.channel_filter);
+            // Only show the swizzled bytes that fit the image, the padding is cropped
+            pixels.truncate(pixel_format.get_slice_pitch(width, height) as usize);
+        }
 
         if display_compressed {
-            pixels = PixelEncoder::encode(&pixels, self.pixel_format, width, height)?;
+            pixels = PixelDecoder::decode(&pixels, encoded_info, width, height)?;
         }
 
-        if let Some(platform) = self.swizzle {
-            let swizzler = Swizzler::default();
+        Ok((width, height, pixels))
+    }
 
-            if display_compressed {
-                swizzler.swizzle(&mut pixels, width, height, self.pixel_format, platform)?;
-            } else {
-                swizzler.swizzle(&mut pixels, width, height, PixelFormat::R8G8B8A8, platform)?;
-            }
-        }
+    /// Transform one linear image of a texture, the whole texture is swizzled by `Texture::new_transformed_texture`
+    /// Pipeline: Decompress -> Decode -> Process -> Compress
+    pub fn get_transformed_pixels(&self, image: &Image) -> Result<Image, TextureError> {
+        let (width, height, pixels) = self.process_rgba8_pixels(image)?;
 
-        if display_compressed {
-            pixels = PixelDecoder::decode(&pixels, self.pixel_format, width, height)?;
-        }
+        self.get_encoded_image(width, height, &pixels, image.pixel_format_info)
+    }
 
-        Ok((width, height, pixels))
+    /// Pipeline: Deswizzle -> Decompress -> Decode -> Process
+    pub fn get_processed_rgba8_pixels(
+        &self,
+        image: &Image,
+    ) -> Result<(u32, u32, Vec<u8>), TextureError> {
+        match self.deswizzle {
+            Some(platform) => {
+                let mut deswizzled = image.clone();
+                let swizzler = Swizzler::with_options(&self.swizzle_options);
+                swizzler.deswizzle(
+                    &mut deswizzled.pixels,
+                    image.width,
+                    image.height,
+                    image.pixel_format_info.pixel_format,
+                    platform,
+                )?;
+
+                self.process_rgba8_pixels(&deswizzled)
+            }
+            None => self.process_rgba8_pixels(image),
+        }
     }
 
-    /// Pipeline: Deswizzle -> Decompress -> Decode -> Process -> Compress -> Swizzle
-    pub fn get_transformed_pixels(&self, image: &Image) -> Result<Image, Box<dyn Error>> {
+    /// Pipeline: Decompress -> Decode -> Process
+    pub fn process_rgba8_pixels(&self, image: &Image) -> Result<(u32, u32, Vec<u8>), TextureError> {
         let mut width = image.width;
         let mut height = image.height;
-        let image_pixel_format = image.pixel_format_info.pixel_format;
-        let mut pixels = image.pixels.clone();
-
-        if let Some(platform) = self.deswizzle {
-            let swizzler = Swizzler::default();
-            swizzler.deswizzle(&mut pixels, width, height, image_pixel_format, platform)?;
-        }
 
-        let mut pixels = PixelDecoder::decode(&pixels, image_pixel_format, width, height)?;
+        let mut pixels =
+            PixelDecoder::decode(&image.pixels, image.pixel_format_info, width, height)?;
 
         if self.flip_vertical_count % 2 == 1 {
             TextureProcessingUtility::flip_vertical_raw(&mut pixels, width, height);
@@ -132,43 +140,71 @@ impl TextureEffects {
         let total_rotation =
             (self.rotate_90_left_count as i32 % 4) - (self.rotate_90_right_count as i32 % 4);
 
-        if total_rotation > 0 {
-            for _ in 0..total_rotation {
-                TextureProcessingUtility::rotate_90_left_raw(&mut pixels, width, height);
-                std::mem::swap(&mut width, &mut height);
+        match total_rotation.cmp(&0) {
+            Ordering::Greater => {
+                for _ in 0..total_rotation {
+                    TextureProcessingUtility::rotate_90_left_raw(&mut pixels, width, height)?;
+                    std::mem::swap(&mut width, &mut height);
+                }
             }
-        } else if total_rotation < 0 {
-            for _ in 0..-total_rotation {
-                TextureProcessingUtility::rotate_90_right_raw(&mut pixels, width, height);
-                std::mem::swap(&mut width, &mut height);
+            Ordering::Less => {
+                for _ in 0..-total_rotation {
+                    TextureProcessingUtility::rotate_90_right_raw(&mut pixels, width, height)?;
+                    std::mem::swap(&mut width, &mut height);
+                }
             }
+            Ordering::Equal => {}
         }
 
         TextureProcessingUtility::filter_colors(&mut pixels, width, height, self.channel_filter);
 
-        let mut pixels = PixelEncoder::encode(&mut pixels, self.pixel_format, width, height)?;
+        Ok((width, height, pixels))
+    }
 
-        if let Some(platform) = self.swizzle {
-            let swizzler = Swizzler::default();
-            swizzler.swizzle(&mut pixels, width, height, self.pixel_format, platform)?;
-        }
+    /// Pipeline: Compress
+    pub fn get_encoded_image(
+        &self,
+        width: u32,
+        height: u32,
+        rgba8_pixels: &[u8],
+        source_pixel_format_info: PixelFormatInfo,
+    ) -> Result<Image, TextureError> {
+        let pixel_format_info = self.get_encoded_pixel_format_info(source_pixel_format_info);
+        let pixels = PixelEncoder::encode(
+            rgba8_pixels,
+            pixel_format_info,
+            width,
+            height,
+            &self.compression,
+        )?;
 
         Ok(Image {
             width,
             height,
-            pixel_format_info: {
-                PixelFormatInfo {
-                    pixel_format: self.pixel_format,
-                    color_space: ColorSpace::Linear,
-                    pixel_data_type: super::pixel_format::PixelDataType::UNorm,
-                    is_premultiplied: false,
-                }
-            },
+            pixel_format_info,
             row_pitch: self.pixel_format.get_row_pitch(width),
             slice_pitch: self.pixel_format.get_slice_pitch(width, height),
             pixels,
         })
     }
+
+    /// Format info of the pixels encoded from a source. The pixels are not converted between
+    /// color spaces, so the source encoding is kept, and its data type too while the pixel
+    /// format stays the same, e.g. signed BC6H.
+    pub fn get_encoded_pixel_format_info(&self, source: PixelFormatInfo) -> PixelFormatInfo {
+        let pixel_data_type = if self.pixel_format == source.pixel_format {
+            source.pixel_data_type
+        } else {
+            PixelDataType::UNorm
+        };
+
+        PixelFormatInfo {
+            pixel_format: self.pixel_format,
+            pixel_data_type,
+            color_space: source.color_space,
+            is_premultiplied: source.is_premultiplied,
+        }
+    }
 }
 
 /// Basic processing utilities
@@ -201,28 +237,40 @@ impl TextureProcessingUtility {
         }
     }
 
-    pub fn rotate_90_left_raw(pixels: &mut [u8], width: u32, height: u32) {
+    pub fn rotate_90_left_raw(
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), TextureError> {
         let img = DynamicImage::ImageRgba8(
             ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels.to_vec())
-                .expect("Failed to create image buffer!"),
+                .ok_or_else(|| rgba8_size_mismatch(pixels, width, height))?,
         );
 
         let rotated_img = img.rotate270();
 
         let rotated_pixels = rotated_img.to_rgba8();
         pixels.copy_from_slice(&rotated_pixels);
+
+        Ok(())
     }
 
-    pub fn rotate_90_right_raw(pixels: &mut [u8], width: u32, height: u32) {
+    pub fn rotate_90_right_raw(
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), TextureError> {
         let img = DynamicImage::ImageRgba8(
             ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels.to_vec())
-                .expect("Failed to create image buffer!"),
+                .ok_or_else(|| rgba8_size_mismatch(pixels, width, height))?,
         );
 
         let rotated_img = img.rotate90();
 
         let rotated_pixels = rotated_img.to_rgba8();
         pixels.copy_from_slice(&rotated_pixels);
+
+        Ok(())
     }
 
     pub fn filter_colors(
@@ -234,11 +282,20 @@ impl TextureProcessingUtility {
         for y in 0..height {
             for x in 0..width {
                 let index = (y * width + x) as usize * 4;
-                pixels[index] = pixels[index] * (r as u8);
-                pixels[index + 1] = pixels[index + 1] * (g as u8);
-                pixels[index + 2] = pixels[index + 2] * (b as u8);
+                pixels[index] *= r as u8;
+                pixels[index + 1] *= g as u8;
+                pixels[index + 2] *= b as u8;
                 pixels[index + 3] = if a { pixels[index + 3] } else { 255 };
             }
         }
     }
 }
+
+fn rgba8_size_mismatch(pixels: &[u8], width: u32, height: u32) -> TextureError {
+    TextureError::DimensionMismatch(format!(
+        "RGBA8 data size doesn't match {}x{}: {}",
+        width,
+        height,
+        pixels.len()
+    ))
+}
@@ -0,0 +1,134 @@
+use std::io;
+
+/// Bounds checked reader for parsing binary file headers
+pub struct BinaryReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    big_endian: bool,
+}
+
+impl<'a> BinaryReader<'a> {
+    /// Create a new little endian reader
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            big_endian: false,
+        }
+    }
+
+    /// Create a new big endian reader
+    pub fn new_big_endian(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            big_endian: true,
+        }
+    }
+
+    pub fn set_big_endian(&mut self, big_endian: bool) {
+        self.big_endian = big_endian;
+    }
+
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    /// Move to an absolute position
+    pub fn seek(&mut self, position: usize) -> Result<(), io::Error> {
+        if position > self.data.len() {
+            return Err(Self::out_of_bounds(position));
+        }
+
+        self.position = position;
+        Ok(())
+    }
+
+    /// Move forward by a number of bytes
+    pub fn skip(&mut self, count: usize) -> Result<(), io::Error> {
+        self.seek(self.position.saturating_add(count))
+    }
+
+    /// Move forward to the next multiple of the alignment
+    pub fn align(&mut self, alignment: usize) -> Result<(), io::Error> {
+        let padding = (alignment - self.position % alignment) % alignment;
+        self.skip(padding)
+    }
+
+    /// Get a slice of the underlying data without moving the reader
+    pub fn slice(&self, offset: usize, count: usize) -> Result<&'a [u8], io::Error> {
+        let end = offset
+            .checked_add(count)
+            .ok_or_else(|| Self::out_of_bounds(usize::MAX))?;
+
+        self.data
+            .get(offset..end)
+            .ok_or_else(|| Self::out_of_bounds(end))
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], io::Error> {
+        let bytes = self.slice(self.position, count)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], io::Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, io::Error> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, io::Error> {
+        let bytes = self.read_array()?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, io::Error> {
+        let bytes = self.read_array()?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, io::Error> {
+        let bytes = self.read_array()?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+
+    fn out_of_bounds(position: usize) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("Unexpected end of data at offset {}", position),
+        )
+    }
+}
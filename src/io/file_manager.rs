@@ -45,16 +45,14 @@ impl FileManager {
             Self::get_files_from_folder(&self.selected_folder_path, &self.filter_extensions)?;
         self.selected_file_index = if self.files.is_empty() {
             None
-        } else {
-            if let Some(index) = self.selected_file_index {
-                if index >= self.files.len() {
-                    Some(self.files.len() - 1)
-                } else {
-                    Some(index)
-                }
+        } else if let Some(index) = self.selected_file_index {
+            if index >= self.files.len() {
+                Some(self.files.len() - 1)
             } else {
-                Some(0)
+                Some(index)
             }
+        } else {
+            Some(0)
         };
         Ok(())
     }
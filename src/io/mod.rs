@@ -1 +1,3 @@
+pub mod binary_reader;
 pub mod file_manager;
+pub mod texture_scanner;
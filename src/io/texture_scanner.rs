@@ -0,0 +1,400 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use directxtex::{CP_FLAGS_NONE, DDS_FLAGS_NONE};
+
+use super::binary_reader::BinaryReader;
+use crate::error::TextureError;
+use crate::{
+    codecs::{
+        codec_manager::{CodecManager, CodecOptions},
+        tga::get_tga_size,
+        PROBE_SIGNATURE,
+    },
+    graphics::texture::{TexMetadata, Texture},
+};
+
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+/// Length of the PNG signature, the first chunk follows it
+const PNG_SIGNATURE_SIZE: usize = 8;
+
+/// Largest embedded file, or decoded PNG, that is loaded. Bigger candidates are skipped.
+const MAX_TEXTURE_SIZE: usize = 512 << 20;
+
+/// Extension of a codec with a signature and a function measuring the whole file from its
+/// header. The signature itself is checked by the probe of the codec.
+type SizeReader = (&'static str, fn(&[u8]) -> Option<usize>);
+
+const SIZE_READERS: &[SizeReader] = &[
+    ("dds", get_dds_size),
+    ("png", get_png_size),
+    ("ktx", get_ktx_size),
+    ("ktx2", get_ktx2_size),
+    ("gnf", get_gnf_size),
+    ("gxt", get_gxt_size),
+];
+
+/// Settings of a scan
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScanOptions {
+    /// TGA has no signature, its header is checked at every offset which is much slower
+    pub include_tga: bool,
+}
+
+/// A texture file found inside another file
+#[derive(Clone, Copy)]
+pub struct ScannedTexture {
+    pub offset: usize,
+    pub size: usize,
+    /// Extension of the codec that validated the texture
+    pub extension: &'static str,
+    pub metadata: TexMetadata,
+}
+
+impl ScannedTexture {
+    /// Bytes of the embedded file
+    pub fn get_bytes<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.offset + self.size]
+    }
+
+    /// Load the embedded file with the codec of its extension
+    pub fn load(&self, data: &[u8], codec_manager: &CodecManager) -> Result<Texture, TextureError> {
+        codec_manager
+            .get_codec_for_extension(self.extension)
+            .ok_or_else(|| {
+                TextureError::UnsupportedFormat(format!("Unsupported format: {}", self.extension))
+            })?
+            .load_from_memory(self.get_bytes(data))
+    }
+
+    /// File name made of the offset in hexadecimal and the extension, e.g. "0001F400.dds"
+    pub fn get_file_name(&self) -> String {
+        format!("{:08X}.{}", self.offset, self.extension)
+    }
+}
+
+/// Walk the whole data looking for texture files. Only offsets starting with a signature are
+/// candidates, each is validated by loading it with its codec, and the scan continues after the
+/// end of each texture found.
+pub fn scan_textures(
+    data: &[u8],
+    codec_manager: &CodecManager,
+    options: &ScanOptions,
+) -> Vec<ScannedTexture> {
+    scan_textures_with_progress(
+        data,
+        codec_manager,
+        options,
+        &AtomicUsize::new(0),
+        &AtomicBool::new(false),
+    )
+}
+
+/// `scan_textures` that stores the current offset in `progress` and stops early, with the
+/// textures found so far, once `cancel` is set
+pub fn scan_textures_with_progress(
+    data: &[u8],
+    codec_manager: &CodecManager,
+    options: &ScanOptions,
+    progress: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> Vec<ScannedTexture> {
+    let mut textures = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() && !cancel.load(Ordering::Relaxed) {
+        progress.store(offset, Ordering::Relaxed);
+
+        match probe_texture(data, offset, codec_manager, options) {
+            Some(texture) => {
+                offset += texture.size;
+                textures.push(texture);
+            }
+            None => offset += 1,
+        }
+    }
+
+    progress.store(data.len(), Ordering::Relaxed);
+    textures
+}
+
+type ScanResult = (Vec<u8>, Vec<ScannedTexture>);
+
+enum ScanWorker {
+    Thread(JoinHandle<ScanResult>),
+    Done(ScanResult),
+}
+
+/// A scan running on a worker thread, so the data can be scanned without blocking the UI
+pub struct ScanTask {
+    size: usize,
+    progress: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    worker: ScanWorker,
+}
+
+impl ScanTask {
+    /// Start scanning the data with codecs made from the options. Threads cannot be spawned
+    /// on the web, the scan is done before returning there.
+    pub fn spawn(data: Vec<u8>, codec_options: CodecOptions, options: ScanOptions) -> Self {
+        let size = data.len();
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let scan = {
+            let progress = progress.clone();
+            let cancel = cancel.clone();
+
+            move || {
+                let codec_manager = CodecManager::with_options(&codec_options);
+                let textures = scan_textures_with_progress(
+                    &data,
+                    &codec_manager,
+                    &options,
+                    &progress,
+                    &cancel,
+                );
+                (data, textures)
+            }
+        };
+
+        let worker = if cfg!(target_arch = "wasm32") {
+            ScanWorker::Done(scan())
+        } else {
+            ScanWorker::Thread(std::thread::spawn(scan))
+        };
+
+        Self {
+            size,
+            progress,
+            cancel,
+            worker,
+        }
+    }
+
+    /// Scanned fraction of the data, from 0.0 to 1.0
+    pub fn get_progress(&self) -> f32 {
+        if self.size == 0 {
+            1.0
+        } else {
+            self.progress.load(Ordering::Relaxed) as f32 / self.size as f32
+        }
+    }
+
+    /// Ask the worker to stop, `join` then returns `None`
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match &self.worker {
+            ScanWorker::Thread(handle) => handle.is_finished(),
+            ScanWorker::Done(_) => true,
+        }
+    }
+
+    /// Wait for the scan, returns the data and the textures found, or `None` if it was
+    /// cancelled or the worker panicked
+    pub fn join(self) -> Option<ScanResult> {
+        let result = match self.worker {
+            ScanWorker::Thread(handle) => handle.join().ok()?,
+            ScanWorker::Done(result) => result,
+        };
+
+        (!self.cancel.load(Ordering::Relaxed)).then_some(result)
+    }
+}
+
+/// Write every scanned texture into the output directory, returns the written paths
+pub fn extract_textures(
+    data: &[u8],
+    textures: &[ScannedTexture],
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, TextureError> {
+    fs::create_dir_all(output_dir)?;
+
+    textures
+        .iter()
+        .map(|texture| {
+            let path = output_dir.join(texture.get_file_name());
+            fs::write(&path, texture.get_bytes(data))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn probe_texture(
+    data: &[u8],
+    offset: usize,
+    codec_manager: &CodecManager,
+    options: &ScanOptions,
+) -> Option<ScannedTexture> {
+    let source = &data[offset..];
+
+    let (extension, size) = match SIZE_READERS.iter().find(|(extension, _)| {
+        codec_manager
+            .get_codec_for_extension(extension)
+            .is_some_and(|codec| codec.probe(source) == PROBE_SIGNATURE)
+    }) {
+        Some(&(extension, get_size)) => (extension, get_size(source)),
+        None if options.include_tga => ("tga", get_tga_size(source)),
+        None => return None,
+    };
+
+    let size = size.filter(|&size| size > 0 && size <= source.len().min(MAX_TEXTURE_SIZE))?;
+    let texture = codec_manager
+        .get_codec_for_extension(extension)?
+        .load_from_memory(&source[..size])
+        .ok()?;
+
+    Some(ScannedTexture {
+        offset,
+        size,
+        extension,
+        metadata: texture.metadata,
+    })
+}
+
+/// Header and DX10 header, then every surface at its DirectXTex pitch
+fn get_dds_size(source: &[u8]) -> Option<usize> {
+    let metadata = directxtex::TexMetadata::from_dds(source, DDS_FLAGS_NONE, None).ok()?;
+    let header_size = match source.get(84..88)? {
+        b"DX10" => DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE,
+        _ => DDS_HEADER_SIZE,
+    };
+
+    let mut size = header_size;
+
+    for _ in 0..metadata.array_size {
+        for mip in 0..metadata.mip_levels {
+            let pitch = metadata.format.compute_pitch(
+                (metadata.width >> mip).max(1),
+                (metadata.height >> mip).max(1),
+                CP_FLAGS_NONE,
+            );
+
+            let surface_size = pitch
+                .ok()?
+                .slice
+                .checked_mul((metadata.depth >> mip).max(1))?;
+            size = size.checked_add(surface_size)?;
+        }
+    }
+
+    Some(size)
+}
+
+/// Chunks up to and including IEND
+fn get_png_size(source: &[u8]) -> Option<usize> {
+    let mut reader = BinaryReader::new_big_endian(source);
+    reader.seek(PNG_SIGNATURE_SIZE + 8).ok()?;
+
+    // IHDR comes first, 16 bit RGBA is the largest decoded size
+    let width = reader.read_u32().ok()? as u64;
+    let height = reader.read_u32().ok()? as u64;
+
+    if width * height * 8 > MAX_TEXTURE_SIZE as u64 {
+        return None;
+    }
+
+    reader.seek(PNG_SIGNATURE_SIZE).ok()?;
+
+    loop {
+        let length = reader.read_u32().ok()? as usize;
+        let chunk_type = reader.read_array::<4>().ok()?;
+        reader.skip(length + 4).ok()?;
+
+        if &chunk_type == b"IEND" {
+            return Some(reader.position());
+        }
+    }
+}
+
+/// Key/value data then every mip with its size, faces of non array cubemaps are stored apart
+fn get_ktx_size(source: &[u8]) -> Option<usize> {
+    let mut reader = BinaryReader::new(source);
+    // Right after the 12 byte identifier
+    reader.seek(12).ok()?;
+    let endianness = reader.read_u32().ok()?;
+
+    match endianness {
+        0x04030201 => {}
+        0x01020304 => reader.set_big_endian(true),
+        _ => return None,
+    }
+
+    reader.seek(48).ok()?;
+    let array_size = reader.read_u32().ok()?;
+    let face_count = reader.read_u32().ok()?;
+    let mip_levels = reader.read_u32().ok()?.max(1);
+    let key_value_size = reader.read_u32().ok()? as usize;
+    reader.skip(key_value_size).ok()?;
+
+    let faces_apart = if array_size == 0 && face_count == 6 {
+        6
+    } else {
+        1
+    };
+
+    for _ in 0..mip_levels {
+        let image_size = reader.read_u32().ok()? as usize;
+        reader
+            .skip(image_size.next_multiple_of(4) * faces_apart)
+            .ok()?;
+    }
+
+    Some(reader.position())
+}
+
+/// End of the furthest level, descriptor or supercompression block
+fn get_ktx2_size(source: &[u8]) -> Option<usize> {
+    let mut reader = BinaryReader::new(source);
+    reader.seek(40).ok()?;
+    let level_count = reader.read_u32().ok()?.max(1);
+    let _supercompression_scheme = reader.read_u32().ok()?;
+
+    let dfd_end = reader.read_u32().ok()? as u64 + reader.read_u32().ok()? as u64;
+    let kvd_end = reader.read_u32().ok()? as u64 + reader.read_u32().ok()? as u64;
+    let sgd_end = reader
+        .read_u64()
+        .ok()?
+        .checked_add(reader.read_u64().ok()?)?;
+    let mut size = dfd_end.max(kvd_end).max(sgd_end);
+
+    for _ in 0..level_count {
+        let level_end = reader
+            .read_u64()
+            .ok()?
+            .checked_add(reader.read_u64().ok()?)?;
+        let _uncompressed_size = reader.read_u64().ok()?;
+        size = size.max(level_end);
+    }
+
+    usize::try_from(size).ok()
+}
+
+/// The header stores the whole stream size
+fn get_gnf_size(source: &[u8]) -> Option<usize> {
+    let mut reader = BinaryReader::new(source);
+    reader.seek(12).ok()?;
+
+    Some(reader.read_u32().ok()? as usize)
+}
+
+/// Texture data follows the header and the texture infos
+fn get_gxt_size(source: &[u8]) -> Option<usize> {
+    let mut reader = BinaryReader::new(source);
+    reader.seek(12).ok()?;
+    let data_offset = reader.read_u32().ok()? as usize;
+    let data_size = reader.read_u32().ok()? as usize;
+
+    data_offset.checked_add(data_size)
+}
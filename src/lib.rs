@@ -3,6 +3,8 @@
 mod app;
 pub use app::TextureViewer;
 
+pub mod cli;
 pub mod codecs;
+pub mod error;
 pub mod graphics;
 pub mod io;
@@ -20,7 +20,7 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Texture Viewer",
         native_options,
-        Box::new(|cc| Ok(Box::new(TextureViewer::TextureViewer::new(cc)))),
+        Box::new(|cc| Ok(Box::new(texture_viewer::TextureViewer::new(cc)))),
     )
 }
 
@@ -50,7 +50,7 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|cc| Ok(Box::new(TextureViewer::TextureViewerApp::new(cc)))),
+                Box::new(|cc| Ok(Box::new(texture_viewer::TextureViewerApp::new(cc)))),
             )
             .await;
 
@@ -0,0 +1,312 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{astc::ASTCCodec, ImageCodec, PROBE_SIGNATURE},
+        error::TextureError,
+        graphics::{
+            astc_decoder::{
+                decode_astc_block, decode_astc_to_rgba, decode_astc_to_rgba_f32, is_astc_hdr_block,
+            },
+            astc_encoder::encode_astc,
+            bc_encoder::{BcQuality, ChannelWeighting, CompressionOptions},
+            pixel_format::{PixelDataType, PixelFormat, PixelFormatInfo},
+            pixel_format_decoder::PixelDecoder,
+            pixel_format_encoder::PixelEncoder,
+            texture::{Image, TexDimension, TexMetadata, Texture},
+        },
+    };
+
+    use crate::common::{build_test_image, get_psnr};
+
+    /// Every LDR footprint with the lowest PSNR its fastest and slowest preset must reach
+    const FOOTPRINTS: [(PixelFormat, [f64; 2]); 6] = [
+        (PixelFormat::Astc4x4, [23.0, 28.5]),
+        (PixelFormat::Astc5x5, [22.5, 28.0]),
+        (PixelFormat::Astc6x6, [20.5, 22.5]),
+        (PixelFormat::Astc8x8, [18.5, 19.5]),
+        (PixelFormat::Astc10x10, [17.5, 18.5]),
+        (PixelFormat::Astc12x12, [16.5, 17.0]),
+    ];
+
+    /// Void extent block of a single color, HDR blocks hold half floats
+    fn build_void_extent(color: [u16; 4], hdr: bool) -> Vec<u8> {
+        let mut bits: u128 = 0x1FC | (hdr as u128) << 9 | 3 << 10 | ((1 << 52) - 1) << 12;
+
+        for (i, &value) in color.iter().enumerate() {
+            bits |= (value as u128) << (64 + i * 16);
+        }
+
+        bits.to_le_bytes().to_vec()
+    }
+
+    /// Hand-built .astc file, sizes are 24 bit little endian
+    fn build_astc_file(block: (u8, u8, u8), size: [u32; 3], data: &[u8]) -> Vec<u8> {
+        let mut file = vec![0x13, 0xAB, 0xA1, 0x5C, block.0, block.1, block.2];
+
+        for value in size {
+            file.extend_from_slice(&value.to_le_bytes()[..3]);
+        }
+
+        file.extend_from_slice(data);
+        file
+    }
+
+    #[test]
+    fn test_block_sizes() {
+        let format = PixelFormat::Astc10x6;
+        assert_eq!(format.get_block_width(), 10);
+        assert_eq!(format.get_block_height(), 6);
+        assert_eq!(format.get_bytes_per_block(), 16);
+        assert!(format.is_compressed());
+        assert!(!format.is_astc_hdr());
+
+        // Partial blocks at the edges take a whole block
+        assert_eq!(format.get_row_pitch(21), 3 * 16);
+        assert_eq!(format.get_slice_pitch(21, 13), 3 * 3 * 16);
+        assert_eq!(PixelFormat::Astc12x12Hdr.get_slice_pitch(1, 1), 16);
+
+        assert_eq!(PixelFormat::Astc8x5Hdr.get_astc_block_size(), Some((8, 5)));
+        assert!(PixelFormat::Astc8x5Hdr.is_astc_hdr());
+        assert_eq!(PixelFormat::BC7.get_astc_block_size(), None);
+        assert_eq!(PixelFormat::BC7.get_block_width(), 4);
+
+        assert_eq!(PixelFormat::Astc5x4.to_string(), "ASTC 5x4");
+        assert_eq!(PixelFormat::Astc12x10Hdr.to_string(), "ASTC 12x10 HDR");
+        assert_eq!("ASTC6X6".parse::<PixelFormat>(), Ok(PixelFormat::Astc6x6));
+        assert_eq!(
+            "ASTC10X8HDR".parse::<PixelFormat>(),
+            Ok(PixelFormat::Astc10x8Hdr)
+        );
+    }
+
+    #[test]
+    fn test_void_extent_blocks() {
+        let block = build_void_extent([0xFFFF, 0x8080, 0, 0xFFFF], false);
+        let decoded = decode_astc_to_rgba(&block, PixelFormat::Astc5x4, 5, 4).unwrap();
+        assert_eq!(decoded, [[255, 128, 0, 255]; 20].concat());
+
+        // 2.0, 0.5, 1.0 and 1.0 as half floats
+        let block = build_void_extent([0x4000, 0x3800, 0x3C00, 0x3C00], true);
+        assert!(is_astc_hdr_block(&block));
+        let decoded = decode_astc_to_rgba_f32(&block, PixelFormat::Astc4x4Hdr, 4, 4).unwrap();
+        assert_eq!(decoded, [[2.0, 0.5, 1.0, 1.0]; 16].concat());
+
+        // The LDR profile cannot decode HDR blocks, they come out magenta like reserved ones
+        let magenta = [[255, 0, 255, 255]; 16].concat();
+        assert_eq!(
+            decode_astc_to_rgba(&block, PixelFormat::Astc4x4, 4, 4).unwrap(),
+            magenta
+        );
+        assert_eq!(
+            decode_astc_to_rgba(&[0; 16], PixelFormat::Astc4x4, 4, 4).unwrap(),
+            magenta
+        );
+        assert_eq!(decode_astc_block(&[0; 16], 6, 5, true).len(), 30);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (width, height) = (37, 22);
+        let pixels = build_test_image(width, height);
+
+        for (format, min_psnrs) in FOOTPRINTS {
+            let qualities = [BcQuality::UltraFast, BcQuality::Slow];
+
+            for (quality, min_psnr) in qualities.into_iter().zip(min_psnrs) {
+                let options = CompressionOptions {
+                    quality,
+                    weighting: ChannelWeighting::Uniform,
+                    ..Default::default()
+                };
+
+                let encoded = encode_astc(&pixels, format, width, height, &options).unwrap();
+                assert_eq!(
+                    encoded.len(),
+                    format.get_slice_pitch(width, height) as usize
+                );
+                assert!(encoded.chunks_exact(16).all(|b| !is_astc_hdr_block(b)));
+
+                let decoded = decode_astc_to_rgba(&encoded, format, width, height).unwrap();
+                let psnr = get_psnr(&pixels, &decoded, 4);
+                assert!(psnr > min_psnr, "{} {}: {:.2} dB", format, quality, psnr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_partition_limits() {
+        let (width, height) = (24, 16);
+        let pixels = build_test_image(width, height);
+
+        // Partition count - 1 of every block, the test image has no solid blocks
+        let get_partitions = |max_partitions| {
+            let options = CompressionOptions {
+                quality: BcQuality::Basic,
+                max_partitions,
+                ..Default::default()
+            };
+            let encoded =
+                encode_astc(&pixels, PixelFormat::Astc4x4, width, height, &options).unwrap();
+
+            encoded
+                .chunks_exact(16)
+                .map(|block| (block[1] >> 3) & 3)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(get_partitions(Some(0)).iter().all(|&p| p == 0));
+        assert!(get_partitions(None).iter().any(|&p| p == 1));
+    }
+
+    #[test]
+    fn test_solid_and_gray_blocks() {
+        // Solid blocks are void extents and come back exactly
+        let solid = [[31, 161, 221, 90]; 64].concat();
+        let options = CompressionOptions::default();
+        let encoded = encode_astc(&solid, PixelFormat::Astc8x8, 8, 8, &options).unwrap();
+        assert_eq!(
+            encoded,
+            build_void_extent([0x1F1F, 0xA1A1, 0xDDDD, 0x5A5A], false)
+        );
+
+        // Gray ramps keep their channels equal
+        let gray: Vec<u8> = (0..36u8).flat_map(|i| [i * 7, i * 7, i * 7, 255]).collect();
+        let encoded = encode_astc(&gray, PixelFormat::Astc6x6, 6, 6, &options).unwrap();
+        let decoded = decode_astc_to_rgba(&encoded, PixelFormat::Astc6x6, 6, 6).unwrap();
+        assert!(decoded
+            .chunks_exact(4)
+            .all(|c| c[0] == c[1] && c[1] == c[2] && c[3] == 255));
+        assert!(get_psnr(&gray, &decoded, 4) > 40.0);
+    }
+
+    #[test]
+    fn test_threads_are_deterministic() {
+        let (width, height) = (40, 36);
+        let pixels = build_test_image(width, height);
+
+        let encode = |threads| {
+            let options = CompressionOptions {
+                threads,
+                ..Default::default()
+            };
+            encode_astc(&pixels, PixelFormat::Astc5x4, width, height, &options).unwrap()
+        };
+
+        let single = encode(1);
+        assert_eq!(single, encode(4));
+        assert_eq!(single, encode(0));
+    }
+
+    #[test]
+    fn test_encoder_integration() {
+        let (width, height) = (14, 9);
+        let pixels = build_test_image(width, height);
+        let options = CompressionOptions::default();
+
+        for format in [PixelFormat::Astc6x5, PixelFormat::Astc6x5Hdr] {
+            let encoded =
+                PixelEncoder::encode(&pixels, format.into(), width, height, &options).unwrap();
+            assert_eq!(
+                encoded,
+                encode_astc(&pixels, format, width, height, &options).unwrap()
+            );
+
+            // HDR blocks hold LDR endpoints, both profiles decode them alike
+            let decoded = PixelDecoder::decode(&encoded, format.into(), width, height).unwrap();
+            assert_eq!(
+                decoded,
+                decode_astc_to_rgba(&encoded, PixelFormat::Astc6x5, width, height).unwrap()
+            );
+        }
+
+        assert!(matches!(
+            encode_astc(&[0; 15], PixelFormat::Astc4x4, 2, 2, &options),
+            Err(TextureError::DimensionMismatch(_))
+        ));
+        assert!(matches!(
+            encode_astc(&pixels, PixelFormat::BC7, width, height, &options),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            decode_astc_to_rgba(&[0; 16], PixelFormat::Astc8x8, 9, 8),
+            Err(TextureError::DimensionMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_codec_round_trip() {
+        let (width, height) = (13, 7);
+        let format = PixelFormat::Astc5x5;
+        let data = encode_astc(
+            &build_test_image(width, height),
+            format,
+            width,
+            height,
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+
+        let file = build_astc_file((5, 5, 1), [width, height, 1], &data);
+        assert_eq!(ASTCCodec.probe(&file), PROBE_SIGNATURE);
+        assert_eq!(ASTCCodec.probe(b"DDS "), 0);
+
+        let texture = ASTCCodec.load_from_memory(&file).unwrap();
+        assert_eq!(texture.metadata.pixel_format_info.pixel_format, format);
+        assert_eq!(texture.metadata.dimensions, TexDimension::Tex2D);
+        assert_eq!((texture.metadata.width, texture.metadata.height), (13, 7));
+        assert_eq!(texture.images[0].pixels, data);
+        assert_eq!(ASTCCodec.save_to_memory(&texture).unwrap(), file);
+
+        // HDR blocks pick the HDR format, slices become a volume
+        let block = build_void_extent([0x4000, 0x3800, 0x3C00, 0x3C00], true);
+        let file = build_astc_file((4, 4, 1), [4, 4, 2], &[block.clone(), block].concat());
+        let texture = ASTCCodec.load_from_memory(&file).unwrap();
+        let pixel_format_info = texture.metadata.pixel_format_info;
+        assert_eq!(pixel_format_info.pixel_format, PixelFormat::Astc4x4Hdr);
+        assert_eq!(pixel_format_info.pixel_data_type, PixelDataType::Float);
+        assert_eq!(texture.metadata.dimensions, TexDimension::Tex3D);
+        assert_eq!(texture.images.len(), 2);
+        assert_eq!(ASTCCodec.save_to_memory(&texture).unwrap(), file);
+
+        // 3D blocks, truncated data and other pixel formats are rejected
+        let file = build_astc_file((4, 4, 4), [4, 4, 4], &[0; 16]);
+        assert!(matches!(
+            ASTCCodec.load_from_memory(&file),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+        let file = build_astc_file((6, 6, 1), [7, 7, 1], &[0; 16]);
+        assert!(ASTCCodec.load_from_memory(&file).is_err());
+
+        // Sizes too large for the pitches or the file
+        for size in [[0xFFFFFF, 0xFFFFFF, 1], [4096, 4096, 0xFFFFFF]] {
+            let file = build_astc_file((4, 4, 1), size, &[0; 16]);
+            assert!(matches!(
+                ASTCCodec.load_from_memory(&file),
+                Err(TextureError::CorruptHeader(_))
+            ));
+        }
+
+        let pixel_format_info = PixelFormatInfo::default();
+        let texture = Texture {
+            metadata: TexMetadata {
+                width: 4,
+                height: 4,
+                depth: 1,
+                array_size: 1,
+                mip_levels: 1,
+                pixel_format_info,
+                alpha_mode: 0,
+                dimensions: TexDimension::Tex2D,
+                is_cubemap: false,
+                is_volumemap: false,
+            },
+            images: vec![Image::new(4, 4, pixel_format_info, vec![0; 64])],
+        };
+        assert!(matches!(
+            ASTCCodec.save_to_memory(&texture),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+    }
+}
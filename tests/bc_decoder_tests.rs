@@ -0,0 +1,207 @@
+#[cfg(test)]
+mod tests {
+    use directxtex::{Image, DXGI_FORMAT};
+    use texture_viewer::{
+        error::TextureError,
+        graphics::{
+            bc_decoder::{decode_bc6h_to_rgba_f32, decode_bc_to_rgba},
+            crate_directxtex_utility::DirectXTexUtility,
+            pixel_format::{PixelDataType, PixelFormat, PixelFormatInfo},
+            pixel_format_decoder::PixelDecoder,
+        },
+    };
+
+    const FORMATS: [PixelFormat; 7] = [
+        PixelFormat::BC1,
+        PixelFormat::BC2,
+        PixelFormat::BC3,
+        PixelFormat::BC4,
+        PixelFormat::BC5,
+        PixelFormat::BC6H,
+        PixelFormat::BC7,
+    ];
+
+    /// Deterministic noise, every block is a random bit pattern
+    fn random_blocks(seed: u64, size: usize) -> Vec<u8> {
+        let mut state = seed | 1;
+
+        (0..size)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    /// Overwrite the mode bits of every 16 byte block so each mode gets covered
+    fn force_modes(blocks: &mut [u8], format: PixelFormat) {
+        // BC6H mode values, 2 bit ones followed by 5 bit ones, including the reserved ones
+        const BC6H_MODES: [u8; 18] = [
+            0x00, 0x01, 0x02, 0x06, 0x0A, 0x0E, 0x12, 0x16, 0x1A, 0x1E, 0x03, 0x07, 0x0B, 0x0F,
+            0x13, 0x17, 0x1B, 0x1F,
+        ];
+
+        for (index, block) in blocks.chunks_exact_mut(16).enumerate() {
+            if format == PixelFormat::BC7 {
+                // Mode n is n zero bits followed by a one, mode 8 is reserved
+                let mode = index % 9;
+                let bits = (block[0] as u16 | (block[1] as u16) << 8) & !0x1FF;
+                let bits = bits | if mode < 8 { 1 << mode } else { 0 };
+                block[0] = bits as u8;
+                block[1] = (bits >> 8) as u8;
+            } else {
+                let mode = BC6H_MODES[index % BC6H_MODES.len()];
+                let mask = if mode < 2 { 0x03 } else { 0x1F };
+                block[0] = (block[0] & !mask) | mode;
+            }
+        }
+    }
+
+    fn get_test_pixels(format: PixelFormat, width: u32, height: u32, seed: u64) -> Vec<u8> {
+        let mut pixels = random_blocks(seed, format.get_slice_pitch(width, height) as usize);
+
+        if matches!(format, PixelFormat::BC6H | PixelFormat::BC7) {
+            force_modes(&mut pixels, format);
+        }
+
+        pixels
+    }
+
+    #[test]
+    fn test_matches_directxtex() {
+        for format in FORMATS {
+            for (seed, (width, height)) in [(64, 64), (13, 7), (1, 1), (6, 30)].iter().enumerate() {
+                let pixels = get_test_pixels(format, *width, *height, seed as u64 + 1);
+
+                let expected = DirectXTexUtility::decompress_bc_to_rgba(
+                    format,
+                    *width as usize,
+                    *height as usize,
+                    pixels.clone(),
+                )
+                .unwrap();
+                let decoded = decode_bc_to_rgba(&pixels, format, *width, *height, false).unwrap();
+
+                assert_eq!(decoded.len(), expected.len());
+                if let Some(index) = (0..decoded.len()).find(|&i| decoded[i] != expected[i]) {
+                    let pixel = index / 4 * 4;
+                    panic!(
+                        "{} {}x{}: pixel {} is {:?}, DirectXTex has {:?}",
+                        format,
+                        width,
+                        height,
+                        index / 4,
+                        &decoded[pixel..pixel + 4],
+                        &expected[pixel..pixel + 4]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bc6h_float_matches_directxtex() {
+        let (width, height) = (64, 64);
+
+        for (signed, format) in [
+            (false, DXGI_FORMAT::DXGI_FORMAT_BC6H_UF16),
+            (true, DXGI_FORMAT::DXGI_FORMAT_BC6H_SF16),
+        ] {
+            let pixels = get_test_pixels(PixelFormat::BC6H, width, height, 7);
+
+            let image = Image {
+                width: width as usize,
+                height: height as usize,
+                format,
+                pixels: pixels.as_ptr() as *mut u8,
+                row_pitch: (width / 4 * 16) as usize,
+                slice_pitch: pixels.len(),
+            };
+            let scratch = image
+                .decompress(DXGI_FORMAT::DXGI_FORMAT_R32G32B32A32_FLOAT)
+                .unwrap();
+            let expected: Vec<f32> = scratch.pixels()[..(width * height * 16) as usize]
+                .chunks_exact(4)
+                .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+
+            let decoded = decode_bc6h_to_rgba_f32(&pixels, width, height, signed).unwrap();
+
+            assert_eq!(decoded.len(), expected.len());
+            for (index, (decoded, expected)) in decoded.iter().zip(&expected).enumerate() {
+                assert_eq!(
+                    decoded.to_bits(),
+                    expected.to_bits(),
+                    "signed: {}, value {}: {} != {}",
+                    signed,
+                    index,
+                    decoded,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bc6h_signed_through_pixel_decoder() {
+        let (width, height) = (16, 16);
+        let pixels = get_test_pixels(PixelFormat::BC6H, width, height, 11);
+
+        let image = Image {
+            width: width as usize,
+            height: height as usize,
+            format: DXGI_FORMAT::DXGI_FORMAT_BC6H_SF16,
+            pixels: pixels.as_ptr() as *mut u8,
+            row_pitch: (width / 4 * 16) as usize,
+            slice_pitch: pixels.len(),
+        };
+        let scratch = image
+            .decompress(DXGI_FORMAT::DXGI_FORMAT_R8G8B8A8_UNORM)
+            .unwrap();
+        let expected = &scratch.pixels()[..(width * height * 4) as usize];
+
+        let signed_info = PixelFormatInfo {
+            pixel_format: PixelFormat::BC6H,
+            pixel_data_type: PixelDataType::SFloat,
+            ..Default::default()
+        };
+        let decoded = PixelDecoder::decode(&pixels, signed_info, width, height).unwrap();
+        assert!(decoded == expected);
+
+        let unsigned = PixelDecoder::decode(&pixels, PixelFormat::BC6H.into(), width, height);
+        assert!(unsigned.unwrap() != decoded);
+    }
+
+    #[test]
+    fn test_decode_edge_cases() {
+        // BC1 with color0 <= color1 has a transparent black entry
+        let block = [0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let decoded = PixelDecoder::decode(&block, PixelFormat::BC1.into(), 4, 4).unwrap();
+        assert_eq!(&decoded[..4], &[0, 0, 0, 0]);
+
+        // Reserved BC7 mode 8 is transparent black
+        let decoded = decode_bc_to_rgba(&[0; 16], PixelFormat::BC7, 4, 4, false).unwrap();
+        assert!(decoded.iter().all(|&value| value == 0));
+
+        // Reserved BC6H modes are opaque black
+        let decoded = decode_bc6h_to_rgba_f32(&[0x13; 16], 4, 4, false).unwrap();
+        assert!(decoded
+            .chunks_exact(4)
+            .all(|pixel| pixel == [0.0, 0.0, 0.0, 1.0]));
+
+        assert!(decode_bc_to_rgba(&[], PixelFormat::BC1, 0, 0, false)
+            .unwrap()
+            .is_empty());
+
+        assert!(matches!(
+            decode_bc_to_rgba(&[0; 15], PixelFormat::BC7, 4, 4, false),
+            Err(TextureError::DimensionMismatch(_))
+        ));
+        assert!(matches!(
+            decode_bc_to_rgba(&[0; 16], PixelFormat::R8G8B8A8, 2, 2, false),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+    }
+}
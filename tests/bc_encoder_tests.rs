@@ -0,0 +1,352 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        error::TextureError,
+        graphics::{
+            bc_decoder::{decode_bc6h_to_rgba_f32, decode_bc_to_rgba},
+            bc_encoder::{
+                encode_bc6h, encode_bc7, BcQuality, ChannelWeighting, CompressionOptions,
+            },
+            crate_directxtex_utility::DirectXTexUtility,
+            pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+            pixel_format_decoder::PixelDecoder,
+            pixel_format_encoder::PixelEncoder,
+            texture_utility::TextureEffects,
+        },
+    };
+
+    use crate::common::{build_test_image, build_texture, get_psnr};
+
+    /// Every preset with the lowest PSNR it must reach on the test image
+    const QUALITIES: [(BcQuality, f64); 5] = [
+        (BcQuality::UltraFast, 23.0),
+        (BcQuality::VeryFast, 28.0),
+        (BcQuality::Fast, 30.5),
+        (BcQuality::Basic, 30.5),
+        (BcQuality::Slow, 31.5),
+    ];
+
+    /// BC7 mode of every block, from its leading zero bits
+    fn get_bc7_modes(blocks: &[u8]) -> Vec<u32> {
+        blocks
+            .chunks_exact(16)
+            .map(|block| (block[0] as u32 | (block[1] as u32) << 8).trailing_zeros())
+            .collect()
+    }
+
+    #[test]
+    fn test_bc7_round_trip() {
+        let (width, height) = (37, 22);
+        let pixels = build_test_image(width, height);
+        let mut previous_psnr = 0.0;
+
+        for (quality, min_psnr) in QUALITIES {
+            // PSNR weighs every channel the same
+            let options = CompressionOptions {
+                quality,
+                weighting: ChannelWeighting::Uniform,
+                ..Default::default()
+            };
+
+            let encoded = encode_bc7(&pixels, width, height, &options).unwrap();
+            assert_eq!(
+                encoded.len(),
+                PixelFormat::BC7.get_slice_pitch(width, height) as usize
+            );
+
+            // DirectXTex must read back exactly what the native decoder reads
+            let decoded =
+                decode_bc_to_rgba(&encoded, PixelFormat::BC7, width, height, false).unwrap();
+            let expected = DirectXTexUtility::decompress_bc_to_rgba(
+                PixelFormat::BC7,
+                width as usize,
+                height as usize,
+                encoded,
+            )
+            .unwrap();
+            assert_eq!(decoded, expected);
+
+            let psnr = get_psnr(&pixels, &decoded, 4);
+            assert!(psnr > min_psnr, "{}: {:.2} dB", quality, psnr);
+            assert!(psnr > previous_psnr - 0.1, "{}: {:.2} dB", quality, psnr);
+            previous_psnr = psnr;
+        }
+    }
+
+    #[test]
+    fn test_bc7_mode_limits() {
+        let (width, height) = (16, 16);
+        let pixels = build_test_image(width, height);
+
+        for mode in 0..8 {
+            let options = CompressionOptions {
+                bc7_modes: Some(1 << mode),
+                max_partitions: Some(3),
+                weighting: ChannelWeighting::Uniform,
+                ..Default::default()
+            };
+
+            let encoded = encode_bc7(&pixels, width, height, &options).unwrap();
+            assert!(get_bc7_modes(&encoded).iter().all(|&m| m == mode));
+
+            let decoded =
+                decode_bc_to_rgba(&encoded, PixelFormat::BC7, width, height, false).unwrap();
+
+            // Modes 0 to 3 have no alpha, compare the color only
+            let psnr = get_psnr(&pixels, &decoded, 3);
+            assert!(psnr > 24.0, "mode {}: {:.2} dB", mode, psnr);
+        }
+
+        // Opaque blocks stay on mode 6 with the fastest preset
+        let opaque = vec![[31, 161, 221, 255]; 64].concat();
+        let options = CompressionOptions {
+            quality: BcQuality::UltraFast,
+            ..Default::default()
+        };
+        let encoded = encode_bc7(&opaque, 8, 8, &options).unwrap();
+        assert!(get_bc7_modes(&encoded).iter().all(|&m| m == 6));
+        assert_eq!(
+            decode_bc_to_rgba(&encoded, PixelFormat::BC7, 8, 8, false).unwrap(),
+            opaque
+        );
+    }
+
+    #[test]
+    fn test_bc6h_round_trip() {
+        let (width, height) = (21, 18);
+        // A brightness ramp over a slowly changing hue, like most HDR content
+        let pixels: Vec<f32> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                let intensity = 0.5 + x * 0.2 + y * 0.1;
+                [
+                    intensity,
+                    intensity * (0.4 + y * 0.02),
+                    intensity * (0.9 - x * 0.02),
+                    1.0,
+                ]
+            })
+            .collect();
+
+        for (quality, signed) in QUALITIES
+            .iter()
+            .flat_map(|&(quality, _)| [(quality, false), (quality, true)])
+        {
+            let options = CompressionOptions {
+                quality,
+                ..Default::default()
+            };
+
+            let encoded = encode_bc6h(&pixels, width, height, signed, &options).unwrap();
+            let decoded = decode_bc6h_to_rgba_f32(&encoded, width, height, signed).unwrap();
+            // Signed endpoints spend a bit on the sign
+            let tolerance = if signed { 0.09 } else { 0.08 };
+
+            for (index, (&expected, &actual)) in pixels.iter().zip(&decoded).enumerate() {
+                if index % 4 == 3 {
+                    assert_eq!(actual, 1.0);
+                    continue;
+                }
+
+                assert!(
+                    (expected - actual).abs() <= expected * tolerance,
+                    "{} signed: {}: value {} is {}, expected {}",
+                    quality,
+                    signed,
+                    index,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_compression_options() {
+        let (width, height) = (32, 24);
+        let pixels = build_test_image(width, height);
+
+        let get_channel_error = |decoded: &[u8], channel: usize| -> f64 {
+            pixels
+                .iter()
+                .zip(decoded)
+                .skip(channel)
+                .step_by(4)
+                .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+                .sum()
+        };
+
+        // Perceptual weighting trades blue accuracy for green accuracy
+        let encode = |options: CompressionOptions| {
+            let encoded = encode_bc7(&pixels, width, height, &options).unwrap();
+            decode_bc_to_rgba(&encoded, PixelFormat::BC7, width, height, false).unwrap()
+        };
+        let perceptual = encode(CompressionOptions::default());
+        let uniform = encode(CompressionOptions {
+            weighting: ChannelWeighting::Uniform,
+            ..Default::default()
+        });
+        assert!(get_channel_error(&perceptual, 1) < get_channel_error(&uniform, 1));
+        assert!(get_channel_error(&perceptual, 2) > get_channel_error(&uniform, 2));
+
+        // sRGB aware encoding has the lower error in linear light
+        let get_linear_error = |decoded: &[u8]| -> f64 {
+            let to_linear = |c: u8| (c as f64 / 255.0).powf(2.2);
+            pixels
+                .iter()
+                .zip(decoded)
+                .enumerate()
+                .filter(|(i, _)| i % 4 != 3)
+                .map(|(_, (&a, &b))| (to_linear(a) - to_linear(b)).powi(2))
+                .sum()
+        };
+        let srgb = encode(CompressionOptions {
+            srgb: true,
+            ..Default::default()
+        });
+        assert!(get_linear_error(&srgb) < get_linear_error(&perceptual));
+
+        // BC1 punches out pixels below the alpha threshold
+        let translucent = [[200, 100, 50, 100]; 16].concat();
+        for (alpha_threshold, expected_alpha) in [(0.5, 0), (0.3, 255)] {
+            let options = CompressionOptions {
+                alpha_threshold,
+                ..Default::default()
+            };
+            let encoded =
+                PixelEncoder::encode(&translucent, PixelFormat::BC1.into(), 4, 4, &options)
+                    .unwrap();
+            let decoded = decode_bc_to_rgba(&encoded, PixelFormat::BC1, 4, 4, false).unwrap();
+            assert!(decoded
+                .iter()
+                .skip(3)
+                .step_by(4)
+                .all(|&a| a == expected_alpha));
+        }
+
+        // Dithering spreads the error of smooth gradients differently
+        let gradient: Vec<u8> = (0..16 * 16)
+            .flat_map(|i| [(i % 16 * 3) as u8, (i / 16 * 5) as u8, 80, 255])
+            .collect();
+        let dither = |dither| {
+            let options = CompressionOptions {
+                dither,
+                ..Default::default()
+            };
+            PixelEncoder::encode(&gradient, PixelFormat::BC1.into(), 16, 16, &options).unwrap()
+        };
+        assert_ne!(dither(true), dither(false));
+    }
+
+    #[test]
+    fn test_threads_are_deterministic() {
+        let (width, height) = (40, 36);
+        let pixels = build_test_image(width, height);
+
+        let encode = |threads| {
+            let options = CompressionOptions {
+                threads,
+                ..Default::default()
+            };
+            encode_bc7(&pixels, width, height, &options).unwrap()
+        };
+
+        let single = encode(1);
+        assert_eq!(single, encode(4));
+        assert_eq!(single, encode(0));
+    }
+
+    #[test]
+    fn test_transform_keeps_pixel_data_type() {
+        let mut texture = build_texture(16, 16, PixelFormat::BC6H);
+        let signed_info = PixelFormatInfo {
+            pixel_format: PixelFormat::BC6H,
+            pixel_data_type: PixelDataType::SFloat,
+            color_space: ColorSpace::Linear,
+            is_premultiplied: false,
+        };
+        texture.metadata.pixel_format_info = signed_info;
+        texture.images[0].pixel_format_info = signed_info;
+
+        let effects = TextureEffects {
+            pixel_format: PixelFormat::BC6H,
+            ..Default::default()
+        };
+        let transformed = texture.new_transformed_texture(&effects).unwrap();
+        let image = &transformed.images[0];
+        assert_eq!(
+            transformed.metadata.pixel_format_info.pixel_data_type,
+            PixelDataType::SFloat
+        );
+        assert_eq!(
+            image.pixel_format_info.pixel_data_type,
+            PixelDataType::SFloat
+        );
+
+        // Encoded as signed BC6H, so it decodes back close to the source
+        let source = PixelDecoder::decode(&texture.images[0].pixels, signed_info, 16, 16).unwrap();
+        let decoded = PixelDecoder::decode(&image.pixels, signed_info, 16, 16).unwrap();
+        assert!(get_psnr(&source, &decoded, 3) > 30.0);
+
+        // Other formats do not keep the data type of the source
+        let effects = TextureEffects {
+            pixel_format: PixelFormat::BC7,
+            ..Default::default()
+        };
+        let transformed = texture.new_transformed_texture(&effects).unwrap();
+        assert_eq!(
+            transformed.images[0].pixel_format_info.pixel_data_type,
+            PixelDataType::UNorm
+        );
+
+        let mut srgb = build_texture(8, 8, PixelFormat::BC7);
+        srgb.metadata.pixel_format_info.color_space = ColorSpace::SRGB;
+        srgb.images[0].pixel_format_info.color_space = ColorSpace::SRGB;
+        let transformed = srgb.new_transformed_texture(&effects).unwrap();
+        assert_eq!(
+            transformed.images[0].pixel_format_info.color_space,
+            ColorSpace::SRGB
+        );
+    }
+
+    #[test]
+    fn test_encoder_integration() {
+        let (width, height) = (12, 8);
+        let pixels = build_test_image(width, height);
+        let options = CompressionOptions::default();
+
+        let encoded =
+            PixelEncoder::encode(&pixels, PixelFormat::BC7.into(), width, height, &options);
+        assert_eq!(
+            encoded.unwrap(),
+            encode_bc7(&pixels, width, height, &options).unwrap()
+        );
+
+        let encoded =
+            PixelEncoder::encode(&pixels, PixelFormat::BC6H.into(), width, height, &options)
+                .unwrap();
+        assert_eq!(encoded.len(), 16 * 3 * 2);
+
+        let effects = TextureEffects {
+            pixel_format: PixelFormat::BC7,
+            compression: CompressionOptions {
+                quality: BcQuality::Slow,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(effects.compression.quality, BcQuality::Slow);
+
+        assert!(matches!(
+            encode_bc7(&[0; 15], 2, 2, &options),
+            Err(TextureError::DimensionMismatch(_))
+        ));
+        assert!(encode_bc6h(&[], 0, 0, false, &options).unwrap().is_empty());
+
+        assert_eq!("ultrafast".parse::<BcQuality>(), Ok(BcQuality::UltraFast));
+        assert_eq!("Very Fast".parse::<BcQuality>(), Ok(BcQuality::VeryFast));
+        assert!("best".parse::<BcQuality>().is_err());
+    }
+}
@@ -0,0 +1,238 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{
+            bntx::{get_texture_names, BNTXCodec},
+            ImageCodec,
+        },
+        graphics::{
+            pixel_format::PixelFormat,
+            swizzling::{switch::SwitchSwizzler, Swizzable},
+            texture::Texture,
+        },
+    };
+
+    use crate::common::{assert_same_images, build_mip_texture};
+
+    const FORMAT_BC1_UNORM: u32 = 0x1A01;
+    const FORMAT_R8G8B8A8_UNORM: u32 = 0x0B01;
+    const BRTI_SIZE: usize = 0xA0;
+
+    struct BNTXEntry {
+        name: &'static str,
+        format: u32,
+        texture: Texture,
+        block_height: u32,
+    }
+
+    /// Assemble a BNTX file with the BRTI blocks, their mip offsets and names, then the data
+    fn build_bntx(entries: &[BNTXEntry]) -> Vec<u8> {
+        let info_pointers = 0x48;
+        let first_brti = info_pointers + entries.len() * 8;
+        let entry_size = BRTI_SIZE + 0x10 * 8 + 0x20;
+        let mut data_offset = (first_brti + entries.len() * entry_size).next_multiple_of(0x200);
+
+        let mut bntx = vec![0; first_brti + entries.len() * entry_size];
+        let mut data = Vec::new();
+
+        bntx[..4].copy_from_slice(b"BNTX");
+        bntx[0x0C..0x0E].copy_from_slice(&0xFEFFu16.to_le_bytes());
+        bntx[0x20..0x24].copy_from_slice(b"NX  ");
+        bntx[0x24..0x28].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+        bntx[0x28..0x30].copy_from_slice(&(info_pointers as u64).to_le_bytes());
+
+        for (index, entry) in entries.iter().enumerate() {
+            let brti = first_brti + index * entry_size;
+            let mip_offsets = brti + BRTI_SIZE;
+            let name = mip_offsets + 0x10 * 8;
+            let metadata = &entry.texture.metadata;
+            let pixel_format = metadata.pixel_format_info.pixel_format;
+            let swizzler = SwitchSwizzler {
+                block_height: Some(entry.block_height),
+            };
+            let swizzled = swizzler.swizzle_texture(&entry.texture).unwrap();
+
+            let mut write = |offset: usize, bytes: &[u8]| {
+                bntx[offset..offset + bytes.len()].copy_from_slice(bytes)
+            };
+
+            write(info_pointers + index * 8, &(brti as u64).to_le_bytes());
+            write(brti, b"BRTI");
+            write(brti + 0x11, &[2]);
+            write(brti + 0x16, &(metadata.mip_levels as u16).to_le_bytes());
+            write(brti + 0x1C, &entry.format.to_le_bytes());
+            write(brti + 0x24, &metadata.width.to_le_bytes());
+            write(brti + 0x28, &metadata.height.to_le_bytes());
+            write(brti + 0x2C, &1u32.to_le_bytes());
+            write(brti + 0x30, &metadata.array_size.to_le_bytes());
+            write(
+                brti + 0x34,
+                &entry.block_height.trailing_zeros().to_le_bytes(),
+            );
+            write(brti + 0x50, &(swizzled.len() as u32).to_le_bytes());
+            write(brti + 0x5C, &1u32.to_le_bytes());
+            write(brti + 0x60, &(name as u64).to_le_bytes());
+            write(brti + 0x70, &(mip_offsets as u64).to_le_bytes());
+
+            for mip in 0..metadata.mip_levels {
+                let offset = data_offset
+                    + swizzler
+                        .get_mip_chain_size(metadata.width, metadata.height, mip, pixel_format)
+                        .unwrap();
+                write(
+                    mip_offsets + mip as usize * 8,
+                    &(offset as u64).to_le_bytes(),
+                );
+            }
+
+            write(name, &(entry.name.len() as u16).to_le_bytes());
+            write(name + 2, entry.name.as_bytes());
+
+            data.extend_from_slice(&swizzled);
+            data_offset += swizzled.len();
+        }
+
+        bntx.resize(bntx.len().next_multiple_of(0x200), 0);
+        bntx.extend_from_slice(&data);
+        bntx
+    }
+
+    #[test]
+    fn test_bntx_entries_as_array() {
+        let first = build_mip_texture(64, 64, 2, PixelFormat::BC1, 3);
+        let second = build_mip_texture(64, 64, 2, PixelFormat::BC1, 5);
+
+        let bntx = build_bntx(&[
+            BNTXEntry {
+                name: "first",
+                format: FORMAT_BC1_UNORM,
+                texture: build_mip_texture(64, 64, 2, PixelFormat::BC1, 3),
+                block_height: 2,
+            },
+            BNTXEntry {
+                name: "second",
+                format: FORMAT_BC1_UNORM,
+                texture: build_mip_texture(64, 64, 2, PixelFormat::BC1, 5),
+                block_height: 2,
+            },
+        ]);
+
+        assert_eq!(get_texture_names(&bntx).unwrap(), vec!["first", "second"]);
+
+        let texture = BNTXCodec::default().load_from_memory(&bntx).unwrap();
+        assert_eq!(texture.metadata.array_size, 2);
+        assert_eq!(texture.metadata.mip_levels, 2);
+        assert_same_images(&texture, &[&first.images[..], &second.images[..]].concat());
+
+        let codec = BNTXCodec {
+            texture_index: Some(1),
+            ..Default::default()
+        };
+        let texture = codec.load_from_memory(&bntx).unwrap();
+        assert_eq!(texture.metadata.array_size, 1);
+        assert_same_images(&texture, &second.images);
+    }
+
+    #[test]
+    fn test_bntx_replace_texture() {
+        let bntx = build_bntx(&[
+            BNTXEntry {
+                name: "color",
+                format: FORMAT_R8G8B8A8_UNORM,
+                texture: build_mip_texture(32, 16, 2, PixelFormat::R8G8B8A8, 3),
+                block_height: 1,
+            },
+            BNTXEntry {
+                name: "detail",
+                format: FORMAT_BC1_UNORM,
+                texture: build_mip_texture(128, 128, 2, PixelFormat::BC1, 5),
+                block_height: 4,
+            },
+        ]);
+
+        let codec = BNTXCodec {
+            texture_index: Some(1),
+            source: Some(bntx.clone()),
+        };
+
+        let replacement = build_mip_texture(128, 128, 2, PixelFormat::BC1, 7);
+        let saved = codec.save_to_memory(&replacement).unwrap();
+        assert_eq!(saved.len(), bntx.len());
+
+        let first = BNTXCodec {
+            texture_index: Some(0),
+            ..Default::default()
+        };
+        assert_same_images(
+            &first.load_from_memory(&saved).unwrap(),
+            &build_mip_texture(32, 16, 2, PixelFormat::R8G8B8A8, 3).images,
+        );
+        assert_same_images(
+            &codec.load_from_memory(&saved).unwrap(),
+            &replacement.images,
+        );
+
+        // The replacement must have the same size
+        let smaller = build_mip_texture(64, 64, 2, PixelFormat::BC1, 7);
+        assert!(codec.save_to_memory(&smaller).is_err());
+    }
+
+    #[test]
+    fn test_bntx_errors() {
+        assert!(BNTXCodec::default().load_from_memory(b"GNF ").is_err());
+
+        let bntx = build_bntx(&[
+            BNTXEntry {
+                name: "small",
+                format: FORMAT_BC1_UNORM,
+                texture: build_mip_texture(16, 16, 2, PixelFormat::BC1, 1),
+                block_height: 1,
+            },
+            BNTXEntry {
+                name: "large",
+                format: FORMAT_BC1_UNORM,
+                texture: build_mip_texture(32, 32, 2, PixelFormat::BC1, 1),
+                block_height: 1,
+            },
+        ]);
+
+        // Entries of different sizes must be picked one by one
+        assert!(BNTXCodec::default().load_from_memory(&bntx).is_err());
+
+        let codec = BNTXCodec {
+            texture_index: Some(2),
+            ..Default::default()
+        };
+        assert!(codec.load_from_memory(&bntx).is_err());
+
+        // Saving needs the file to write into
+        let texture = build_mip_texture(16, 16, 2, PixelFormat::BC1, 1);
+        assert!(BNTXCodec::default().save_to_memory(&texture).is_err());
+    }
+
+    #[test]
+    fn test_load_bntx_invalid_counts() {
+        let bntx = build_bntx(&[BNTXEntry {
+            name: "texture",
+            format: FORMAT_R8G8B8A8_UNORM,
+            texture: build_mip_texture(16, 16, 2, PixelFormat::R8G8B8A8, 1),
+            block_height: 1,
+        }]);
+        let brti = 0x48 + 8;
+
+        for (offset, value) in [
+            // Texture count
+            (0x24, &u32::MAX.to_le_bytes()[..]),
+            // Mip levels
+            (brti + 0x16, &u16::MAX.to_le_bytes()[..]),
+            // Array length
+            (brti + 0x30, &u32::MAX.to_le_bytes()[..]),
+        ] {
+            let mut corrupt = bntx.clone();
+            corrupt[offset..offset + value.len()].copy_from_slice(value);
+            assert!(BNTXCodec::default().load_from_memory(&corrupt).is_err());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        io,
+    };
+
+    use tempfile::tempdir;
+    use texture_viewer::{
+        cli::{convert_batch, matches_wildcard, BatchOptions},
+        codecs::{codec_manager::CodecManager, dds::DDSHeaderFormat, ktx2::KTX2Supercompression},
+        graphics::{
+            mipmap_generator::MipmapFilter,
+            pixel_format::PixelFormat,
+            swizzling::{wii_u::GX2TileMode, Platform},
+        },
+    };
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = BatchOptions::from_args(&to_args(&[
+            "textures",
+            "-o",
+            "out",
+            "--format",
+            "bc3",
+            "--deswizzle",
+            "switch",
+            "--block-height",
+            "8",
+            "--tile-mode",
+            "2",
+            "--wii-u-swizzle",
+            "0x600",
+            "--rotate-left",
+            "1",
+            "--flip-vertical",
+            "--channels",
+            "rgb",
+            "-e",
+            ".dds",
+        ]))
+        .expect("Failed to parse arguments!")
+        .expect("Expected options!");
+
+        assert_eq!(options.inputs, vec!["textures".to_string()]);
+        assert_eq!(options.output_dir, std::path::PathBuf::from("out"));
+        assert_eq!(options.output_extension.as_deref(), Some("dds"));
+        assert_eq!(options.effects.pixel_format, PixelFormat::BC3);
+        assert_eq!(options.effects.deswizzle, Some(Platform::Switch));
+        assert_eq!(options.effects.swizzle_options.switch_block_height, Some(8));
+        assert_eq!(
+            options.effects.swizzle_options.wii_u_tile_mode,
+            GX2TileMode::Tiled1DThin1
+        );
+        assert_eq!(options.effects.swizzle_options.wii_u_swizzle, 0x600);
+        assert_eq!(options.effects.rotate_90_left_count, 1);
+        assert_eq!(options.effects.flip_vertical_count, 1);
+        assert_eq!(options.effects.channel_filter, (true, true, true, false));
+
+        assert!(options.effects.mipmaps.is_none());
+
+        let options = BatchOptions::from_args(&to_args(&[
+            "a.png",
+            "-o",
+            "out",
+            "--mipmaps",
+            "kaiser",
+            "--max-mips",
+            "4",
+            "--dds-header",
+            "legacy",
+            "--texture-index",
+            "2",
+            "--ktx2-supercompression",
+            "zstd",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            options.codec_options.dds_header_format,
+            DDSHeaderFormat::Legacy
+        );
+        assert_eq!(options.codec_options.texture_index, Some(2));
+        assert_eq!(
+            options.codec_options.ktx2_supercompression,
+            KTX2Supercompression::Zstandard
+        );
+        let mipmaps = options.effects.mipmaps.expect("Expected mipmap options!");
+        assert_eq!(mipmaps.filter, MipmapFilter::Kaiser);
+        assert_eq!(mipmaps.max_levels, Some(4));
+        assert_eq!(mipmaps.alpha_coverage_reference, None);
+
+        assert!(BatchOptions::from_args(&to_args(&["--help"]))
+            .unwrap()
+            .is_none());
+        assert!(BatchOptions::from_args(&to_args(&["a.png"])).is_err());
+        assert!(BatchOptions::from_args(&to_args(&["a.png", "-o", "out", "-f", "X"])).is_err());
+        assert!(
+            BatchOptions::from_args(&to_args(&["a.png", "-o", "out", "--dds-header", "dx11"]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_matches_wildcard() {
+        assert!(matches_wildcard("*.dds", "texture.DDS"));
+        assert!(matches_wildcard("tex_??.png", "tex_01.png"));
+        assert!(matches_wildcard("*_n*.png", "wall_normal.png"));
+        assert!(!matches_wildcard("*.dds", "texture.png"));
+        assert!(!matches_wildcard("tex_?.png", "tex_01.png"));
+    }
+
+    #[test]
+    fn test_convert_batch() -> Result<(), io::Error> {
+        let input_dir = tempdir()?;
+        let output_dir = tempdir()?;
+
+        fs::copy(
+            "tests/test_images/sample.png",
+            input_dir.path().join("sample.png"),
+        )?;
+        fs::create_dir(input_dir.path().join("nested"))?;
+        fs::copy(
+            "tests/test_images/sample.jpg",
+            input_dir.path().join("nested").join("sample.jpg"),
+        )?;
+        // Not a valid image, should fail without aborting the batch
+        File::create(input_dir.path().join("broken.png"))?;
+
+        let options = BatchOptions::from_args(&to_args(&[
+            input_dir.path().to_str().unwrap(),
+            "-r",
+            "-o",
+            output_dir.path().to_str().unwrap(),
+            "-e",
+            "dds",
+            "-f",
+            "BC1",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        let report = convert_batch(&options, &CodecManager::default());
+
+        assert_eq!(report.converted.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.has_failures());
+        assert!(output_dir.path().join("sample.dds").is_file());
+        assert!(output_dir
+            .path()
+            .join("nested")
+            .join("sample.dds")
+            .is_file());
+
+        let texture = CodecManager::default()
+            .load_from_file(&output_dir.path().join("sample.dds"))
+            .expect("Failed to load converted DDS!");
+
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_batch_duplicate_outputs() -> Result<(), io::Error> {
+        let input_dir = tempdir()?;
+        let output_dir = tempdir()?;
+
+        for directory in ["first", "second"] {
+            fs::create_dir(input_dir.path().join(directory))?;
+            fs::copy(
+                "tests/test_images/sample.png",
+                input_dir.path().join(directory).join("sample.png"),
+            )?;
+        }
+
+        // Both files would be written to the same output file
+        let options = BatchOptions::from_args(&to_args(&[
+            input_dir.path().join("first/sample.png").to_str().unwrap(),
+            input_dir.path().join("second/*.png").to_str().unwrap(),
+            "-o",
+            output_dir.path().to_str().unwrap(),
+        ]))
+        .unwrap()
+        .unwrap();
+
+        let report = convert_batch(&options, &CodecManager::default());
+
+        assert_eq!(report.converted.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(
+            report.failed[0].0,
+            input_dir.path().join("second").join("sample.png")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+    use texture_viewer::{
+        codecs::{
+            codec_manager::{CodecManager, CodecOptions},
+            dds::{DDSCodec, DDSHeaderFormat},
+            gnf::GNFCodec,
+            ktx::KTXCodec,
+            ktx2::KTX2Supercompression,
+            png::PNGCodec,
+            tga::TGACodec,
+            ImageCodec, PROBE_HEURISTIC, PROBE_SIGNATURE,
+        },
+        graphics::{pixel_format::PixelFormat, texture_utility::TextureEffects},
+    };
+
+    use crate::common::{build_texture, build_tga};
+
+    #[test]
+    fn test_probe_scores() {
+        let png = PNGCodec
+            .save_to_memory(&build_texture(4, 4, PixelFormat::R8G8B8A8))
+            .unwrap();
+        let dds = DDSCodec::default()
+            .save_to_memory(&build_texture(8, 8, PixelFormat::BC1))
+            .unwrap();
+        let tga = build_tga(4, 4);
+
+        assert_eq!(PNGCodec.probe(&png), PROBE_SIGNATURE);
+        assert_eq!(PNGCodec.probe(&dds), 0);
+        assert_eq!(DDSCodec::default().probe(&dds), PROBE_SIGNATURE);
+        assert_eq!(KTXCodec.probe(&dds), 0);
+        assert_eq!(GNFCodec.probe(&png), 0);
+        assert_eq!(TGACodec.probe(&tga), PROBE_HEURISTIC);
+        assert_eq!(TGACodec.probe(&png), 0);
+
+        // Truncated pixel data
+        assert_eq!(TGACodec.probe(&tga[..40]), 0);
+
+        let mut tga_v2 = tga.clone();
+        tga_v2.extend_from_slice(&[0; 8]);
+        tga_v2.extend_from_slice(b"TRUEVISION-XFILE.\0");
+        assert_eq!(TGACodec.probe(&tga_v2), PROBE_SIGNATURE);
+    }
+
+    #[test]
+    fn test_load_from_memory_detects_format() {
+        let codec_manager = CodecManager::default();
+        let dds = DDSCodec::default()
+            .save_to_memory(&build_texture(8, 8, PixelFormat::BC1))
+            .unwrap();
+
+        let texture = codec_manager.load_from_memory(&dds, None).unwrap();
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+
+        // The content wins over a wrong extension
+        let texture = codec_manager.load_from_memory(&dds, Some("png")).unwrap();
+        assert_eq!(texture.metadata.width, 8);
+
+        let texture = codec_manager
+            .load_from_memory(&build_tga(4, 2), None)
+            .unwrap();
+        assert_eq!(texture.metadata.height, 2);
+
+        assert!(codec_manager.load_from_memory(&[0xAA; 64], None).is_err());
+        assert!(codec_manager
+            .load_from_memory(&[0xAA; 64], Some("unknown"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_load_from_file_with_wrong_extension() {
+        let codec_manager = CodecManager::default();
+        let png = PNGCodec
+            .save_to_memory(&build_texture(6, 3, PixelFormat::R8G8B8A8))
+            .unwrap();
+
+        let dir = tempdir().unwrap();
+        let mislabeled = dir.path().join("mislabeled.tga");
+        let no_extension = dir.path().join("no_extension");
+        fs::write(&mislabeled, &png).unwrap();
+        fs::write(&no_extension, &png).unwrap();
+
+        for path in [mislabeled, no_extension] {
+            let texture = codec_manager.load_from_file(&path).unwrap();
+            assert_eq!(texture.metadata.width, 6);
+            assert_eq!(texture.metadata.height, 3);
+        }
+    }
+
+    #[test]
+    fn test_save_with_options() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("options.dds");
+        let texture = build_texture(8, 8, PixelFormat::BC1);
+        let effects = TextureEffects {
+            pixel_format: PixelFormat::BC1,
+            ..Default::default()
+        };
+
+        for (header_format, fourcc) in [
+            (DDSHeaderFormat::Auto, b"DXT1"),
+            (DDSHeaderFormat::DX10, b"DX10"),
+        ] {
+            let codec_manager = CodecManager::with_options(&CodecOptions {
+                dds_header_format: header_format,
+                ..Default::default()
+            });
+            codec_manager
+                .save_to_file(&path, &texture, &effects)
+                .unwrap();
+
+            assert_eq!(&fs::read(&path).unwrap()[84..88], fourcc);
+        }
+
+        let path = dir.path().join("options.ktx2");
+        let mut saved_pixels = None;
+
+        for (supercompression, scheme) in [
+            (KTX2Supercompression::None, 0u32),
+            (KTX2Supercompression::Zstandard, 2),
+            (KTX2Supercompression::Zlib, 3),
+        ] {
+            let codec_manager = CodecManager::with_options(&CodecOptions {
+                ktx2_supercompression: supercompression,
+                ..Default::default()
+            });
+            codec_manager
+                .save_to_file(&path, &texture, &effects)
+                .unwrap();
+
+            assert_eq!(fs::read(&path).unwrap()[44..48], scheme.to_le_bytes());
+
+            // Supercompression is lossless, every scheme loads the same blocks
+            let pixels = codec_manager.load_from_file(&path).unwrap().images[0]
+                .pixels
+                .clone();
+            assert_eq!(pixels, *saved_pixels.get_or_insert(pixels.clone()));
+        }
+    }
+}
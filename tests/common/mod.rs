@@ -0,0 +1,151 @@
+//! Fixtures shared by the integration tests, each test crate only uses some of them
+#![allow(dead_code)]
+
+use texture_viewer::graphics::{
+    pixel_format::{PixelFormat, PixelFormatInfo},
+    texture::{Image, TexDimension, TexMetadata, Texture},
+};
+
+/// Single image 2D texture filled with a repeating pattern
+pub fn build_texture(width: u32, height: u32, pixel_format: PixelFormat) -> Texture {
+    build_mip_texture(width, height, 1, pixel_format, 13)
+}
+
+/// 2D texture with `mip_levels` levels, `seed` changes the pattern so textures can be told apart
+pub fn build_mip_texture(
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    pixel_format: PixelFormat,
+    seed: u8,
+) -> Texture {
+    let pixel_format_info = PixelFormatInfo {
+        pixel_format,
+        ..Default::default()
+    };
+
+    let metadata = TexMetadata {
+        width,
+        height,
+        depth: 1,
+        array_size: 1,
+        mip_levels,
+        pixel_format_info,
+        dimensions: TexDimension::Tex2D,
+        ..Default::default()
+    };
+
+    let images = metadata
+        .get_image_sizes()
+        .into_iter()
+        .enumerate()
+        .map(|(mip, (width, height))| {
+            let size = pixel_format.get_slice_pitch(width, height) as usize;
+            let pixels = (0..size)
+                .map(|i| (i as u8).wrapping_mul(seed).wrapping_add(mip as u8))
+                .collect();
+            Image::new(width, height, pixel_format_info, pixels)
+        })
+        .collect();
+
+    Texture { metadata, images }
+}
+
+/// 2D texture with every mip and array item of the size
+pub fn build_array_texture(
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    array_size: u32,
+    pixel_format: PixelFormat,
+) -> Texture {
+    build_texture_with_metadata(TexMetadata {
+        width,
+        height,
+        depth: 1,
+        array_size,
+        mip_levels,
+        pixel_format_info: PixelFormatInfo {
+            pixel_format,
+            ..Default::default()
+        },
+        dimensions: TexDimension::Tex2D,
+        ..Default::default()
+    })
+}
+
+/// Texture with every image of the metadata, the bytes count up from the image index so each
+/// block and each image is distinguishable
+pub fn build_texture_with_metadata(metadata: TexMetadata) -> Texture {
+    let pixel_format = metadata.pixel_format_info.pixel_format;
+
+    let images = metadata
+        .get_image_sizes()
+        .into_iter()
+        .enumerate()
+        .map(|(index, (width, height))| {
+            let size =
+                pixel_format.get_slice_pitch(width, height) + pixel_format.get_palette_size();
+            let pixels = (0..size as usize).map(|i| (i * 7 + index) as u8).collect();
+
+            Image::new(width, height, metadata.pixel_format_info, pixels)
+        })
+        .collect();
+
+    Texture { metadata, images }
+}
+
+/// Same sizes and pixels, in the same order
+pub fn assert_same_images(texture: &Texture, expected: &[Image]) {
+    assert_eq!(texture.images.len(), expected.len());
+
+    for (index, (image, expected)) in texture.images.iter().zip(expected).enumerate() {
+        assert_eq!(
+            (image.width, image.height),
+            (expected.width, expected.height),
+            "image {}",
+            index
+        );
+        assert!(image.pixels == expected.pixels, "image {}", index);
+    }
+}
+
+/// Uncompressed 32 bit TGA with a top left origin
+pub fn build_tga(width: u16, height: u16) -> Vec<u8> {
+    let mut tga = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    tga.extend_from_slice(&width.to_le_bytes());
+    tga.extend_from_slice(&height.to_le_bytes());
+    tga.extend_from_slice(&[32, 0x28]);
+    tga.extend((0..width as usize * height as usize * 4).map(|i| (i * 5) as u8));
+    tga
+}
+
+/// Smooth gradients with some sharp edges and a varying alpha, as RGBA8
+pub fn build_test_image(width: u32, height: u32) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let edge = if (x / 5 + y / 3) % 2 == 0 { 96 } else { 0 };
+            [
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                ((x + y) * 4 % 160) as u8 + edge,
+                255 - (x * y % 200) as u8,
+            ]
+        })
+        .collect()
+}
+
+/// PSNR of RGBA8 pixels over their first `channels` channels
+pub fn get_psnr(a: &[u8], b: &[u8], channels: usize) -> f64 {
+    let errors: Vec<f64> = a
+        .iter()
+        .zip(b)
+        .enumerate()
+        .filter(|(i, _)| i % 4 < channels)
+        .map(|(_, (&a, &b))| (a as f64 - b as f64).powi(2))
+        .collect();
+    let error = errors.iter().sum::<f64>() / errors.len() as f64;
+
+    10.0 * (255.0 * 255.0 / error.max(1e-9)).log10()
+}
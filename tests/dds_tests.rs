@@ -0,0 +1,160 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{
+            dds::{DDSCodec, DDSHeaderFormat},
+            ImageCodec,
+        },
+        graphics::{
+            pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+            texture::{TexDimension, TexMetadata, Texture},
+        },
+    };
+
+    use crate::common::build_texture_with_metadata;
+
+    // DirectXTex TEX_ALPHA_MODE values
+    const ALPHA_MODE_STRAIGHT: u32 = 1;
+    const ALPHA_MODE_PREMULTIPLIED: u32 = 2;
+
+    /// 2D metadata with a single image, meant to be extended with struct update syntax
+    fn build_metadata(pixel_format: PixelFormat, color_space: ColorSpace) -> TexMetadata {
+        TexMetadata {
+            width: 4,
+            height: 4,
+            depth: 1,
+            array_size: 1,
+            mip_levels: 1,
+            pixel_format_info: PixelFormatInfo {
+                pixel_format,
+                pixel_data_type: PixelDataType::UNorm,
+                color_space,
+                is_premultiplied: false,
+            },
+            alpha_mode: 0,
+            dimensions: TexDimension::Tex2D,
+            is_cubemap: false,
+            is_volumemap: false,
+        }
+    }
+
+    fn get_fourcc(dds: &[u8]) -> &[u8] {
+        &dds[84..88]
+    }
+
+    fn round_trip(codec: &DDSCodec, texture: &Texture) -> (Vec<u8>, Texture) {
+        let saved = codec.save_to_memory(texture).expect("Failed to save DDS!");
+        let loaded = codec
+            .load_from_memory(&saved)
+            .expect("Failed to reload DDS!");
+
+        assert_eq!(loaded.metadata.width, texture.metadata.width);
+        assert_eq!(loaded.metadata.height, texture.metadata.height);
+        assert_eq!(loaded.metadata.depth, texture.metadata.depth);
+        assert_eq!(loaded.metadata.array_size, texture.metadata.array_size);
+        assert_eq!(loaded.metadata.mip_levels, texture.metadata.mip_levels);
+        assert_eq!(loaded.metadata.dimensions, texture.metadata.dimensions);
+        assert_eq!(loaded.metadata.is_cubemap, texture.metadata.is_cubemap);
+        assert_eq!(
+            loaded.metadata.pixel_format_info.pixel_format,
+            texture.metadata.pixel_format_info.pixel_format
+        );
+        assert_eq!(
+            loaded.metadata.pixel_format_info.color_space,
+            texture.metadata.pixel_format_info.color_space
+        );
+        assert_eq!(loaded.images.len(), texture.images.len());
+
+        for (a, b) in loaded.images.iter().zip(&texture.images) {
+            assert_eq!(a.pixels, b.pixels);
+        }
+
+        (saved, loaded)
+    }
+
+    #[test]
+    fn test_save_dds_cubemap() {
+        let texture = build_texture_with_metadata(TexMetadata {
+            width: 8,
+            height: 8,
+            array_size: 6,
+            mip_levels: 2,
+            is_cubemap: true,
+            ..build_metadata(PixelFormat::BC1, ColorSpace::Linear)
+        });
+
+        let (saved, _) = round_trip(&DDSCodec::default(), &texture);
+        assert_eq!(get_fourcc(&saved), b"DXT1");
+
+        let codec = DDSCodec {
+            header_format: DDSHeaderFormat::DX10,
+        };
+        let (saved, _) = round_trip(&codec, &texture);
+        assert_eq!(get_fourcc(&saved), b"DX10");
+
+        let codec = DDSCodec {
+            header_format: DDSHeaderFormat::Legacy,
+        };
+        let (saved, _) = round_trip(&codec, &texture);
+        assert_eq!(get_fourcc(&saved), b"DXT1");
+    }
+
+    #[test]
+    fn test_save_dds_srgb_array() {
+        let texture = build_texture_with_metadata(TexMetadata {
+            array_size: 3,
+            mip_levels: 3,
+            ..build_metadata(PixelFormat::R8G8B8A8, ColorSpace::SRGB)
+        });
+
+        let (saved, _) = round_trip(&DDSCodec::default(), &texture);
+        assert_eq!(get_fourcc(&saved), b"DX10");
+
+        let codec = DDSCodec {
+            header_format: DDSHeaderFormat::Legacy,
+        };
+        assert!(codec.save_to_memory(&texture).is_err());
+    }
+
+    #[test]
+    fn test_save_dds_volume() {
+        let texture = build_texture_with_metadata(TexMetadata {
+            depth: 4,
+            mip_levels: 3,
+            dimensions: TexDimension::Tex3D,
+            is_volumemap: true,
+            ..build_metadata(PixelFormat::R8, ColorSpace::Linear)
+        });
+
+        let (_, loaded) = round_trip(&DDSCodec::default(), &texture);
+        assert!(loaded.metadata.is_volumemap);
+        assert_eq!(
+            loaded.get_image(1, 0, 1).unwrap().pixels,
+            texture.get_image(1, 0, 1).unwrap().pixels
+        );
+    }
+
+    #[test]
+    fn test_save_dds_alpha_mode() {
+        let mut texture = build_texture_with_metadata(TexMetadata {
+            array_size: 2,
+            ..build_metadata(PixelFormat::R8G8B8A8, ColorSpace::Linear)
+        });
+        texture.metadata.alpha_mode = ALPHA_MODE_STRAIGHT;
+
+        let (_, loaded) = round_trip(&DDSCodec::default(), &texture);
+        assert_eq!(loaded.metadata.alpha_mode, ALPHA_MODE_STRAIGHT);
+
+        texture.metadata.alpha_mode = 0;
+        texture.metadata.pixel_format_info.is_premultiplied = true;
+
+        let codec = DDSCodec {
+            header_format: DDSHeaderFormat::DX10,
+        };
+        let (_, loaded) = round_trip(&codec, &texture);
+        assert_eq!(loaded.metadata.alpha_mode, ALPHA_MODE_PREMULTIPLIED);
+        assert!(loaded.metadata.pixel_format_info.is_premultiplied);
+    }
+}
@@ -0,0 +1,81 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, io};
+
+    use texture_viewer::{
+        codecs::{
+            codec_manager::CodecManager, dds::DDSCodec, ktx::KTXCodec, png::PNGCodec, ImageCodec,
+        },
+        error::TextureError,
+        graphics::{
+            pixel_format::PixelFormat,
+            pixel_format_decoder::PixelDecoder,
+            swizzling::{swizzle_manager::Swizzler, Platform},
+        },
+    };
+
+    use crate::common::build_texture;
+
+    #[test]
+    fn test_malformed_files() {
+        let dds = DDSCodec::default()
+            .save_to_memory(&build_texture(8, 8, PixelFormat::BC1))
+            .unwrap();
+
+        // Magic and header but no pixels
+        assert!(DDSCodec::default().load_from_memory(&dds[..128]).is_err());
+
+        let ktx = KTXCodec
+            .save_to_memory(&build_texture(4, 4, PixelFormat::R8G8B8A8))
+            .unwrap();
+        assert!(matches!(
+            KTXCodec.load_from_memory(&ktx[..30]),
+            Err(TextureError::CorruptHeader(_))
+        ));
+        assert!(matches!(
+            KTXCodec.load_from_memory(&dds),
+            Err(TextureError::CorruptHeader(_))
+        ));
+
+        assert!(matches!(
+            PNGCodec.load_from_memory(&[0x89, b'P', b'N', b'G', 0, 0]),
+            Err(TextureError::CorruptHeader(_) | TextureError::UnsupportedFormat(_))
+        ));
+
+        let codec_manager = CodecManager::default();
+        assert!(matches!(
+            codec_manager.load_from_memory(&[0xAA; 64], Some("unknown")),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        // 8 bytes is a single BC1 block, not 16x16 pixels
+        assert!(matches!(
+            PixelDecoder::decode(&[0; 8], PixelFormat::BC1.into(), 16, 16),
+            Err(TextureError::DimensionMismatch(_))
+        ));
+
+        let mut pixels = vec![0; 8];
+        assert!(matches!(
+            Swizzler::default().deswizzle(&mut pixels, 32, 32, PixelFormat::BC1, Platform::PS4),
+            Err(TextureError::Swizzle(_))
+        ));
+
+        let texture = build_texture(4, 4, PixelFormat::R8G8B8A8);
+        assert!(matches!(
+            texture.get_image(1, 0, 0),
+            Err(TextureError::InvalidParameter(_))
+        ));
+
+        let error = TextureError::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(error, TextureError::Io(_)));
+        assert!(error.source().is_some());
+
+        let error = TextureError::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(matches!(error, TextureError::CorruptHeader(_)));
+    }
+}
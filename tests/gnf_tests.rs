@@ -0,0 +1,227 @@
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{dds::DDSCodec, gnf::GNFCodec, ImageCodec},
+        error::TextureError,
+        graphics::{
+            pixel_format::{ColorSpace, PixelFormat, PixelFormatInfo},
+            swizzling::ps4::deswizzle_surface,
+            texture::{Image, TexDimension, TexMetadata, Texture},
+        },
+    };
+
+    const DATA_FORMAT_8_8_8_8: u32 = 10;
+    const DATA_FORMAT_BC3: u32 = 37;
+    const NUM_FORMAT_UNORM: u32 = 0;
+    const NUM_FORMAT_SRGB: u32 = 9;
+    const SELECT_XYZW: u32 = 4 | (5 << 3) | (6 << 6) | (7 << 9);
+    const SELECT_ZYXW: u32 = 6 | (5 << 3) | (4 << 6) | (7 << 9);
+    const TILE_MODE_LINEAR_ALIGNED: u32 = 8;
+    const TILE_MODE_THIN_1D_THIN: u32 = 13;
+    const TILE_MODE_THIN_2D_THIN: u32 = 14;
+    const TEXTURE_TYPE_2D: u32 = 9;
+
+    /// Assemble a single texture GNF file with its data at 0x100
+    fn build_gnf(
+        (data_format, num_format, select): (u32, u32, u32),
+        (width, height, pitch): (u32, u32, u32),
+        tile_mode: u32,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut gnf = b"GNF ".to_vec();
+        gnf.extend_from_slice(&0xF8u32.to_le_bytes());
+        gnf.extend_from_slice(&[2, 1, 8, 0]);
+        gnf.extend_from_slice(&(0x100 + data.len() as u32).to_le_bytes());
+
+        for register in [
+            0,
+            (data_format << 20) | (num_format << 26),
+            (width - 1) | ((height - 1) << 14),
+            select | (tile_mode << 20) | (TEXTURE_TYPE_2D << 28),
+            (pitch - 1) << 13,
+            0,
+            0,
+            data.len() as u32,
+        ] {
+            gnf.extend_from_slice(&register.to_le_bytes());
+        }
+
+        gnf.resize(0x100, 0);
+        gnf.extend_from_slice(data);
+        gnf
+    }
+
+    #[test]
+    fn test_gnf_deswizzle_sample() {
+        let dds = DDSCodec::default()
+            .load_from_file("tests/test_images/ps4_sample.dds".into())
+            .unwrap();
+        let swizzled = &dds.images[0].pixels;
+
+        let gnf = build_gnf(
+            (DATA_FORMAT_BC3, NUM_FORMAT_SRGB, SELECT_XYZW),
+            (1024, 1024, 1024),
+            TILE_MODE_THIN_1D_THIN,
+            swizzled,
+        );
+
+        let texture = GNFCodec.load_from_memory(&gnf).unwrap();
+        let pixel_format_info = texture.metadata.pixel_format_info;
+
+        assert_eq!(pixel_format_info.pixel_format, PixelFormat::BC3);
+        assert_eq!(pixel_format_info.color_space, ColorSpace::SRGB);
+        assert_eq!(
+            texture.images[0].pixels,
+            deswizzle_surface(swizzled, 1024, 1024, PixelFormat::BC3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_gnf_linear_aligned() {
+        // 2x2 B8G8R8A8 texture with rows padded to a pitch of 4 texels
+        let mut data = Vec::new();
+
+        for row in 0..2u8 {
+            data.extend_from_slice(&[row, 1, 2, 3, row, 5, 6, 7]);
+            data.extend_from_slice(&[0xEE; 8]);
+        }
+
+        let gnf = build_gnf(
+            (DATA_FORMAT_8_8_8_8, NUM_FORMAT_UNORM, SELECT_ZYXW),
+            (2, 2, 4),
+            TILE_MODE_LINEAR_ALIGNED,
+            &data,
+        );
+
+        let texture = GNFCodec.load_from_memory(&gnf).unwrap();
+
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::B8G8R8A8
+        );
+        assert_eq!(
+            texture.images[0].pixels,
+            vec![0, 1, 2, 3, 0, 5, 6, 7, 1, 1, 2, 3, 1, 5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn test_gnf_round_trip() {
+        let pixel_format_info = PixelFormatInfo {
+            pixel_format: PixelFormat::BC1,
+            ..Default::default()
+        };
+
+        let metadata = TexMetadata {
+            width: 40,
+            height: 24,
+            depth: 1,
+            array_size: 2,
+            mip_levels: 4,
+            pixel_format_info,
+            dimensions: TexDimension::Tex2D,
+            ..Default::default()
+        };
+
+        let images = metadata
+            .get_image_sizes()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (width, height))| {
+                let size = PixelFormat::BC1.get_slice_pitch(width, height) as usize;
+                let pixels = (0..size).map(|i| (i * 3 + index) as u8).collect();
+                Image::new(width, height, pixel_format_info, pixels)
+            })
+            .collect();
+
+        let texture = Texture { metadata, images };
+        let gnf = GNFCodec.save_to_memory(&texture).unwrap();
+        let loaded = GNFCodec.load_from_memory(&gnf).unwrap();
+
+        assert_eq!(loaded.metadata.width, 40);
+        assert_eq!(loaded.metadata.height, 24);
+        assert_eq!(loaded.metadata.array_size, 2);
+        assert_eq!(loaded.metadata.mip_levels, 4);
+
+        for (loaded_image, image) in loaded.images.iter().zip(&texture.images) {
+            assert_eq!(loaded_image.width, image.width);
+            assert_eq!(loaded_image.pixels, image.pixels);
+        }
+    }
+
+    #[test]
+    fn test_gnf_errors() {
+        assert!(GNFCodec.load_from_memory(b"GXT\0").is_err());
+
+        // Data smaller than the 8x8 tile padded surface
+        let gnf = build_gnf(
+            (DATA_FORMAT_8_8_8_8, NUM_FORMAT_UNORM, SELECT_XYZW),
+            (4, 4, 8),
+            TILE_MODE_THIN_1D_THIN,
+            &[0; 64],
+        );
+        assert!(GNFCodec.load_from_memory(&gnf).is_err());
+
+        // 2D thin tiling is not implemented
+        let gnf = build_gnf(
+            (DATA_FORMAT_8_8_8_8, NUM_FORMAT_UNORM, SELECT_XYZW),
+            (8, 8, 8),
+            TILE_MODE_THIN_2D_THIN,
+            &[0; 256],
+        );
+        assert!(matches!(
+            GNFCodec.load_from_memory(&gnf),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+
+        // More mips than an 8x8 image has
+        let mut gnf = build_gnf(
+            (DATA_FORMAT_8_8_8_8, NUM_FORMAT_UNORM, SELECT_XYZW),
+            (8, 8, 8),
+            TILE_MODE_THIN_1D_THIN,
+            &[0; 256],
+        );
+        gnf[0x1E] |= 0x0F;
+        assert!(GNFCodec.load_from_memory(&gnf).is_err());
+    }
+
+    #[test]
+    fn test_save_gnf_invalid_counts() {
+        let metadata = TexMetadata {
+            width: 4,
+            height: 4,
+            depth: 1,
+            array_size: 0,
+            mip_levels: 1,
+            pixel_format_info: PixelFormatInfo::default(),
+            alpha_mode: 0,
+            dimensions: TexDimension::Tex2D,
+            is_cubemap: false,
+            is_volumemap: false,
+        };
+        let texture = Texture {
+            metadata,
+            images: Vec::new(),
+        };
+        assert!(GNFCodec.save_to_memory(&texture).is_err());
+
+        let texture = Texture {
+            metadata: TexMetadata {
+                array_size: 0x2001,
+                ..metadata
+            },
+            images: Vec::new(),
+        };
+        assert!(GNFCodec.save_to_memory(&texture).is_err());
+
+        let texture = Texture {
+            metadata: TexMetadata {
+                array_size: 1,
+                mip_levels: 4,
+                ..metadata
+            },
+            images: Vec::new(),
+        };
+        assert!(GNFCodec.save_to_memory(&texture).is_err());
+    }
+}
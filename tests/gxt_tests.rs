@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{gxt::GXTCodec, ImageCodec},
+        graphics::{
+            pixel_format::{PixelFormat, PixelFormatInfo},
+            swizzling::psvita::swizzle_surface,
+            texture::{Image, TexDimension, TexMetadata, Texture},
+        },
+    };
+
+    const TEXTURE_SWIZZLED: u32 = 0x00000000;
+    const TEXTURE_LINEAR: u32 = 0x60000000;
+    const FORMAT_U8U8U8U8_ABGR: u32 = 0x0C000000;
+    const FORMAT_P8_ARGB: u32 = 0x95001000;
+
+    /// Hand-built GXT texture description
+    struct GXTTexture {
+        texture_type: u32,
+        texture_format: u32,
+        width: u16,
+        height: u16,
+        palette_index: i32,
+        data: Vec<u8>,
+    }
+
+    /// Assemble a GXT file, the P8 palettes go at the end of the data
+    fn build_gxt(textures: &[GXTTexture], p8_palettes: &[Vec<u8>]) -> Vec<u8> {
+        let data_offset = 0x20 + textures.len() * 0x20;
+        let mut infos = Vec::new();
+        let mut data = Vec::new();
+
+        for texture in textures {
+            for value in [
+                (data_offset + data.len()) as u32,
+                texture.data.len() as u32,
+                texture.palette_index as u32,
+                0,
+                texture.texture_type,
+                texture.texture_format,
+            ] {
+                infos.extend_from_slice(&value.to_le_bytes());
+            }
+
+            infos.extend_from_slice(&texture.width.to_le_bytes());
+            infos.extend_from_slice(&texture.height.to_le_bytes());
+            infos.extend_from_slice(&[1, 0, 0, 0]);
+            data.extend_from_slice(&texture.data);
+        }
+
+        for palette in p8_palettes {
+            data.extend_from_slice(palette);
+        }
+
+        let mut gxt = b"GXT\0".to_vec();
+
+        for value in [
+            0x10000003,
+            textures.len() as u32,
+            data_offset as u32,
+            data.len() as u32,
+            0,
+            p8_palettes.len() as u32,
+            0,
+        ] {
+            gxt.extend_from_slice(&value.to_le_bytes());
+        }
+
+        gxt.extend_from_slice(&infos);
+        gxt.extend_from_slice(&data);
+        gxt
+    }
+
+    #[test]
+    fn test_gxt_paletted_swizzled() {
+        let indices: Vec<u8> = (0..64).collect();
+        let swizzled = swizzle_surface(&indices, 8, 8, PixelFormat::R8).unwrap();
+
+        // ARGB entries are stored as B, G, R, A
+        let palette: Vec<u8> = (0..=255u8).flat_map(|i| [i, 1, 2, 3]).collect();
+
+        let gxt = build_gxt(
+            &[GXTTexture {
+                texture_type: TEXTURE_SWIZZLED,
+                texture_format: FORMAT_P8_ARGB,
+                width: 8,
+                height: 8,
+                palette_index: 0,
+                data: swizzled,
+            }],
+            &[palette],
+        );
+
+        let texture = GXTCodec.load_from_memory(&gxt).unwrap();
+
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::R8G8B8A8
+        );
+
+        let pixels = &texture.get_image(0, 0, 0).unwrap().pixels;
+        let expected: Vec<u8> = (0..64u8).flat_map(|i| [2, 1, i, 3]).collect();
+        assert_eq!(pixels, &expected);
+    }
+
+    #[test]
+    fn test_gxt_linear_array() {
+        // 4 texel rows are padded to 8 texels
+        let build_texture = |value: u8| {
+            let mut data = Vec::new();
+
+            for _ in 0..2 {
+                data.extend(std::iter::repeat_n(value, 4 * 4));
+                data.extend(std::iter::repeat_n(0xEE, 4 * 4));
+            }
+
+            GXTTexture {
+                texture_type: TEXTURE_LINEAR,
+                texture_format: FORMAT_U8U8U8U8_ABGR,
+                width: 4,
+                height: 2,
+                palette_index: -1,
+                data,
+            }
+        };
+
+        let gxt = build_gxt(&[build_texture(1), build_texture(2)], &[]);
+        let texture = GXTCodec.load_from_memory(&gxt).unwrap();
+
+        assert_eq!(texture.metadata.array_size, 2);
+        assert_eq!(texture.get_image(0, 0, 0).unwrap().pixels, vec![1; 32]);
+        assert_eq!(texture.get_image(0, 1, 0).unwrap().pixels, vec![2; 32]);
+    }
+
+    #[test]
+    fn test_gxt_round_trip() {
+        let pixel_format_info = PixelFormatInfo {
+            pixel_format: PixelFormat::BC1,
+            ..Default::default()
+        };
+
+        let metadata = TexMetadata {
+            width: 16,
+            height: 8,
+            depth: 1,
+            array_size: 2,
+            mip_levels: 3,
+            pixel_format_info,
+            dimensions: TexDimension::Tex2D,
+            ..Default::default()
+        };
+
+        let images = metadata
+            .get_image_sizes()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (width, height))| {
+                let size = PixelFormat::BC1.get_slice_pitch(width, height) as usize;
+                let pixels = (0..size).map(|i| (i + index * 7) as u8).collect();
+                Image::new(width, height, pixel_format_info, pixels)
+            })
+            .collect();
+
+        let texture = Texture { metadata, images };
+        let gxt = GXTCodec.save_to_memory(&texture).unwrap();
+        let loaded = GXTCodec.load_from_memory(&gxt).unwrap();
+
+        assert_eq!(loaded.metadata.array_size, 2);
+        assert_eq!(loaded.metadata.mip_levels, 3);
+        assert_eq!(
+            loaded.metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+
+        for (loaded_image, image) in loaded.images.iter().zip(&texture.images) {
+            assert_eq!(loaded_image.width, image.width);
+            assert_eq!(loaded_image.height, image.height);
+            assert_eq!(loaded_image.pixels, image.pixels);
+        }
+    }
+
+    #[test]
+    fn test_gxt_errors() {
+        assert!(GXTCodec.load_from_memory(b"DDS \0\0\0\0").is_err());
+
+        let texture = |width: u16| GXTTexture {
+            texture_type: TEXTURE_LINEAR,
+            texture_format: FORMAT_U8U8U8U8_ABGR,
+            width,
+            height: 1,
+            palette_index: -1,
+            data: vec![0; 64],
+        };
+
+        let gxt = build_gxt(&[texture(4), texture(8)], &[]);
+        assert!(GXTCodec.load_from_memory(&gxt).is_err());
+    }
+
+    #[test]
+    fn test_load_gxt_invalid_counts() {
+        let texture = GXTTexture {
+            texture_type: TEXTURE_LINEAR,
+            texture_format: FORMAT_U8U8U8U8_ABGR,
+            width: 4,
+            height: 4,
+            palette_index: -1,
+            data: vec![0; 64],
+        };
+        let gxt = build_gxt(&[texture], &[]);
+
+        // More texture infos than fit in the file
+        let mut corrupt = gxt.clone();
+        corrupt[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(GXTCodec.load_from_memory(&corrupt).is_err());
+
+        // More mips than a 4x4 image has
+        let mut corrupt = gxt.clone();
+        corrupt[0x20 + 0x1C] = 40;
+        assert!(GXTCodec.load_from_memory(&corrupt).is_err());
+    }
+}
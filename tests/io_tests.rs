@@ -5,7 +5,7 @@ use std::io::{self};
 mod tests {
     use super::*;
     use tempfile::tempdir;
-    use TextureViewer::io::file_manager::FileManager;
+    use texture_viewer::io::file_manager::FileManager;
 
     #[test]
     fn test_new_file_manager() -> Result<(), io::Error> {
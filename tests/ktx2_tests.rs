@@ -0,0 +1,385 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+    use texture_viewer::{
+        codecs::{
+            ktx2::{KTX2Codec, KTX2Supercompression},
+            ImageCodec,
+        },
+        graphics::{
+            pixel_format::{ColorSpace, PixelDataType, PixelFormat},
+            texture::{TexDimension, Texture},
+        },
+    };
+
+    const VK_FORMAT_R8_UNORM: u32 = 9;
+    const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+    const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+    const VK_FORMAT_BC1_RGBA_UNORM_BLOCK: u32 = 133;
+    const VK_FORMAT_BC6H_SFLOAT_BLOCK: u32 = 144;
+
+    /// Hand-built KTX2 header description
+    struct KTX2Header {
+        vk_format: u32,
+        width: u32,
+        height: u32,
+        depth: u32,
+        layers: u32,
+        faces: u32,
+        scheme: u32,
+    }
+
+    /// Assemble a KTX2 file without DFD/KVD from mip levels (level 0 first)
+    fn build_ktx2(header: KTX2Header, levels: &[Vec<u8>]) -> Vec<u8> {
+        let compressed_levels: Vec<Vec<u8>> = levels
+            .iter()
+            .map(|level| match header.scheme {
+                2 => zstd::bulk::compress(level, 3).unwrap(),
+                3 => {
+                    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(level).unwrap();
+                    encoder.finish().unwrap()
+                }
+                _ => level.clone(),
+            })
+            .collect();
+
+        let mut data = vec![
+            0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+        ];
+
+        for value in [
+            header.vk_format,
+            1,
+            header.width,
+            header.height,
+            header.depth,
+            header.layers,
+            header.faces,
+            levels.len() as u32,
+            header.scheme,
+            0,
+            0,
+            0,
+            0,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        data.extend_from_slice(&[0; 16]);
+
+        let mut offset = 80 + 24 * levels.len();
+
+        for (level, compressed) in levels.iter().zip(&compressed_levels) {
+            for value in [offset, compressed.len(), level.len()] {
+                data.extend_from_slice(&(value as u64).to_le_bytes());
+            }
+            offset += compressed.len();
+        }
+
+        for compressed in &compressed_levels {
+            data.extend_from_slice(compressed);
+        }
+
+        data
+    }
+
+    /// Level data where every image is filled with its index inside the level
+    fn build_level(image_size: usize, image_count: usize, mip: u8) -> Vec<u8> {
+        (0..image_count)
+            .flat_map(|image| vec![mip * 16 + image as u8; image_size])
+            .collect()
+    }
+
+    fn assert_same_texture(a: &Texture, b: &Texture) {
+        assert_eq!(a.metadata.width, b.metadata.width);
+        assert_eq!(a.metadata.height, b.metadata.height);
+        assert_eq!(a.metadata.depth, b.metadata.depth);
+        assert_eq!(a.metadata.array_size, b.metadata.array_size);
+        assert_eq!(a.metadata.mip_levels, b.metadata.mip_levels);
+        assert_eq!(a.metadata.is_cubemap, b.metadata.is_cubemap);
+        assert_eq!(a.metadata.dimensions, b.metadata.dimensions);
+        assert_eq!(
+            a.metadata.pixel_format_info.pixel_format,
+            b.metadata.pixel_format_info.pixel_format
+        );
+        assert_eq!(a.images.len(), b.images.len());
+
+        for (image_a, image_b) in a.images.iter().zip(&b.images) {
+            assert_eq!(image_a.width, image_b.width);
+            assert_eq!(image_a.height, image_b.height);
+            assert_eq!(image_a.pixels, image_b.pixels);
+        }
+    }
+
+    fn round_trip(texture: &Texture) {
+        for supercompression in [
+            KTX2Supercompression::None,
+            KTX2Supercompression::Zstandard,
+            KTX2Supercompression::Zlib,
+        ] {
+            let codec = KTX2Codec { supercompression };
+            let bytes = codec.save_to_memory(texture).expect("Failed to save KTX2!");
+            let loaded = codec
+                .load_from_memory(&bytes)
+                .expect("Failed to reload KTX2!");
+
+            assert_same_texture(texture, &loaded);
+        }
+    }
+
+    #[test]
+    fn test_load_ktx2_mip_chain() {
+        let levels = vec![
+            build_level(8 * 4 * 4, 1, 0),
+            build_level(4 * 2 * 4, 1, 1),
+            build_level(2 * 4, 1, 2),
+            build_level(4, 1, 3),
+        ];
+
+        let data = build_ktx2(
+            KTX2Header {
+                vk_format: VK_FORMAT_R8G8B8A8_SRGB,
+                width: 8,
+                height: 4,
+                depth: 0,
+                layers: 0,
+                faces: 1,
+                scheme: 0,
+            },
+            &levels,
+        );
+
+        let texture = KTX2Codec::default()
+            .load_from_memory(&data)
+            .expect("Failed to load KTX2!");
+
+        assert_eq!(texture.metadata.width, 8);
+        assert_eq!(texture.metadata.height, 4);
+        assert_eq!(texture.metadata.mip_levels, 4);
+        assert_eq!(texture.metadata.array_size, 1);
+        assert_eq!(texture.metadata.dimensions, TexDimension::Tex2D);
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::R8G8B8A8
+        );
+        assert_eq!(
+            texture.metadata.pixel_format_info.color_space,
+            ColorSpace::SRGB
+        );
+
+        let image = texture.get_image(2, 0, 0).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, levels[2]);
+
+        round_trip(&texture);
+    }
+
+    #[test]
+    fn test_load_ktx2_cubemap_array() {
+        // 2 layers of 6 faces, BC1 4x4 blocks
+        let levels = vec![build_level(2 * 2 * 8, 12, 0), build_level(8, 12, 1)];
+
+        let data = build_ktx2(
+            KTX2Header {
+                vk_format: VK_FORMAT_BC1_RGBA_UNORM_BLOCK,
+                width: 8,
+                height: 8,
+                depth: 0,
+                layers: 2,
+                faces: 6,
+                scheme: 0,
+            },
+            &levels,
+        );
+
+        let texture = KTX2Codec::default()
+            .load_from_memory(&data)
+            .expect("Failed to load KTX2!");
+
+        assert!(texture.metadata.is_cubemap);
+        assert_eq!(texture.metadata.array_size, 12);
+        assert_eq!(texture.metadata.mip_levels, 2);
+        assert_eq!(texture.images.len(), 24);
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+
+        // Second layer, third face (+Y), second mip
+        let image = texture.get_image(1, 8, 0).unwrap();
+        assert_eq!(image.pixels, vec![16 + 8; 8]);
+
+        round_trip(&texture);
+    }
+
+    #[test]
+    fn test_load_ktx2_volume() {
+        let levels = vec![
+            build_level(4 * 4, 4, 0),
+            build_level(2 * 2, 2, 1),
+            build_level(1, 1, 2),
+        ];
+
+        let data = build_ktx2(
+            KTX2Header {
+                vk_format: VK_FORMAT_R8_UNORM,
+                width: 4,
+                height: 4,
+                depth: 4,
+                layers: 0,
+                faces: 1,
+                scheme: 0,
+            },
+            &levels,
+        );
+
+        let texture = KTX2Codec::default()
+            .load_from_memory(&data)
+            .expect("Failed to load KTX2!");
+
+        assert_eq!(texture.metadata.dimensions, TexDimension::Tex3D);
+        assert!(texture.metadata.is_volumemap);
+        assert_eq!(texture.metadata.depth, 4);
+        assert_eq!(texture.images.len(), 7);
+
+        assert_eq!(texture.get_image(0, 0, 3).unwrap().pixels, vec![3; 16]);
+        assert_eq!(texture.get_image(1, 0, 1).unwrap().pixels, vec![17; 4]);
+        assert_eq!(texture.get_image(2, 0, 0).unwrap().pixels, vec![32]);
+
+        round_trip(&texture);
+    }
+
+    #[test]
+    fn test_load_ktx2_supercompressed() {
+        let levels = vec![build_level(4 * 4 * 4, 1, 0), build_level(2 * 2 * 4, 1, 1)];
+
+        for scheme in [2, 3] {
+            let data = build_ktx2(
+                KTX2Header {
+                    vk_format: VK_FORMAT_R8G8B8A8_UNORM,
+                    width: 4,
+                    height: 4,
+                    depth: 0,
+                    layers: 0,
+                    faces: 1,
+                    scheme,
+                },
+                &levels,
+            );
+
+            let texture = KTX2Codec::default()
+                .load_from_memory(&data)
+                .expect("Failed to load supercompressed KTX2!");
+
+            assert_eq!(texture.get_image(0, 0, 0).unwrap().pixels, levels[0]);
+            assert_eq!(texture.get_image(1, 0, 0).unwrap().pixels, levels[1]);
+        }
+    }
+
+    #[test]
+    fn test_load_ktx2_invalid() {
+        let codec = KTX2Codec::default();
+
+        assert!(codec.load_from_memory(b"not a ktx2 file").is_err());
+
+        // Truncated level data
+        let mut data = build_ktx2(
+            KTX2Header {
+                vk_format: VK_FORMAT_R8G8B8A8_UNORM,
+                width: 4,
+                height: 4,
+                depth: 0,
+                layers: 0,
+                faces: 1,
+                scheme: 0,
+            },
+            &[build_level(64, 1, 0)],
+        );
+        data.truncate(data.len() - 1);
+
+        assert!(codec.load_from_memory(&data).is_err());
+    }
+
+    #[test]
+    fn test_load_ktx2_invalid_counts() {
+        let codec = KTX2Codec::default();
+        let header = || KTX2Header {
+            vk_format: VK_FORMAT_R8G8B8A8_UNORM,
+            width: 4,
+            height: 4,
+            depth: 0,
+            layers: 0,
+            faces: 1,
+            scheme: 0,
+        };
+        let data = build_ktx2(header(), &[build_level(64, 1, 0)]);
+
+        // More levels than a 4x4 chain has, up to shifts of 32 bits and more
+        for level_count in [4u32, 40, u32::MAX] {
+            let mut corrupt = data.clone();
+            corrupt[40..44].copy_from_slice(&level_count.to_le_bytes());
+            assert!(codec.load_from_memory(&corrupt).is_err());
+        }
+
+        // Layer count overflowing once multiplied by the 6 faces
+        let mut corrupt = data.clone();
+        corrupt[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        corrupt[36..40].copy_from_slice(&6u32.to_le_bytes());
+        assert!(codec.load_from_memory(&corrupt).is_err());
+
+        // Huge dimensions whose level size does not fit
+        let mut corrupt = data.clone();
+        corrupt[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        corrupt[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(codec.load_from_memory(&corrupt).is_err());
+
+        // The uncompressed length of a supercompressed level is not used as a capacity
+        let level = build_level(64, 1, 0);
+        let mut data = build_ktx2(
+            KTX2Header {
+                scheme: 2,
+                ..header()
+            },
+            &[level.clone()],
+        );
+        data[96..104].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let texture = codec
+            .load_from_memory(&data)
+            .expect("Failed to load KTX2 with a wrong uncompressed length!");
+        assert_eq!(texture.get_image(0, 0, 0).unwrap().pixels, level);
+    }
+
+    #[test]
+    fn test_ktx2_bc6h_signed_round_trip() {
+        let data = build_ktx2(
+            KTX2Header {
+                vk_format: VK_FORMAT_BC6H_SFLOAT_BLOCK,
+                width: 4,
+                height: 4,
+                depth: 0,
+                layers: 0,
+                faces: 1,
+                scheme: 0,
+            },
+            &[build_level(16, 1, 0)],
+        );
+
+        let codec = KTX2Codec::default();
+        let texture = codec
+            .load_from_memory(&data)
+            .expect("Failed to load signed BC6H KTX2!");
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_data_type,
+            PixelDataType::SFloat
+        );
+
+        let saved = codec
+            .save_to_memory(&texture)
+            .expect("Failed to save KTX2!");
+        assert_eq!(saved[12..16], VK_FORMAT_BC6H_SFLOAT_BLOCK.to_le_bytes());
+    }
+}
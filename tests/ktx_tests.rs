@@ -0,0 +1,313 @@
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{ktx::KTXCodec, ImageCodec},
+        graphics::pixel_format::{ColorSpace, PixelFormat},
+    };
+
+    const GL_UNSIGNED_BYTE: u32 = 0x1401;
+    const GL_UNSIGNED_SHORT: u32 = 0x1403;
+    const GL_RED: u32 = 0x1903;
+    const GL_RGB: u32 = 0x1907;
+    const GL_RGBA: u32 = 0x1908;
+    const GL_R16: u32 = 0x822A;
+    const GL_SRGB8: u32 = 0x8C41;
+    const GL_COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+
+    /// Hand-built KTX header description
+    struct KTXHeader {
+        big_endian: bool,
+        gl_type: u32,
+        gl_type_size: u32,
+        gl_format: u32,
+        gl_internal_format: u32,
+        gl_base_internal_format: u32,
+        width: u32,
+        height: u32,
+        array_elements: u32,
+        faces: u32,
+    }
+
+    /// Assemble a KTX file from already padded mip levels (level 0 first).
+    /// Each level is a list of images (array elements/faces).
+    fn build_ktx(header: KTXHeader, levels: &[Vec<Vec<u8>>]) -> Vec<u8> {
+        let to_bytes = |value: u32| {
+            if header.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+
+        let mut data = vec![
+            0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+        ];
+
+        // Key/value data: "KTXorientation\0S=r,T=d\0" padded to 4 bytes
+        let key_value = b"KTXorientation\0S=r,T=d\0";
+        let mut key_value_data = to_bytes(key_value.len() as u32).to_vec();
+        key_value_data.extend_from_slice(key_value);
+        key_value_data.resize(key_value_data.len().div_ceil(4) * 4, 0);
+
+        for value in [
+            0x04030201,
+            header.gl_type,
+            header.gl_type_size,
+            header.gl_format,
+            header.gl_internal_format,
+            header.gl_base_internal_format,
+            header.width,
+            header.height,
+            0,
+            header.array_elements,
+            header.faces,
+            levels.len() as u32,
+            key_value_data.len() as u32,
+        ] {
+            data.extend_from_slice(&to_bytes(value));
+        }
+
+        data.extend_from_slice(&key_value_data);
+
+        let is_non_array_cubemap = header.faces == 6 && header.array_elements == 0;
+
+        for level in levels {
+            let image_size = if is_non_array_cubemap {
+                level[0].len()
+            } else {
+                level.iter().map(|image| image.len()).sum()
+            };
+
+            data.extend_from_slice(&to_bytes(image_size as u32));
+
+            for image in level {
+                data.extend_from_slice(image);
+            }
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_load_ktx_row_padding() {
+        // 3x2 RGB8: 9 byte rows padded to 12, 1x1 mip padded to 4
+        let mip0: Vec<u8> = (0..2)
+            .flat_map(|row| {
+                let mut padded_row: Vec<u8> = (0..9).map(|i| row * 9 + i).collect();
+                padded_row.extend_from_slice(&[0xEE; 3]);
+                padded_row
+            })
+            .collect();
+        let mip1 = vec![100, 101, 102, 0xEE];
+
+        let data = build_ktx(
+            KTXHeader {
+                big_endian: false,
+                gl_type: GL_UNSIGNED_BYTE,
+                gl_type_size: 1,
+                gl_format: GL_RGB,
+                gl_internal_format: GL_SRGB8,
+                gl_base_internal_format: GL_RGB,
+                width: 3,
+                height: 2,
+                array_elements: 0,
+                faces: 1,
+            },
+            &[vec![mip0], vec![mip1]],
+        );
+
+        let codec = KTXCodec;
+        let texture = codec.load_from_memory(&data).expect("Failed to load KTX!");
+
+        assert_eq!(texture.metadata.width, 3);
+        assert_eq!(texture.metadata.height, 2);
+        assert_eq!(texture.metadata.mip_levels, 2);
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::R8G8B8
+        );
+        assert_eq!(
+            texture.metadata.pixel_format_info.color_space,
+            ColorSpace::SRGB
+        );
+        assert_eq!(
+            texture.get_image(0, 0, 0).unwrap().pixels,
+            (0..18).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            texture.get_image(1, 0, 0).unwrap().pixels,
+            vec![100, 101, 102]
+        );
+
+        // Round trip restores the padding
+        let saved = codec.save_to_memory(&texture).expect("Failed to save KTX!");
+        let reloaded = codec
+            .load_from_memory(&saved)
+            .expect("Failed to reload KTX!");
+
+        assert_eq!(reloaded.images.len(), texture.images.len());
+        for (a, b) in reloaded.images.iter().zip(&texture.images) {
+            assert_eq!(a.pixels, b.pixels);
+        }
+        assert_eq!(
+            reloaded.metadata.pixel_format_info.color_space,
+            ColorSpace::SRGB
+        );
+    }
+
+    #[test]
+    fn test_load_ktx_big_endian() {
+        // 2x1 R16 with big endian file and pixel data
+        let data = build_ktx(
+            KTXHeader {
+                big_endian: true,
+                gl_type: GL_UNSIGNED_SHORT,
+                gl_type_size: 2,
+                gl_format: GL_RED,
+                gl_internal_format: GL_R16,
+                gl_base_internal_format: GL_RED,
+                width: 2,
+                height: 1,
+                array_elements: 0,
+                faces: 1,
+            },
+            &[vec![vec![0x12, 0x34, 0xAB, 0xCD]]],
+        );
+
+        let texture = KTXCodec
+            .load_from_memory(&data)
+            .expect("Failed to load big endian KTX!");
+
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::R16
+        );
+        assert_eq!(
+            texture.get_image(0, 0, 0).unwrap().pixels,
+            vec![0x34, 0x12, 0xCD, 0xAB]
+        );
+    }
+
+    #[test]
+    fn test_load_ktx_bc1_cubemap() {
+        // Non-array cubemap, 8x8 BC1 with 2 mips, each face filled with its index
+        let levels: Vec<Vec<Vec<u8>>> = [32, 8]
+            .iter()
+            .enumerate()
+            .map(|(mip, &size)| {
+                (0..6)
+                    .map(|face| vec![(mip * 16 + face) as u8; size])
+                    .collect()
+            })
+            .collect();
+
+        let data = build_ktx(
+            KTXHeader {
+                big_endian: false,
+                gl_type: 0,
+                gl_type_size: 1,
+                gl_format: 0,
+                gl_internal_format: GL_COMPRESSED_RGBA_S3TC_DXT1,
+                gl_base_internal_format: GL_RGBA,
+                width: 8,
+                height: 8,
+                array_elements: 0,
+                faces: 6,
+            },
+            &levels,
+        );
+
+        let codec = KTXCodec;
+        let texture = codec
+            .load_from_memory(&data)
+            .expect("Failed to load KTX cubemap!");
+
+        assert!(texture.metadata.is_cubemap);
+        assert_eq!(texture.metadata.array_size, 6);
+        assert_eq!(texture.metadata.mip_levels, 2);
+        assert_eq!(texture.images.len(), 12);
+        assert_eq!(
+            texture.metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+        assert_eq!(texture.get_image(0, 4, 0).unwrap().pixels, vec![4; 32]);
+        assert_eq!(texture.get_image(1, 5, 0).unwrap().pixels, vec![21; 8]);
+
+        // Saving writes the same layout back
+        let saved = codec.save_to_memory(&texture).expect("Failed to save KTX!");
+        let reloaded = codec
+            .load_from_memory(&saved)
+            .expect("Failed to reload KTX!");
+
+        assert!(reloaded.metadata.is_cubemap);
+        for (a, b) in reloaded.images.iter().zip(&texture.images) {
+            assert_eq!(a.pixels, b.pixels);
+        }
+    }
+
+    #[test]
+    fn test_load_ktx_invalid() {
+        assert!(KTXCodec.load_from_memory(b"not a ktx file").is_err());
+
+        let mut data = build_ktx(
+            KTXHeader {
+                big_endian: false,
+                gl_type: GL_UNSIGNED_BYTE,
+                gl_type_size: 1,
+                gl_format: GL_RGBA,
+                gl_internal_format: 0x8058,
+                gl_base_internal_format: GL_RGBA,
+                width: 4,
+                height: 4,
+                array_elements: 0,
+                faces: 1,
+            },
+            &[vec![vec![0; 64]]],
+        );
+        data.truncate(data.len() - 4);
+
+        assert!(KTXCodec.load_from_memory(&data).is_err());
+    }
+
+    #[test]
+    fn test_load_ktx_invalid_counts() {
+        let data = build_ktx(
+            KTXHeader {
+                big_endian: false,
+                gl_type: GL_UNSIGNED_BYTE,
+                gl_type_size: 1,
+                gl_format: GL_RGBA,
+                gl_internal_format: 0x8058,
+                gl_base_internal_format: GL_RGBA,
+                width: 4,
+                height: 4,
+                array_elements: 0,
+                faces: 1,
+            },
+            &[vec![vec![0; 64]]],
+        );
+
+        // More levels than a 4x4 chain has, up to shifts of 32 bits and more
+        for level_count in [4u32, 40, u32::MAX] {
+            let mut corrupt = data.clone();
+            corrupt[56..60].copy_from_slice(&level_count.to_le_bytes());
+            assert!(KTXCodec.load_from_memory(&corrupt).is_err());
+        }
+
+        // Array element count overflowing once multiplied by the 6 faces
+        let mut corrupt = data.clone();
+        corrupt[48..52].copy_from_slice(&u32::MAX.to_le_bytes());
+        corrupt[52..56].copy_from_slice(&6u32.to_le_bytes());
+        assert!(KTXCodec.load_from_memory(&corrupt).is_err());
+
+        // Array elements and dimensions far larger than the file
+        let mut corrupt = data.clone();
+        corrupt[48..52].copy_from_slice(&0x0100_0000u32.to_le_bytes());
+        assert!(KTXCodec.load_from_memory(&corrupt).is_err());
+
+        let mut corrupt = data;
+        corrupt[36..40].copy_from_slice(&u32::MAX.to_le_bytes());
+        corrupt[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(KTXCodec.load_from_memory(&corrupt).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use texture_viewer::graphics::{
+        mipmap_generator::{MipmapFilter, MipmapGenerator, MipmapOptions},
+        pixel_format::{ColorSpace, PixelDataType, PixelFormat, PixelFormatInfo},
+        texture::{Image, TexDimension, TexMetadata, Texture},
+        texture_utility::TextureEffects,
+    };
+
+    const FILTERS: [MipmapFilter; 4] = [
+        MipmapFilter::Box,
+        MipmapFilter::Triangle,
+        MipmapFilter::Kaiser,
+        MipmapFilter::Lanczos,
+    ];
+
+    fn build_rgba8_texture(
+        width: u32,
+        height: u32,
+        depth: u32,
+        array_size: u32,
+        dimensions: TexDimension,
+        is_cubemap: bool,
+    ) -> Texture {
+        let pixel_format_info = PixelFormatInfo {
+            pixel_format: PixelFormat::R8G8B8A8,
+            pixel_data_type: PixelDataType::UNorm,
+            color_space: ColorSpace::Linear,
+            is_premultiplied: false,
+        };
+
+        let image_count = if dimensions == TexDimension::Tex3D {
+            depth
+        } else {
+            array_size
+        };
+
+        // Each image is filled with its own solid color
+        let images = (0..image_count)
+            .map(|index| Image {
+                width,
+                height,
+                pixel_format_info,
+                row_pitch: width * 4,
+                slice_pitch: width * height * 4,
+                pixels: [index as u8 * 10, 255 - index as u8 * 2, 0, 255]
+                    .repeat((width * height) as usize),
+            })
+            .collect();
+
+        Texture {
+            metadata: TexMetadata {
+                width,
+                height,
+                depth,
+                array_size,
+                mip_levels: 1,
+                pixel_format_info,
+                alpha_mode: 0,
+                dimensions,
+                is_cubemap,
+                is_volumemap: dimensions == TexDimension::Tex3D,
+            },
+            images,
+        }
+    }
+
+    #[test]
+    fn test_mip_count() {
+        assert_eq!(MipmapGenerator::get_mip_count(256, 128, 1, None), 9);
+        assert_eq!(MipmapGenerator::get_mip_count(256, 128, 1, Some(3)), 3);
+        assert_eq!(MipmapGenerator::get_mip_count(5, 3, 1, None), 3);
+        assert_eq!(MipmapGenerator::get_mip_count(4, 4, 16, None), 5);
+        assert_eq!(MipmapGenerator::get_mip_count(1, 1, 1, Some(0)), 1);
+    }
+
+    #[test]
+    fn test_generate_2d_filters() {
+        // A solid image must stay solid with every filter
+        let pixels = [40, 80, 120, 200].repeat(7 * 5);
+
+        for filter in FILTERS {
+            let options = MipmapOptions {
+                filter,
+                ..Default::default()
+            };
+
+            let levels =
+                MipmapGenerator::generate_2d(7, 5, &pixels, ColorSpace::Linear, &options).unwrap();
+
+            assert_eq!(levels.len(), 3);
+            assert_eq!(levels[1], [40, 80, 120, 200].repeat(3 * 2));
+            assert_eq!(levels[2], vec![40, 80, 120, 200]);
+        }
+    }
+
+    #[test]
+    fn test_generate_2d_srgb() {
+        // Black and white columns
+        let pixels = [[0, 0, 0, 255], [255, 255, 255, 255]].concat().repeat(2);
+        let options = MipmapOptions::default();
+
+        let linear =
+            MipmapGenerator::generate_2d(2, 2, &pixels, ColorSpace::Linear, &options).unwrap();
+        assert_eq!(linear[1], vec![128, 128, 128, 255]);
+
+        // The average of black and white in linear light is brighter in sRGB
+        let srgb = MipmapGenerator::generate_2d(2, 2, &pixels, ColorSpace::SRGB, &options).unwrap();
+        assert_eq!(srgb[1], vec![188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn test_generate_alpha_coverage() {
+        // Half of the pixels pass an alpha test at 0.5, but every 2x2 block averages to 0.5
+        let pixels: Vec<u8> = (0..16)
+            .flat_map(|i| [255, 255, 255, if (i + i / 4) % 2 == 0 { 255 } else { 0 }])
+            .collect();
+
+        let options = MipmapOptions {
+            filter: MipmapFilter::Box,
+            ..Default::default()
+        };
+        let levels =
+            MipmapGenerator::generate_2d(4, 4, &pixels, ColorSpace::Linear, &options).unwrap();
+        assert!(levels[1].chunks(4).all(|pixel| pixel[3] <= 128));
+
+        let options = MipmapOptions {
+            alpha_coverage_reference: Some(0.5),
+            ..options
+        };
+        let levels =
+            MipmapGenerator::generate_2d(4, 4, &pixels, ColorSpace::Linear, &options).unwrap();
+        assert!(levels[1].chunks(4).all(|pixel| pixel[3] > 128));
+    }
+
+    #[test]
+    fn test_generate_cubemap_texture() {
+        let texture = build_rgba8_texture(8, 8, 1, 6, TexDimension::Tex2D, true);
+
+        let effects = TextureEffects {
+            pixel_format: PixelFormat::BC1,
+            mipmaps: Some(MipmapOptions {
+                filter: MipmapFilter::Lanczos,
+                max_levels: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mipmapped = texture.new_transformed_texture(&effects).unwrap();
+
+        assert_eq!(mipmapped.metadata.mip_levels, 3);
+        assert!(mipmapped.metadata.is_cubemap);
+        assert_eq!(mipmapped.images.len(), 18);
+
+        let image = mipmapped.get_image(2, 5, 0).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixel_format_info.pixel_format, PixelFormat::BC1);
+        assert_eq!(image.pixels.len(), 8);
+    }
+
+    #[test]
+    fn test_generate_volume_texture() {
+        let texture = build_rgba8_texture(4, 4, 4, 1, TexDimension::Tex3D, false);
+
+        let effects = TextureEffects {
+            mipmaps: Some(MipmapOptions::default()),
+            ..Default::default()
+        };
+
+        let mipmapped = texture.new_transformed_texture(&effects).unwrap();
+
+        assert_eq!(mipmapped.metadata.mip_levels, 3);
+        assert_eq!(mipmapped.images.len(), 4 + 2 + 1);
+
+        // Slices 0 and 1 are averaged into the first slice of mip 1
+        let image = mipmapped.get_image(1, 0, 0).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels[..4], [5, 254, 0, 255]);
+
+        let image = mipmapped.get_image(2, 0, 0).unwrap();
+        assert_eq!(image.pixels, vec![15, 252, 0, 255]);
+    }
+}
@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use texture_viewer::graphics::{
+        bc_encoder::CompressionOptions,
+        pixel_decoders::{
+            decode_ps2_clut_pixel, decode_rgb5a3_pixel, shuffle_ps2_clut_index, swap_gx_cmpr_block,
+        },
+        pixel_encoders::encode_ps2_clut_pixel,
+        pixel_format::PixelFormat,
+        pixel_format_decoder::PixelDecoder,
+        pixel_format_encoder::PixelEncoder,
+    };
+
+    const GX_FORMATS: [PixelFormat; 11] = [
+        PixelFormat::GxI4,
+        PixelFormat::GxI8,
+        PixelFormat::GxIA4,
+        PixelFormat::GxIA8,
+        PixelFormat::GxRGB565,
+        PixelFormat::GxRGB5A3,
+        PixelFormat::GxRGBA8,
+        PixelFormat::GxCMPR,
+        PixelFormat::GxC4,
+        PixelFormat::GxC8,
+        PixelFormat::GxC14X2,
+    ];
+
+    /// Vertical gray gradient in steps of 17, every third pixel transparent
+    fn build_gray_pixels(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let value = ((i / width) * 255 / (height - 1).max(1)) as u8;
+                [value, value, value, if i % 3 == 0 { 0 } else { 255 }]
+            })
+            .collect()
+    }
+
+    fn assert_close(decoded: &[u8], expected: &[u8], tolerance: u8) {
+        for (&a, &b) in decoded.iter().zip(expected) {
+            assert!(
+                a.abs_diff(b) <= tolerance,
+                "{:?} != {:?}",
+                decoded,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_gx_rgb5a3() {
+        assert_eq!(decode_rgb5a3_pixel(&[0xFF, 0xFF]), [255, 255, 255, 255]);
+        assert_eq!(decode_rgb5a3_pixel(&[0xFC, 0x00]), [255, 0, 0, 255]);
+        assert_eq!(decode_rgb5a3_pixel(&[0x7F, 0x00]), [255, 0, 0, 255]);
+        assert_eq!(decode_rgb5a3_pixel(&[0x30, 0x0F]), [0, 0, 255, 109]);
+        assert_eq!(decode_rgb5a3_pixel(&[0x00, 0x00]), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_gx_cmpr_block() {
+        // Colors are big endian and the first pixel of a row is in the high bits
+        let cmpr = [0xF8, 0x00, 0x00, 0x1F, 0b00_01_10_11, 0, 0xFF, 0x1B];
+        let bc1 = swap_gx_cmpr_block(&cmpr);
+
+        assert_eq!(bc1, [0x00, 0xF8, 0x1F, 0x00, 0b11_10_01_00, 0, 0xFF, 0xE4]);
+        assert_eq!(swap_gx_cmpr_block(&bc1), cmpr);
+
+        let rgba8 = PixelDecoder::decode(&cmpr, PixelFormat::GxCMPR.into(), 4, 4).unwrap();
+        assert_eq!(rgba8[0..4], [255, 0, 0, 255]);
+        assert_eq!(rgba8[4..8], [0, 0, 255, 255]);
+        assert_eq!(rgba8[12..16], [85, 0, 170, 255]);
+    }
+
+    #[test]
+    fn test_gx_round_trip() {
+        // 4 bit intensity and alpha keep these pixels exact
+        let pixels = build_gray_pixels(8, 16);
+
+        for pixel_format in GX_FORMATS {
+            let encoded = PixelEncoder::encode(
+                &pixels,
+                pixel_format.into(),
+                8,
+                16,
+                &CompressionOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(
+                encoded.len() as u32,
+                pixel_format.get_slice_pitch(8, 16) + pixel_format.get_palette_size(),
+                "{}",
+                pixel_format
+            );
+
+            let decoded = PixelDecoder::decode(&encoded, pixel_format.into(), 8, 16).unwrap();
+            assert_eq!(decoded.len(), pixels.len());
+
+            for (decoded, pixel) in decoded.chunks_exact(4).zip(pixels.chunks_exact(4)) {
+                match pixel_format {
+                    // Intensity is also the alpha
+                    PixelFormat::GxI4 | PixelFormat::GxI8 => assert_eq!(decoded, [pixel[0]; 4]),
+                    PixelFormat::GxRGB565 => assert_close(&decoded[..3], &pixel[..3], 8),
+                    // Transparent CMPR pixels are black
+                    PixelFormat::GxCMPR if pixel[3] == 0 => assert_eq!(decoded, [0; 4]),
+                    PixelFormat::GxCMPR => assert_close(decoded, pixel, 16),
+                    // 32 colors don't fit, see test_gx_palette_quantization
+                    PixelFormat::GxC4 => {}
+                    PixelFormat::GxIA4 | PixelFormat::GxIA8 | PixelFormat::GxRGBA8 => {
+                        assert_eq!(decoded, pixel, "{}", pixel_format)
+                    }
+                    // 5 bit channels, directly or in the TLUT
+                    _ => assert_close(decoded, pixel, 8),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gx_palette_quantization() {
+        // 64 colors don't fit in a C4 palette
+        let pixels: Vec<u8> = (0..64u32)
+            .flat_map(|i| [(i * 4) as u8, 255 - (i * 4) as u8, (i % 8 * 32) as u8, 255])
+            .collect();
+
+        let encoded = PixelEncoder::encode(
+            &pixels,
+            PixelFormat::GxC4.into(),
+            8,
+            8,
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(encoded.len(), 32 + 32);
+
+        let decoded = PixelDecoder::decode(&encoded, PixelFormat::GxC4.into(), 8, 8).unwrap();
+        let colors: HashSet<&[u8]> = decoded.chunks_exact(4).collect();
+        assert!(colors.len() <= 16);
+
+        let error: u32 = decoded
+            .iter()
+            .zip(&pixels)
+            .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs())
+            .max()
+            .unwrap();
+        assert!(error < 48, "max error {}", error);
+
+        // Too small for the palette
+        assert!(PixelDecoder::decode(&encoded[..40], PixelFormat::GxC4.into(), 8, 8).is_err());
+    }
+
+    #[test]
+    fn test_gx_format_names() {
+        for pixel_format in GX_FORMATS {
+            assert_eq!(
+                pixel_format.to_string().parse::<PixelFormat>(),
+                Ok(pixel_format)
+            );
+        }
+
+        assert_eq!("gx_cmpr".parse::<PixelFormat>(), Ok(PixelFormat::GxCMPR));
+        assert_eq!(PixelFormat::GxCMPR.get_slice_pitch(8, 8), 32);
+        assert_eq!(PixelFormat::GxI4.get_row_pitch(5), 3);
+    }
+
+    #[test]
+    fn test_ps2_clut() {
+        assert_eq!(
+            decode_ps2_clut_pixel(&[10, 20, 30, 0x80]),
+            [10, 20, 30, 255]
+        );
+        assert_eq!(decode_ps2_clut_pixel(&[0, 0, 0, 0xFF]), [0, 0, 0, 255]);
+        assert_eq!(
+            encode_ps2_clut_pixel(&[10, 20, 30, 255]),
+            [10, 20, 30, 0x80]
+        );
+        assert_eq!(encode_ps2_clut_pixel(&[0, 0, 0, 0]), [0, 0, 0, 0]);
+
+        // Entries 8-15 and 16-23 of every 32 are swapped
+        assert_eq!(shuffle_ps2_clut_index(7), 7);
+        assert_eq!(shuffle_ps2_clut_index(8), 16);
+        assert_eq!(shuffle_ps2_clut_index(23), 15);
+        assert_eq!(shuffle_ps2_clut_index(40), 48);
+        for index in 0..256 {
+            assert_eq!(shuffle_ps2_clut_index(shuffle_ps2_clut_index(index)), index);
+        }
+    }
+
+    #[test]
+    fn test_ps2_round_trip() {
+        // 32 colors fit in both CLUTs once PSMT4 keeps every other one
+        let pixels: Vec<u8> = (0..16 * 8u32)
+            .flat_map(|i| [(i % 32 * 8) as u8, 0, 255 - (i % 32 * 8) as u8, 255])
+            .collect();
+
+        let encoded = PixelEncoder::encode(
+            &pixels,
+            PixelFormat::Ps2PSMT8.into(),
+            16,
+            8,
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(encoded.len(), 16 * 8 + 1024);
+        let decoded = PixelDecoder::decode(&encoded, PixelFormat::Ps2PSMT8.into(), 16, 8).unwrap();
+        assert_eq!(decoded, pixels);
+
+        let pixels: Vec<u8> = (0..16 * 8u32)
+            .flat_map(|i| {
+                [
+                    (i % 16 * 16) as u8,
+                    128,
+                    0,
+                    if i % 2 == 0 { 255 } else { 0 },
+                ]
+            })
+            .collect();
+
+        let encoded = PixelEncoder::encode(
+            &pixels,
+            PixelFormat::Ps2PSMT4.into(),
+            16,
+            8,
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(encoded.len(), 16 * 8 / 2 + 64);
+        let decoded = PixelDecoder::decode(&encoded, PixelFormat::Ps2PSMT4.into(), 16, 8).unwrap();
+        assert_eq!(decoded, pixels);
+
+        // The first pixel is in the low nibble
+        let mut pixels = vec![0; 8 * 8 * 4];
+        pixels[..4].copy_from_slice(&[255; 4]);
+        let encoded = PixelEncoder::encode(
+            &pixels,
+            PixelFormat::Ps2PSMT4.into(),
+            8,
+            8,
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(encoded[0], 0x01);
+        assert_eq!(encoded[32..40], [0, 0, 0, 0, 255, 255, 255, 0x80]);
+
+        assert_eq!("psmt4".parse::<PixelFormat>(), Ok(PixelFormat::Ps2PSMT4));
+        assert_eq!(
+            PixelFormat::Ps2PSMT8.to_string().parse::<PixelFormat>(),
+            Ok(PixelFormat::Ps2PSMT8)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{
+            raw::{guess_raw_layouts, RawCodec, RawLayoutGuess, RAW_PIXEL_FORMATS},
+            ImageCodec,
+        },
+        error::TextureError,
+        graphics::{
+            pixel_format::PixelFormat,
+            swizzling::{
+                swizzle_manager::{SwizzleOptions, Swizzler},
+                Platform,
+            },
+        },
+    };
+
+    #[test]
+    fn test_raw_offset_and_mips() {
+        // 4x4 R8G8B8A8 with 3 mips after an 8 byte header
+        let mut source = vec![0xEE; 8];
+        source.extend((0..(16 + 4 + 1) * 4).map(|i| i as u8));
+
+        let codec = RawCodec {
+            offset: 8,
+            width: 4,
+            height: 4,
+            mip_levels: 3,
+            ..Default::default()
+        };
+
+        let texture = codec.load_from_memory(&source).unwrap();
+        assert_eq!(texture.metadata.mip_levels, 3);
+        assert_eq!(texture.images.len(), 3);
+        assert_eq!(texture.images[0].pixels, source[8..72]);
+        assert_eq!(texture.images[1].pixels, source[72..88]);
+        assert_eq!(texture.images[2].pixels, source[88..92]);
+
+        assert_eq!(codec.save_to_memory(&texture).unwrap(), source[8..]);
+
+        // One byte short of the last mip
+        assert!(codec.load_from_memory(&source[..91]).is_err());
+
+        let codec = RawCodec {
+            mip_levels: 4,
+            ..codec
+        };
+        assert!(codec.load_from_memory(&source).is_err());
+    }
+
+    #[test]
+    fn test_raw_too_large() {
+        // 16384x16384 R32G32B32A32 is 4 GiB, more than a u32 slice pitch
+        let codec = RawCodec {
+            width: 16384,
+            height: 16384,
+            pixel_format: PixelFormat::R32G32B32A32,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            codec.load_from_memory(&[0; 64]),
+            Err(TextureError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_raw_deswizzle() {
+        let codec = RawCodec {
+            width: 32,
+            height: 32,
+            pixel_format: PixelFormat::BC1,
+            platform: Some(Platform::PS4),
+            ..Default::default()
+        };
+
+        let linear = RawCodec {
+            platform: None,
+            ..codec
+        };
+
+        let source: Vec<u8> = (0..512).map(|i| (i * 7) as u8).collect();
+        let texture = linear.load_from_memory(&source).unwrap();
+        let swizzled = codec.save_to_memory(&texture).unwrap();
+        assert_ne!(swizzled, source);
+
+        let deswizzled = codec.load_from_memory(&swizzled).unwrap();
+        assert_eq!(deswizzled.images[0].pixels, source);
+    }
+
+    #[test]
+    fn test_raw_swizzle_options() {
+        let swizzle_options = SwizzleOptions {
+            switch_block_height: Some(1),
+            ..Default::default()
+        };
+        let codec = RawCodec {
+            width: 64,
+            height: 64,
+            platform: Some(Platform::Switch),
+            swizzle_options,
+            ..Default::default()
+        };
+
+        let linear = RawCodec {
+            platform: None,
+            ..codec
+        };
+
+        let source: Vec<u8> = (0..64 * 64 * 4).map(|i| (i * 7) as u8).collect();
+        let texture = linear.load_from_memory(&source).unwrap();
+
+        // The block height of the options is used, not the one picked from the size
+        let swizzled = codec.save_to_memory(&texture).unwrap();
+        let expected = Swizzler::with_options(&swizzle_options)
+            .swizzle_texture(&texture, Platform::Switch)
+            .unwrap();
+        assert_eq!(swizzled, expected);
+
+        let default_options = RawCodec {
+            swizzle_options: SwizzleOptions::default(),
+            ..codec
+        };
+        assert_ne!(default_options.save_to_memory(&texture).unwrap(), swizzled);
+
+        let deswizzled = codec.load_from_memory(&swizzled).unwrap();
+        assert_eq!(deswizzled.images[0].pixels, source);
+    }
+
+    #[test]
+    fn test_raw_guess_layouts() {
+        let guesses = guess_raw_layouts(64 * 64 * 4, RAW_PIXEL_FORMATS);
+        assert_eq!(
+            guesses[0],
+            RawLayoutGuess {
+                width: 64,
+                height: 64,
+                pixel_format: PixelFormat::R8G8B8A8,
+                mip_levels: 1,
+            }
+        );
+        assert!(guesses.contains(&RawLayoutGuess {
+            width: 128,
+            height: 128,
+            pixel_format: PixelFormat::BC3,
+            mip_levels: 1,
+        }));
+        assert!(guesses
+            .iter()
+            .all(|guess| guess.width.max(guess.height) <= guess.width.min(guess.height) * 8));
+
+        // A full mip chain
+        let guesses = guess_raw_layouts(
+            (4096 + 1024 + 256 + 64 + 16 + 4 + 1) * 4,
+            &[PixelFormat::R8G8B8A8],
+        );
+        assert_eq!(
+            guesses,
+            vec![RawLayoutGuess {
+                width: 64,
+                height: 64,
+                pixel_format: PixelFormat::R8G8B8A8,
+                mip_levels: 7,
+            }]
+        );
+
+        assert!(guess_raw_layouts(0, RAW_PIXEL_FORMATS).is_empty());
+    }
+}
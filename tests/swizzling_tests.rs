@@ -0,0 +1,1013 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use texture_viewer::graphics::{
+        pixel_format::{PixelFormat, PixelFormatInfo},
+        swizzling::{
+            gx::{self, GXSwizzler},
+            morton::{self, MortonSwizzler},
+            ps2::PS2Swizzler,
+            ps4,
+            psvita::{self, PSVitaLayout, PSVitaSwizzler},
+            switch::{self, SwitchSwizzler},
+            swizzle_manager::{SwizzleOptions, Swizzler},
+            wii_u::{GX2TileMode, WiiUSwizzler},
+            xbox_360, Platform, Swizzable,
+        },
+        texture::Image,
+        texture_utility::TextureEffects,
+    };
+
+    use crate::common::{assert_same_images, build_array_texture};
+
+    /// Bytes counting up, so every block is distinguishable
+    fn build_pixels(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_switch_gob_layout() {
+        // 16x8 RGBA8 is exactly one GOB. Pixel values are their linear index.
+        let linear: Vec<u8> = (0..16 * 8).flat_map(|i| [i as u8; 4]).collect();
+
+        let swizzled = switch::swizzle_block_linear(&linear, 16, 8, PixelFormat::R8G8B8A8, 1)
+            .expect("Failed to swizzle!");
+
+        assert_eq!(swizzled.len(), 512);
+        // The first 16 byte sector is the first 4 pixels of row 0, then 4 pixels of row 1
+        assert_eq!(swizzled[0..4], [0; 4]);
+        assert_eq!(swizzled[16..20], [16; 4]);
+        // Sectors of the same row are 32 bytes apart
+        assert_eq!(swizzled[32..36], [4; 4]);
+        // Rows 2-3 start after 64 bytes
+        assert_eq!(swizzled[64..68], [32; 4]);
+        // Right half of the GOB
+        assert_eq!(swizzled[256..260], [8; 4]);
+    }
+
+    #[test]
+    fn test_switch_block_height() {
+        assert_eq!(switch::get_block_height(1), 1);
+        assert_eq!(switch::get_block_height(16), 2);
+        assert_eq!(switch::get_block_height(64), 8);
+        assert_eq!(switch::get_block_height(256), 16);
+
+        assert_eq!(switch::get_mip_block_height(256, 16), 16);
+        assert_eq!(switch::get_mip_block_height(64, 16), 8);
+        assert_eq!(switch::get_mip_block_height(20, 16), 4);
+        assert_eq!(switch::get_mip_block_height(1, 16), 1);
+    }
+
+    #[test]
+    fn test_switch_npot_bc1() {
+        // 100x60 BC1 is 25x15 blocks, padded to 4 GOBs wide and one 32 row block
+        let linear = build_pixels(PixelFormat::BC1.get_slice_pitch(100, 60) as usize);
+        let size = switch::get_surface_size(100, 60, PixelFormat::BC1, 4);
+        assert_eq!(size, 4 * 4 * 512);
+
+        let swizzled = switch::swizzle_block_linear(&linear, 100, 60, PixelFormat::BC1, 4).unwrap();
+        assert_eq!(swizzled.len(), size);
+
+        let deswizzled =
+            switch::deswizzle_block_linear(&swizzled, 100, 60, PixelFormat::BC1, 4).unwrap();
+        assert_eq!(deswizzled, linear);
+
+        // Through the Swizzable trait, with room for the padding
+        let swizzler = SwitchSwizzler {
+            block_height: Some(4),
+        };
+        let mut pixels = linear.clone();
+        pixels.resize(size, 0);
+
+        swizzler
+            .swizzle(&mut pixels, 100, 60, PixelFormat::BC1)
+            .unwrap();
+        assert_eq!(pixels, swizzled);
+
+        swizzler
+            .deswizzle(&mut pixels, 100, 60, PixelFormat::BC1)
+            .unwrap();
+        assert_eq!(pixels[..linear.len()], linear);
+    }
+
+    #[test]
+    fn test_switch_block_height_option() {
+        let swizzler = Swizzler::with_options(&SwizzleOptions {
+            switch_block_height: Some(1),
+            ..Default::default()
+        });
+
+        // 20 rows pick 4 GOB blocks (32 rows) by default, a single GOB pads them to 24 rows
+        let size = swizzler
+            .get_surface_size(64, 20, PixelFormat::R8G8B8A8, Platform::Switch)
+            .unwrap();
+        assert_eq!(size, 64 * 4 * 24);
+        assert_eq!(
+            Swizzler::default()
+                .get_surface_size(64, 20, PixelFormat::R8G8B8A8, Platform::Switch)
+                .unwrap(),
+            64 * 4 * 32
+        );
+
+        let linear = build_pixels(64 * 64 * 4);
+        let mut pixels = linear.clone();
+        swizzler
+            .swizzle(&mut pixels, 64, 64, PixelFormat::R8G8B8A8, Platform::Switch)
+            .unwrap();
+        assert_eq!(
+            pixels,
+            switch::swizzle_block_linear(&linear, 64, 64, PixelFormat::R8G8B8A8, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_switch_mip_chain() {
+        let swizzler = SwitchSwizzler::default();
+        let levels: Vec<Vec<u8>> = (0..7)
+            .map(|mip| {
+                build_pixels(PixelFormat::BC3.get_slice_pitch(256 >> mip, 128 >> mip) as usize)
+            })
+            .collect();
+
+        let swizzled = swizzler
+            .swizzle_mip_chain(&levels, 256, 128, PixelFormat::BC3)
+            .unwrap();
+        assert_eq!(
+            swizzled.len(),
+            swizzler
+                .get_mip_chain_size(256, 128, 7, PixelFormat::BC3)
+                .unwrap()
+        );
+
+        let deswizzled = swizzler
+            .deswizzle_mip_chain(&swizzled, 256, 128, 7, PixelFormat::BC3)
+            .unwrap();
+        assert_eq!(deswizzled, levels);
+
+        assert!(swizzler
+            .deswizzle_mip_chain(
+                &swizzled[..swizzled.len() - 1],
+                256,
+                128,
+                7,
+                PixelFormat::BC3
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_switch_errors() {
+        let mut pixels = build_pixels(64 * 64 * 4);
+
+        let swizzler = SwitchSwizzler {
+            block_height: Some(3),
+        };
+        assert!(swizzler
+            .swizzle(&mut pixels, 64, 64, PixelFormat::R8G8B8A8)
+            .is_err());
+
+        // 20x20 RGBA8 needs padding that doesn't fit in the linear buffer
+        let mut pixels = build_pixels(20 * 20 * 4);
+        assert!(Swizzler::default()
+            .swizzle(&mut pixels, 20, 20, PixelFormat::R8G8B8A8, Platform::Switch)
+            .is_err());
+    }
+
+    #[test]
+    fn test_wii_u_micro_tile_layout() {
+        // Pixel values are their linear index
+        let linear: Vec<u8> = (0..16 * 8).flat_map(|i| [i as u8; 4]).collect();
+
+        let swizzler = WiiUSwizzler {
+            tile_mode: GX2TileMode::LinearAligned,
+            ..Default::default()
+        };
+        // The pitch is padded to 64 pixels
+        let tiled = swizzler
+            .swizzle_surface(&linear, 16, 8, PixelFormat::R8G8B8A8)
+            .unwrap();
+        assert_eq!(tiled.len(), 64 * 8 * 4);
+        assert_eq!(tiled[(64 + 1) * 4..(64 + 2) * 4], [17; 4]);
+
+        let swizzler = WiiUSwizzler {
+            tile_mode: GX2TileMode::Tiled1DThin1,
+            ..Default::default()
+        };
+        let tiled = swizzler
+            .swizzle_surface(&linear, 16, 8, PixelFormat::R8G8B8A8)
+            .unwrap();
+        assert_eq!(tiled.len(), 16 * 8 * 4);
+        // 32 bit pixels in a 8x8 micro tile go x0, x1, y0, x2, y1, y2
+        assert_eq!(tiled[4..8], [1; 4]);
+        assert_eq!(tiled[8..12], [2; 4]);
+        assert_eq!(tiled[16..20], [16; 4]);
+        assert_eq!(tiled[32..36], [4; 4]);
+        // Second micro tile
+        assert_eq!(tiled[256..260], [8; 4]);
+    }
+
+    #[test]
+    fn test_wii_u_macro_tile_swizzle() {
+        let linear = build_pixels(32 * 16 * 4);
+
+        let swizzler = WiiUSwizzler::default();
+        let tiled = swizzler
+            .swizzle_surface(&linear, 32, 16, PixelFormat::R8G8B8A8)
+            .unwrap();
+        assert_eq!(tiled.len(), linear.len());
+        assert_eq!(tiled[..4], linear[..4]);
+        // Pixel (8, 0) is on pipe 1, bank 1
+        assert_eq!(tiled[768..772], linear[32..36]);
+
+        // The pipe swizzle moves the first micro tile to pipe 1
+        let swizzled_pipe = WiiUSwizzler {
+            swizzle: 0x100,
+            ..Default::default()
+        };
+        let tiled_pipe = swizzled_pipe
+            .swizzle_surface(&linear, 32, 16, PixelFormat::R8G8B8A8)
+            .unwrap();
+        assert_eq!(tiled_pipe[256..260], linear[..4]);
+
+        for swizzler in [swizzler, swizzled_pipe] {
+            let deswizzled = swizzler
+                .deswizzle_surface(&tiled_pipe, 32, 16, PixelFormat::R8G8B8A8)
+                .unwrap();
+            assert_eq!(deswizzled == linear, swizzler.swizzle == 0x100);
+        }
+    }
+
+    #[test]
+    fn test_wii_u_round_trip() {
+        let tile_modes = [
+            GX2TileMode::LinearGeneral,
+            GX2TileMode::LinearAligned,
+            GX2TileMode::Tiled1DThin1,
+            GX2TileMode::Tiled2DThin1,
+            GX2TileMode::Tiled2DThin2,
+            GX2TileMode::Tiled2DThin4,
+            GX2TileMode::Tiled2BThin1,
+            GX2TileMode::Tiled2BThin4,
+            GX2TileMode::Tiled3DThin1,
+            GX2TileMode::Tiled3BThin1,
+        ];
+        let formats = [
+            PixelFormat::R8,
+            PixelFormat::B5G6R5,
+            PixelFormat::R8G8B8A8,
+            PixelFormat::R16G16B16A16,
+            PixelFormat::R32G32B32A32,
+            PixelFormat::BC1,
+            PixelFormat::BC3,
+            PixelFormat::BC5,
+        ];
+
+        for tile_mode in tile_modes {
+            for pixel_format in formats {
+                let swizzler = WiiUSwizzler {
+                    tile_mode,
+                    swizzle: 0x600,
+                };
+                // NPOT, so the surface gets padded
+                let linear = build_pixels(pixel_format.get_slice_pitch(100, 60) as usize);
+                let size = swizzler.get_surface_size(100, 60, pixel_format).unwrap();
+
+                let mut pixels = linear.clone();
+                pixels.resize(size, 0);
+                swizzler
+                    .swizzle(&mut pixels, 100, 60, pixel_format)
+                    .unwrap();
+                swizzler
+                    .deswizzle(&mut pixels, 100, 60, pixel_format)
+                    .unwrap();
+
+                assert_eq!(
+                    pixels[..linear.len()],
+                    linear,
+                    "{:?} {}",
+                    tile_mode,
+                    pixel_format
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_wii_u_small_mip_tile_mode() {
+        let swizzler = WiiUSwizzler {
+            tile_mode: GX2TileMode::Tiled2BThin1,
+            swizzle: 0x200,
+        };
+        let pixel_format = PixelFormat::R8G8B8A8;
+
+        // RGBA8 macro tiles are 32x16, mips below that are 1D tiled and never bank swapped
+        assert_eq!(
+            swizzler.get_mip_tile_mode(16, 16, 0, pixel_format),
+            GX2TileMode::Tiled2BThin1
+        );
+        assert_eq!(
+            swizzler.get_mip_tile_mode(32, 32, 1, pixel_format),
+            GX2TileMode::Tiled2DThin1
+        );
+        assert_eq!(
+            swizzler.get_mip_tile_mode(16, 16, 2, pixel_format),
+            GX2TileMode::Tiled1DThin1
+        );
+        // R8 micro tiles are grouped by 4 to fill a pipe interleave
+        assert_eq!(
+            swizzler.get_mip_tile_mode(64, 64, 1, PixelFormat::R8),
+            GX2TileMode::Tiled1DThin1
+        );
+
+        let texture = build_array_texture(64, 64, 4, 1, pixel_format);
+        let swizzled = swizzler.swizzle_texture(&texture).unwrap();
+        assert_eq!(
+            swizzled.len(),
+            swizzler.get_texture_size(&texture.metadata).unwrap()
+        );
+
+        // The 16x16 mip is laid out as a 1D surface right after the two 2D levels
+        let one_d = WiiUSwizzler {
+            tile_mode: GX2TileMode::Tiled1DThin1,
+            ..Default::default()
+        };
+        let offset = 64 * 64 * 4 + 32 * 32 * 4;
+        let mip_2 = one_d
+            .swizzle_surface(&texture.images[2].pixels, 16, 16, pixel_format)
+            .unwrap();
+        assert_eq!(swizzled[offset..offset + mip_2.len()], mip_2);
+
+        let deswizzled = swizzler
+            .deswizzle_texture(&swizzled, &texture.metadata)
+            .unwrap();
+        assert_same_images(&deswizzled, &texture.images);
+    }
+
+    #[test]
+    fn test_wii_u_errors() {
+        // 25x15 BC1 blocks are padded to a 32x16 macro tiled surface
+        let swizzler = WiiUSwizzler::default();
+        assert_eq!(
+            swizzler
+                .get_surface_size(100, 60, PixelFormat::BC1)
+                .unwrap(),
+            32 * 16 * 8
+        );
+
+        let mut pixels = build_pixels(PixelFormat::BC1.get_slice_pitch(100, 60) as usize);
+        assert!(Swizzler::default()
+            .deswizzle(&mut pixels, 100, 60, PixelFormat::BC1, Platform::WiiU)
+            .is_err());
+
+        let swizzler = WiiUSwizzler {
+            tile_mode: GX2TileMode::Tiled2DThick,
+            ..Default::default()
+        };
+        let mut pixels = build_pixels(64 * 64 * 4);
+        assert!(swizzler
+            .swizzle(&mut pixels, 64, 64, PixelFormat::R8G8B8A8)
+            .is_err());
+
+        assert_eq!("4".parse::<GX2TileMode>(), Ok(GX2TileMode::Tiled2DThin1));
+        assert!("16".parse::<GX2TileMode>().is_err());
+    }
+
+    #[test]
+    fn test_gx_tile_layout() {
+        // 16x4 I8 is two 8x4 tiles. Pixel values are their linear index.
+        let linear: Vec<u8> = (0..16 * 4).collect();
+
+        let tiled = gx::swizzle_tiles(&linear, 16, 4, PixelFormat::GxI8).unwrap();
+        assert_eq!(tiled.len(), 64);
+        assert_eq!(tiled[0..8], [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tiled[8..16], [16, 17, 18, 19, 20, 21, 22, 23]);
+        assert_eq!(tiled[32..40], [8, 9, 10, 11, 12, 13, 14, 15]);
+
+        // RGBA8 tiles store the AR pairs of all 16 pixels, then the GB pairs
+        let linear: Vec<u8> = (0..16).flat_map(|i| [i, 100 + i, 200, 50 + i]).collect();
+
+        let tiled = gx::swizzle_tiles(&linear, 4, 4, PixelFormat::GxRGBA8).unwrap();
+        assert_eq!(tiled[0..4], [0, 100, 1, 101]);
+        assert_eq!(tiled[32..36], [200, 50, 200, 51]);
+        assert_eq!(
+            gx::deswizzle_tiles(&tiled, 4, 4, PixelFormat::GxRGBA8).unwrap(),
+            linear
+        );
+    }
+
+    #[test]
+    fn test_gx_cmpr_sub_blocks() {
+        // 16x8 CMPR is 4x2 DXT1 blocks, every 8x8 tile holds 2x2 of them
+        let linear: Vec<u8> = (0..8).flat_map(|block| [block; 8]).collect();
+
+        let tiled = gx::swizzle_tiles(&linear, 16, 8, PixelFormat::GxCMPR).unwrap();
+        let block_order: Vec<u8> = tiled.chunks_exact(8).map(|block| block[0]).collect();
+        assert_eq!(block_order, [0, 1, 4, 5, 2, 3, 6, 7]);
+    }
+
+    #[test]
+    fn test_gx_npot_palette() {
+        // 10x6 C4 pads to 16x8 pixels, the TLUT follows the pixels in both layouts
+        let linear_size = PixelFormat::GxC4.get_slice_pitch(10, 6) as usize;
+        let palette_size = PixelFormat::GxC4.get_palette_size() as usize;
+        let surface_size = gx::get_surface_size(10, 6, PixelFormat::GxC4).unwrap();
+        assert_eq!((linear_size, surface_size), (30, 64));
+
+        let linear = build_pixels(linear_size + palette_size);
+        let mut pixels = linear.clone();
+        pixels.resize(surface_size + palette_size, 0);
+
+        let swizzler = Swizzler::default();
+        for platform in [Platform::Wii, Platform::GameCube] {
+            swizzler
+                .swizzle(&mut pixels, 10, 6, PixelFormat::GxC4, platform)
+                .unwrap();
+            assert_eq!(pixels[surface_size..], linear[linear_size..]);
+
+            swizzler
+                .deswizzle(&mut pixels, 10, 6, PixelFormat::GxC4, platform)
+                .unwrap();
+            assert_eq!(pixels[..linear.len()], linear);
+        }
+
+        // No room for the padding
+        let mut pixels = linear.clone();
+        assert!(GXSwizzler::default()
+            .swizzle(&mut pixels, 10, 6, PixelFormat::GxC4)
+            .is_err());
+        assert!(gx::get_surface_size(8, 8, PixelFormat::BC3).is_err());
+    }
+
+    #[test]
+    fn test_ps2_psmt8_layout() {
+        // 128x64 PSMT8 is one page, pixel values are their linear index
+        let linear: Vec<u8> = (0..128 * 64).map(|i| (i % 128) as u8).collect();
+        let mut pixels = linear.clone();
+
+        PS2Swizzler
+            .swizzle(&mut pixels, 128, 64, PixelFormat::Ps2PSMT8)
+            .unwrap();
+        // Pixels 0, 1 and 2 are the first byte of PSMCT32 words 0, 1 and 4
+        assert_eq!(pixels[0], 0);
+        assert_eq!(pixels[4], 1);
+        assert_eq!(pixels[8], 2);
+        // Pixel 8 is the third byte of word 0
+        assert_eq!(pixels[2], 8);
+        assert_ne!(pixels, linear);
+
+        PS2Swizzler
+            .deswizzle(&mut pixels, 128, 64, PixelFormat::Ps2PSMT8)
+            .unwrap();
+        assert_eq!(pixels, linear);
+    }
+
+    #[test]
+    fn test_ps2_round_trip() {
+        let swizzler = Swizzler::default();
+
+        for (pixel_format, width, height) in [
+            (PixelFormat::Ps2PSMT8, 32, 32),
+            (PixelFormat::Ps2PSMT8, 256, 128),
+            (PixelFormat::Ps2PSMT4, 32, 32),
+            (PixelFormat::Ps2PSMT4, 128, 128),
+            (PixelFormat::Ps2PSMT4, 256, 256),
+            (PixelFormat::R8G8B8A8, 64, 32),
+        ] {
+            // The CLUT follows the pixels and stays in place
+            let size = pixel_format.get_slice_pitch(width, height) as usize;
+            let linear = build_pixels(size + pixel_format.get_palette_size() as usize);
+            let mut pixels = linear.clone();
+
+            swizzler
+                .swizzle(&mut pixels, width, height, pixel_format, Platform::PS2)
+                .unwrap();
+            assert_eq!(pixels[size..], linear[size..]);
+            assert_eq!(
+                pixels == linear,
+                pixel_format == PixelFormat::R8G8B8A8,
+                "{} {}x{}",
+                pixel_format,
+                width,
+                height
+            );
+
+            swizzler
+                .deswizzle(&mut pixels, width, height, pixel_format, Platform::PS2)
+                .unwrap();
+            assert_eq!(pixels, linear, "{} {}x{}", pixel_format, width, height);
+        }
+    }
+
+    #[test]
+    fn test_ps2_errors() {
+        let mut pixels = build_pixels(64 * 64);
+        assert!(PS2Swizzler
+            .swizzle(&mut pixels, 63, 63, PixelFormat::Ps2PSMT8)
+            .is_err());
+        assert!(PS2Swizzler
+            .swizzle(&mut pixels, 64, 64, PixelFormat::BC1)
+            .is_err());
+        assert!(PS2Swizzler
+            .swizzle(&mut pixels[..100], 64, 64, PixelFormat::Ps2PSMT8)
+            .is_err());
+    }
+
+    #[test]
+    fn test_morton_layout() {
+        assert_eq!(morton::get_morton_index(1, 0, 4, 4), 1);
+        assert_eq!(morton::get_morton_index(0, 1, 4, 4), 2);
+        assert_eq!(morton::get_morton_index(3, 3, 4, 4), 15);
+        // 8x2 interleaves one bit of y, the rest of x follows
+        assert_eq!(morton::get_morton_index(0, 1, 8, 2), 2);
+        assert_eq!(morton::get_morton_index(2, 0, 8, 2), 4);
+        assert_eq!(morton::get_morton_index(7, 1, 8, 2), 15);
+        // 2x8 is the same with y
+        assert_eq!(morton::get_morton_index(1, 2, 2, 8), 5);
+        assert_eq!(morton::get_morton_index(0, 7, 2, 8), 14);
+
+        // Pixel values are their linear index
+        let linear: Vec<u8> = (0..4 * 4).flat_map(|i| [i as u8; 2]).collect();
+        let swizzled = morton::swizzle_morton(&linear, 4, 4, PixelFormat::B5G6R5).unwrap();
+        let order: Vec<u8> = swizzled.chunks_exact(2).map(|pixel| pixel[0]).collect();
+        assert_eq!(
+            order,
+            [0, 1, 4, 5, 2, 3, 6, 7, 8, 9, 12, 13, 10, 11, 14, 15]
+        );
+    }
+
+    #[test]
+    fn test_morton_round_trip() {
+        let swizzler = Swizzler::default();
+
+        for platform in [Platform::PS3, Platform::Xbox] {
+            for (pixel_format, width, height) in [
+                (PixelFormat::R8G8B8A8, 64, 64),
+                (PixelFormat::R8G8B8A8, 256, 32),
+                (PixelFormat::B5G6R5, 16, 128),
+                (PixelFormat::R8, 1, 64),
+                (PixelFormat::R32G32B32A32, 8, 4),
+            ] {
+                let linear = build_pixels(pixel_format.get_slice_pitch(width, height) as usize);
+                let mut pixels = linear.clone();
+
+                swizzler
+                    .swizzle(&mut pixels, width, height, pixel_format, platform)
+                    .unwrap();
+                assert_eq!(
+                    pixels,
+                    morton::swizzle_morton(&linear, width, height, pixel_format).unwrap()
+                );
+
+                swizzler
+                    .deswizzle(&mut pixels, width, height, pixel_format, platform)
+                    .unwrap();
+                assert_eq!(pixels, linear, "{} {}x{}", pixel_format, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn test_morton_compressed_and_errors() {
+        // Compressed textures are stored linearly
+        let linear = build_pixels(PixelFormat::BC3.get_slice_pitch(64, 32) as usize);
+        let mut pixels = linear.clone();
+        MortonSwizzler::default()
+            .swizzle(&mut pixels, 64, 32, PixelFormat::BC3)
+            .unwrap();
+        assert_eq!(pixels, linear);
+
+        let swizzler = MortonSwizzler {
+            platform: Platform::Xbox,
+        };
+        let mut pixels = build_pixels(48 * 32 * 4);
+        assert!(swizzler
+            .swizzle(&mut pixels, 48, 32, PixelFormat::R8G8B8A8)
+            .is_err());
+        assert!(swizzler
+            .deswizzle(&mut pixels, 64, 64, PixelFormat::R8G8B8A8)
+            .is_err());
+    }
+
+    #[test]
+    fn test_texture_default_layout() {
+        let swizzler = Swizzler::default();
+
+        for (platform, pixel_format) in [
+            (Platform::PS4, PixelFormat::BC3),
+            (Platform::PS3, PixelFormat::R8G8B8A8),
+            (Platform::Wii, PixelFormat::GxC8),
+            (Platform::WiiU, PixelFormat::BC1),
+        ] {
+            let texture = build_array_texture(64, 32, 6, 2, pixel_format);
+            let swizzled = swizzler.swizzle_texture(&texture, platform).unwrap();
+            assert_eq!(
+                swizzled.len(),
+                swizzler
+                    .get_texture_size(&texture.metadata, platform)
+                    .unwrap()
+            );
+
+            let deswizzled = swizzler
+                .deswizzle_texture(&swizzled, &texture.metadata, platform)
+                .unwrap();
+            assert_same_images(&deswizzled, &texture.images);
+
+            assert!(swizzler
+                .deswizzle_texture(&swizzled[..swizzled.len() - 1], &texture.metadata, platform)
+                .is_err());
+        }
+
+        // Images follow each other, the first one is a regular swizzled image
+        let texture = build_array_texture(16, 16, 5, 1, PixelFormat::R8G8B8A8);
+        let swizzled = swizzler.swizzle_texture(&texture, Platform::PS3).unwrap();
+        let first =
+            morton::swizzle_morton(&texture.images[0].pixels, 16, 16, PixelFormat::R8G8B8A8)
+                .unwrap();
+        assert_eq!(swizzled[..first.len()], first);
+    }
+
+    #[test]
+    fn test_ps4_texture_layout() {
+        let swizzler = Swizzler::default();
+        let texture = build_array_texture(16, 8, 3, 2, PixelFormat::BC1);
+
+        let swizzled = swizzler.swizzle_texture(&texture, Platform::PS4).unwrap();
+        assert_eq!(
+            swizzled.len(),
+            swizzler
+                .get_texture_size(&texture.metadata, Platform::PS4)
+                .unwrap()
+        );
+
+        // Every level holds the surfaces of all the array items, each padded to an 8x8 block tile
+        let surface_size = ps4::get_surface_size(16, 8, PixelFormat::BC1).unwrap();
+        assert_eq!(swizzled.len(), 3 * 2 * surface_size);
+
+        let second_item = texture.get_image(0, 1, 0).unwrap();
+        let expected = ps4::swizzle_surface(&second_item.pixels, 16, 8, PixelFormat::BC1).unwrap();
+        assert_eq!(swizzled[surface_size..2 * surface_size], expected);
+
+        let deswizzled = swizzler
+            .deswizzle_texture(&swizzled, &texture.metadata, Platform::PS4)
+            .unwrap();
+        assert_same_images(&deswizzled, &texture.images);
+
+        assert!(swizzler
+            .deswizzle_texture(
+                &swizzled[..swizzled.len() - 1],
+                &texture.metadata,
+                Platform::PS4
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_switch_texture_layers() {
+        // Array items are aligned to a whole block of the top level
+        let swizzler = SwitchSwizzler::default();
+        let texture = build_array_texture(256, 256, 4, 3, PixelFormat::BC1);
+        let chain_size = swizzler
+            .get_mip_chain_size(256, 256, 4, PixelFormat::BC1)
+            .unwrap();
+        let layer_size = chain_size.next_multiple_of(512 * switch::get_block_height(64) as usize);
+        assert_ne!(chain_size, layer_size);
+
+        let swizzled = swizzler.swizzle_texture(&texture).unwrap();
+        assert_eq!(swizzled.len(), layer_size * 3);
+        assert_eq!(
+            swizzler.get_texture_size(&texture.metadata).unwrap(),
+            swizzled.len()
+        );
+
+        let levels: Vec<Vec<u8>> = texture.images[4..8]
+            .iter()
+            .map(|image| image.pixels.clone())
+            .collect();
+        let second_layer = swizzler
+            .swizzle_mip_chain(&levels, 256, 256, PixelFormat::BC1)
+            .unwrap();
+        assert_eq!(swizzled[layer_size..layer_size + chain_size], second_layer);
+
+        let deswizzled = swizzler
+            .deswizzle_texture(&swizzled, &texture.metadata)
+            .unwrap();
+        assert_same_images(&deswizzled, &texture.images);
+    }
+
+    #[test]
+    fn test_xbox_360_packed_mips() {
+        assert_eq!(xbox_360::get_packed_mip_base(256, 128), 3);
+        assert_eq!(xbox_360::get_packed_mip_base(16, 512), 0);
+        assert_eq!(xbox_360::get_packed_mip_base(100, 60), 2);
+
+        // 16x16: the first three mips go along x, the next ones along y
+        assert_eq!(xbox_360::get_packed_mip_offset(16, 16, 0), Some((16, 0)));
+        assert_eq!(xbox_360::get_packed_mip_offset(16, 16, 1), Some((8, 0)));
+        assert_eq!(xbox_360::get_packed_mip_offset(16, 16, 2), Some((4, 0)));
+        assert_eq!(xbox_360::get_packed_mip_offset(16, 16, 3), Some((0, 8)));
+        assert_eq!(xbox_360::get_packed_mip_offset(16, 16, 4), Some((0, 4)));
+        // Wide textures are the other way around
+        assert_eq!(xbox_360::get_packed_mip_offset(64, 16, 0), Some((0, 16)));
+        assert_eq!(xbox_360::get_packed_mip_offset(64, 16, 3), Some((32, 0)));
+        assert_eq!(xbox_360::get_packed_mip_offset(256, 256, 3), None);
+    }
+
+    #[test]
+    fn test_xbox_360_texture() {
+        let swizzler = Swizzler::default();
+
+        for (width, height, mip_levels, array_size, pixel_format) in [
+            (128, 64, 8, 2, PixelFormat::BC1),
+            (256, 256, 9, 1, PixelFormat::BC3),
+            (100, 60, 7, 1, PixelFormat::R8G8B8A8),
+            (64, 64, 1, 6, PixelFormat::B5G6R5),
+            (16, 16, 5, 1, PixelFormat::R8G8B8A8),
+        ] {
+            let texture = build_array_texture(width, height, mip_levels, array_size, pixel_format);
+            let swizzled = swizzler
+                .swizzle_texture(&texture, Platform::Xbox360)
+                .unwrap();
+            assert_eq!(swizzled.len() % 4096, 0);
+            assert_eq!(
+                swizzled.len(),
+                swizzler
+                    .get_texture_size(&texture.metadata, Platform::Xbox360)
+                    .unwrap()
+            );
+
+            let deswizzled = swizzler
+                .deswizzle_texture(&swizzled, &texture.metadata, Platform::Xbox360)
+                .unwrap();
+            assert_same_images(&deswizzled, &texture.images);
+        }
+
+        // 128x64 BC1: the 8 KB top level, mip 1, then the tail from mip 2 (64x16)
+        let texture = build_array_texture(128, 64, 8, 1, PixelFormat::BC1);
+        assert_eq!(
+            swizzler
+                .get_texture_size(&texture.metadata, Platform::Xbox360)
+                .unwrap(),
+            8192 * 3
+        );
+    }
+
+    #[test]
+    fn test_padded_surface_sizes() {
+        // PS4 pads to 8x8 block tiles, PS Vita to powers of two and Xbox 360 to 32x32 blocks
+        assert_eq!(
+            ps4::get_surface_size(100, 60, PixelFormat::BC1).unwrap(),
+            32 * 16 * 8
+        );
+        assert_eq!(
+            ps4::get_surface_size(4, 4, PixelFormat::BC3).unwrap(),
+            8 * 8 * 16
+        );
+        assert_eq!(
+            psvita::get_surface_size(100, 60, PixelFormat::R8G8B8A8).unwrap(),
+            128 * 64 * 4
+        );
+        assert_eq!(
+            xbox_360::get_surface_size(100, 60, PixelFormat::BC1).unwrap(),
+            32 * 32 * 8
+        );
+        assert_eq!(
+            xbox_360::get_surface_size(64, 64, PixelFormat::R8G8B8A8).unwrap(),
+            64 * 64 * 4
+        );
+    }
+
+    #[test]
+    fn test_swizzled_preview_npot() {
+        let image = Image::new(
+            100,
+            60,
+            PixelFormatInfo::default(),
+            build_pixels(100 * 60 * 4),
+        );
+
+        for platform in [
+            Platform::PS4,
+            Platform::PSVita,
+            Platform::Xbox360,
+            Platform::Switch,
+            Platform::WiiU,
+            Platform::Wii,
+        ] {
+            for display_compressed in [false, true] {
+                let effects = TextureEffects {
+                    swizzle: Some(platform),
+                    pixel_format: PixelFormat::BC1,
+                    ..Default::default()
+                };
+
+                // The padded surface is cropped to the size of the preview
+                let (width, height, pixels) = effects
+                    .get_transformed_rgba8_pixels(&image, display_compressed)
+                    .unwrap();
+                assert_eq!((width, height), (100, 60));
+                assert_eq!(pixels.len(), 100 * 60 * 4, "{}", platform);
+            }
+        }
+    }
+
+    #[test]
+    fn test_effects_swizzle_whole_texture() {
+        let texture = build_array_texture(64, 32, 4, 2, PixelFormat::R8G8B8A8);
+        let swizzler = Swizzler::default();
+        let expected = swizzler.swizzle_texture(&texture, Platform::PS4).unwrap();
+
+        let effects = TextureEffects {
+            swizzle: Some(Platform::PS4),
+            ..Default::default()
+        };
+        let swizzled = texture.new_transformed_texture(&effects).unwrap();
+
+        // Written one after another, the images are the swizzled texture
+        let data: Vec<u8> = swizzled
+            .images
+            .iter()
+            .flat_map(|image| image.pixels.iter().copied())
+            .collect();
+        assert!(data == expected);
+
+        let effects = TextureEffects {
+            deswizzle: Some(Platform::PS4),
+            ..Default::default()
+        };
+        let deswizzled = swizzled.new_transformed_texture(&effects).unwrap();
+        assert_same_images(&deswizzled, &texture.images);
+    }
+
+    #[test]
+    fn test_padded_surface_round_trip() {
+        let swizzler = Swizzler::default();
+
+        for platform in [Platform::PS4, Platform::PSVita, Platform::Xbox360] {
+            for (pixel_format, width, height) in [
+                (PixelFormat::R8G8B8A8, 100, 60),
+                (PixelFormat::R8G8B8A8, 13, 7),
+                (PixelFormat::R16G16B16A16, 64, 64),
+                (PixelFormat::R8G8B8A8, 256, 128),
+            ] {
+                let linear = build_pixels(pixel_format.get_slice_pitch(width, height) as usize);
+                let size = swizzler
+                    .get_surface_size(width, height, pixel_format, platform)
+                    .unwrap();
+
+                // Too small for the padding
+                if size > linear.len() {
+                    let mut pixels = linear.clone();
+                    assert!(swizzler
+                        .swizzle(&mut pixels, width, height, pixel_format, platform)
+                        .is_err());
+                }
+
+                // Larger buffers are fine
+                let mut pixels = linear.clone();
+                pixels.resize(size + 100, 0);
+
+                swizzler
+                    .swizzle(&mut pixels, width, height, pixel_format, platform)
+                    .unwrap();
+                swizzler
+                    .deswizzle(&mut pixels, width, height, pixel_format, platform)
+                    .unwrap();
+                assert_eq!(
+                    pixels[..linear.len()],
+                    linear,
+                    "{} {} {}x{}",
+                    platform,
+                    pixel_format,
+                    width,
+                    height
+                );
+            }
+        }
+
+        // Block compressed PS4 and Xbox 360 surfaces
+        for platform in [Platform::PS4, Platform::Xbox360] {
+            let linear = build_pixels(PixelFormat::BC3.get_slice_pitch(100, 60) as usize);
+            let size = swizzler
+                .get_surface_size(100, 60, PixelFormat::BC3, platform)
+                .unwrap();
+            let mut pixels = linear.clone();
+            pixels.resize(size, 0);
+
+            swizzler
+                .swizzle(&mut pixels, 100, 60, PixelFormat::BC3, platform)
+                .unwrap();
+            swizzler
+                .deswizzle(&mut pixels, 100, 60, PixelFormat::BC3, platform)
+                .unwrap();
+            assert_eq!(pixels[..linear.len()], linear, "{}", platform);
+        }
+    }
+
+    #[test]
+    fn test_padded_surface_errors() {
+        let swizzler = Swizzler::default();
+
+        for platform in [Platform::PS4, Platform::PSVita, Platform::Xbox360] {
+            // Truncated surfaces and odd sized buffers are errors, not panics
+            let mut pixels = build_pixels(33);
+            assert!(swizzler
+                .deswizzle(&mut pixels, 64, 64, PixelFormat::R8G8B8A8, platform)
+                .is_err());
+            assert!(swizzler
+                .swizzle(&mut pixels, 64, 64, PixelFormat::R8G8B8A8, platform)
+                .is_err());
+        }
+
+        let mut pixels = build_pixels(64 * 64);
+        assert!(swizzler
+            .swizzle(&mut pixels, 64, 64, PixelFormat::GxI4, Platform::Xbox360)
+            .is_err());
+        assert!(swizzler
+            .swizzle(&mut pixels, 64, 64, PixelFormat::GxI4, Platform::PS4)
+            .is_err());
+    }
+
+    #[test]
+    fn test_psvita_compressed_blocks() {
+        // 16x16 BC1 is 4x4 blocks in Morton order, y first. Block values are their linear index.
+        let linear: Vec<u8> = (0..16).flat_map(|block| [block; 8]).collect();
+
+        let swizzled = psvita::swizzle_surface(&linear, 16, 16, PixelFormat::BC1).unwrap();
+        let block_order: Vec<u8> = swizzled.chunks_exact(8).map(|block| block[0]).collect();
+        assert_eq!(
+            block_order,
+            [0, 4, 1, 5, 8, 12, 9, 13, 2, 6, 3, 7, 10, 14, 11, 15]
+        );
+        assert_eq!(
+            psvita::deswizzle_surface(&swizzled, 16, 16, PixelFormat::BC1).unwrap(),
+            linear
+        );
+    }
+
+    #[test]
+    fn test_psvita_round_trip() {
+        for layout in [PSVitaLayout::Swizzled, PSVitaLayout::Tiled] {
+            let swizzler = PSVitaSwizzler { layout };
+
+            for (pixel_format, width, height) in [
+                (PixelFormat::R8, 100, 60),
+                (PixelFormat::B5G6R5, 64, 32),
+                (PixelFormat::R8G8B8, 30, 50),
+                (PixelFormat::R8G8B8A8, 128, 128),
+                (PixelFormat::GxI4, 20, 12),
+                (PixelFormat::BC1, 100, 60),
+                (PixelFormat::BC3, 256, 64),
+                (PixelFormat::GxC8, 16, 16),
+            ] {
+                let size = pixel_format.get_slice_pitch(width, height) as usize
+                    + pixel_format.get_palette_size() as usize;
+                let linear = build_pixels(size);
+                let mut pixels = linear.clone();
+                pixels.resize(
+                    swizzler
+                        .get_surface_size(width, height, pixel_format)
+                        .unwrap(),
+                    0,
+                );
+
+                swizzler
+                    .swizzle(&mut pixels, width, height, pixel_format)
+                    .unwrap();
+                swizzler
+                    .deswizzle(&mut pixels, width, height, pixel_format)
+                    .unwrap();
+                assert_eq!(
+                    pixels[..linear.len()],
+                    linear,
+                    "{:?} {} {}x{}",
+                    layout,
+                    pixel_format,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_psvita_tiled_layout() {
+        // 64x64 R8 is 2x2 tiles of 32x32 texels. Pixel values are their column index.
+        let linear: Vec<u8> = (0..64 * 64).map(|i| (i % 64) as u8).collect();
+
+        let tiled = psvita::tile_surface(&linear, 64, 64, PixelFormat::R8).unwrap();
+        assert_eq!(tiled.len(), 64 * 64);
+        assert_eq!(tiled[..32], linear[..32]);
+        // The second row of the first tile
+        assert_eq!(tiled[32..64], linear[64..96]);
+        // The second tile is the right half of the first 32 rows
+        assert_eq!(tiled[1024..1056], linear[32..64]);
+
+        // BC1 tiles are 8x8 blocks
+        assert_eq!(
+            psvita::get_tiled_surface_size(100, 60, PixelFormat::BC1).unwrap(),
+            32 * 16 * 8
+        );
+        assert!(psvita::get_surface_size(16, 16, PixelFormat::R32G32B32).is_ok());
+    }
+}
@@ -0,0 +1,173 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::atomic::{AtomicBool, AtomicUsize},
+    };
+
+    use tempfile::tempdir;
+    use texture_viewer::{
+        codecs::{
+            codec_manager::{CodecManager, CodecOptions},
+            dds::DDSCodec,
+            gxt::GXTCodec,
+            ktx2::KTX2Codec,
+            png::PNGCodec,
+            ImageCodec,
+        },
+        graphics::pixel_format::PixelFormat,
+        io::texture_scanner::{
+            extract_textures, scan_textures, scan_textures_with_progress, ScanOptions, ScanTask,
+        },
+    };
+
+    use crate::common::{build_texture, build_tga};
+
+    const WITH_TGA: ScanOptions = ScanOptions { include_tga: true };
+
+    #[test]
+    fn test_scan_and_extract_textures() {
+        let rgba = build_texture(16, 8, PixelFormat::R8G8B8A8);
+        let bc1 = build_texture(32, 32, PixelFormat::BC1);
+
+        let files: Vec<(&str, Vec<u8>)> = vec![
+            ("png", PNGCodec.save_to_memory(&rgba).unwrap()),
+            ("dds", DDSCodec::default().save_to_memory(&bc1).unwrap()),
+            ("ktx2", KTX2Codec::default().save_to_memory(&bc1).unwrap()),
+            ("gxt", GXTCodec.save_to_memory(&bc1).unwrap()),
+            ("tga", build_tga(4, 2)),
+        ];
+
+        // Files separated by filler that looks like nothing
+        let mut blob = vec![0x55; 37];
+        let mut offsets = Vec::new();
+
+        for (_, bytes) in &files {
+            offsets.push(blob.len());
+            blob.extend_from_slice(bytes);
+            blob.extend_from_slice(&[0x55; 29]);
+        }
+
+        let codec_manager = CodecManager::default();
+        let textures = scan_textures(&blob, &codec_manager, &WITH_TGA);
+        assert_eq!(textures.len(), files.len());
+
+        for ((texture, (extension, bytes)), offset) in textures.iter().zip(&files).zip(&offsets) {
+            assert_eq!(texture.extension, *extension);
+            assert_eq!(texture.offset, *offset);
+            assert_eq!(texture.size, bytes.len());
+            assert_eq!(texture.get_bytes(&blob), &bytes[..]);
+
+            let loaded = texture.load(&blob, &codec_manager).unwrap();
+            assert_eq!(loaded.metadata.width, texture.metadata.width);
+        }
+
+        assert_eq!(textures[0].metadata.width, 16);
+        assert_eq!(textures[0].metadata.height, 8);
+        assert_eq!(
+            textures[1].metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+
+        let dir = tempdir().unwrap();
+        let paths = extract_textures(&blob, &textures, dir.path()).unwrap();
+        assert_eq!(paths.len(), files.len());
+        assert_eq!(paths[1], dir.path().join(format!("{:08X}.dds", offsets[1])));
+
+        for (path, (_, bytes)) in paths.iter().zip(&files) {
+            assert_eq!(&fs::read(path).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_scan_rejects_broken_headers() {
+        let codec_manager = CodecManager::default();
+        let mut png = PNGCodec
+            .save_to_memory(&build_texture(4, 4, PixelFormat::R8G8B8A8))
+            .unwrap();
+
+        // Truncated before IEND
+        png.truncate(png.len() - 4);
+        assert!(scan_textures(&png, &codec_manager, &WITH_TGA).is_empty());
+
+        let mut blob = b"DDS ".to_vec();
+        blob.extend_from_slice(&[0; 200]);
+        assert!(scan_textures(&blob, &codec_manager, &WITH_TGA).is_empty());
+
+        // Sizes far past the end of the data
+        let mut dds = DDSCodec::default()
+            .save_to_memory(&build_texture(4, 4, PixelFormat::R8G8B8A8))
+            .unwrap();
+        dds[12..16].copy_from_slice(&16384u32.to_le_bytes());
+        dds[16..20].copy_from_slice(&16384u32.to_le_bytes());
+        assert!(scan_textures(&dds, &codec_manager, &WITH_TGA).is_empty());
+    }
+
+    #[test]
+    fn test_scan_signatures_only() {
+        let codec_manager = CodecManager::default();
+        let mut blob = vec![0x55; 16];
+        blob.extend_from_slice(&build_tga(4, 2));
+        blob.extend_from_slice(
+            &PNGCodec
+                .save_to_memory(&build_texture(4, 4, PixelFormat::R8G8B8A8))
+                .unwrap(),
+        );
+
+        // TGA has no signature, it is only looked for on request
+        let textures = scan_textures(&blob, &codec_manager, &ScanOptions::default());
+        assert_eq!(textures.len(), 1);
+        assert_eq!(textures[0].extension, "png");
+
+        let textures = scan_textures(&blob, &codec_manager, &WITH_TGA);
+        assert_eq!(textures.len(), 2);
+        assert_eq!(textures[0].extension, "tga");
+    }
+
+    #[test]
+    fn test_scan_progress_and_cancel() {
+        let codec_manager = CodecManager::default();
+        let mut blob = vec![0x55; 64];
+        blob.extend_from_slice(
+            &DDSCodec::default()
+                .save_to_memory(&build_texture(8, 8, PixelFormat::BC1))
+                .unwrap(),
+        );
+
+        let progress = AtomicUsize::new(0);
+        let textures = scan_textures_with_progress(
+            &blob,
+            &codec_manager,
+            &ScanOptions::default(),
+            &progress,
+            &AtomicBool::new(true),
+        );
+        assert!(textures.is_empty());
+
+        let textures = scan_textures_with_progress(
+            &blob,
+            &codec_manager,
+            &ScanOptions::default(),
+            &progress,
+            &AtomicBool::new(false),
+        );
+        assert_eq!(textures.len(), 1);
+        assert_eq!(progress.into_inner(), blob.len());
+
+        let task = ScanTask::spawn(
+            blob.clone(),
+            CodecOptions::default(),
+            ScanOptions::default(),
+        );
+        let (source, textures) = task.join().unwrap();
+        assert_eq!(source, blob);
+        assert_eq!(textures.len(), 1);
+        assert_eq!(textures[0].offset, 64);
+
+        let task = ScanTask::spawn(blob, CodecOptions::default(), ScanOptions::default());
+        task.cancel();
+        assert!(task.join().is_none());
+    }
+}
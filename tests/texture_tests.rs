@@ -3,7 +3,7 @@ mod tests {
 
     use std::path::{Path, PathBuf};
 
-    use TextureViewer::{
+    use texture_viewer::{
         codecs::{
             codec_manager::CodecManager, dds::DDSCodec, jpeg::JPEGCodec, png::PNGCodec, ImageCodec,
         },
@@ -68,7 +68,7 @@ mod tests {
     #[test]
     fn test_load_dds() {
         let dds_data = include_bytes!("test_images/sample.dds");
-        let codec = DDSCodec;
+        let codec = DDSCodec::default();
         let texture = codec
             .load_from_memory(dds_data)
             .expect("Failed to load DDS!");
@@ -82,7 +82,7 @@ mod tests {
         assert_eq!(texture.metadata.depth, 1);
         assert_eq!(texture.metadata.mip_levels, 12);
         assert_eq!(texture.metadata.array_size, 6);
-        assert_eq!(texture.metadata.is_cubemap, true);
+        assert!(texture.metadata.is_cubemap);
         assert_eq!(texture.images.len(), 72);
 
         let texture = codec
@@ -98,7 +98,7 @@ mod tests {
         assert_eq!(texture.metadata.depth, 1);
         assert_eq!(texture.metadata.mip_levels, 12);
         assert_eq!(texture.metadata.array_size, 6);
-        assert_eq!(texture.metadata.is_cubemap, true);
+        assert!(texture.metadata.is_cubemap);
         assert_eq!(texture.images.len(), 72);
     }
 
@@ -0,0 +1,312 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use texture_viewer::{
+        codecs::{
+            xpr2::{get_texture_names, XPR2Codec},
+            ImageCodec,
+        },
+        error::TextureError,
+        graphics::{
+            pixel_format::PixelFormat,
+            swizzling::{xbox_360::Xbox360Swizzler, Swizzable},
+            texture::TexMetadata,
+        },
+    };
+
+    use crate::common::{assert_same_images, build_mip_texture};
+
+    const FORMAT_8_8_8_8: u32 = 6;
+    const FORMAT_DXT1: u32 = 18;
+    const ENDIAN_8_IN_16: u32 = 1;
+    const ENDIAN_8_IN_32: u32 = 2;
+    const SWIZZLE_ZYXW: u32 = 2 | (1 << 3) | (3 << 9);
+    const DIMENSION_2D: u32 = 1;
+    const DIMENSION_3D: u32 = 2;
+
+    struct XPR2Resource {
+        name: &'static str,
+        format: u32,
+        endian: u32,
+        is_tiled: bool,
+        has_packed_mips: bool,
+        dimension: u32,
+        size: (u32, u32, u32),
+        mip_levels: u32,
+        base_address: u32,
+        mip_address: u32,
+    }
+
+    impl Default for XPR2Resource {
+        fn default() -> Self {
+            Self {
+                name: "texture",
+                format: FORMAT_DXT1,
+                endian: ENDIAN_8_IN_16,
+                is_tiled: true,
+                has_packed_mips: false,
+                dimension: DIMENSION_2D,
+                size: (1, 1, 32),
+                mip_levels: 1,
+                base_address: 0,
+                mip_address: 0,
+            }
+        }
+    }
+
+    /// Assemble an XPR2 file with one D3D texture header per resource, then the data
+    fn build_xpr2(resources: &[XPR2Resource], data: &[u8]) -> Vec<u8> {
+        // Offsets are relative to the resource count, the table follows it
+        let table_size = 4 + resources.len() * 16;
+        let header_size = (table_size + resources.len() * 0x40).next_multiple_of(0x800) as u32;
+
+        let mut xpr2 = b"XPR2".to_vec();
+        xpr2.extend_from_slice(&header_size.to_be_bytes());
+        xpr2.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        xpr2.extend_from_slice(&(resources.len() as u32).to_be_bytes());
+
+        let mut infos = Vec::new();
+
+        for (index, resource) in resources.iter().enumerate() {
+            let info_offset = (table_size + index * 0x40) as u32;
+            xpr2.extend_from_slice(b"TX2D");
+            xpr2.extend_from_slice(&info_offset.to_be_bytes());
+            xpr2.extend_from_slice(&0x34u32.to_be_bytes());
+            xpr2.extend_from_slice(&(info_offset + 0x34).to_be_bytes());
+
+            let (width, height, pitch) = resource.size;
+            let mut info = vec![0; 0x1C];
+
+            for dword in [
+                ((resource.is_tiled as u32) << 31) | ((pitch >> 5) << 22),
+                resource.format | (resource.endian << 6) | resource.base_address,
+                (width - 1) | ((height - 1) << 13),
+                SWIZZLE_ZYXW << 1,
+                (resource.mip_levels - 1) << 6,
+                (resource.dimension << 9)
+                    | ((resource.has_packed_mips as u32) << 11)
+                    | resource.mip_address,
+            ] {
+                info.extend_from_slice(&dword.to_be_bytes());
+            }
+
+            info.extend_from_slice(resource.name.as_bytes());
+            info.resize(0x40, 0);
+            infos.extend_from_slice(&info);
+        }
+
+        xpr2.extend_from_slice(&infos);
+        xpr2.resize(12 + header_size as usize, 0);
+        xpr2.extend_from_slice(data);
+        xpr2
+    }
+
+    #[test]
+    fn test_xpr2_tiled_mips() {
+        let texture = build_mip_texture(64, 64, 3, PixelFormat::BC1, 7);
+        let swizzler = Xbox360Swizzler;
+        let tiled = swizzler.swizzle_texture(&texture).unwrap();
+        let base_size = swizzler
+            .get_texture_size(&TexMetadata {
+                mip_levels: 1,
+                ..texture.metadata
+            })
+            .unwrap();
+
+        // The mips are stored apart from the top level
+        let mut data = tiled[..base_size].to_vec();
+        data.resize(0x2000, 0xEE);
+        data.extend_from_slice(&tiled[base_size..]);
+
+        let xpr2 = build_xpr2(
+            &[XPR2Resource {
+                name: "diffuse",
+                size: (64, 64, 64),
+                mip_levels: 3,
+                mip_address: 0x2000,
+                ..Default::default()
+            }],
+            &data,
+        );
+
+        assert_eq!(get_texture_names(&xpr2).unwrap(), vec!["diffuse"]);
+
+        let loaded = XPR2Codec::default().load_from_memory(&xpr2).unwrap();
+        assert_eq!(
+            loaded.metadata.pixel_format_info.pixel_format,
+            PixelFormat::BC1
+        );
+        assert_eq!(loaded.metadata.mip_levels, 3);
+        assert_same_images(&loaded, &texture.images);
+    }
+
+    #[test]
+    fn test_xpr2_endian_8_in_32() {
+        let texture = build_mip_texture(32, 32, 1, PixelFormat::B8G8R8A8, 7);
+        let mut tiled = Xbox360Swizzler.swizzle_texture(&texture).unwrap();
+
+        // The swizzler writes 8in16 words, turn them into 8in32 ones
+        for word in tiled.chunks_exact_mut(4) {
+            word.swap(0, 1);
+            word.swap(2, 3);
+            word.reverse();
+        }
+
+        let xpr2 = build_xpr2(
+            &[
+                XPR2Resource {
+                    name: "first",
+                    format: FORMAT_8_8_8_8,
+                    endian: ENDIAN_8_IN_32,
+                    size: (32, 32, 32),
+                    ..Default::default()
+                },
+                XPR2Resource {
+                    name: "second",
+                    format: FORMAT_8_8_8_8,
+                    endian: ENDIAN_8_IN_32,
+                    size: (32, 32, 32),
+                    ..Default::default()
+                },
+            ],
+            &tiled,
+        );
+
+        let loaded = XPR2Codec::default().load_from_memory(&xpr2).unwrap();
+        assert_eq!(
+            loaded.metadata.pixel_format_info.pixel_format,
+            PixelFormat::B8G8R8A8
+        );
+        assert_eq!(loaded.metadata.array_size, 2);
+        assert_same_images(
+            &loaded,
+            &[&texture.images[..], &texture.images[..]].concat(),
+        );
+    }
+
+    #[test]
+    fn test_xpr2_linear() {
+        // 2x2 A8R8G8B8 texture, big endian words with rows padded to 32 texels
+        let mut data = Vec::new();
+
+        for row in 0..2u8 {
+            data.extend_from_slice(&[3, 2, 1, row, 7, 6, 5, row]);
+            data.resize(data.len() + 120, 0xEE);
+        }
+
+        let xpr2 = build_xpr2(
+            &[XPR2Resource {
+                format: FORMAT_8_8_8_8,
+                endian: ENDIAN_8_IN_32,
+                is_tiled: false,
+                size: (2, 2, 32),
+                ..Default::default()
+            }],
+            &data,
+        );
+
+        let loaded = XPR2Codec::default().load_from_memory(&xpr2).unwrap();
+        assert_eq!(
+            loaded.images[0].pixels,
+            vec![0, 1, 2, 3, 0, 5, 6, 7, 1, 1, 2, 3, 1, 5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn test_xpr2_packed_mips() {
+        let texture = build_mip_texture(64, 64, 3, PixelFormat::BC1, 7);
+        let tiled = Xbox360Swizzler.swizzle_texture(&texture).unwrap();
+        let resource = |mip_levels| XPR2Resource {
+            size: (64, 64, 64),
+            mip_levels,
+            has_packed_mips: true,
+            ..Default::default()
+        };
+
+        // The 64x64 top level is not packed
+        let xpr2 = build_xpr2(&[resource(1)], &tiled);
+        assert_same_images(
+            &XPR2Codec::default().load_from_memory(&xpr2).unwrap(),
+            &texture.images[..1],
+        );
+
+        // The 16x16 level is
+        let xpr2 = build_xpr2(&[resource(3)], &tiled);
+        assert!(matches!(
+            XPR2Codec::default().load_from_memory(&xpr2),
+            Err(TextureError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_xpr2_errors() {
+        assert!(XPR2Codec::default().load_from_memory(b"XPR0").is_err());
+
+        let data = vec![0; 0x2000];
+
+        let volume = build_xpr2(
+            &[XPR2Resource {
+                dimension: DIMENSION_3D,
+                ..Default::default()
+            }],
+            &data,
+        );
+        assert!(XPR2Codec::default().load_from_memory(&volume).is_err());
+
+        let different_sizes = build_xpr2(
+            &[
+                XPR2Resource {
+                    size: (32, 32, 32),
+                    ..Default::default()
+                },
+                XPR2Resource {
+                    size: (64, 64, 64),
+                    ..Default::default()
+                },
+            ],
+            &data,
+        );
+        assert!(XPR2Codec::default()
+            .load_from_memory(&different_sizes)
+            .is_err());
+
+        let codec = XPR2Codec {
+            texture_index: Some(1),
+        };
+        assert!(codec.load_from_memory(&different_sizes).is_ok());
+
+        // Data smaller than the 32x32 block padded surface
+        let truncated = build_xpr2(
+            &[XPR2Resource {
+                size: (256, 256, 256),
+                ..Default::default()
+            }],
+            &data,
+        );
+        assert!(XPR2Codec::default().load_from_memory(&truncated).is_err());
+
+        let texture = build_mip_texture(4, 4, 1, PixelFormat::BC1, 7);
+        assert!(XPR2Codec::default().save_to_memory(&texture).is_err());
+    }
+
+    #[test]
+    fn test_load_xpr2_invalid_counts() {
+        let xpr2 = build_xpr2(&[XPR2Resource::default()], &[0; 0x2000]);
+
+        // More resources than fit in the file
+        let mut corrupt = xpr2.clone();
+        corrupt[12..16].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(XPR2Codec::default().load_from_memory(&corrupt).is_err());
+
+        // More mips than a 1x1 image has
+        let xpr2 = build_xpr2(
+            &[XPR2Resource {
+                mip_levels: 4,
+                ..Default::default()
+            }],
+            &[0; 0x2000],
+        );
+        assert!(XPR2Codec::default().load_from_memory(&xpr2).is_err());
+    }
+}